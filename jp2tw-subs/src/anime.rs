@@ -0,0 +1,70 @@
+//! Anime dialog conventions for the translation prompt: how honorifics on
+//! names are carried over (`--honorifics`) and attack/technique names kept
+//! as called out with a zh-TW gloss (`--keep-techniques`), instead of the
+//! neutral register the default prompt produces.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Honorifics {
+    /// Let the model convey honorifics through wording (the default prompt)
+    #[default]
+    Translate,
+    /// Keep the Japanese suffix on the name as written: 田中さん stays 田中さん
+    Keep,
+    /// Map suffixes to fixed zh-TW equivalents: さん becomes 先生 or 小姐, 様 becomes 大人, and so on
+    Map,
+}
+
+/// Honorific suffixes and their zh-TW renderings for `--honorifics map`.
+const MAPPING: &[(&str, &str)] = &[
+    ("さん", "先生 (men) or 小姐 (women)"),
+    ("様", "大人"),
+    ("くん", "君"),
+    ("君", "君"),
+    ("ちゃん", "醬"),
+    ("先輩", "學長 (men) or 學姊 (women)"),
+    ("先生", "老師"),
+    ("殿", "閣下"),
+];
+
+const KEEP: &str = "Keep Japanese honorific suffixes on names exactly as written (さん, くん, ちゃん, 様, 先輩, 殿), attached to the translated name, e.g. 田中さん → 田中さん; never turn them into 先生, 小姐, or similar.";
+
+const TECHNIQUES: &str = "Keep attack, technique, and spell names that characters call out as written in Japanese, followed by a short zh-TW gloss in full-width parentheses, e.g. かめはめ波 → かめはめ波（龜派氣功）; do not gloss names that are already plain kanji.";
+
+/// Instructions to append to the system prompt, if any.
+pub fn prompt(honorifics: Honorifics, keep_techniques: bool) -> Option<String> {
+    let mut parts = Vec::new();
+    match honorifics {
+        Honorifics::Translate => {}
+        Honorifics::Keep => parts.push(KEEP.to_string()),
+        Honorifics::Map => {
+            let pairs: Vec<String> = MAPPING
+                .iter()
+                .map(|(ja, zh)| format!("{ja} → {zh}"))
+                .collect();
+            parts.push(format!(
+                "Render honorific suffixes on names with these zh-TW equivalents, after the translated name: {}.",
+                pairs.join(", ")
+            ));
+        }
+    }
+    if keep_techniques {
+        parts.push(TECHNIQUES.to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt() {
+        assert_eq!(prompt(Honorifics::Translate, false), None);
+        assert_eq!(prompt(Honorifics::Keep, false).as_deref(), Some(KEEP));
+        let mapped = prompt(Honorifics::Map, true).unwrap();
+        assert!(mapped.contains("さん → 先生 (men) or 小姐 (women)"));
+        assert!(mapped.ends_with(TECHNIQUES));
+    }
+}
@@ -0,0 +1,146 @@
+//! Extracted audio reused across runs in the same `--work-dir`: the 16kHz
+//! WAV (when one was written) and its chunks are recorded in `audio.cache.json` with the input's
+//! size and modification time and the extraction settings, so a rerun on an
+//! unchanged input skips extraction, and segmentation too when the chunk
+//! length is the same.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const FILE: &str = "audio.cache.json";
+
+/// What the extracted audio was made from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    input: PathBuf,
+    size: u64,
+    /// Modification time, seconds and nanoseconds since the epoch
+    modified: (u64, u32),
+    /// ffmpeg, libav, or symphonia
+    extractor: String,
+    extract_args: Vec<String>,
+}
+
+impl Source {
+    pub fn of(input: &Path, extractor: &str, extract_args: &[String]) -> Result<Self> {
+        let meta = std::fs::metadata(input).with_context(|| format!("Stat {}", input.display()))?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            input: input.canonicalize().unwrap_or_else(|_| input.to_path_buf()),
+            size: meta.len(),
+            modified: (modified.as_secs(), modified.subsec_nanos()),
+            extractor: extractor.to_string(),
+            extract_args: extract_args.to_vec(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    source: Source,
+    /// Set once the WAV was split
+    chunk_seconds: Option<u32>,
+    chunks: Vec<PathBuf>,
+}
+
+pub enum Cached {
+    /// The chunks at the requested length
+    Chunks(Vec<PathBuf>),
+    /// The WAV only
+    Wav,
+    Nothing,
+}
+
+/// What `work_dir` still holds for `source`.
+pub fn lookup(work_dir: &Path, wav: &Path, source: &Source, chunk_seconds: u32) -> Cached {
+    let Some(saved) = std::fs::read_to_string(work_dir.join(FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str::<Saved>(&text).ok())
+    else {
+        return Cached::Nothing;
+    };
+    if saved.source != *source {
+        return Cached::Nothing;
+    }
+    if saved.chunk_seconds == Some(chunk_seconds)
+        && !saved.chunks.is_empty()
+        && saved.chunks.iter().all(|c| c.is_file())
+    {
+        return Cached::Chunks(saved.chunks);
+    }
+    if wav.is_file() {
+        Cached::Wav
+    } else {
+        Cached::Nothing
+    }
+}
+
+/// Forget what `work_dir` holds, before its audio is rewritten.
+pub fn clear(work_dir: &Path) {
+    let _ = std::fs::remove_file(work_dir.join(FILE));
+}
+
+pub fn save(
+    work_dir: &Path,
+    source: &Source,
+    chunk_seconds: Option<u32>,
+    chunks: &[PathBuf],
+) -> Result<()> {
+    let saved = Saved {
+        source: source.clone(),
+        chunk_seconds,
+        chunks: chunks.to_vec(),
+    };
+    let path = work_dir.join(FILE);
+    std::fs::write(&path, serde_json::to_string(&saved)?)
+        .with_context(|| format!("Write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("ep.mp4");
+        std::fs::write(&input, b"video").unwrap();
+        let wav = dir.path().join("audio.wav");
+        let chunk = dir.path().join("chunk_00000.wav");
+        let source = Source::of(&input, "ffmpeg", &[]).unwrap();
+        assert!(matches!(
+            lookup(dir.path(), &wav, &source, 600),
+            Cached::Nothing
+        ));
+
+        std::fs::write(&wav, b"RIFF").unwrap();
+        std::fs::write(&chunk, b"RIFF").unwrap();
+        save(dir.path(), &source, Some(600), std::slice::from_ref(&chunk)).unwrap();
+        assert!(
+            matches!(lookup(dir.path(), &wav, &source, 600), Cached::Chunks(c) if c == [chunk.clone()])
+        );
+        // Another chunk length splits the same WAV again
+        assert!(matches!(
+            lookup(dir.path(), &wav, &source, 300),
+            Cached::Wav
+        ));
+        // Other extraction settings or a changed input start over
+        let other = Source::of(&input, "ffmpeg", &["-af".into(), "loudnorm".into()]).unwrap();
+        assert!(matches!(
+            lookup(dir.path(), &wav, &other, 600),
+            Cached::Nothing
+        ));
+        std::fs::write(&input, b"longer video").unwrap();
+        let changed = Source::of(&input, "ffmpeg", &[]).unwrap();
+        assert!(matches!(
+            lookup(dir.path(), &wav, &changed, 600),
+            Cached::Nothing
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+//! Speech-to-text and translation providers behind two traits, picked by
+//! name from a registry (`--transcription-backend`, `--translation-backend`).
+//!
+//! The pipeline builds every request itself (chunking, prompts, batching,
+//! retries on garbled replies), so a backend only answers one request at a
+//! time: a transcription of a 16 kHz mono WAV chunk, or a chat completion.
+//! A new provider is a module implementing one or both traits, registered
+//! in [`Registry::default`] (behind a cargo feature when it pulls in
+//! dependencies). Programs embedding the library can instead register their
+//! own on a registry and pass it to [`crate::main_with`]. Cassettes (`--record` / `--replay`) work for any backend.
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+
+use crate::openai::{
+    ApiError, ChatRequest, OpenAiClient, TranscriptionRequest, TranscriptionResponse,
+};
+use crate::whisper_cpp::{Device, WhisperCpp};
+
+/// The built-in provider, served by the run's own client.
+pub const DEFAULT: &str = "openai";
+
+/// The providers this process runs with, set by [`crate::main_with`].
+static INSTALLED: OnceLock<Registry> = OnceLock::new();
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait TranscriptionBackend: Send + Sync {
+    /// Segments of one audio chunk, timed from its start.
+    fn transcribe<'a>(
+        &'a self,
+        req: &'a TranscriptionRequest,
+    ) -> BoxFuture<'a, Result<TranscriptionResponse, ApiError>>;
+}
+
+pub trait TranslationBackend: Send + Sync {
+    /// The assistant's reply to `req`.
+    fn chat<'a>(&'a self, req: &'a ChatRequest) -> BoxFuture<'a, Result<String, ApiError>>;
+}
+
+/// What a backend is built from.
+pub struct Config {
+    pub api_key: String,
+    /// `--api-base-url`
+    pub base_url: Option<String>,
+    /// `--whisper-cpp-model`
+    pub whisper_cpp_model: Option<PathBuf>,
+    /// `--whisper-device`
+    pub whisper_device: Device,
+}
+
+type Factory<T> = Box<dyn Fn(&Config) -> Result<Arc<T>> + Send + Sync>;
+
+pub struct Registry {
+    transcription: BTreeMap<String, Factory<dyn TranscriptionBackend>>,
+    translation: BTreeMap<String, Factory<dyn TranslationBackend>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self {
+            transcription: BTreeMap::new(),
+            translation: BTreeMap::new(),
+        };
+        registry.register_transcription(DEFAULT, |config| Ok(Arc::new(openai(config))));
+        registry.register_translation(DEFAULT, |config| Ok(Arc::new(openai(config))));
+        registry.register_transcription("whisper-cpp", |config| {
+            Ok(Arc::new(WhisperCpp::new(config)?))
+        });
+        registry
+    }
+}
+
+fn openai(config: &Config) -> OpenAiClient {
+    let client = OpenAiClient::new(config.api_key.clone());
+    match &config.base_url {
+        Some(url) => client.with_base_url(url),
+        None => client,
+    }
+}
+
+impl Registry {
+    pub fn register_transcription(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&Config) -> Result<Arc<dyn TranscriptionBackend>> + Send + Sync + 'static,
+    ) {
+        self.transcription
+            .insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn register_translation(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&Config) -> Result<Arc<dyn TranslationBackend>> + Send + Sync + 'static,
+    ) {
+        self.translation.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn transcription(
+        &self,
+        name: &str,
+        config: &Config,
+    ) -> Result<Arc<dyn TranscriptionBackend>> {
+        let factory = self.transcription.get(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown transcription backend {name} (available: {})",
+                names(&self.transcription)
+            )
+        })?;
+        factory(config)
+    }
+
+    pub fn translation(&self, name: &str, config: &Config) -> Result<Arc<dyn TranslationBackend>> {
+        let factory = self.translation.get(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown translation backend {name} (available: {})",
+                names(&self.translation)
+            )
+        })?;
+        factory(config)
+    }
+}
+
+/// Use `registry` for every run in this process; the first call wins.
+pub(crate) fn install(registry: Registry) {
+    let _ = INSTALLED.set(registry);
+}
+
+/// The installed registry, or the built-in one when none was installed.
+pub(crate) fn installed() -> &'static Registry {
+    INSTALLED.get_or_init(Registry::default)
+}
+
+fn names<T>(map: &BTreeMap<String, T>) -> String {
+    map.keys().cloned().collect::<Vec<_>>().join(", ")
+}
+
+impl TranscriptionBackend for OpenAiClient {
+    fn transcribe<'a>(
+        &'a self,
+        req: &'a TranscriptionRequest,
+    ) -> BoxFuture<'a, Result<TranscriptionResponse, ApiError>> {
+        Box::pin(OpenAiClient::transcribe(self, req))
+    }
+}
+
+impl TranslationBackend for OpenAiClient {
+    fn chat<'a>(&'a self, req: &'a ChatRequest) -> BoxFuture<'a, Result<String, ApiError>> {
+        Box::pin(OpenAiClient::chat(self, req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl TranslationBackend for Echo {
+        fn chat<'a>(&'a self, req: &'a ChatRequest) -> BoxFuture<'a, Result<String, ApiError>> {
+            Box::pin(async move { Ok(req.model.clone()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry() {
+        let mut registry = Registry::default();
+        registry.register_translation("echo", |_| Ok(Arc::new(Echo)));
+        let config = Config {
+            api_key: String::new(),
+            base_url: None,
+            whisper_cpp_model: None,
+            whisper_device: Device::Auto,
+        };
+        let echo = registry.translation("echo", &config).unwrap();
+        let reply = echo.chat(&ChatRequest::new("m")).await.unwrap();
+        assert_eq!(reply, "m");
+        assert!(registry.translation(DEFAULT, &config).is_ok());
+        let err = registry.transcription("echo", &config).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Unknown transcription backend echo (available: openai, whisper-cpp)"
+        );
+        let err = registry
+            .transcription("whisper-cpp", &config)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("--whisper-cpp-model"));
+    }
+}
@@ -0,0 +1,406 @@
+//! `--jobs-file`: run a batch of inputs from a CSV manifest. Each row is one
+//! run; its columns are CLI options (`font_size` or `font-size` for
+//! `--font-size`) layered over the options given on the command line, so
+//! different shows with different settings can share one invocation.
+//!
+//! Besides the option names, the columns `glossary` (for `--replace-rules`)
+//! and `args` (extra flags, shell-quoted) are understood.
+//! Empty cells leave the option as given on the command line.
+//!
+//! With `--jobs N` up to N rows run at once. Transcription and translation
+//! are network-bound and overlap freely, while burn-in encodes take one of
+//! `--encode-jobs` slots so the CPU is not oversubscribed.
+
+use anyhow::{anyhow, Context, Result};
+use clap::CommandFactory;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::{JoinError, JoinSet};
+
+use crate::skip::SkipRange;
+use crate::{cancel, parse_args, plan, preset, project, run_reported, split_args, themes, Args};
+
+/// Burn-in slots shared by the jobs of a batch; unset outside batch mode.
+static ENCODES: OnceLock<Semaphore> = OnceLock::new();
+
+/// Wait for a free encode slot; immediate outside batch mode.
+pub async fn encode_slot() -> Option<SemaphorePermit<'static>> {
+    ENCODES.get()?.acquire().await.ok()
+}
+
+/// One manifest row: (column, value) pairs in header order.
+pub type Row = Vec<(String, String)>;
+
+pub async fn run(
+    jobs_file: &Path,
+    jobs: u32,
+    encode_jobs: u32,
+    detect: themes::DetectOpEd,
+) -> Result<()> {
+    let text = std::fs::read_to_string(jobs_file)
+        .with_context(|| format!("Read {}", jobs_file.display()))?;
+    let rows = parse_csv(&text).with_context(|| format!("Parse {}", jobs_file.display()))?;
+    if rows.is_empty() {
+        return Err(anyhow!("No jobs in {}", jobs_file.display()));
+    }
+    let base = base_argv(project::argv(std::env::args_os())?);
+    let _ = ENCODES.set(Semaphore::new(encode_jobs as usize));
+    let themes = if detect == themes::DetectOpEd::Off {
+        Vec::new()
+    } else {
+        detect_themes(&base, &rows)?
+    };
+
+    let total = rows.len();
+    let mut failed = Vec::new();
+    let mut running = JoinSet::new();
+    for (n, row) in rows.iter().enumerate() {
+        while running.len() >= jobs as usize {
+            if let Some(joined) = running.join_next().await {
+                finish(joined, total, &mut failed)?;
+            }
+        }
+        if cancel::is_cancelled() {
+            break;
+        }
+        let label = row
+            .iter()
+            .find(|(k, _)| k == "input")
+            .map(|(_, v)| v.as_str())
+            .unwrap_or("?");
+        eprintln!("\n[job {}/{total}] {label}", n + 1);
+        let args = match job_args(&base, row) {
+            Ok(mut args) => {
+                let found = themes.get(n).cloned().unwrap_or_default();
+                match detect {
+                    themes::DetectOpEd::Skip => args.skip_range.extend(found),
+                    themes::DetectOpEd::Songs => args.song_range.extend(found),
+                    themes::DetectOpEd::Off => {}
+                }
+                args
+            }
+            Err(e) => {
+                finish(Ok((n + 1, Err(e))), total, &mut failed)?;
+                continue;
+            }
+        };
+        if args.plan {
+            // Printed in order, not interleaved
+            finish(
+                Ok((n + 1, plan::print(&args).map(|_| Vec::new()))),
+                total,
+                &mut failed,
+            )?;
+            continue;
+        }
+        running.spawn(async move { (n + 1, run_reported(args).await) });
+    }
+    while let Some(joined) = running.join_next().await {
+        finish(joined, total, &mut failed)?;
+    }
+
+    let done = total - failed.len();
+    eprintln!("\nBatch: {done} of {total} job(s) succeeded");
+    failed.sort_unstable();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        let list: Vec<String> = failed.iter().map(|n| n.to_string()).collect();
+        Err(anyhow!("Failed job(s): {}", list.join(", ")))
+    }
+}
+
+/// `--detect-op-ed`: the OP/ED ranges of each row's input, found across
+/// the local files of the batch; rows without one get none.
+fn detect_themes(base: &[OsString], rows: &[Row]) -> Result<Vec<Vec<SkipRange>>> {
+    let inputs: Vec<Option<PathBuf>> = rows
+        .iter()
+        .map(|row| {
+            let args = job_args(base, row).ok()?;
+            Some(args.input().to_path_buf()).filter(|p| p.is_file())
+        })
+        .collect();
+    let files: Vec<&Path> = inputs.iter().flatten().map(PathBuf::as_path).collect();
+    if files.len() < 2 {
+        eprintln!("--detect-op-ed: needs at least two local episodes; captioning without it");
+        return Ok(vec![Vec::new(); rows.len()]);
+    }
+    crate::ensure_ffmpeg_cli()
+        .context("--detect-op-ed decodes the episodes with the ffmpeg binary")?;
+    let found = tokio::task::block_in_place(|| themes::detect(&files))?;
+    let mut found = found.into_iter();
+    let mut out = Vec::new();
+    for (n, input) in inputs.iter().enumerate() {
+        let ranges = match input {
+            Some(_) => found.next().unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let list: Vec<String> = ranges
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} {}-{}",
+                    r.label.as_deref().unwrap_or_default(),
+                    plan::clock(r.start),
+                    plan::clock(r.end)
+                )
+            })
+            .collect();
+        eprintln!(
+            "[job {}/{}] OP/ED: {}",
+            n + 1,
+            rows.len(),
+            if list.is_empty() {
+                "none found".to_string()
+            } else {
+                list.join(", ")
+            }
+        );
+        out.push(ranges);
+    }
+    Ok(out)
+}
+
+/// Options for one row: the process arguments with the row's flags added.
+fn job_args(base: &[OsString], row: &Row) -> Result<Args> {
+    let extra = row_args(row)?;
+    let mut args = parse_args(preset::argv(
+        base.iter()
+            .cloned()
+            .chain(extra.into_iter().map(OsString::from))
+            .collect(),
+    ))
+    .map_err(|e| {
+        let msg = e.to_string();
+        let first = msg.lines().next().unwrap_or_default();
+        anyhow!("{}", first.trim_start_matches("error: "))
+    })?;
+    project::expand_templates(&mut args);
+    Ok(args)
+}
+
+/// Report a finished job, noting it in `failed` if it did not succeed.
+fn finish(
+    joined: Result<(usize, Result<Vec<PathBuf>>), JoinError>,
+    total: usize,
+    failed: &mut Vec<usize>,
+) -> Result<()> {
+    let (n, result) = joined?;
+    match result {
+        Ok(outputs) if outputs.is_empty() => eprintln!("[job {n}/{total}] done"),
+        Ok(outputs) => {
+            let list: Vec<String> = outputs.iter().map(|p| p.display().to_string()).collect();
+            eprintln!("[job {n}/{total}] done: {}", list.join(", "));
+        }
+        Err(e) => {
+            eprintln!("[job {n}/{total}] failed: {e:#}");
+            failed.push(n);
+        }
+    }
+    Ok(())
+}
+
+/// The process arguments without `--jobs-file`, which each row extends.
+fn base_argv(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut out = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == "--jobs-file" {
+            args.next();
+        } else if !a.to_string_lossy().starts_with("--jobs-file=") {
+            out.push(a);
+        }
+    }
+    out
+}
+
+/// CLI flags for one row.
+fn row_args(row: &Row) -> Result<Vec<String>> {
+    if !row.iter().any(|(k, v)| k == "input" && !v.is_empty()) {
+        return Err(anyhow!("Row has no input"));
+    }
+    option_flags(row)
+}
+
+/// CLI flags for (option, value) pairs keyed by option id (`font_size`),
+/// plus `glossary` and `args`; empty values are skipped. Shared with the
+/// project file.
+pub fn option_flags(row: &Row) -> Result<Vec<String>> {
+    let command = Args::command();
+    let mut out = Vec::new();
+    for (key, value) in row {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        if key == "args" {
+            out.extend(split_args(value)?);
+            continue;
+        }
+        let id = match key.as_str() {
+            "glossary" => "replace_rules",
+            k => k,
+        };
+        let arg = command
+            .get_arguments()
+            .find(|a| a.get_id() == id && a.get_long().is_some())
+            .ok_or_else(|| anyhow!("Unknown option {key:?}"))?;
+        let flag = format!("--{}", arg.get_long().unwrap_or_default());
+        // Switches a preset may set take `=false` to turn them off again
+        let switch_off = arg.get_action().takes_values()
+            && arg
+                .get_possible_values()
+                .iter()
+                .any(|v| v.get_name() == "false");
+        if arg.get_action().takes_values() && !switch_off {
+            out.push(flag);
+            out.push(value.to_string());
+        } else {
+            match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => out.push(flag),
+                "0" | "false" | "no" if switch_off => out.push(format!("{flag}=false")),
+                "0" | "false" | "no" => {}
+                _ => return Err(anyhow!("{key:?} takes true or false, got {value:?}")),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Rows of a CSV file with a header line. Fields may be double-quoted (with
+/// `""` for a quote) to hold commas or line breaks; blank lines and lines
+/// starting with `#` are skipped. Header names are lowercased with `-` read
+/// as `_`.
+pub fn parse_csv(text: &str) -> Result<Vec<Row>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut at_line_start = true;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        if at_line_start && c == '#' {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            continue;
+        }
+        at_line_start = false;
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                at_line_start = true;
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("Unterminated quoted field"));
+    }
+    if !at_line_start {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+
+    let mut records = records.into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = header
+        .iter()
+        .map(|h| h.trim().to_ascii_lowercase().replace('-', "_"))
+        .collect();
+    records
+        .enumerate()
+        .map(|(i, r)| {
+            if r.len() > header.len() {
+                return Err(anyhow!(
+                    "Row {} has {} fields; the header has {}",
+                    i + 1,
+                    r.len(),
+                    header.len()
+                ));
+            }
+            Ok(header.iter().cloned().zip(r).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "\u{feff}Input,Font-Size,args\r\n# comment\r\n\r\na.mp4,40,\"--offset -1.5 --format \"\"srt\"\"\"\r\nb,c.mp4,,\n";
+        let rows = parse_csv(text).unwrap_err();
+        assert!(rows.to_string().contains("Row 2"));
+        let rows = parse_csv(&text.replace("b,c.mp4,,\n", "\"b,c.mp4\",\n")).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][1], ("font_size".into(), "40".into()));
+        assert_eq!(rows[0][2].1, r#"--offset -1.5 --format "srt""#);
+        assert_eq!(rows[1][0].1, "b,c.mp4");
+        assert_eq!(rows[1].len(), 2);
+    }
+
+    #[test]
+    fn test_row_args() {
+        let row: Row = vec![
+            ("input".into(), "ep1.mp4".into()),
+            ("glossary".into(), "show.toml".into()),
+            ("burn_in".into(), "yes".into()),
+            ("font_size".into(), "".into()),
+            ("target_lang".into(), "zh-TW,en".into()),
+            ("args".into(), "--offset -2".into()),
+        ];
+        assert_eq!(
+            row_args(&row).unwrap(),
+            [
+                "--input",
+                "ep1.mp4",
+                "--replace-rules",
+                "show.toml",
+                "--burn-in",
+                "--target-lang",
+                "zh-TW,en",
+                "--offset",
+                "-2"
+            ]
+        );
+        let bad: Row = vec![("input".into(), "a".into()), ("colour".into(), "x".into())];
+        assert!(row_args(&bad).is_err());
+        assert_eq!(
+            base_argv(
+                [
+                    "jp2tw-subs",
+                    "--jobs-file",
+                    "j.csv",
+                    "--jobs-file=k",
+                    "--plan"
+                ]
+                .map(OsString::from)
+            ),
+            ["jp2tw-subs", "--plan"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! `--batch-api`: translation batches go through the OpenAI Batch API, at
+//! half the price, instead of being requested one by one. The first run
+//! transcribes as usual, queues every translation request, submits them as
+//! one job, and records its id in the run checkpoint; rerunning the same
+//! command collects the results (waiting for them with `--batch-wait`) and
+//! finishes the subtitles. Requests are keyed by model and source lines, so
+//! they are matched up again however the resumed run splits its work.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::cancel;
+use crate::checkpoint::Checkpoint;
+use crate::openai::{self, ChatRequest, OpenAiClient};
+
+/// How often `--batch-wait` checks on a running job.
+const POLL: Duration = Duration::from_secs(60);
+
+/// Returned by translation when its requests were queued instead of sent.
+#[derive(Debug, thiserror::Error)]
+#[error("translation queued for the Batch API")]
+pub struct Deferred;
+
+/// Translation requests waiting to be submitted, and replies collected from
+/// earlier jobs.
+#[derive(Default)]
+pub struct Collector {
+    results: Mutex<HashMap<String, String>>,
+    queued: Mutex<BTreeMap<String, ChatRequest>>,
+}
+
+impl Collector {
+    /// Reply content for request `id`, if a job returned one.
+    pub fn result(&self, id: &str) -> Option<String> {
+        self.results.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn queue(&self, id: String, req: ChatRequest) {
+        self.queued.lock().unwrap().insert(id, req);
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.lock().unwrap().len()
+    }
+}
+
+/// Stable id of the request translating `lines` with `model` (FNV-1a).
+pub fn request_id(model: &str, lines: &[String]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in std::iter::once(model).chain(lines.iter().map(String::as_str)) {
+        for b in part.bytes().chain([0]) {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("tr-{hash:016x}")
+}
+
+/// Collect the replies of the jobs recorded in `checkpoint`. Returns false
+/// when a job is still running (and `wait` is off).
+pub async fn collect(
+    client: &OpenAiClient,
+    checkpoint: &Checkpoint,
+    collector: &Collector,
+    wait: bool,
+) -> Result<bool> {
+    let mut done = true;
+    for id in checkpoint.batches() {
+        let batch = loop {
+            let batch = client.batch(&id).await?;
+            let counts = batch.request_counts.clone().unwrap_or_default();
+            match batch.status.as_str() {
+                "completed" | "failed" | "expired" | "cancelled" => break batch,
+                status if wait => {
+                    eprintln!(
+                        "Batch {id} is {status} ({}/{} requests done, {} failed); checking again in {} s",
+                        counts.completed,
+                        counts.total,
+                        counts.failed,
+                        POLL.as_secs()
+                    );
+                    cancel::check()?;
+                    tokio::time::sleep(POLL).await;
+                }
+                status => {
+                    eprintln!(
+                        "Batch {id} is {status} ({}/{} requests done, {} failed); rerun the same command later, or with --batch-wait, to collect it",
+                        counts.completed, counts.total, counts.failed
+                    );
+                    done = false;
+                    break batch;
+                }
+            }
+        };
+        // Expired and cancelled jobs still return what they finished
+        if let Some(file) = &batch.output_file_id {
+            let text = client
+                .file_content(file)
+                .await
+                .with_context(|| format!("Download the results of batch {id}"))?;
+            let n = parse_output(&text, collector);
+            eprintln!("Collected {n} translation replies from batch {id}");
+        }
+        if matches!(batch.status.as_str(), "failed" | "expired" | "cancelled") {
+            eprintln!(
+                "Batch {id} {}; requests without a reply are submitted again",
+                batch.status
+            );
+            checkpoint.forget_batch(&id)?;
+        }
+    }
+    Ok(done)
+}
+
+/// Submit the queued requests as one job and record it in `checkpoint`.
+pub async fn submit(
+    client: &OpenAiClient,
+    checkpoint: &Checkpoint,
+    collector: &Collector,
+) -> Result<()> {
+    let queued = std::mem::take(&mut *collector.queued.lock().unwrap());
+    let batch = client
+        .create_batch(&input_file(&queued))
+        .await
+        .context("Submit the translation batch")?;
+    checkpoint.record_batch(&batch.id)?;
+    eprintln!(
+        "Submitted {} translation requests as batch {} (billed at half price, done within 24 h). Rerun the same command to collect them and finish the subtitles",
+        queued.len(),
+        batch.id
+    );
+    Ok(())
+}
+
+/// The job's input: one chat completion request per JSON line.
+fn input_file(queued: &BTreeMap<String, ChatRequest>) -> Vec<u8> {
+    let mut out = String::new();
+    for (id, req) in queued {
+        let line = json!({
+            "custom_id": id,
+            "method": "POST",
+            "url": "/v1/chat/completions",
+            "body": req,
+        });
+        out.push_str(&line.to_string());
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+/// Store the successful replies of a job's output file; returns how many.
+fn parse_output(text: &str, collector: &Collector) -> usize {
+    let mut results = collector.results.lock().unwrap();
+    let mut n = 0;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        let (Some(id), body) = (v["custom_id"].as_str(), &v["response"]["body"]) else {
+            continue;
+        };
+        let Some(content) = body["choices"][0]["message"]["content"].as_str() else {
+            continue;
+        };
+        let usage = &body["usage"];
+        openai::record_batch_usage(
+            body["model"].as_str().unwrap_or("unknown"),
+            usage["prompt_tokens"].as_u64().unwrap_or(0),
+            usage["completion_tokens"].as_u64().unwrap_or(0),
+        );
+        results.insert(id.to_string(), content.to_string());
+        n += 1;
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_files() {
+        let lines = vec!["はい".to_string(), "いいえ".to_string()];
+        let id = request_id("gpt-4o-mini", &lines);
+        assert_eq!(id, request_id("gpt-4o-mini", &lines));
+        assert_ne!(id, request_id("gpt-4o", &lines));
+        assert_ne!(id, request_id("gpt-4o-mini", &["はいいいえ".to_string()]));
+
+        let collector = Collector::default();
+        collector.queue(id.clone(), ChatRequest::new("gpt-4o-mini").user("x"));
+        let input = String::from_utf8(input_file(&collector.queued.lock().unwrap())).unwrap();
+        let v: serde_json::Value = serde_json::from_str(input.trim()).unwrap();
+        assert_eq!(v["custom_id"], id.as_str());
+        assert_eq!(v["url"], "/v1/chat/completions");
+        assert_eq!(v["body"]["messages"][0]["content"], "x");
+
+        let output = format!(
+            "{}\n{}\n",
+            json!({"custom_id": id, "response": {"status_code": 200, "body": {
+                "model": "gpt-4o-mini-2024-07-18",
+                "choices": [{"message": {"content": "{\"translations\":[\"好\",\"不\"]}"}}],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 5},
+            }}}),
+            json!({"custom_id": "tr-other", "response": null, "error": {"message": "failed"}}),
+        );
+        assert_eq!(parse_output(&output, &collector), 1);
+        assert!(collector.result(&id).unwrap().contains("translations"));
+        assert_eq!(collector.result("tr-other"), None);
+    }
+}
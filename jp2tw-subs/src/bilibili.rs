@@ -0,0 +1,136 @@
+//! Bilibili uploads: `--format bcc` writes the JSON subtitle file the
+//! Bilibili creator center accepts as CC subtitles (`<name>.zh-TW.bcc`), and
+//! `--format danmaku` writes a danmaku XML (`<name>.danmaku.xml`) with each
+//! cue as a bottom-fixed comment, for players and uploads that only take
+//! danmaku. Bottom danmaku show for a fixed few seconds, so a longer cue is
+//! repeated until it ends.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::formats::xml_escape;
+use crate::WhisperSegment;
+
+/// How long Bilibili shows a bottom danmaku
+const DANMAKU_SECONDS: f64 = 4.0;
+/// Danmaku mode 4: fixed at the bottom
+const MODE_BOTTOM: u8 = 4;
+const FONT_SIZE: u8 = 25;
+const WHITE: u32 = 0xFFFFFF;
+/// Longest danmaku Bilibili accepts, in characters
+const MAX_CHARS: usize = 100;
+
+pub fn write_bcc(path: &Path, segments: &[WhisperSegment], lines: &[String]) -> Result<()> {
+    std::fs::write(path, bcc(segments, lines))
+        .with_context(|| format!("Write Bilibili subtitles at {}", path.display()))
+}
+
+fn bcc(segments: &[WhisperSegment], lines: &[String]) -> String {
+    let body: Vec<serde_json::Value> = segments
+        .iter()
+        .zip(lines)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(seg, line)| {
+            json!({
+                "from": round(seg.start),
+                "to": round(seg.end),
+                "location": 2,
+                "content": line.trim(),
+            })
+        })
+        .collect();
+    let doc = json!({
+        "font_size": 0.4,
+        "font_color": "#FFFFFF",
+        "background_alpha": 0.5,
+        "background_color": "#9C27B0",
+        "Stroke": "none",
+        "body": body,
+    });
+    serde_json::to_string_pretty(&doc).unwrap_or_default()
+}
+
+pub fn write_danmaku(path: &Path, segments: &[WhisperSegment], lines: &[String]) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    std::fs::write(path, danmaku(segments, lines, now))
+        .with_context(|| format!("Write danmaku at {}", path.display()))
+}
+
+/// Danmaku XML; `sent` is the Unix time stamped on every comment.
+fn danmaku(segments: &[WhisperSegment], lines: &[String], sent: u64) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<i>\n  <chatserver>chat.bilibili.com</chatserver>\n  <chatid>0</chatid>\n",
+    );
+    let mut id = 0;
+    for (seg, line) in segments.iter().zip(lines) {
+        // One line of text: danmaku do not wrap
+        let text: String = line
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(MAX_CHARS)
+            .collect();
+        if text.is_empty() {
+            continue;
+        }
+        let mut at = seg.start;
+        loop {
+            id += 1;
+            let _ = writeln!(
+                out,
+                "  <d p=\"{:.3},{MODE_BOTTOM},{FONT_SIZE},{WHITE},{sent},0,0,{id}\">{}</d>",
+                at.max(0.0),
+                xml_escape(&text)
+            );
+            at += DANMAKU_SECONDS;
+            if at >= seg.end - 0.5 {
+                break;
+            }
+        }
+    }
+    out.push_str("</i>\n");
+    out
+}
+
+fn round(seconds: f64) -> f64 {
+    (seconds.max(0.0) * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_bilibili_exports() {
+        let segments = [seg(1.0, 2.5, ""), seg(10.0, 19.0, ""), seg(20.0, 21.0, "")];
+        let lines = [
+            "你好\nこんにちは".to_string(),
+            "<好久不見>".to_string(),
+            " ".to_string(),
+        ];
+        let doc: serde_json::Value = serde_json::from_str(&bcc(&segments, &lines)).unwrap();
+        assert_eq!(doc["body"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["body"][0]["content"], "你好\nこんにちは");
+        assert_eq!(doc["body"][1]["to"], 19.0);
+
+        let xml = danmaku(&segments, &lines, 1700000000);
+        let d: Vec<&str> = xml.lines().filter(|l| l.contains("<d ")).collect();
+        assert_eq!(
+            d,
+            [
+                r#"  <d p="1.000,4,25,16777215,1700000000,0,0,1">你好 こんにちは</d>"#,
+                r#"  <d p="10.000,4,25,16777215,1700000000,0,0,2">&lt;好久不見&gt;</d>"#,
+                r#"  <d p="14.000,4,25,16777215,1700000000,0,0,3">&lt;好久不見&gt;</d>"#,
+                r#"  <d p="18.000,4,25,16777215,1700000000,0,0,4">&lt;好久不見&gt;</d>"#,
+            ]
+        );
+        roxmltree::Document::parse(&xml).unwrap();
+    }
+}
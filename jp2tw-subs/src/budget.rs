@@ -0,0 +1,197 @@
+//! `--target-cost`: pick the translation model from the input's duration so
+//! the run is predicted to stay under a budget. Usage is estimated per
+//! minute of audio for dense dialog, the name table, `--pivot` and
+//! `--punctuate` passes included, and priced like the cost ledger; the
+//! most capable model that fits wins, and the decision is printed before
+//! anything runs. Only the translation model is picked: `--whisper-model` is
+//! priced into the estimate, with the `--language-guard` probe, but kept as
+//! given.
+
+use anyhow::{anyhow, Result};
+
+use crate::openai::{self, ModelUsage};
+use crate::{language_guard, ledger, probe_duration, Args};
+
+/// Translation models from most to least capable (and costly).
+const MODELS: [&str; 5] = [
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4.1-mini",
+    "gpt-4o-mini",
+    "gpt-4.1-nano",
+];
+
+/// Chat tokens per minute of dense dialog for one kind of request.
+struct Tokens {
+    prompt: f64,
+    completion: f64,
+}
+
+/// Translation: the Japanese lines and instructions in, the zh-TW JSON out,
+/// retries included. The `--pivot` hop costs the same.
+const TRANSLATION: Tokens = Tokens {
+    prompt: 600.0,
+    completion: 400.0,
+};
+/// The name table: the Japanese lines in, the few names found out.
+const NAME_TABLE: Tokens = Tokens {
+    prompt: 350.0,
+    completion: 50.0,
+};
+/// `--punctuate`: the Japanese lines in and, cleaned up, back out.
+const PUNCTUATE: Tokens = Tokens {
+    prompt: 350.0,
+    completion: 250.0,
+};
+
+/// Predicted cost of a run over `minutes` of audio translating with `model`.
+fn estimate(args: &Args, minutes: f64, model: &str) -> Option<f64> {
+    // --language-guard sends the start of the first chunk twice
+    let probe = if args.language_guard.detects() {
+        f64::from(language_guard::PROBE_SECONDS).min(minutes * 60.0)
+    } else {
+        0.0
+    };
+    let transcription = ledger::estimate_cost(
+        &args.whisper_model,
+        &ModelUsage {
+            audio_seconds: minutes * 60.0 + probe,
+            ..Default::default()
+        },
+    )?;
+    let batched = |model: &str| {
+        if args.batch_api {
+            format!("{model}{}", openai::BATCH_SUFFIX)
+        } else {
+            model.to_string()
+        }
+    };
+    // --candidates asks N times and adds a judge request
+    let requests = match args.candidates {
+        1 => 1.0,
+        n => f64::from(n) + 1.0,
+    };
+    let mut chat = vec![(batched(model), TRANSLATION, requests)];
+    if args.pivot.is_some() {
+        chat.push((
+            batched(args.pivot_model.as_deref().unwrap_or(model)),
+            TRANSLATION,
+            1.0,
+        ));
+    }
+    if !args.no_name_table {
+        chat.push((model.to_string(), NAME_TABLE, 1.0));
+    }
+    if args.punctuate {
+        chat.push((model.to_string(), PUNCTUATE, 1.0));
+    }
+    let mut total = transcription;
+    for (model, tokens, requests) in chat {
+        total += ledger::estimate_cost(
+            &model,
+            &ModelUsage {
+                prompt_tokens: (minutes * tokens.prompt * requests) as u64,
+                completion_tokens: (minutes * tokens.completion * requests) as u64,
+                ..Default::default()
+            },
+        )?;
+    }
+    Some(total)
+}
+
+/// The most capable model predicted to cost at most `target` for `minutes`,
+/// with its estimate; the cheapest one's estimate when none fits.
+fn choose(args: &Args, minutes: f64, target: f64) -> Result<(&'static str, f64), f64> {
+    let estimates: Vec<(&str, f64)> = MODELS
+        .iter()
+        .filter_map(|&m| Some((m, estimate(args, minutes, m)?)))
+        .collect();
+    estimates
+        .iter()
+        .find(|(_, usd)| *usd <= target)
+        .copied()
+        .ok_or_else(|| estimates.last().map_or(f64::INFINITY, |(_, usd)| *usd))
+}
+
+/// Set `--translate-model` for `--target-cost`, if given.
+pub fn apply(args: &mut Args) -> Result<()> {
+    let Some(target) = args.target_cost else {
+        return Ok(());
+    };
+    let seconds: Option<f64> = args.input.iter().map(|p| probe_duration(p)).sum();
+    let minutes = seconds
+        .ok_or_else(|| anyhow!("--target-cost needs the input's duration (ffprobe)"))?
+        / 60.0;
+    match choose(args, minutes, target) {
+        Ok((model, usd)) => {
+            eprintln!(
+                "--target-cost ${target:.2}: {minutes:.0} min of audio with {} + {model} is estimated at ${usd:.2}",
+                args.whisper_model
+            );
+            // A preset or the project file may have set it too
+            if args.translate_model_given && args.translate_model != model {
+                eprintln!(
+                    "Warning: --target-cost replaces --translate-model {} with {model}",
+                    args.translate_model
+                );
+            }
+            args.translate_model = model.to_string();
+            Ok(())
+        }
+        Err(cheapest) => Err(anyhow!(
+            "--target-cost ${target:.2}: {minutes:.0} min of audio is estimated at ${cheapest:.2} even with {}; raise the target",
+            MODELS[MODELS.len() - 1]
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_choose() {
+        let args = Args::parse_from(["jp2tw-subs", "-i", "x.mp4"]);
+        // An hour: whisper-1 alone is $0.36
+        let (model, usd) = choose(&args, 60.0, 2.00).unwrap();
+        assert_eq!(model, "gpt-4o");
+        assert!(usd <= 2.00);
+        assert_eq!(choose(&args, 60.0, 0.40).unwrap().0, "gpt-4o-mini");
+        assert!(choose(&args, 60.0, 0.30).is_err());
+        // Three candidates and a judge quadruple the translation cost
+        let args = Args::parse_from(["jp2tw-subs", "-i", "x.mp4", "--candidates", "3"]);
+        assert_eq!(choose(&args, 60.0, 0.60).unwrap().0, "gpt-4.1-mini");
+    }
+
+    #[test]
+    fn test_estimate_passes() {
+        let usd = |flags: &[&str]| {
+            let argv = ["jp2tw-subs", "-i", "x.mp4"].iter().chain(flags);
+            estimate(&Args::parse_from(argv), 60.0, "gpt-4o").unwrap()
+        };
+        let base = usd(&[]);
+        assert!(usd(&["--no-name-table"]) < base);
+        assert!(usd(&["--language-guard", "off"]) < base);
+        assert!(usd(&["--punctuate"]) > base);
+        assert!(usd(&["--pivot", "en"]) > usd(&["--punctuate"]));
+        // The JA→en hop priced at the cheaper --pivot-model
+        assert!(usd(&["--pivot", "en", "--pivot-model", "gpt-4o-mini"]) < usd(&["--pivot", "en"]));
+    }
+
+    #[test]
+    fn test_translate_model_given() {
+        let parse =
+            |argv: &[&str]| crate::parse_args(argv.iter().map(Into::into).collect()).unwrap();
+        assert!(!parse(&["jp2tw-subs", "-i", "x.mp4"]).translate_model_given);
+        // Given even when it names the default
+        let args = parse(&[
+            "jp2tw-subs",
+            "-i",
+            "x.mp4",
+            "--translate-model",
+            "gpt-4o-mini",
+        ]);
+        assert!(args.translate_model_given);
+    }
+}
@@ -0,0 +1,121 @@
+//! Graceful cancellation: the first Ctrl-C (or SIGTERM) stops new API calls
+//! and kills running ffmpeg children, so finished work stays in the
+//! checkpoint for a resumed run. A second signal exits immediately.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CHILDREN: LazyLock<Mutex<HashMap<u32, Child>>> = LazyLock::new(Default::default);
+/// How to pick up where an interrupted run left off, if anything was saved.
+static RESUME_HINT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Listen for SIGINT/SIGTERM in the background.
+pub fn install() {
+    tokio::spawn(async {
+        loop {
+            wait_for_signal().await;
+            if CANCELLED.swap(true, Ordering::SeqCst) {
+                eprintln!("\nInterrupted again; exiting now");
+                std::process::exit(130);
+            }
+            eprintln!("\nInterrupted: finishing in-flight requests (Ctrl-C again to quit now)...");
+            kill_children();
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut term) = signal(SignalKind::terminate()) else {
+        let _ = tokio::signal::ctrl_c().await;
+        return;
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = term.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Error out if the run was cancelled; call before starting new work.
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        return Err(anyhow!("Cancelled"));
+    }
+    Ok(())
+}
+
+pub fn set_resume_hint(hint: String) {
+    *RESUME_HINT.lock().unwrap() = Some(hint);
+}
+
+pub fn resume_hint() -> Option<String> {
+    RESUME_HINT.lock().unwrap().clone()
+}
+
+/// Like `Command::status`, but the child is killed if the run is cancelled.
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    if is_cancelled() {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+    let child = cmd.spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().insert(pid, child);
+    loop {
+        let exited = {
+            let mut children = CHILDREN.lock().unwrap();
+            match children.get_mut(&pid) {
+                Some(child) => child.try_wait()?,
+                None => return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled")),
+            }
+        };
+        if let Some(status) = exited {
+            CHILDREN.lock().unwrap().remove(&pid);
+            return Ok(status);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn kill_children() {
+    for (_, mut child) in CHILDREN.lock().unwrap().drain() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_runs_child() {
+        let mut cmd = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.args(["/C", "exit 3"]);
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.args(["-c", "exit 3"]);
+            c
+        };
+        let status = status(&mut cmd).unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(CHILDREN.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+//! `--record` / `--replay`: a cassette of OpenAI interactions. Recording
+//! keeps every successful transcription and chat response with the request
+//! it answered; replaying serves them back instead of calling the API, so a
+//! run can be redone offline (new fonts, layout, output formats) without an
+//! API key or credits, and a bug report can carry the exact responses that
+//! produced it.
+//!
+//! Requests are matched by content: the chat payload as sent, and for
+//! transcriptions the model, language hint, and a hash of the audio. Equal
+//! requests are answered in recorded order. A request the cassette does not
+//! hold stops the run, since the options or input changed since recording.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    endpoint: String,
+    request: serde_json::Value,
+    /// Parsed when it is JSON, so cassettes stay readable
+    response: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct File {
+    version: u32,
+    interactions: Vec<Interaction>,
+}
+
+enum Mode {
+    /// Written to the path when the client is dropped
+    Record {
+        path: PathBuf,
+        interactions: Mutex<Vec<Interaction>>,
+    },
+    /// Response bodies by request, in recorded order
+    Replay(Mutex<HashMap<String, VecDeque<String>>>),
+}
+
+pub struct Cassette(Mode);
+
+impl Cassette {
+    pub fn record(path: &Path) -> Self {
+        Cassette(Mode::Record {
+            path: path.to_path_buf(),
+            interactions: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn replay(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Read cassette {}", path.display()))?;
+        let file: File = serde_json::from_str(&text)
+            .with_context(|| format!("Parse cassette {}", path.display()))?;
+        if file.version != VERSION {
+            return Err(anyhow!(
+                "{} is a version {} cassette; this build reads version {VERSION}",
+                path.display(),
+                file.version
+            ));
+        }
+        let mut index: HashMap<String, VecDeque<String>> = HashMap::new();
+        for i in file.interactions {
+            let body = match i.response {
+                serde_json::Value::String(s) => s,
+                v => v.to_string(),
+            };
+            index
+                .entry(key(&i.endpoint, &i.request))
+                .or_default()
+                .push_back(body);
+        }
+        Ok(Cassette(Mode::Replay(Mutex::new(index))))
+    }
+
+    pub fn replaying(&self) -> bool {
+        matches!(self.0, Mode::Replay(_))
+    }
+
+    /// The recorded response to `request`; the last one again once equal
+    /// requests outnumber the recording.
+    pub fn play(&self, endpoint: &str, request: &serde_json::Value) -> Option<String> {
+        let Mode::Replay(index) = &self.0 else {
+            return None;
+        };
+        let mut index = index.lock().unwrap();
+        let bodies = index.get_mut(&key(endpoint, request))?;
+        if bodies.len() > 1 {
+            bodies.pop_front()
+        } else {
+            bodies.front().cloned()
+        }
+    }
+
+    /// Keep a successful response when recording.
+    pub fn keep(&self, endpoint: &str, request: &serde_json::Value, body: &str) {
+        if let Mode::Record { interactions, .. } = &self.0 {
+            interactions.lock().unwrap().push(Interaction {
+                endpoint: endpoint.to_string(),
+                request: request.clone(),
+                response: serde_json::from_str(body)
+                    .unwrap_or_else(|_| serde_json::Value::String(body.to_string())),
+            });
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Mode::Record { path, interactions } = &self.0 else {
+            return Ok(());
+        };
+        let file = File {
+            version: VERSION,
+            interactions: std::mem::take(&mut *interactions.lock().unwrap()),
+        };
+        let text = serde_json::to_string_pretty(&file)?;
+        std::fs::write(path, text).with_context(|| format!("Write cassette {}", path.display()))?;
+        eprintln!(
+            "Recorded {} API response(s) to {}",
+            file.interactions.len(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            eprintln!("Warning: {e:#}");
+        }
+    }
+}
+
+fn key(endpoint: &str, request: &serde_json::Value) -> String {
+    format!("{endpoint} {request}")
+}
+
+/// FNV-1a of a file's contents: stable across builds, unlike std's hasher.
+pub fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &b in &buf[..n] {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    Ok(format!("{hash:016x}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let request =
+            json!({"model": "m", "messages": [{"role": "user", "content": "こんにちは"}]});
+        {
+            let cassette = Cassette::record(&path);
+            cassette.keep(
+                "chat",
+                &request,
+                r#"{"choices":[{"message":{"content":"你好"}}]}"#,
+            );
+            cassette.keep("chat", &request, "second");
+        }
+        let cassette = Cassette::replay(&path).unwrap();
+        assert!(cassette.replaying());
+        let first = cassette.play("chat", &request).unwrap();
+        assert!(first.contains("你好"));
+        assert_eq!(cassette.play("chat", &request).as_deref(), Some("second"));
+        assert_eq!(cassette.play("chat", &request).as_deref(), Some("second"));
+        assert_eq!(cassette.play("chat", &json!({"model": "other"})), None);
+        std::fs::write(&path, "a").unwrap();
+        assert_eq!(file_hash(&path).unwrap(), "af63dc4c8601ec8c");
+    }
+}
@@ -0,0 +1,176 @@
+//! `--split-by-chapter`: one SRT, and one burned-in MP4 when `--output` is
+//! set, per chapter of the source, each timed from zero, for publishing a
+//! long recording as a series of episodes.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{cancel, WhisperSegment};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+impl Chapter {
+    /// A cue's times clipped to the chapter and made relative to its start;
+    /// `None` if the cue lies outside it.
+    pub fn clip(&self, start: f64, end: f64) -> Option<(f64, f64)> {
+        let (start, end) = (start.max(self.start), end.min(self.end));
+        (end > start).then_some((start - self.start, end - self.start))
+    }
+}
+
+pub fn probe(input: &Path) -> Result<Vec<Chapter>> {
+    let out = Command::new("ffprobe")
+        .args(["-v", "error", "-show_chapters", "-of", "json"])
+        .arg(input)
+        .output()
+        .context("ffprobe is required to read chapters")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    parse_probe_json(&String::from_utf8_lossy(&out.stdout))
+}
+
+fn parse_probe_json(s: &str) -> Result<Vec<Chapter>> {
+    let v: serde_json::Value = serde_json::from_str(s).context("Parse ffprobe JSON")?;
+    let chapters = v["chapters"].as_array().cloned().unwrap_or_default();
+    // ffprobe prints times as strings
+    let time = |t: &serde_json::Value| t.as_str()?.parse::<f64>().ok();
+    Ok(chapters
+        .iter()
+        .filter_map(|ch| {
+            Some(Chapter {
+                start: time(&ch["start_time"])?,
+                end: time(&ch["end_time"])?,
+                title: ch["tags"]["title"].as_str().map(str::to_string),
+            })
+        })
+        .filter(|ch| ch.end > ch.start)
+        .collect())
+}
+
+/// The cues of `chapter`, clipped to it and shifted to start at zero, with
+/// their lines.
+pub fn slice(
+    segments: &[WhisperSegment],
+    lines: &[String],
+    chapter: &Chapter,
+) -> (Vec<WhisperSegment>, Vec<String>) {
+    segments
+        .iter()
+        .zip(lines)
+        .filter_map(|(s, line)| {
+            let (start, end) = chapter.clip(s.start, s.end)?;
+            let seg = WhisperSegment {
+                start,
+                end,
+                ..s.clone()
+            };
+            Some((seg, line.clone()))
+        })
+        .unzip()
+}
+
+/// `path` with `.chNN` before its extension (and before a `.zh-TW` tag).
+pub fn path(path: &Path, n: usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let split = name
+        .find(".zh-TW.")
+        .or_else(|| name.rfind('.'))
+        .unwrap_or(name.len());
+    let (stem, ext) = name.split_at(split);
+    path.with_file_name(format!("{stem}.ch{n:02}{ext}"))
+}
+
+/// ffmpeg arguments that cut `chapter` from `input` and burn `filter` into
+/// it; timestamps restart at zero, matching the sliced cues.
+pub fn burn_in_args(
+    input: &Path,
+    chapter: &Chapter,
+    filter: &str,
+    out: &Path,
+    extra: &[String],
+) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y"].map(String::from).into();
+    args.extend([
+        "-ss".to_string(),
+        format!("{:.3}", chapter.start),
+        "-to".to_string(),
+        format!("{:.3}", chapter.end),
+        "-i".to_string(),
+        input.to_string_lossy().into_owned(),
+    ]);
+    args.extend(["-vf", filter, "-c:a", "copy"].map(String::from));
+    args.extend_from_slice(extra);
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+pub fn burn_in(
+    input: &Path,
+    chapter: &Chapter,
+    filter: &str,
+    out: &Path,
+    extra: &[String],
+    nice: bool,
+) -> Result<()> {
+    let status = cancel::status(
+        crate::encode_command(nice).args(burn_in_args(input, chapter, filter, out, extra)),
+    )
+    .context("ffmpeg chapter burn-in failed")?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg burn-in failed for {}", out.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_parse_and_slice() {
+        let json = r#"{"chapters":[
+            {"id":0,"start_time":"0.000000","end_time":"60.000000","tags":{"title":"OP"}},
+            {"id":1,"start_time":"60.000000","end_time":"300.500000"}
+        ]}"#;
+        let chapters = parse_probe_json(json).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title.as_deref(), Some("OP"));
+        let segments = [
+            seg(10.0, 12.0, ""),
+            seg(59.0, 61.0, ""),
+            seg(70.0, 72.0, ""),
+        ];
+        let lines = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let (segs, texts) = slice(&segments, &lines, &chapters[1]);
+        // The cue straddling the boundary is clipped into both chapters
+        assert_eq!(texts, ["b", "c"]);
+        assert_eq!((segs[0].start, segs[0].end), (0.0, 1.0));
+        assert_eq!((segs[1].start, segs[1].end), (10.0, 12.0));
+    }
+
+    #[test]
+    fn test_chapter_path() {
+        assert_eq!(
+            path(Path::new("/v/ep.zh-TW.srt"), 3),
+            Path::new("/v/ep.ch03.zh-TW.srt")
+        );
+        assert_eq!(
+            path(Path::new("/v/my.show_subbed.mp4"), 12),
+            Path::new("/v/my.show_subbed.ch12.mp4")
+        );
+    }
+}
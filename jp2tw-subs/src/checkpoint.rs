@@ -0,0 +1,283 @@
+//! Run checkpoint (`<name>.state.json` next to the SRT): transcribed segments
+//! and translations per chunk, saved as each one finishes. An interrupted or
+//! failed run picks them up on the next invocation with the same settings
+//! instead of paying for them again; the file is removed after success.
+//! Chunks or batches that still failed after retries are recorded too, so
+//! `--repair` can retry just those. `--batch-api` jobs still running are
+//! recorded by id, to be collected on the next run.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{cancel, skip, Args, WhisperSegment};
+
+/// Settings that make saved work reusable; any change starts over.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunKey {
+    input: String,
+    input_bytes: u64,
+    source: String,
+    chunk_seconds: u32,
+    whisper_model: String,
+    translate_model: String,
+    mode: String,
+    /// `--pivot` language and model, empty for direct translation
+    #[serde(default)]
+    pivot: String,
+    /// `--skip-range` windows, which change what each chunk uploads
+    #[serde(default)]
+    skip: String,
+}
+
+impl RunKey {
+    /// `source` names where the Japanese lines come from (audio, embedded, ocr).
+    pub fn from_args(args: &Args, source: &str) -> Self {
+        Self {
+            input: args.input().to_string_lossy().into_owned(),
+            input_bytes: std::fs::metadata(args.input())
+                .map(|m| m.len())
+                .unwrap_or(0),
+            source: source.to_string(),
+            chunk_seconds: args.chunk_seconds,
+            whisper_model: args.whisper_model.clone(),
+            translate_model: args.translate_model.clone(),
+            mode: format!("{:?}", args.mode).to_lowercase(),
+            pivot: args
+                .pivot
+                .as_ref()
+                .map(|p| format!("{p}:{}", args.pivot_model()))
+                .unwrap_or_default(),
+            skip: skip::key(&args.skip_range),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ChunkState {
+    /// Raw Whisper output, before non-speech/SFX filtering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segments: Option<Vec<WhisperSegment>>,
+    /// Source lines the translations were made for
+    #[serde(default)]
+    ja: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zh: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transcribe_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translate_error: Option<String>,
+}
+
+/// Pipeline stage a failure belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Transcribe,
+    Translate,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    key: RunKey,
+    chunks: BTreeMap<usize, ChunkState>,
+    /// Submitted Batch API jobs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    batches: Vec<String>,
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Checkpoint {
+    /// Load saved work from `path` if it was made with the same settings.
+    pub fn open(path: &Path, key: RunKey) -> Result<Self> {
+        let mut state = State {
+            key: key.clone(),
+            ..Default::default()
+        };
+        if let Ok(text) = std::fs::read_to_string(path) {
+            match serde_json::from_str::<State>(&text) {
+                Ok(saved) if saved.key == key => {
+                    let transcribed = saved.chunks.values().filter(|c| c.segments.is_some());
+                    let translated = saved.chunks.values().filter(|c| c.zh.is_some());
+                    eprintln!(
+                        "Resuming from {}: {} chunks transcribed, {} translated",
+                        path.display(),
+                        transcribed.count(),
+                        translated.count()
+                    );
+                    state = saved;
+                }
+                _ => eprintln!(
+                    "Ignoring {} (made with different settings); starting over",
+                    path.display()
+                ),
+            }
+        }
+        cancel::set_resume_hint(format!(
+            "Finished chunks are saved in {}; rerun the same command to resume.",
+            path.display()
+        ));
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn segments(&self, chunk: usize) -> Option<Vec<WhisperSegment>> {
+        let state = self.state.lock().unwrap();
+        state.chunks.get(&chunk)?.segments.clone()
+    }
+
+    pub fn record_segments(&self, chunk: usize, segments: &[WhisperSegment]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let c = state.chunks.entry(chunk).or_default();
+        c.segments = Some(segments.to_vec());
+        c.transcribe_error = None;
+        self.save(&state)
+    }
+
+    /// Saved translations for `chunk`, if they were made for exactly `ja`.
+    pub fn translations(&self, chunk: usize, ja: &[String]) -> Option<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let c = state.chunks.get(&chunk)?;
+        c.zh.clone().filter(|zh| c.ja == ja && zh.len() == ja.len())
+    }
+
+    pub fn record_translations(&self, chunk: usize, ja: &[String], zh: &[String]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let c = state.chunks.entry(chunk).or_default();
+        c.ja = ja.to_vec();
+        c.zh = Some(zh.to_vec());
+        c.translate_error = None;
+        self.save(&state)
+    }
+
+    /// Remember that `chunk` failed at `stage` after all retries.
+    pub fn record_failure(&self, chunk: usize, stage: Stage, error: &anyhow::Error) -> Result<()> {
+        eprintln!(
+            "Warning: chunk {} failed to {}: {error:#}",
+            chunk + 1,
+            match stage {
+                Stage::Transcribe => "transcribe",
+                Stage::Translate => "translate",
+            }
+        );
+        let mut state = self.state.lock().unwrap();
+        let c = state.chunks.entry(chunk).or_default();
+        let slot = match stage {
+            Stage::Transcribe => &mut c.transcribe_error,
+            Stage::Translate => &mut c.translate_error,
+        };
+        *slot = Some(format!("{error:#}"));
+        self.save(&state)
+    }
+
+    /// Ids of submitted `--batch-api` jobs.
+    pub fn batches(&self) -> Vec<String> {
+        self.state.lock().unwrap().batches.clone()
+    }
+
+    pub fn record_batch(&self, id: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.batches.push(id.to_string());
+        self.save(&state)
+    }
+
+    /// Stop tracking a job that failed or expired; its requests are queued
+    /// again.
+    pub fn forget_batch(&self, id: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.batches.retain(|b| b != id);
+        self.save(&state)
+    }
+
+    /// Failed chunk transcriptions plus failed translation batches.
+    pub fn failure_count(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state
+            .chunks
+            .values()
+            .map(|c| {
+                usize::from(c.transcribe_error.is_some()) + usize::from(c.translate_error.is_some())
+            })
+            .sum()
+    }
+
+    /// The run completed; saved work is no longer needed.
+    pub fn finish(self) -> Result<()> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)
+                .with_context(|| format!("Remove {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, state: &State) -> Result<()> {
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".part");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, serde_json::to_string(state)?)
+            .with_context(|| format!("Write checkpoint {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Move {} into place", tmp.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(model: &str) -> RunKey {
+        RunKey {
+            input: "in.mp4".into(),
+            translate_model: model.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resume_with_same_settings_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in.state.json");
+        let ja = vec!["はい".to_string()];
+        let cp = Checkpoint::open(&path, key("a")).unwrap();
+        cp.record_segments(
+            0,
+            &[WhisperSegment {
+                text: "はい".into(),
+                ..Default::default()
+            }],
+        )
+        .unwrap();
+        cp.record_translations(0, &ja, &["好".to_string()]).unwrap();
+
+        let resumed = Checkpoint::open(&path, key("a")).unwrap();
+        assert_eq!(resumed.segments(0).unwrap()[0].text, "はい");
+        assert_eq!(resumed.translations(0, &ja), Some(vec!["好".to_string()]));
+        // Different source lines (e.g. changed filters) are translated again
+        assert_eq!(resumed.translations(0, &["いいえ".to_string()]), None);
+        assert!(resumed.segments(1).is_none());
+
+        let err = anyhow::anyhow!("server error");
+        resumed.record_failure(1, Stage::Transcribe, &err).unwrap();
+        resumed.record_failure(2, Stage::Translate, &err).unwrap();
+        assert_eq!(
+            Checkpoint::open(&path, key("a")).unwrap().failure_count(),
+            2
+        );
+        resumed
+            .record_translations(2, &ja, &["好".to_string()])
+            .unwrap();
+        assert_eq!(resumed.failure_count(), 1);
+
+        let other = Checkpoint::open(&path, key("b")).unwrap();
+        assert!(other.segments(0).is_none());
+        resumed.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
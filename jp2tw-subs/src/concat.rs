@@ -0,0 +1,70 @@
+//! Several `--input` files: multi-part recordings (cameras split long takes
+//! into files of a few GB) are joined with ffmpeg's concat demuxer, without
+//! re-encoding, and captioned as one video with continuous timing.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cancel;
+
+/// Concat demuxer list naming `inputs` in order, one `file '...'` per line.
+pub fn list(inputs: &[PathBuf]) -> Result<String> {
+    let mut out = String::new();
+    for input in inputs {
+        let path = std::fs::canonicalize(input)
+            .with_context(|| format!("Input file not found: {}", input.display()))?;
+        // A quote inside a quoted path is written as '\''
+        let quoted = path.to_string_lossy().replace('\'', r"'\''");
+        out.push_str(&format!("file '{quoted}'\n"));
+    }
+    Ok(out)
+}
+
+/// ffmpeg arguments joining the files in `list_path` into `out` by stream copy.
+pub fn ffmpeg_args(list_path: &Path, out: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-f", "concat", "-safe", "0", "-i"]
+        .map(String::from)
+        .into();
+    args.push(list_path.to_string_lossy().into_owned());
+    args.extend(["-map", "0", "-c", "copy", "-movflags", "+faststart"].map(String::from));
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+/// Join `inputs` into `work_dir/concat.mp4`. The parts must share codecs
+/// and resolution, as the parts of one recording do.
+pub fn join(inputs: &[PathBuf], work_dir: &Path) -> Result<PathBuf> {
+    let list_path = work_dir.join("concat.txt");
+    let out = work_dir.join("concat.mp4");
+    std::fs::write(&list_path, list(inputs)?)
+        .with_context(|| format!("Write {}", list_path.display()))?;
+    let status = cancel::status(Command::new("ffmpeg").args(ffmpeg_args(&list_path, &out)))
+        .context("Failed to run ffmpeg to concatenate inputs")?;
+    if !status.success() {
+        return Err(anyhow!(
+            "ffmpeg could not concatenate the inputs (they must share codecs and resolution)"
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_quotes_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("part1.mp4");
+        let b = dir.path().join("it's part2.mp4");
+        std::fs::write(&a, b"").unwrap();
+        std::fs::write(&b, b"").unwrap();
+        let text = list(&[a, b]).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("file '/") && lines[0].ends_with("/part1.mp4'"));
+        assert!(lines[1].ends_with(r"/it'\''s part2.mp4'"));
+        assert!(list(&[dir.path().join("missing.mp4")]).is_err());
+    }
+}
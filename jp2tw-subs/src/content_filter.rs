@@ -0,0 +1,124 @@
+//! Optional profanity handling for translations (`--content-filter`): mask or
+//! soften a built-in list of zh-TW profanity and slurs, extendable with a
+//! user word list.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FilterMode {
+    /// Leave translations untouched
+    Off,
+    /// Keep the first character and mask the rest (他＊＊)
+    Mask,
+    /// Replace with a milder expression; masks words without one
+    Soften,
+}
+
+/// (term, milder replacement). Only multi-character terms: single characters
+/// such as 幹 are too ambiguous (幹嘛, 幹部) to filter blindly.
+const BUILTIN: &[(&str, &str)] = &[
+    ("幹你娘", "可惡"),
+    ("他媽的", "該死的"),
+    ("她媽的", "該死的"),
+    ("你媽的", "該死的"),
+    ("媽的", "該死"),
+    ("靠北", "可惡"),
+    ("靠杯", "可惡"),
+    ("操你", "去你的"),
+    ("王八蛋", "混蛋"),
+    ("狗娘養的", "混蛋"),
+    ("婊子", "壞女人"),
+    ("賤人", "壞傢伙"),
+    ("白痴", "笨蛋"),
+    ("白癡", "笨蛋"),
+    ("智障", "笨蛋"),
+    ("去死", "滾開"),
+];
+
+pub struct ContentFilter {
+    mode: FilterMode,
+    /// Longest terms first so "他媽的" wins over "媽的"
+    terms: Vec<(String, Option<String>)>,
+}
+
+impl ContentFilter {
+    /// Built-in list plus an optional user file: one term per line, optionally
+    /// `term=replacement` for soften mode; `#` starts a comment.
+    pub fn new(mode: FilterMode, user_words: Option<&Path>) -> Result<Self> {
+        let mut terms: Vec<(String, Option<String>)> = BUILTIN
+            .iter()
+            .map(|(t, r)| (t.to_string(), Some(r.to_string())))
+            .collect();
+        if let Some(path) = user_words {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Read content filter words {}", path.display()))?;
+            terms.extend(parse_word_list(&text));
+        }
+        terms.sort_by_key(|(t, _)| std::cmp::Reverse(t.chars().count()));
+        Ok(Self { mode, terms })
+    }
+
+    pub fn apply(&self, line: &str) -> String {
+        if self.mode == FilterMode::Off {
+            return line.to_string();
+        }
+        let mut out = line.to_string();
+        for (term, replacement) in &self.terms {
+            if !out.contains(term.as_str()) {
+                continue;
+            }
+            let with = match (self.mode, replacement) {
+                (FilterMode::Soften, Some(r)) => r.clone(),
+                _ => mask(term),
+            };
+            out = out.replace(term.as_str(), &with);
+        }
+        out
+    }
+}
+
+fn parse_word_list(text: &str) -> Vec<(String, Option<String>)> {
+    text.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| match l.split_once('=') {
+            Some((t, r)) => (t.trim().to_string(), Some(r.trim().to_string())),
+            None => (l.to_string(), None),
+        })
+        .filter(|(t, _)| !t.is_empty())
+        .collect()
+}
+
+fn mask(term: &str) -> String {
+    let mut chars = term.chars();
+    let first = chars.next().map(String::from).unwrap_or_default();
+    first + &"＊".repeat(chars.count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_and_soften() {
+        let mask = ContentFilter::new(FilterMode::Mask, None).unwrap();
+        assert_eq!(mask.apply("他媽的，你這白痴"), "他＊＊，你這白＊");
+        let soften = ContentFilter::new(FilterMode::Soften, None).unwrap();
+        assert_eq!(soften.apply("他媽的，你這白痴"), "該死的，你這笨蛋");
+        let off = ContentFilter::new(FilterMode::Off, None).unwrap();
+        assert_eq!(off.apply("媽的"), "媽的");
+    }
+
+    #[test]
+    fn test_user_word_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "# extra terms\n廢物=沒用的傢伙\n垃圾人\n").unwrap();
+        let f = ContentFilter::new(FilterMode::Soften, Some(&words)).unwrap();
+        assert_eq!(f.apply("你這廢物"), "你這沒用的傢伙");
+        // No replacement given: soften falls back to masking
+        assert_eq!(f.apply("垃圾人"), "垃＊＊");
+    }
+}
@@ -0,0 +1,294 @@
+//! `doctor` subcommand: check the environment a run depends on and say how
+//! to fix what is missing. Most failed runs are environment problems: no
+//! ffmpeg, an ffmpeg built without libass (no burn-in), no Traditional
+//! Chinese font, a missing or revoked API key, a proxy blocking the API, or
+//! a full temp disk. Exits non-zero when a check fails.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use crate::openai::{ApiError, OpenAiClient};
+use crate::{fonts, Args};
+
+/// Below this much free space in the work directory is a warning
+const LOW_DISK_BYTES: u64 = 2 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warn,
+            fix: Some(fix.into()),
+            ..Self::ok(name, detail)
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            ..Self::warn(name, detail, fix)
+        }
+    }
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `6.1.1-3ubuntu5` from `ffmpeg -version`.
+fn ffmpeg_version(version: &str) -> Option<&str> {
+    version
+        .lines()
+        .next()?
+        .strip_prefix("ffmpeg version ")?
+        .split_whitespace()
+        .next()
+}
+
+/// Whether `ffmpeg -filters` lists the libass `subtitles` filter.
+fn has_libass(filters: &str) -> bool {
+    filters
+        .lines()
+        .any(|l| l.split_whitespace().nth(1) == Some("subtitles"))
+}
+
+/// Available bytes from `df -Pk` output.
+fn df_available(df: &str) -> Option<u64> {
+    let kb: u64 = df.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+fn check_ffmpeg(checks: &mut Vec<Check>) {
+    let fix = "install ffmpeg (brew install ffmpeg, apt install ffmpeg, or choco install ffmpeg) and reopen the terminal";
+    let Some(version) = output("ffmpeg", &["-version"]) else {
+        // Linked libav extracts and burns in; only some options need the binary
+        checks.push(if cfg!(feature = "native-ffmpeg") {
+            Check::warn("ffmpeg", "not found in PATH; options that run the ffmpeg binary (--hls-dir, --split-by-chapter, --live, ...) will fail", fix)
+        } else {
+            Check::fail("ffmpeg", "not found in PATH", fix)
+        });
+        return;
+    };
+    checks.push(Check::ok(
+        "ffmpeg",
+        ffmpeg_version(&version).unwrap_or("unknown version"),
+    ));
+    if output("ffprobe", &["-version"]).is_none() {
+        checks.push(Check::warn(
+            "ffprobe",
+            "not found in PATH",
+            "install the full ffmpeg package; ffprobe is needed for chapters, embedded subtitles, verification, and timeline exports",
+        ));
+    }
+    match output("ffmpeg", &["-hide_banner", "-filters"]) {
+        Some(filters) if has_libass(&filters) => {
+            checks.push(Check::ok("libass", "subtitles filter available"))
+        }
+        _ => checks.push(Check::fail(
+            "libass",
+            "ffmpeg has no subtitles filter, so burn-in (--output) fails",
+            "install an ffmpeg built with --enable-libass (the brew, apt, and gyan.dev builds are)",
+        )),
+    }
+}
+
+fn check_fonts(args: &Args, checks: &mut Vec<Check>) {
+    let install = "install Noto Sans CJK TC (apt install fonts-noto-cjk, brew install --cask font-noto-sans-cjk-tc) or copy a Traditional Chinese font into ./fonts";
+    let dirs = fonts::search_dirs(args.font_dir.as_deref());
+    let faces = fonts::scan(&dirs);
+    let families: HashSet<&str> = faces
+        .iter()
+        .filter(|f| f.traditional)
+        .map(|f| f.family.as_str())
+        .collect();
+    if families.is_empty() {
+        checks.push(Check::warn(
+            "fonts",
+            format!("no Traditional Chinese font in {} font(s)", faces.len()),
+            install,
+        ));
+    } else {
+        checks.push(Check::ok(
+            "fonts",
+            format!("{} Traditional Chinese famil(ies)", families.len()),
+        ));
+    }
+    let name = args.font_name.as_deref().unwrap_or(fonts::DEFAULT_FONT);
+    match fonts::find(&faces, name) {
+        Some(face) if face.traditional => checks.push(Check::ok(
+            "font",
+            format!("{name}: {}", face.path.display()),
+        )),
+        Some(face) => checks.push(Check::warn(
+            "font",
+            format!(
+                "{name} ({}) has no Traditional Chinese glyphs",
+                face.path.display()
+            ),
+            "pick a family listed by the fonts subcommand with --font-name",
+        )),
+        None => checks.push(Check::warn(
+            "font",
+            format!("{name} not found; libass will substitute, possibly without Chinese glyphs"),
+            format!("{install}, or pick a family listed by the fonts subcommand with --font-name"),
+        )),
+    }
+}
+
+async fn check_api(args: &Args, checks: &mut Vec<Check>) {
+    let key = env::var("OPENAI_API_KEY").ok().filter(|k| !k.is_empty());
+    if key.is_none() && args.api_base_url.is_none() {
+        checks.push(Check::fail(
+            "API key",
+            "OPENAI_API_KEY is not set",
+            "export OPENAI_API_KEY=sk-... or put OPENAI_API_KEY=sk-... in a .env file in the working directory",
+        ));
+    }
+    let mut client = OpenAiClient::new(key.clone().unwrap_or_default());
+    if let Some(url) = &args.api_base_url {
+        client = client.with_base_url(url);
+    }
+    let server = args.api_base_url.as_deref().unwrap_or("api.openai.com");
+    match client.models().await {
+        Ok(models) => {
+            checks.push(Check::ok("network", format!("{server} reachable")));
+            let detail = match key {
+                Some(_) => format!("accepted ({} models)", models.len()),
+                None => format!("not required by {server}"),
+            };
+            checks.push(Check::ok("API key", detail));
+            for model in [&args.whisper_model, &args.translate_model] {
+                if !models.is_empty() && !models.contains(model) {
+                    checks.push(Check::warn(
+                        "model",
+                        format!("{model} is not available to this key"),
+                        "check the model name, or the project's model access in the OpenAI dashboard",
+                    ));
+                }
+            }
+        }
+        Err(ApiError::Transport(e)) => checks.push(Check::fail(
+            "network",
+            format!("cannot reach {server}: {e}"),
+            "check the connection, firewall, and HTTPS_PROXY; raise --http-connect-timeout on slow networks",
+        )),
+        Err(ApiError::Auth { status, message }) => {
+            checks.push(Check::ok("network", format!("{server} reachable")));
+            if key.is_some() {
+                checks.push(Check::fail(
+                    "API key",
+                    format!("rejected ({status}): {message}"),
+                    "create a new key at https://platform.openai.com/api-keys; keys of deleted projects stop working",
+                ));
+            }
+        }
+        Err(e) => {
+            checks.push(Check::ok("network", format!("{server} reachable")));
+            checks.push(Check::warn(
+                "API key",
+                e.to_string(),
+                "see the OpenAI status page and the billing settings of the key's project",
+            ));
+        }
+    }
+}
+
+fn check_disk(args: &Args, checks: &mut Vec<Check>) {
+    let dir = args.work_dir.clone().unwrap_or_else(env::temp_dir);
+    let probe = if dir.exists() {
+        dir.as_path()
+    } else {
+        Path::new(".")
+    };
+    let available = if cfg!(windows) {
+        None
+    } else {
+        output("df", &["-Pk", &probe.to_string_lossy()]).and_then(|df| df_available(&df))
+    };
+    match available {
+        Some(bytes) if bytes < LOW_DISK_BYTES => checks.push(Check::warn(
+            "disk",
+            format!("{:.1} GB free in {}", bytes as f64 / 1e9, dir.display()),
+            "free some space or point --work-dir (or TMPDIR) at a larger disk; audio, chunks, and burned-in video are written there",
+        )),
+        Some(bytes) => checks.push(Check::ok(
+            "disk",
+            format!("{:.1} GB free in {}", bytes as f64 / 1e9, dir.display()),
+        )),
+        None => {}
+    }
+}
+
+/// Run every check and print the results; true when none failed.
+pub async fn run(args: &Args) -> Result<bool> {
+    let _ = dotenvy::dotenv();
+    let mut checks = Vec::new();
+    check_ffmpeg(&mut checks);
+    check_fonts(args, &mut checks);
+    check_api(args, &mut checks).await;
+    check_disk(args, &mut checks);
+    for check in &checks {
+        let mark = match check.status {
+            Status::Ok => "ok  ",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!("[{mark}] {}: {}", check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("       fix: {fix}");
+        }
+    }
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!("{failed} problem(s), {warned} warning(s)");
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_output() {
+        assert_eq!(
+            ffmpeg_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023\nbuilt with gcc"),
+            Some("6.1.1-3ubuntu5")
+        );
+        assert_eq!(ffmpeg_version("garbage"), None);
+        let filters = " ... ass               V->V       Render ASS subtitles onto input video using the libass library.\n ... subtitles         V->V       Render text subtitles onto input video using the libass library.\n";
+        assert!(has_libass(filters));
+        assert!(!has_libass(
+            " ... scale             V->V       Scale the input video size"
+        ));
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/sda1        102400000  51200000  1048576      98% /\n";
+        assert_eq!(df_available(df), Some(1 << 30));
+    }
+}
@@ -0,0 +1,150 @@
+//! Plain HTTP(S) `--input` URLs: the file is downloaded into the work dir
+//! before processing, resuming with a Range request when the connection
+//! drops. Outputs are named after the URL's file name, in the current
+//! directory.
+
+use anyhow::{anyhow, Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{header, StatusCode};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{cancel, http};
+
+/// Connection attempts before a download gives up.
+const ATTEMPTS: u32 = 5;
+
+/// An HTTP error status; client errors other than timeouts are not retried.
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP {0}")]
+struct HttpStatus(StatusCode);
+
+pub fn is_url(input: &Path) -> bool {
+    let s = input.to_string_lossy().to_ascii_lowercase();
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Where a URL input would be if it were local: its file name in the current
+/// directory. Other inputs are returned as they are.
+pub fn local_name(input: &Path) -> PathBuf {
+    if !is_url(input) {
+        return input.to_path_buf();
+    }
+    let s = input.to_string_lossy();
+    let path = s.split(['?', '#']).next().unwrap_or_default();
+    let (_, rest) = path.split_once("://").unwrap_or_default();
+    match rest
+        .split_once('/')
+        .map(|(_, p)| p.rsplit('/').next().unwrap_or_default())
+    {
+        Some(name) if !name.is_empty() => PathBuf::from(name),
+        _ => PathBuf::from("download.mp4"),
+    }
+}
+
+/// Download `url` into `work_dir`, resuming after dropped connections. A
+/// complete earlier download in `work_dir` is reused.
+pub async fn fetch(url: &str, work_dir: &Path) -> Result<PathBuf> {
+    let name = local_name(Path::new(url));
+    let out = work_dir.join(&name);
+    if out.exists() {
+        eprintln!("Using earlier download {}", out.display());
+        return Ok(out);
+    }
+    let part = work_dir.join(format!("{}.part", name.display()));
+    let client = http::client();
+    let mut attempt = 1;
+    loop {
+        match download(&client, url, &part).await {
+            Ok(()) => break,
+            Err(e) if attempt < ATTEMPTS && retryable(&e) && !cancel::is_cancelled() => {
+                eprintln!("Download interrupted ({e:#}); resuming (attempt {attempt}/{ATTEMPTS})");
+                tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e).with_context(|| format!("Download {url}")),
+        }
+    }
+    std::fs::rename(&part, &out).with_context(|| format!("Rename {}", part.display()))?;
+    Ok(out)
+}
+
+fn retryable(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<HttpStatus>() {
+        Some(HttpStatus(s)) => !s.is_client_error() || *s == StatusCode::REQUEST_TIMEOUT,
+        None => true,
+    }
+}
+
+/// One attempt: continue `part` from where it ends, or start it over when
+/// the server ignores the range.
+async fn download(client: &reqwest::Client, url: &str, part: &Path) -> Result<()> {
+    let have = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    let mut req = client.get(url);
+    if have > 0 {
+        req = req.header(header::RANGE, format!("bytes={have}-"));
+    }
+    let mut resp = req.send().await?;
+    let status = resp.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && have > 0 {
+        // Nothing past the end: the last attempt got everything
+        return Ok(());
+    }
+    if !status.is_success() {
+        return Err(HttpStatus(status).into());
+    }
+    let resumed = status == StatusCode::PARTIAL_CONTENT;
+    let mut done = if resumed { have } else { 0 };
+    let total = resp.content_length().map(|n| n + done);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(resumed)
+        .write(true)
+        .truncate(!resumed)
+        .open(part)
+        .with_context(|| format!("Open {}", part.display()))?;
+
+    let progress = ProgressBar::new(total.unwrap_or(0));
+    progress.set_style(
+        ProgressStyle::with_template("Downloading {bytes}/{total_bytes} ({bytes_per_sec})")
+            .unwrap(),
+    );
+    progress.set_position(done);
+    while let Some(chunk) = resp.chunk().await? {
+        cancel::check()?;
+        file.write_all(&chunk)?;
+        done += chunk.len() as u64;
+        progress.set_position(done);
+    }
+    progress.finish_and_clear();
+    match total {
+        Some(total) if done < total => {
+            Err(anyhow!("connection closed after {done} of {total} bytes"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_names() {
+        assert!(is_url(Path::new("HTTPS://example.com/a.mp4")));
+        assert!(!is_url(Path::new("/videos/a.mp4")));
+        assert_eq!(
+            local_name(Path::new("https://cdn.example.com/shows/ep01.mp4?token=x")),
+            Path::new("ep01.mp4")
+        );
+        assert_eq!(
+            local_name(Path::new("https://example.com/")),
+            Path::new("download.mp4")
+        );
+        assert_eq!(
+            local_name(Path::new("/v/ep01.mp4")),
+            Path::new("/v/ep01.mp4")
+        );
+    }
+}
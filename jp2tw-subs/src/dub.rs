@@ -0,0 +1,244 @@
+//! `--dub`: a zh-TW voice track. Each translated line is synthesized with
+//! OpenAI text-to-speech and placed at its cue. A line longer than the time
+//! it has (its cue, plus up to a second of the gap before the next one) is
+//! sped up without changing pitch, at most 1.5×, and cut off with a short
+//! fade if even that does not fit. The track is muxed into the burned-in
+//! video as an extra audio stream, or written as `<name>.dub.wav`.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::openai::{OpenAiClient, SpeechRequest};
+use crate::{cancel, verify, wav, WhisperSegment};
+
+/// Sample rate of the speech endpoint's raw PCM
+pub const SAMPLE_RATE: u32 = 24_000;
+const MAX_SPEEDUP: f64 = 1.5;
+/// Seconds a line may run past its cue into a gap
+const MAX_SPILL: f64 = 1.0;
+const FADE_SECONDS: f64 = 0.02;
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub lines: usize,
+    pub sped_up: usize,
+    pub cut: usize,
+}
+
+/// `line` as it should be read aloud: no sound-effect labels, music notes,
+/// or line breaks.
+pub fn speakable(line: &str) -> String {
+    let mut out = String::new();
+    let mut depth = 0usize;
+    for c in line.chars() {
+        match c {
+            '[' | '【' | '(' | '（' => depth += 1,
+            ']' | '】' | ')' | '）' => depth = depth.saturating_sub(1),
+            '♪' | '♫' => {}
+            '\n' if depth == 0 => out.push('，'),
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().trim_matches('，').trim().to_string()
+}
+
+/// `--dub-model`, `--dub-voice`, and the length of the track.
+pub struct Voice<'a> {
+    pub model: &'a str,
+    pub voice: &'a str,
+    pub duration: f64,
+}
+
+/// Synthesize `zh_lines` into a mono WAV at `out`; cues marked in `skip`
+/// (sung lines) stay silent.
+pub async fn synthesize(
+    client: &OpenAiClient,
+    voice: &Voice<'_>,
+    segments: &[WhisperSegment],
+    zh_lines: &[String],
+    skip: &[bool],
+    out: &Path,
+    progress: impl Fn(usize, usize),
+) -> Result<Stats> {
+    let duration = voice.duration;
+    let rate = f64::from(SAMPLE_RATE);
+    let mut track = vec![0i32; (duration * rate).ceil() as usize];
+    let mut stats = Stats::default();
+    for (i, (seg, zh)) in segments.iter().zip(zh_lines).enumerate() {
+        progress(i + 1, segments.len());
+        let text = speakable(zh);
+        if skip.get(i).copied().unwrap_or(false) || !text.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        let pcm = client
+            .speech(&SpeechRequest::new(voice.model, voice.voice, &text))
+            .await
+            .with_context(|| format!("Text-to-speech for cue {} failed", i + 1))?;
+        let clip: Vec<i16> = pcm
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        let next = segments.get(i + 1).map_or(duration, |n| n.start);
+        let room = next.min(seg.end + MAX_SPILL).max(seg.end) - seg.start;
+        let (clip, sped_up, cut) = fit(clip, (room * rate) as usize);
+        stats.lines += 1;
+        stats.sped_up += usize::from(sped_up);
+        stats.cut += usize::from(cut);
+        let at = (seg.start * rate) as usize;
+        for (slot, s) in track.iter_mut().skip(at).zip(clip) {
+            *slot += i32::from(s);
+        }
+    }
+    let samples: Vec<i16> = track
+        .into_iter()
+        .map(|s| s.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16)
+        .collect();
+    write_wav(out, &samples)?;
+    Ok(stats)
+}
+
+/// `clip` squeezed into `room` samples: sped up as far as allowed, then
+/// cut with a fade. Also says whether it was sped up and whether cut.
+fn fit(clip: Vec<i16>, room: usize) -> (Vec<i16>, bool, bool) {
+    if clip.len() <= room || room == 0 {
+        return (clip, false, false);
+    }
+    let factor = (clip.len() as f64 / room as f64).min(MAX_SPEEDUP);
+    let mut clip = stretch(&clip, factor);
+    if clip.len() <= room {
+        return (clip, true, false);
+    }
+    clip.truncate(room);
+    let fade = ((FADE_SECONDS * f64::from(SAMPLE_RATE)) as usize).min(room);
+    for (k, s) in clip[room - fade..].iter_mut().enumerate() {
+        *s = (f64::from(*s) * (1.0 - (k + 1) as f64 / fade as f64)) as i16;
+    }
+    (clip, true, true)
+}
+
+/// Play `samples` `factor` times faster at the same pitch: WSOLA, which
+/// overlap-adds windowed frames taken further apart than they are laid
+/// down, each nudged to where it best continues the previous one.
+fn stretch(samples: &[i16], factor: f64) -> Vec<i16> {
+    const FRAME: usize = 960; // 40 ms
+    const HOP: usize = FRAME / 2;
+    const SEARCH: usize = 240; // ±10 ms
+    let input: Vec<f64> = samples.iter().map(|&s| f64::from(s)).collect();
+    let window: Vec<f64> = (0..FRAME)
+        .map(|n| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * n as f64 / FRAME as f64).cos())
+        .collect();
+    let frames = ((input.len() as f64 / factor) as usize).div_ceil(HOP);
+    let mut out = vec![0.0; frames * HOP + FRAME];
+    let frame_at = |start: usize, k: usize| input.get(start + k).copied().unwrap_or(0.0);
+    // Where the last frame placed would naturally continue
+    let mut follow = 0usize;
+    for k in 0..frames {
+        let nominal = (k as f64 * HOP as f64 * factor) as usize;
+        let start = if k == 0 {
+            0
+        } else {
+            let lo = nominal.saturating_sub(SEARCH);
+            (lo..=nominal + SEARCH)
+                .max_by(|&a, &b| {
+                    let score = |s: usize| -> f64 {
+                        (0..HOP)
+                            .step_by(4)
+                            .map(|n| frame_at(s, n) * frame_at(follow, n))
+                            .sum()
+                    };
+                    score(a).total_cmp(&score(b))
+                })
+                .unwrap_or(nominal)
+        };
+        for n in 0..FRAME {
+            out[k * HOP + n] += frame_at(start, n) * window[n];
+        }
+        follow = start + HOP;
+    }
+    out.truncate((input.len() as f64 / factor).round() as usize);
+    out.into_iter()
+        .map(|s| s.round().clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16)
+        .collect()
+}
+
+fn write_wav(path: &Path, samples: &[i16]) -> Result<()> {
+    let mut bytes = Vec::with_capacity(44 + samples.len() * 2);
+    wav::write_header_at(&mut bytes, SAMPLE_RATE, (samples.len() * 2) as u32)?;
+    for s in samples {
+        bytes.extend_from_slice(&s.to_le_bytes());
+    }
+    std::fs::write(path, bytes).with_context(|| format!("Write {}", path.display()))
+}
+
+/// ffmpeg arguments adding `dub` to `video` as audio stream `index` (after
+/// the existing ones), written to `out` with everything else copied.
+pub fn mux_args(video: &Path, dub: &Path, index: usize, out: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-i"].map(String::from).into();
+    args.push(video.to_string_lossy().into_owned());
+    args.push("-i".into());
+    args.push(dub.to_string_lossy().into_owned());
+    args.extend(["-map", "0", "-map", "1:a", "-c", "copy"].map(String::from));
+    args.extend([
+        format!("-c:a:{index}"),
+        "aac".into(),
+        format!("-b:a:{index}"),
+        "128k".into(),
+        format!("-metadata:s:a:{index}"),
+        "language=chi".into(),
+        format!("-metadata:s:a:{index}"),
+        "title=國語配音".into(),
+        format!("-disposition:a:{index}"),
+        "0".into(),
+    ]);
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+/// Add `dub` to `video` in place as an extra, non-default audio track.
+pub fn mux(video: &Path, dub: &Path) -> Result<()> {
+    let index = verify::probe(video).map_or(1, |m| m.audio);
+    let ext = video.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let tmp = video.with_extension(format!("dub.{ext}"));
+    let status = cancel::status(Command::new("ffmpeg").args(mux_args(video, dub, index, &tmp)))
+        .context("Failed to run ffmpeg to add the dubbed track")?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(anyhow!(
+            "ffmpeg could not add the dubbed track to {}",
+            video.display()
+        ));
+    }
+    std::fs::rename(&tmp, video).with_context(|| format!("Replace {}", video.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speakable_and_fit() {
+        assert_eq!(speakable("[笑聲] 你好\n（小聲）走吧♪"), "你好，走吧");
+        assert_eq!(speakable("♪～♪"), "～");
+
+        // A 440 Hz tone, 2 s, into 1.5 s: sped up, no cut
+        let rate = SAMPLE_RATE as usize;
+        let tone: Vec<i16> = (0..rate * 2)
+            .map(|i| {
+                ((i as f64 * 440.0 * std::f64::consts::TAU / rate as f64).sin() * 8000.0) as i16
+            })
+            .collect();
+        let (clip, sped_up, cut) = fit(tone.clone(), rate * 3 / 2);
+        assert!(sped_up && !cut);
+        assert!(clip.len() <= rate * 3 / 2 && clip.len() > rate * 13 / 10);
+        // Pitch kept: about as many zero crossings per second as before
+        let crossings = |s: &[i16]| s.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+        let per_second = crossings(&clip[..rate]) as f64;
+        assert!((per_second - 880.0).abs() < 40.0, "{per_second}");
+        // Into 1 s: 1.5× is not enough, so it is cut
+        let (clip, _, cut) = fit(tone, rate);
+        assert!(cut);
+        assert_eq!(clip.len(), rate);
+    }
+}
@@ -0,0 +1,144 @@
+//! Embedded subtitle tracks: probe the input for Japanese text subtitle
+//! streams and extract one as timed segments, replacing transcription.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::subparse::{self, SubtitleKind};
+use crate::{cancel, WhisperSegment};
+
+/// Codecs ffmpeg can convert to SRT (bitmap formats like PGS need OCR).
+const TEXT_CODECS: &[&str] = &["subrip", "srt", "ass", "ssa", "mov_text", "webvtt", "text"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleStream {
+    pub index: u32,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+impl SubtitleStream {
+    fn is_text(&self) -> bool {
+        TEXT_CODECS.contains(&self.codec.as_str())
+    }
+
+    fn is_japanese(&self) -> bool {
+        let lang = self.language.as_deref().unwrap_or("").to_ascii_lowercase();
+        let title = self.title.as_deref().unwrap_or("");
+        matches!(lang.as_str(), "jpn" | "ja" | "jp")
+            || title.contains("日本語")
+            || title.to_ascii_lowercase().contains("japanese")
+    }
+}
+
+pub fn probe_subtitle_streams(input: &Path) -> Result<Vec<SubtitleStream>> {
+    let out = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "s",
+            "-show_entries",
+            "stream=index,codec_name:stream_tags=language,title",
+            "-of",
+            "json",
+            input.to_str().unwrap(),
+        ])
+        .output()
+        .context("ffprobe is required to inspect subtitle streams")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    parse_probe_json(&String::from_utf8_lossy(&out.stdout))
+}
+
+fn parse_probe_json(s: &str) -> Result<Vec<SubtitleStream>> {
+    let v: serde_json::Value = serde_json::from_str(s).context("Parse ffprobe JSON")?;
+    let streams = v["streams"].as_array().cloned().unwrap_or_default();
+    Ok(streams
+        .iter()
+        .filter_map(|st| {
+            Some(SubtitleStream {
+                index: st["index"].as_u64()? as u32,
+                codec: st["codec_name"].as_str().unwrap_or("").to_string(),
+                language: st["tags"]["language"].as_str().map(str::to_string),
+                title: st["tags"]["title"].as_str().map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+/// First Japanese-tagged text subtitle stream, if any.
+pub fn pick_japanese(streams: &[SubtitleStream]) -> Option<&SubtitleStream> {
+    streams.iter().find(|s| s.is_text() && s.is_japanese())
+}
+
+/// Extract `stream` as SRT into `work_dir` and parse it into segments.
+pub fn extract(
+    input: &Path,
+    stream: &SubtitleStream,
+    work_dir: &Path,
+) -> Result<Vec<WhisperSegment>> {
+    let srt = work_dir.join("embedded_ja.srt");
+    let status = cancel::status(Command::new("ffmpeg").args(extract_args(input, stream, &srt)))
+        .context("ffmpeg subtitle extraction failed")?;
+    if !status.success() {
+        return Err(anyhow!(
+            "ffmpeg could not extract subtitle stream #{}",
+            stream.index
+        ));
+    }
+    let text = std::fs::read_to_string(&srt).context("Read extracted subtitles")?;
+    Ok(parse_srt(&text))
+}
+
+/// ffmpeg arguments that convert subtitle `stream` to SRT at `srt`.
+pub fn extract_args(input: &Path, stream: &SubtitleStream, srt: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-i"].map(String::from).into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-map".to_string(), format!("0:{}", stream.index)]);
+    args.extend(["-c:s", "srt"].map(String::from));
+    args.push(srt.to_string_lossy().into_owned());
+    args
+}
+
+/// Parse SRT cues into segments; markup is stripped and multi-line cue
+/// text is joined (Japanese needs no separator).
+fn parse_srt(text: &str) -> Vec<WhisperSegment> {
+    subparse::parse(text, SubtitleKind::Srt)
+        .map(|cues| subparse::to_segments(&cues))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_japanese_text_stream() {
+        let json = r#"{"streams":[
+            {"index":2,"codec_name":"hdmv_pgs_subtitle","tags":{"language":"jpn"}},
+            {"index":3,"codec_name":"ass","tags":{"language":"eng"}},
+            {"index":4,"codec_name":"subrip","tags":{"language":"jpn","title":"日本語"}}
+        ]}"#;
+        let streams = parse_probe_json(json).unwrap();
+        assert_eq!(streams.len(), 3);
+        assert_eq!(pick_japanese(&streams).unwrap().index, 4);
+    }
+
+    #[test]
+    fn test_parse_srt_strips_markup() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>こんにちは</i>\r\n{\\an8}世界\r\n\r\n2\r\n00:01:00,000 --> 00:01:01,000\r\nはい\r\n";
+        let segs = parse_srt(srt);
+        assert_eq!(segs.len(), 2);
+        assert_eq!(segs[0].text, "こんにちは世界");
+        assert_eq!(segs[0].start, 1.0);
+        assert_eq!(segs[0].end, 2.5);
+        assert_eq!(segs[1].start, 60.0);
+    }
+}
@@ -0,0 +1,173 @@
+//! Emoji and pictographic symbols in translations (`--emoji`). Models now
+//! and then add 😂 or ❤️, which burn-in fonts usually cannot draw (they come
+//! out as boxes) while text players show them fine, so the handling is
+//! chosen per output: `keep,burn-in=text` keeps them in files and replaces
+//! them with a word, or drops them, on the burned-in picture.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmojiMode {
+    /// Leave emoji as written
+    Keep,
+    /// Remove them
+    Strip,
+    /// Replace common ones with a bracketed word, remove the rest
+    Text,
+}
+
+/// Outputs a mode can be set for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// SRT files, including review and extra-language SRTs
+    Srt,
+    /// WebVTT in HLS packages and live captions
+    Vtt,
+    Lrc,
+    Json,
+    Sbv,
+    /// Bilibili CC subtitles
+    Bcc,
+    /// Bilibili danmaku XML
+    Danmaku,
+    /// Premiere Pro marker comments
+    Markers,
+    /// Burned-in video
+    BurnIn,
+}
+
+/// Emoji handling per output: a default mode and per-output overrides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    default: EmojiMode,
+    overrides: Vec<(Output, EmojiMode)>,
+}
+
+impl Policy {
+    /// `MODE` and/or `OUTPUT=MODE` items, comma-separated
+    /// (e.g. `keep,burn-in=text`). Later items win.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut policy = Policy {
+            default: EmojiMode::Keep,
+            overrides: Vec::new(),
+        };
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let mode = |m: &str| EmojiMode::from_str(m.trim(), true);
+            match item.split_once('=') {
+                Some((output, m)) => {
+                    let output = Output::from_str(output.trim(), true)?;
+                    policy.overrides.retain(|(o, _)| *o != output);
+                    policy.overrides.push((output, mode(m)?));
+                }
+                None => policy.default = mode(item)?,
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn mode(&self, output: Output) -> EmojiMode {
+        self.overrides
+            .iter()
+            .find(|(o, _)| *o == output)
+            .map_or(self.default, |(_, m)| *m)
+    }
+
+    /// `lines` as they should be written to `output`.
+    pub fn lines(&self, output: Output, lines: &[String]) -> Vec<String> {
+        let mode = self.mode(output);
+        lines.iter().map(|l| sanitize(l, mode)).collect()
+    }
+}
+
+/// Common emoji and their zh-TW words for `text` mode.
+const WORDS: &[(char, &str)] = &[
+    ('😂', "笑"),
+    ('🤣', "笑"),
+    ('😄', "笑"),
+    ('😆', "笑"),
+    ('😊', "微笑"),
+    ('😭', "哭"),
+    ('😢', "哭"),
+    ('😡', "生氣"),
+    ('😱', "驚"),
+    ('😅', "尷尬"),
+    ('🤔', "思考"),
+    ('👍', "讚"),
+    ('👏', "拍手"),
+    ('🙏', "拜託"),
+    ('❤', "愛心"),
+    ('💕', "愛心"),
+    ('💔', "心碎"),
+    ('🎵', "音樂"),
+    ('🎶', "音樂"),
+    ('🔥', "火"),
+    ('💦', "汗"),
+    ('✨', "閃亮"),
+];
+
+pub fn sanitize(text: &str, mode: EmojiMode) -> String {
+    if mode == EmojiMode::Keep || !text.chars().any(is_emoji) {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !is_emoji(c) {
+            out.push(c);
+            continue;
+        }
+        if mode == EmojiMode::Text {
+            if let Some((_, word)) = WORDS.iter().find(|(e, _)| *e == c) {
+                out.push_str(&format!("（{word}）"));
+            }
+        }
+    }
+    // A stripped emoji leaves its surrounding spaces behind
+    out.split(' ')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("）（", "")
+}
+
+/// Pictographs and their joiners. Music notes, stars, and card suits, which
+/// CJK fonts carry, are not emoji here.
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}'
+        | '\u{2700}'..='\u{27BF}'
+        | '\u{2B50}' | '\u{2B55}' | '\u{2B1B}' | '\u{2B1C}'
+        | '\u{FE0F}' | '\u{200D}' | '\u{20E3}')
+        || matches!(c, '\u{2600}'..='\u{26FF}')
+            && !matches!(c, '\u{2605}' | '\u{2606}' | '\u{2660}'..='\u{2667}' | '\u{266A}'..='\u{266C}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_and_sanitize() {
+        let p = Policy::parse("keep,burn-in=text,srt=strip").unwrap();
+        assert_eq!(p.mode(Output::Json), EmojiMode::Keep);
+        assert_eq!(p.mode(Output::BurnIn), EmojiMode::Text);
+        assert_eq!(p.mode(Output::Danmaku), EmojiMode::Keep);
+        assert_eq!(
+            Policy::parse("strip,danmaku=keep")
+                .unwrap()
+                .mode(Output::Danmaku),
+            EmojiMode::Keep
+        );
+        assert!(Policy::parse("burn-in=blink").is_err());
+        let line = "太好笑了😂 我愛你❤️ ♪";
+        assert_eq!(sanitize(line, EmojiMode::Keep), line);
+        assert_eq!(sanitize(line, EmojiMode::Strip), "太好笑了 我愛你 ♪");
+        assert_eq!(
+            sanitize(line, EmojiMode::Text),
+            "太好笑了（笑） 我愛你（愛心） ♪"
+        );
+        assert_eq!(
+            p.lines(Output::Srt, &["好🥺".to_string()]),
+            ["好".to_string()]
+        );
+    }
+}
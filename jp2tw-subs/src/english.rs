@@ -0,0 +1,125 @@
+//! English inside Japanese speech (acronyms, brand and product names,
+//! loanwords written in Latin letters): with `--english keep` the translator
+//! is told to carry these over as written, and translated lines are checked
+//! afterwards, so 「AIってすごい」 becomes 「AI好厲害」 rather than an odd
+//! transliteration.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum English {
+    /// Keep English words and acronyms from the Japanese as written
+    #[default]
+    Keep,
+    /// Let the model translate them like any other word
+    Translate,
+}
+
+pub const PROMPT: &str = "Keep English words, acronyms, and brand or product names that appear in Latin letters in the Japanese (such as AI, YouTube, iPhone, OK) exactly as written; do not translate or transliterate them into Chinese.";
+
+/// Characters joining the parts of one term: COVID-19, Mr.Children, AT&T.
+const JOINERS: [char; 4] = ['-', '.', '&', '\''];
+
+/// Full-width Latin letters and digits (ＡＩ, ５Ｇ) as ASCII.
+fn half_width(c: char) -> char {
+    match c {
+        'Ａ'..='Ｚ' | 'ａ'..='ｚ' | '０'..='９' => {
+            char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+/// The English terms in a Japanese line: runs of Latin letters and digits
+/// (full-width ones normalized) with at least two characters and a letter.
+pub fn terms(ja: &str) -> Vec<String> {
+    let chars: Vec<char> = ja.chars().map(half_width).collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_alphanumeric() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len()
+            && (chars[i].is_ascii_alphanumeric()
+                || JOINERS.contains(&chars[i])
+                    && chars.get(i + 1).is_some_and(char::is_ascii_alphanumeric))
+        {
+            i += 1;
+        }
+        let term: String = chars[start..i].iter().collect();
+        if term.len() >= 2 && term.chars().any(|c| c.is_ascii_alphabetic()) && !out.contains(&term)
+        {
+            out.push(term);
+        }
+    }
+    out
+}
+
+/// `zh` with the English terms of `ja` written as in `ja`: full-width
+/// letters made ASCII and case restored (Youtube → YouTube).
+pub fn restore(ja: &str, zh: &str) -> String {
+    let terms = terms(ja);
+    if terms.is_empty() {
+        return zh.to_string();
+    }
+    let mut out: String = zh.chars().map(half_width).collect();
+    for term in &terms {
+        let lower = out.to_ascii_lowercase();
+        let needle = term.to_ascii_lowercase();
+        // ASCII lowercasing keeps byte offsets, so matches map back; a match
+        // inside a longer word is left alone
+        let bytes = lower.as_bytes();
+        let found: Vec<usize> = lower
+            .match_indices(&needle)
+            .map(|(at, _)| at)
+            .filter(|&at| {
+                let before = at.checked_sub(1).map(|i| bytes[i]);
+                let after = bytes.get(at + needle.len()).copied();
+                ![before, after]
+                    .into_iter()
+                    .flatten()
+                    .any(|b| b.is_ascii_alphanumeric())
+            })
+            .collect();
+        for at in found {
+            out.replace_range(at..at + term.len(), term);
+        }
+    }
+    out
+}
+
+/// The first English term of `ja` missing from `zh`, if any.
+pub fn missing(ja: &str, zh: &str) -> Option<String> {
+    let zh = restore(ja, zh);
+    terms(ja).into_iter().find(|t| !zh.contains(t.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_and_restore() {
+        assert_eq!(terms("AIってすごい"), ["AI"]);
+        assert_eq!(
+            terms("ｉＰｈｏｎｅで COVID-19 のニュース"),
+            ["iPhone", "COVID-19"]
+        );
+        // Lone letters and numbers are not terms
+        assert!(terms("Aランクで100点").is_empty());
+        assert_eq!(
+            restore("YouTubeで見た", "在Youtube上看到的"),
+            "在YouTube上看到的"
+        );
+        assert_eq!(restore("ＡＩです", "是ＡＩ"), "是AI");
+        assert_eq!(restore("AIです", "Maine的ai"), "Maine的AI");
+        assert_eq!(
+            missing("AIってすごい", "人工智慧好厲害").as_deref(),
+            Some("AI")
+        );
+        assert_eq!(missing("AIってすごい", "ai好厲害"), None);
+    }
+}
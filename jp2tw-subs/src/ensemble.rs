@@ -0,0 +1,125 @@
+//! `--candidates N`: ask for several translations of each batch, spread over
+//! temperatures and `--candidate-models`, then have a judge model pick or
+//! merge the best rendering of every line. Costs about N+1 times a plain run.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::openai::{ChatRequest, OpenAiClient};
+use crate::{extract_first_json_object, try_parse_translations_json, Args};
+
+const JUDGE_SYSTEM: &str = "You are a senior subtitle editor for Traditional Chinese (Taiwan). For each Japanese line you get several candidate translations. Return the best one, or merge their strengths into a better line, keeping it accurate, natural, and short enough to read as a subtitle. Never merge, split, or reorder lines. Do not add explanations.";
+
+#[derive(Debug, Clone)]
+pub struct Ensemble {
+    /// Candidate translations per batch; 1 disables the judge
+    pub candidates: u32,
+    /// Models the candidates cycle through
+    pub models: Vec<String>,
+    pub judge_model: String,
+}
+
+impl Ensemble {
+    pub fn from_args(args: &Args) -> Self {
+        let models = if args.candidate_models.is_empty() {
+            vec![args.translate_model.clone()]
+        } else {
+            args.candidate_models.clone()
+        };
+        Self {
+            candidates: args.candidates.max(1),
+            models,
+            judge_model: args
+                .judge_model
+                .clone()
+                .unwrap_or_else(|| args.translate_model.clone()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.candidates > 1
+    }
+
+    /// Model for candidate `i`.
+    pub fn model(&self, i: u32) -> &str {
+        &self.models[i as usize % self.models.len()]
+    }
+
+    /// Sampling temperature for candidate `i`: the first keeps the model's
+    /// default, the rest spread from 0.2 to 1.0.
+    pub fn temperature(&self, i: u32) -> Option<f32> {
+        match i {
+            0 => None,
+            _ if self.candidates <= 2 => Some(0.8),
+            _ => Some(0.2 + 0.8 * (i - 1) as f32 / (self.candidates - 2) as f32),
+        }
+    }
+}
+
+/// Pick or merge the best translation of each line from `candidates`, each
+/// aligned with `lines`. A reply of the wrong length keeps the first
+/// candidate.
+pub async fn judge(
+    lines: &[String],
+    candidates: &[Vec<String>],
+    client: &OpenAiClient,
+    model: &str,
+) -> Result<Vec<String>> {
+    let req = ChatRequest::new(model)
+        .system(JUDGE_SYSTEM)
+        .user(judge_input(lines, candidates).to_string())
+        .json_object();
+    let content = client
+        .chat(&req)
+        .await
+        .context("OpenAI judge request failed")?;
+    let picked = try_parse_translations_json(&content)
+        .or_else(|| {
+            extract_first_json_object(&content).and_then(|s| try_parse_translations_json(&s))
+        })
+        .ok_or_else(|| anyhow!("Judge JSON missing 'translations' array"))?;
+    if picked.len() != lines.len() {
+        eprintln!(
+            "Judge returned {} lines for {}; keeping the first candidate",
+            picked.len(),
+            lines.len()
+        );
+        return Ok(candidates[0].clone());
+    }
+    Ok(picked)
+}
+
+fn judge_input(lines: &[String], candidates: &[Vec<String>]) -> Value {
+    let items: Vec<Value> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, ja)| {
+            let options: Vec<&str> = candidates.iter().map(|c| c[i].as_str()).collect();
+            json!({ "ja": ja, "candidates": options })
+        })
+        .collect();
+    json!({
+        "instruction": "For each item choose or merge the best zh-TW translation. Return strict JSON with {\"translations\": string[]} matching the input length.",
+        "items": items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_settings_and_judge_input() {
+        let e = Ensemble {
+            candidates: 4,
+            models: vec!["a".into(), "b".into()],
+            judge_model: "j".into(),
+        };
+        let models: Vec<&str> = (0..4).map(|i| e.model(i)).collect();
+        assert_eq!(models, ["a", "b", "a", "b"]);
+        let temps: Vec<Option<f32>> = (0..4).map(|i| e.temperature(i)).collect();
+        assert_eq!(temps, [None, Some(0.2), Some(0.6), Some(1.0)]);
+        let v = judge_input(&["はい".into()], &[vec!["是".into()], vec!["好".into()]]);
+        assert_eq!(v["items"][0]["candidates"], json!(["是", "好"]));
+    }
+}
@@ -0,0 +1,169 @@
+//! Run-level name table: proper nouns (characters, places, groups) are
+//! extracted from the Japanese transcript and translated once, then injected
+//! into every translation batch and enforced on the output, so names do not
+//! drift between batches.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+
+use crate::openai::{ChatRequest, OpenAiClient};
+
+/// Transcript lines sent per extraction request.
+const EXTRACT_CHUNK_LINES: usize = 300;
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Entity {
+    pub ja: String,
+    pub zh: String,
+    /// Other plausible zh-TW renderings, rewritten to `zh` after translation
+    #[serde(default)]
+    pub variants: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct NameTable {
+    entries: Vec<Entity>,
+}
+
+impl NameTable {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Add entities not already in the table; the first rendering of a name
+    /// wins so later chunks cannot change it mid-run.
+    pub fn merge(&mut self, found: Vec<Entity>) {
+        for e in found {
+            let (ja, zh) = (e.ja.trim(), e.zh.trim());
+            if ja.is_empty() || zh.is_empty() || self.entries.iter().any(|x| x.ja == ja) {
+                continue;
+            }
+            self.entries.push(Entity {
+                ja: ja.to_string(),
+                zh: zh.to_string(),
+                variants: e.variants,
+            });
+        }
+    }
+
+    /// (ja, zh) pairs for names that occur in `lines`.
+    pub fn relevant(&self, lines: &[String]) -> Vec<(String, String)> {
+        self.entries
+            .iter()
+            .filter(|e| lines.iter().any(|l| l.contains(&e.ja)))
+            .map(|e| (e.ja.clone(), e.zh.clone()))
+            .collect()
+    }
+
+    /// Rewrite stray variants (or an untranslated Japanese form) of every name
+    /// the source line mentions to the table's rendering.
+    pub fn enforce(&self, ja: &str, zh: &str) -> String {
+        let mut out = zh.to_string();
+        for e in self.entries.iter().filter(|e| ja.contains(&e.ja)) {
+            for v in e.variants.iter().chain(std::iter::once(&e.ja)) {
+                // Skip variants that are part of the canonical name itself
+                if !v.is_empty() && !e.zh.contains(v.as_str()) {
+                    out = out.replace(v.as_str(), &e.zh);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Deserialize)]
+struct NamesFile {
+    #[serde(default)]
+    name: Vec<Entity>,
+}
+
+/// Names pinned in a `--names` TOML file.
+pub fn load(path: &Path) -> Result<Vec<Entity>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Read names {}", path.display()))?;
+    let file: NamesFile =
+        toml::from_str(&text).with_context(|| format!("Parse names {}", path.display()))?;
+    Ok(file.name)
+}
+
+/// Ask the model for the proper nouns in `lines` with one zh-TW rendering each.
+pub async fn extract(lines: &[String], client: &OpenAiClient, model: &str) -> Result<Vec<Entity>> {
+    let mut found = Vec::new();
+    for chunk in lines.chunks(EXTRACT_CHUNK_LINES) {
+        found.extend(extract_chunk(chunk, client, model).await?);
+    }
+    Ok(found)
+}
+
+async fn extract_chunk(
+    lines: &[String],
+    client: &OpenAiClient,
+    model: &str,
+) -> Result<Vec<Entity>> {
+    let system = "You build name tables for Japanese-to-Traditional-Chinese (Taiwan) subtitle translation. List proper nouns only: people, places, organizations, titles of works, and named items. Give each one the standard or most natural zh-TW rendering, and list other renderings translators commonly use as variants. For characters of anime and other licensed works, prefer the names used by official Taiwan releases. Leave honorific suffixes such as さん, くん, or 様 out of names.";
+    let user = json!({
+        "instruction": "Return strict JSON {\"entities\": [{\"ja\": string, \"zh\": string, \"variants\": string[]}]}. Use the exact Japanese surface form from the transcript for ja. Return an empty array if there are none.",
+        "transcript": lines,
+    })
+    .to_string();
+    let req = ChatRequest::new(model)
+        .system(system)
+        .user(user)
+        .json_object();
+    let content = client
+        .chat(&req)
+        .await
+        .context("OpenAI name extraction failed")?;
+    parse_entities(&content)
+}
+
+fn parse_entities(content: &str) -> Result<Vec<Entity>> {
+    #[derive(Deserialize)]
+    struct Entities {
+        entities: Vec<Entity>,
+    }
+    let parsed: Entities = serde_json::from_str(content.trim()).context("Parse name table JSON")?;
+    Ok(parsed.entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge_first_rendering_wins() {
+        let mut table = NameTable::default();
+        table.merge(
+            parse_entities(r#"{"entities":[{"ja":"ルフィ","zh":"魯夫","variants":["路飛"]}]}"#)
+                .unwrap(),
+        );
+        table.merge(vec![Entity {
+            ja: "ルフィ".into(),
+            zh: "路飛".into(),
+            variants: vec![],
+        }]);
+        assert_eq!(table.len(), 1);
+        let lines = vec!["ルフィ、行くぞ".to_string(), "はい".to_string()];
+        assert_eq!(
+            table.relevant(&lines),
+            vec![("ルフィ".to_string(), "魯夫".to_string())]
+        );
+        assert!(table.relevant(&lines[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_enforce_rewrites_variants() {
+        let mut table = NameTable::default();
+        table.merge(vec![Entity {
+            ja: "ルフィ".into(),
+            zh: "魯夫".into(),
+            variants: vec!["路飛".into()],
+        }]);
+        assert_eq!(table.enforce("ルフィ、行くぞ", "路飛，走吧"), "魯夫，走吧");
+        assert_eq!(table.enforce("ルフィ！", "ルフィ！"), "魯夫！");
+        // Lines that do not mention the name are left alone
+        assert_eq!(table.enforce("行くぞ", "路飛"), "路飛");
+    }
+}
@@ -0,0 +1,474 @@
+//! `fonts` subcommand: the font families burn-in can use. Scans the fonts
+//! directory (`--font-dir`, as resolved for burn-in) and the system font
+//! locations, reads each font's family names and character map, and lists
+//! the families that cover Traditional Chinese, marking those that also
+//! cover kana (usable for `--ja-font-name`). It then shows which font
+//! `--font-name` resolves to: libass takes fonts from the fonts directory
+//! before the system ones, and substitutes another font, possibly without
+//! Chinese glyphs, for a family that is not installed.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{resolve_fonts_dir, Args};
+
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+/// Common characters written differently in Simplified Chinese; a font
+/// covering all of them can set Traditional Chinese text
+const TRADITIONAL_SAMPLE: &str = "這個們來說時會對國過還學麼點開關見長東車問題體頭為無樣經現動實話後電從當與應該讓認識幾歲書買賣錢萬聽寫讀氣熱";
+const KANA_SAMPLE: &str = "あいうえおかがしゃっアイウエオカガシャッー";
+/// The font burn-in uses without --font-name
+pub const DEFAULT_FONT: &str = "Noto Sans CJK TC";
+
+/// One face of a font file (a TrueType collection holds several).
+#[derive(Debug, Clone)]
+pub struct Face {
+    /// Typographic family name, as listed
+    pub family: String,
+    /// Every family and full name, in every language, as fonts are matched
+    pub names: Vec<String>,
+    pub path: PathBuf,
+    pub traditional: bool,
+    pub kana: bool,
+}
+
+/// Directories to scan: the resolved fonts directory first, then the
+/// system font locations not already inside it.
+pub fn search_dirs(font_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = resolve_fonts_dir(font_dir).into_iter().collect();
+    let home = std::env::var("HOME").map(PathBuf::from).ok();
+    let mut system: Vec<PathBuf> = Vec::new();
+    if cfg!(target_os = "macos") {
+        system.extend(["/System/Library/Fonts", "/Library/Fonts"].map(PathBuf::from));
+        system.extend(home.map(|h| h.join("Library/Fonts")));
+    } else if cfg!(target_os = "windows") {
+        system.push(PathBuf::from("C:/Windows/Fonts"));
+        if let Ok(local) = std::env::var("LOCALAPPDATA") {
+            system.push(Path::new(&local).join("Microsoft/Windows/Fonts"));
+        }
+    } else {
+        system.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        if let Some(h) = home {
+            system.extend([h.join(".local/share/fonts"), h.join(".fonts")]);
+        }
+    }
+    for dir in system {
+        if dir.is_dir() && !dirs.iter().any(|d| dir.starts_with(d)) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Every face of every font file under `dirs`; unreadable files are skipped.
+pub fn scan(dirs: &[PathBuf]) -> Vec<Face> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        font_files(dir, &mut files);
+    }
+    let mut seen = HashSet::new();
+    files
+        .into_iter()
+        .filter(|f| seen.insert(f.canonicalize().unwrap_or_else(|_| f.clone())))
+        .flat_map(|f| faces(&f).unwrap_or_default())
+        .collect()
+}
+
+fn font_files(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            font_files(&path, found);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| FONT_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        {
+            found.push(path);
+        }
+    }
+}
+
+/// Family names compare as fontconfig compares them: ignoring case and
+/// spaces.
+fn same_family(a: &str, b: &str) -> bool {
+    let key = |s: &str| -> String {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    key(a) == key(b)
+}
+
+/// The face `name` resolves to: the first match in search order, or its
+/// regular style from the same directory.
+pub fn find<'a>(faces: &'a [Face], name: &str) -> Option<&'a Face> {
+    let mut matches = faces
+        .iter()
+        .filter(|f| f.names.iter().any(|n| same_family(n, name)));
+    let first = matches.next()?;
+    let regular = format!("{name} Regular");
+    let is_regular = |f: &Face| {
+        f.names
+            .iter()
+            .all(|n| same_family(n, name) || same_family(n, &regular))
+    };
+    Some(
+        std::iter::once(first)
+            .chain(matches.filter(|f| f.path.parent() == first.path.parent()))
+            .find(|f| is_regular(f))
+            .unwrap_or(first),
+    )
+}
+
+/// What fontconfig substitutes for `name` with Traditional Chinese text,
+/// when fc-match is installed.
+pub fn substitute(name: &str) -> Option<String> {
+    let out = Command::new("fc-match")
+        .args(["-f", "%{family}", &format!("{name}:lang=zh-tw")])
+        .output()
+        .ok()?;
+    let family = String::from_utf8_lossy(&out.stdout);
+    let family = family.split(',').next()?.trim();
+    (out.status.success() && !family.is_empty()).then(|| family.to_string())
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![0; len];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// The faces in a font file, reading only its `name` and `cmap` tables.
+fn faces(path: &Path) -> Option<Vec<Face>> {
+    let mut file = File::open(path).ok()?;
+    let head = read_at(&mut file, 0, 12)?;
+    let offsets: Vec<u32> = if &head[..4] == b"ttcf" {
+        let count = be32(&head, 8)?.min(64) as usize;
+        let list = read_at(&mut file, 12, count * 4)?;
+        (0..count).filter_map(|i| be32(&list, i * 4)).collect()
+    } else {
+        vec![0]
+    };
+    let faces = offsets
+        .into_iter()
+        .filter_map(|offset| face(&mut file, u64::from(offset), path))
+        .collect();
+    Some(faces)
+}
+
+fn face(file: &mut File, offset: u64, path: &Path) -> Option<Face> {
+    let header = read_at(file, offset, 12)?;
+    let tables = be16(&header, 4)? as usize;
+    let records = read_at(file, offset + 12, tables * 16)?;
+    let mut table = |tag: &[u8]| -> Option<Vec<u8>> {
+        let i = (0..tables).find(|i| &records[i * 16..i * 16 + 4] == tag)?;
+        let at = be32(&records, i * 16 + 8)?;
+        let len = be32(&records, i * 16 + 12)?;
+        read_at(file, u64::from(at), len as usize)
+    };
+    let (family, names) = names(&table(b"name")?)?;
+    let cmap = table(b"cmap")?;
+    let covers = |sample: &str| sample.chars().all(|c| has_glyph(&cmap, c));
+    Some(Face {
+        family,
+        names,
+        path: path.to_path_buf(),
+        traditional: covers(TRADITIONAL_SAMPLE),
+        kana: covers(KANA_SAMPLE),
+    })
+}
+
+/// The typographic family name (preferring US English) and every family
+/// and full name from a `name` table.
+fn names(data: &[u8]) -> Option<(String, Vec<String>)> {
+    let count = be16(data, 2)? as usize;
+    let strings = be16(data, 4)? as usize;
+    // (name id, is US English, text)
+    let mut found: Vec<(u16, bool, String)> = Vec::new();
+    for i in 0..count {
+        let rec = 6 + i * 12;
+        let (platform, encoding, language, id) = (
+            be16(data, rec)?,
+            be16(data, rec + 2)?,
+            be16(data, rec + 4)?,
+            be16(data, rec + 6)?,
+        );
+        if ![1, 4, 16].contains(&id) {
+            continue;
+        }
+        let (len, at) = (
+            be16(data, rec + 8)? as usize,
+            be16(data, rec + 10)? as usize,
+        );
+        let Some(bytes) = data.get(strings + at..strings + at + len) else {
+            continue;
+        };
+        let text = match (platform, encoding) {
+            (0, _) | (3, 0 | 1 | 10) => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            (1, 0) => bytes.iter().map(|&b| char::from(b)).collect(),
+            _ => continue,
+        };
+        let english = (platform == 3 && language == 0x0409) || (platform == 1 && language == 0);
+        if !text.trim().is_empty() {
+            found.push((id, english, text.trim().to_string()));
+        }
+    }
+    let family = [(16, true), (1, true), (16, false), (1, false)]
+        .iter()
+        .find_map(|&(id, english)| {
+            found
+                .iter()
+                .find(|(i, e, _)| *i == id && (*e || !english))
+                .map(|(_, _, text)| text.clone())
+        })?;
+    let mut all: Vec<String> = Vec::new();
+    for (_, _, text) in found {
+        if !all.contains(&text) {
+            all.push(text);
+        }
+    }
+    Some((family, all))
+}
+
+/// Whether a `cmap` table maps `c` to a glyph, from its Unicode subtable
+/// (format 12, else format 4).
+fn has_glyph(cmap: &[u8], c: char) -> bool {
+    let c = c as u32;
+    let subtables = be16(cmap, 2).unwrap_or(0) as usize;
+    let offsets: Vec<(u16, u32)> = (0..subtables)
+        .filter_map(|i| {
+            let (platform, encoding) = (be16(cmap, 4 + i * 8)?, be16(cmap, 6 + i * 8)?);
+            let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+            let offset = be32(cmap, 8 + i * 8)?;
+            unicode.then_some((be16(cmap, offset as usize)?, offset))
+        })
+        .collect();
+    if let Some(&(_, at)) = offsets.iter().find(|(format, _)| *format == 12) {
+        return format12(cmap, at as usize, c).unwrap_or(false);
+    }
+    if let Some(&(_, at)) = offsets.iter().find(|(format, _)| *format == 4) {
+        return c <= 0xFFFF && format4(cmap, at as usize, c as u16).unwrap_or(false);
+    }
+    false
+}
+
+fn format12(data: &[u8], at: usize, c: u32) -> Option<bool> {
+    let groups = be32(data, at + 12)? as usize;
+    for g in 0..groups {
+        let rec = at + 16 + g * 12;
+        let (start, end) = (be32(data, rec)?, be32(data, rec + 4)?);
+        if (start..=end).contains(&c) {
+            return Some(be32(data, rec + 8)? + (c - start) != 0);
+        }
+    }
+    Some(false)
+}
+
+fn format4(data: &[u8], at: usize, c: u16) -> Option<bool> {
+    let seg_x2 = be16(data, at + 6)? as usize;
+    let ends = at + 14;
+    let starts = ends + seg_x2 + 2;
+    let deltas = starts + seg_x2;
+    let range_offsets = deltas + seg_x2;
+    for s in (0..seg_x2).step_by(2) {
+        if c > be16(data, ends + s)? {
+            continue;
+        }
+        let start = be16(data, starts + s)?;
+        if c < start {
+            return Some(false);
+        }
+        let delta = be16(data, deltas + s)?;
+        let range = be16(data, range_offsets + s)? as usize;
+        let glyph = if range == 0 {
+            c.wrapping_add(delta)
+        } else {
+            let g = be16(data, range_offsets + s + range + 2 * (c - start) as usize)?;
+            if g == 0 {
+                0
+            } else {
+                g.wrapping_add(delta)
+            }
+        };
+        return Some(glyph != 0);
+    }
+    Some(false)
+}
+
+/// How `name` resolves, as one line.
+fn resolution(faces: &[Face], flag: &str, name: &str) -> String {
+    match find(faces, name) {
+        Some(face) if face.traditional => {
+            format!("{flag} {name}: {}", face.path.display())
+        }
+        Some(face) => format!(
+            "{flag} {name}: {} has no Traditional Chinese glyphs; Chinese text falls back to another font or shows as boxes",
+            face.path.display()
+        ),
+        None => match substitute(name) {
+            Some(other) => format!("{flag} {name}: not installed; libass substitutes {other}"),
+            None => format!("{flag} {name}: not installed; libass substitutes another font"),
+        },
+    }
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let dirs = search_dirs(args.font_dir.as_deref());
+    let faces = scan(&dirs);
+    println!("Searched (in order):");
+    for dir in &dirs {
+        println!("  {}", dir.display());
+    }
+    // Family -> (styles, covers kana, first file)
+    let mut families: BTreeMap<&str, (usize, bool, &Path)> = BTreeMap::new();
+    for face in faces.iter().filter(|f| f.traditional) {
+        let entry = families
+            .entry(face.family.as_str())
+            .or_insert((0, false, face.path.as_path()));
+        entry.0 += 1;
+        entry.1 |= face.kana;
+    }
+    let width = families
+        .keys()
+        .map(|f| f.chars().count())
+        .max()
+        .unwrap_or(0);
+    println!(
+        "\nFamilies covering Traditional Chinese ({} of {} faces):",
+        families.len(),
+        faces.len()
+    );
+    for (family, (styles, kana, path)) in &families {
+        println!(
+            "  {family:<width$}  {} {styles} style(s)  {}",
+            if *kana { "[ja]" } else { "    " },
+            path.display()
+        );
+    }
+    if families.is_empty() {
+        println!(
+            "  (none) install Noto Sans CJK TC or copy a Traditional Chinese font into ./fonts"
+        );
+    }
+    println!("\nBurn-in uses:");
+    let name = args.font_name.as_deref().unwrap_or(DEFAULT_FONT);
+    println!("  {}", resolution(&faces, "--font-name", name));
+    if let Some(ja) = &args.ja_font_name {
+        println!("  {}", resolution(&faces, "--ja-font-name", ja));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font file with a name table naming `family` (Windows, US English,
+    /// and Traditional Chinese) and a format 4 cmap covering `chars`.
+    fn font(family: &str, local: &str, chars: &[u16]) -> Vec<u8> {
+        let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(u16::to_be_bytes).collect() };
+        let (en, zh) = (utf16(family), utf16(local));
+        let mut name = Vec::new();
+        for v in [0u16, 2, 30] {
+            name.extend(v.to_be_bytes());
+        }
+        for (lang, len, at) in [(0x0409u16, en.len(), 0), (0x0404, zh.len(), en.len())] {
+            for v in [3u16, 1, lang, 1, len as u16, at as u16] {
+                name.extend(v.to_be_bytes());
+            }
+        }
+        name.extend(&en);
+        name.extend(&zh);
+
+        // One segment per character plus the closing 0xFFFF segment
+        let seg = chars.len() + 1;
+        let mut cmap = Vec::new();
+        for v in [0u16, 1, 3, 1, 0, 12] {
+            cmap.extend(v.to_be_bytes());
+        }
+        for v in [4u16, (16 + seg * 8) as u16, 0, (seg * 2) as u16, 0, 0, 0] {
+            cmap.extend(v.to_be_bytes());
+        }
+        let ends: Vec<u16> = chars.iter().copied().chain([0xFFFF]).collect();
+        for v in ends.iter().chain([&0u16]).chain(&ends) {
+            cmap.extend(v.to_be_bytes());
+        }
+        for (i, c) in chars.iter().enumerate() {
+            cmap.extend((i as u16 + 1).wrapping_sub(*c).to_be_bytes());
+        }
+        cmap.extend(1u16.to_be_bytes());
+        cmap.extend(vec![0; seg * 2]);
+
+        let mut out = 0x0001_0000u32.to_be_bytes().to_vec();
+        for v in [2u16, 0, 0, 0] {
+            out.extend(v.to_be_bytes());
+        }
+        let cmap_at = 12 + 32;
+        let name_at = cmap_at + cmap.len();
+        for (tag, at, len) in [
+            (b"cmap", cmap_at, cmap.len()),
+            (b"name", name_at, name.len()),
+        ] {
+            out.extend(tag);
+            out.extend(0u32.to_be_bytes());
+            out.extend((at as u32).to_be_bytes());
+            out.extend((len as u32).to_be_bytes());
+        }
+        out.extend(cmap);
+        out.extend(name);
+        out
+    }
+
+    #[test]
+    fn test_scan_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tc: Vec<u16> = TRADITIONAL_SAMPLE.chars().map(|c| c as u16).collect();
+        tc.sort();
+        tc.dedup();
+        std::fs::write(
+            dir.path().join("tc.otf"),
+            font("Test Sans TC", "測試黑體", &tc),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("latin.ttf"),
+            font("Plain", "Plain", &[0x41]),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a font").unwrap();
+        let faces = scan(&[dir.path().to_path_buf()]);
+        assert_eq!(faces.len(), 2);
+        let face = find(&faces, "testsans tc").unwrap();
+        assert!(face.traditional && !face.kana);
+        assert_eq!(face.family, "Test Sans TC");
+        assert!(find(&faces, "測試黑體").is_some());
+        let plain = find(&faces, "Plain").unwrap();
+        assert!(!plain.traditional);
+        assert!(find(&faces, "Missing").is_none());
+    }
+}
@@ -0,0 +1,360 @@
+//! Subtitle output formats selected with `--format`, beyond the SRT and ASS
+//! writers in main.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use crate::{qc, WhisperSegment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SubtitleFormat {
+    /// SubRip (.srt)
+    Srt,
+    /// Timestamped lyrics (.lrc) for music players and lyrics sites
+    Lrc,
+    /// JSON sidecar with both languages, timings, and confidence and length flags
+    Json,
+    /// YouTube SubViewer (.sbv), uploadable in YouTube Studio as is
+    Sbv,
+    /// Final Cut Pro caption roll (.fcpxml)
+    Fcpxml,
+    /// Premiere Pro / Resolve sequence markers spanning each cue (Final Cut Pro 7 XML, .markers.xml)
+    PremiereXml,
+    /// Bilibili CC subtitles (.bcc JSON), bilingual like the SRT
+    Bcc,
+    /// Bilibili danmaku XML with each translated cue as a bottom comment
+    Danmaku,
+    /// Scenarist SCC (CEA-608) broadcast captions, for Latin-script --target-lang languages
+    Scc,
+}
+
+/// Character set of written SRT files (`--encoding`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    /// UTF-8 with a byte order mark, which some Windows players need to detect it
+    Utf8Bom,
+    /// Traditional Chinese legacy encoding for older players and set-top boxes
+    Big5,
+}
+
+/// How SRT text is written to disk: `--encoding` and `--crlf`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub charset: Charset,
+    pub crlf: bool,
+}
+
+impl TextEncoding {
+    /// `text` (with `\n` line ends) as bytes to write, and how many characters
+    /// the charset has no code for (written as `?`).
+    pub fn encode(self, text: &str) -> (Vec<u8>, usize) {
+        let text = if self.crlf {
+            text.replace('\n', "\r\n")
+        } else {
+            text.to_string()
+        };
+        match self.charset {
+            Charset::Utf8 => (text.into_bytes(), 0),
+            Charset::Utf8Bom => ([b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat(), 0),
+            Charset::Big5 => encode_big5(&text),
+        }
+    }
+}
+
+fn encode_big5(text: &str) -> (Vec<u8>, usize) {
+    use encoding_rs::EncoderResult;
+    let mut encoder = encoding_rs::BIG5.new_encoder();
+    let mut out = Vec::with_capacity(text.len());
+    let mut buf = [0u8; 1024];
+    let mut rest = text;
+    let mut unmappable = 0;
+    loop {
+        let (result, read, written) =
+            encoder.encode_from_utf8_without_replacement(rest, &mut buf, true);
+        out.extend_from_slice(&buf[..written]);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => return (out, unmappable),
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => {
+                out.push(b'?');
+                unmappable += 1;
+            }
+        }
+    }
+}
+
+/// Read a subtitle file written in any `--encoding`: UTF-8 with or without a
+/// BOM, or Big5 when the bytes are not valid UTF-8.
+pub fn read_text(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("Read {}", path.display()))?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text.to_string()),
+        Err(_) => {
+            let (text, _, malformed) = encoding_rs::BIG5.decode(bytes);
+            if malformed {
+                return Err(anyhow!("{} is neither UTF-8 nor Big5 text", path.display()));
+            }
+            Ok(text.into_owned())
+        }
+    }
+}
+
+/// Prefix for low-confidence cues in the review SRT.
+pub const REVIEW_MARKER: &str = "⚠ ";
+
+/// `x.zh-TW.srt` -> `x.<lang>.<ext>`; falls back to swapping the extension
+/// when the SRT name has no language suffix.
+pub fn sibling_path(output_srt: &Path, lang: &str, ext: &str) -> PathBuf {
+    let stem = output_srt
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let base = stem.strip_suffix(".zh-TW").unwrap_or(stem);
+    output_srt.with_file_name(format!("{}.{}.{}", base, lang, ext))
+}
+
+/// `text` escaped for XML (and HTML) text and quoted attribute values.
+pub fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write LRC next to the SRT: zh-TW lyrics, plus a separate Japanese file
+/// when bilingual (LRC has no notion of multi-line cues).
+pub fn write_lrc_outputs(
+    output_srt: &Path,
+    segments: &[WhisperSegment],
+    ja_lines: &[String],
+    zh_lines: &[String],
+    bilingual: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut written = vec![sibling_path(output_srt, "zh-TW", "lrc")];
+    if bilingual {
+        written.push(sibling_path(output_srt, "ja", "lrc"));
+    }
+    for (path, lines) in written.iter().zip([zh_lines, ja_lines]) {
+        std::fs::write(path, lrc(segments, lines))
+            .with_context(|| format!("Write LRC at {}", path.display()))?;
+    }
+    Ok(written)
+}
+
+/// Cue-level JSON: timings, both languages, Whisper scores, and a
+/// `low_confidence` flag for cues below `review_threshold`.
+pub fn write_json(
+    path: &Path,
+    segments: &[WhisperSegment],
+    ja_lines: &[String],
+    zh_lines: &[String],
+    pivot: Option<(&str, &[Option<String>])>,
+    review_threshold: f64,
+) -> Result<()> {
+    let cues: Vec<serde_json::Value> = segments
+        .iter()
+        .zip(ja_lines.iter().zip(zh_lines))
+        .enumerate()
+        .map(|(i, (seg, (ja, zh)))| {
+            let mut cue = json!({
+                "index": i + 1,
+                "start": seg.start,
+                "end": seg.end,
+                "ja": ja,
+                "zh": zh,
+                "avg_logprob": seg.avg_logprob,
+                "no_speech_prob": seg.no_speech_prob,
+                "low_confidence": seg.is_low_confidence(review_threshold),
+                "length_flag": qc::length_outlier(ja, zh).map(qc::LengthFlag::as_str),
+            });
+            if let Some((_, lines)) = pivot {
+                cue["pivot"] = json!(lines.get(i).cloned().flatten());
+            }
+            cue
+        })
+        .collect();
+    let mut doc = json!({ "review_threshold": review_threshold, "cues": cues });
+    if let Some((lang, _)) = pivot {
+        doc["pivot_language"] = json!(lang);
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&doc)?)
+        .with_context(|| format!("Write JSON at {}", path.display()))
+}
+
+/// SubViewer cues as YouTube reads them: `H:MM:SS.mmm,H:MM:SS.mmm`, the
+/// text lines, then a blank line.
+pub fn write_sbv(path: &Path, segments: &[WhisperSegment], lines: &[String]) -> Result<()> {
+    std::fs::write(path, sbv(segments, lines))
+        .with_context(|| format!("Write SBV at {}", path.display()))
+}
+
+fn sbv(segments: &[WhisperSegment], lines: &[String]) -> String {
+    let mut s = String::new();
+    for (seg, text) in segments.iter().zip(lines) {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        s.push_str(&format!(
+            "{},{}\n{}\n\n",
+            format_sbv_time(seg.start),
+            format_sbv_time(seg.end),
+            text
+        ));
+    }
+    s
+}
+
+fn format_sbv_time(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        total_ms % 1000
+    )
+}
+
+/// `[mm:ss.xx]line` per cue; an empty timestamp line clears the display
+/// when there is a gap before the next cue.
+fn lrc(segments: &[WhisperSegment], lines: &[String]) -> String {
+    let mut s = String::new();
+    for (i, (seg, text)) in segments.iter().zip(lines.iter()).enumerate() {
+        let text = text.replace('\n', " ");
+        s.push_str(&format!(
+            "[{}]{}\n",
+            format_lrc_time(seg.start),
+            text.trim()
+        ));
+        let next_start = segments.get(i + 1).map(|n| n.start);
+        if next_start.is_none_or(|n| n - seg.end > 0.05) {
+            s.push_str(&format!("[{}]\n", format_lrc_time(seg.end)));
+        }
+    }
+    s
+}
+
+fn format_lrc_time(seconds: f64) -> String {
+    // mm:ss.xx (minutes may exceed 59)
+    let total_cs = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:02}",
+        total_cs / 6000,
+        (total_cs / 100) % 60,
+        total_cs % 100
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_lrc_clears_on_gaps() {
+        let segments = vec![seg(1.0, 3.0, ""), seg(3.0, 4.5, ""), seg(65.25, 70.0, "")];
+        let lines = vec!["一".to_string(), "二".to_string(), "三".to_string()];
+        assert_eq!(
+            lrc(&segments, &lines),
+            "[00:01.00]一\n[00:03.00]二\n[00:04.50]\n[01:05.25]三\n[01:10.00]\n"
+        );
+    }
+
+    #[test]
+    fn test_sbv() {
+        let segments = vec![
+            seg(1.5, 3.0, ""),
+            seg(3661.25, 3663.0, ""),
+            seg(4000.0, 4001.0, ""),
+        ];
+        let lines = vec![
+            "你好\nこんにちは".to_string(),
+            "再見".to_string(),
+            " ".to_string(),
+        ];
+        assert_eq!(
+            sbv(&segments, &lines),
+            "0:00:01.500,0:00:03.000\n你好\nこんにちは\n\n1:01:01.250,1:01:03.000\n再見\n\n"
+        );
+    }
+
+    #[test]
+    fn test_json_flags_low_confidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+        let scored = |lp: Option<f64>| WhisperSegment {
+            avg_logprob: lp,
+            ..seg(0.0, 1.0, "")
+        };
+        let segments = vec![scored(Some(-0.2)), scored(Some(-1.4)), scored(None)];
+        let ja = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let zh = vec!["甲".to_string(), "乙".to_string(), "丙".to_string()];
+        let pivot = vec![Some("A".to_string()), None, Some("C".to_string())];
+        write_json(&path, &segments, &ja, &zh, Some(("en", &pivot)), -1.0).unwrap();
+        let v: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let flags: Vec<bool> = v["cues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["low_confidence"].as_bool().unwrap())
+            .collect();
+        assert_eq!(flags, vec![false, true, false]);
+        assert_eq!(v["cues"][1]["zh"], "乙");
+        assert!(v["cues"][1]["length_flag"].is_null());
+        assert_eq!(v["pivot_language"], "en");
+        assert_eq!(v["cues"][0]["pivot"], "A");
+        // Cues resumed from a checkpoint have no pivot text
+        assert!(v["cues"][1]["pivot"].is_null());
+    }
+
+    #[test]
+    fn test_text_encoding() {
+        let crlf = TextEncoding {
+            charset: Charset::Utf8Bom,
+            crlf: true,
+        };
+        assert_eq!(crlf.encode("一\n").0, b"\xEF\xBB\xBF\xE4\xB8\x80\r\n");
+        let big5 = TextEncoding {
+            charset: Charset::Big5,
+            crlf: false,
+        };
+        // 你好 in Big5; the emoji has no code
+        assert_eq!(big5.encode("你好😀"), (b"\xA7\x41\xA6\x6E?".to_vec(), 1));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big5.srt");
+        std::fs::write(&path, big5.encode("「你好」").0).unwrap();
+        assert_eq!(read_text(&path).unwrap(), "「你好」");
+        std::fs::write(&path, crlf.encode("你好").0).unwrap();
+        assert_eq!(read_text(&path).unwrap(), "你好");
+    }
+
+    #[test]
+    fn test_sibling_path() {
+        let srt = Path::new("/v/ep01.zh-TW.srt");
+        assert_eq!(
+            sibling_path(srt, "ja", "lrc"),
+            PathBuf::from("/v/ep01.ja.lrc")
+        );
+        assert_eq!(
+            sibling_path(Path::new("subs.srt"), "zh-TW", "lrc"),
+            PathBuf::from("subs.zh-TW.lrc")
+        );
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape(r#"<b>"A&B"</b>"#),
+            "&lt;b&gt;&quot;A&amp;B&quot;&lt;/b&gt;"
+        );
+    }
+}
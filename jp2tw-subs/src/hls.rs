@@ -0,0 +1,225 @@
+//! HLS packaging: the source video is stream-copied into TS segments by
+//! ffmpeg, then WebVTT subtitle segments aligned to those segments, one
+//! subtitle media playlist per language, and a master playlist tying them
+//! together.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::{cancel, format_vtt_time, WhisperSegment};
+
+/// ffmpeg's mpegts muxer starts timestamps at 1.4s (90kHz clock); VTT
+/// segments map LOCAL zero onto it so players line cues up with the video.
+const MPEGTS_START: u64 = 126_000;
+
+/// One subtitle rendition; the first track of a package is the default.
+pub struct SubtitleTrack<'a> {
+    pub lang: &'a str,
+    /// Shown in the player's track menu
+    pub name: &'a str,
+    pub lines: &'a [String],
+}
+
+impl SubtitleTrack<'_> {
+    /// File name prefix: `subs_zh` for zh-TW, else `subs_<lang>`.
+    fn prefix(&self) -> String {
+        if self.lang.eq_ignore_ascii_case("zh-TW") {
+            "subs_zh".into()
+        } else {
+            format!("subs_{}", self.lang.to_ascii_lowercase())
+        }
+    }
+}
+
+pub fn write_package(
+    input: &Path,
+    out_dir: &Path,
+    segment_seconds: u32,
+    segments: &[WhisperSegment],
+    tracks: &[SubtitleTrack],
+) -> Result<()> {
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Create HLS dir {}", out_dir.display()))?;
+    let video_playlist = out_dir.join("video.m3u8");
+    let status =
+        cancel::status(Command::new("ffmpeg").args(ffmpeg_args(input, out_dir, segment_seconds)))
+            .context("ffmpeg HLS segmenting failed (ffmpeg binary required)")?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg HLS segmenting failed"));
+    }
+
+    // Subtitle segments follow the actual (keyframe-aligned) video durations
+    let playlist = std::fs::read_to_string(&video_playlist)?;
+    let durations: Vec<f64> = parse_media_playlist(&playlist)
+        .into_iter()
+        .map(|(d, _)| d)
+        .collect();
+    if durations.is_empty() {
+        return Err(anyhow!("ffmpeg produced an empty HLS playlist"));
+    }
+    let total: f64 = durations.iter().sum();
+    for track in tracks {
+        let prefix = track.prefix();
+        let mut start = 0.0;
+        for (i, d) in durations.iter().enumerate() {
+            let body = vtt_segment(segments, track.lines, start, start + d);
+            std::fs::write(out_dir.join(format!("{prefix}_{i:05}.vtt")), body)?;
+            start += d;
+        }
+        std::fs::write(
+            out_dir.join(format!("{prefix}.m3u8")),
+            subtitle_playlist(&durations, &prefix),
+        )?;
+    }
+
+    let total_bytes: u64 = std::fs::read_dir(out_dir)?
+        .flatten()
+        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("ts"))
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    let bandwidth = ((total_bytes as f64 * 8.0) / total.max(1.0)).ceil() as u64;
+    std::fs::write(
+        out_dir.join("master.m3u8"),
+        master_playlist(bandwidth, tracks),
+    )?;
+    Ok(())
+}
+
+/// ffmpeg arguments that stream-copy `input` into TS segments and `video.m3u8`.
+pub fn ffmpeg_args(input: &Path, out_dir: &Path, segment_seconds: u32) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-i"].map(String::from).into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(
+        [
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a?",
+            "-c",
+            "copy",
+            "-f",
+            "hls",
+            "-hls_time",
+        ]
+        .map(String::from),
+    );
+    args.push(segment_seconds.to_string());
+    args.extend(["-hls_playlist_type", "vod", "-hls_segment_filename"].map(String::from));
+    args.push(out_dir.join("video_%05d.ts").to_string_lossy().into_owned());
+    args.push(out_dir.join("video.m3u8").to_string_lossy().into_owned());
+    args
+}
+
+/// (EXTINF duration, URI) pairs of a media playlist.
+pub fn parse_media_playlist(text: &str) -> Vec<(f64, String)> {
+    let mut out = Vec::new();
+    let mut pending: Option<f64> = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("#EXTINF:") {
+            let dur = rest.split(',').next().unwrap_or("0");
+            pending = dur.trim().parse().ok();
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(d) = pending.take() {
+                out.push((d, line.to_string()));
+            }
+        }
+    }
+    out
+}
+
+/// One WebVTT segment with every cue overlapping [start, end).
+pub fn vtt_segment(segments: &[WhisperSegment], lines: &[String], start: f64, end: f64) -> String {
+    let mut s = format!("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:{MPEGTS_START},LOCAL:00:00:00.000\n\n");
+    for (seg, text) in segments.iter().zip(lines.iter()) {
+        if seg.end > start && seg.start < end {
+            s.push_str(&format!(
+                "{} --> {}\n{}\n\n",
+                format_vtt_time(seg.start),
+                format_vtt_time(seg.end),
+                text
+            ));
+        }
+    }
+    s
+}
+
+pub fn subtitle_playlist(durations: &[f64], prefix: &str) -> String {
+    let target = durations.iter().fold(0.0f64, |a, &b| a.max(b)).ceil() as u64;
+    let mut s = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+        target.max(1)
+    );
+    for (i, d) in durations.iter().enumerate() {
+        s.push_str(&format!("#EXTINF:{:.6},\n{}_{:05}.vtt\n", d, prefix, i));
+    }
+    s.push_str("#EXT-X-ENDLIST\n");
+    s
+}
+
+fn master_playlist(bandwidth: u64, tracks: &[SubtitleTrack]) -> String {
+    let mut s = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+    for (i, t) in tracks.iter().enumerate() {
+        s.push_str(&format!(
+            "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"{}\",LANGUAGE=\"{}\",DEFAULT={},AUTOSELECT=YES,URI=\"{}.m3u8\"\n",
+            t.name,
+            t.lang,
+            if i == 0 { "YES" } else { "NO" },
+            t.prefix()
+        ));
+    }
+    s.push_str(&format!(
+        "#EXT-X-STREAM-INF:BANDWIDTH={},SUBTITLES=\"subs\"\nvideo.m3u8\n",
+        bandwidth.max(1)
+    ));
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let text = "#EXTM3U\n#EXT-X-TARGETDURATION:7\n#EXTINF:6.006000,\nvideo_00000.ts\n#EXTINF:4.5,\nvideo_00001.ts\n#EXT-X-ENDLIST\n";
+        let parsed = parse_media_playlist(text);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0], (6.006, "video_00000.ts".to_string()));
+        assert_eq!(parsed[1].0, 4.5);
+    }
+
+    #[test]
+    fn test_vtt_segment_includes_overlapping_cues() {
+        let segments = vec![seg(1.0, 2.0, ""), seg(5.5, 7.0, ""), seg(9.0, 10.0, "")];
+        let lines = vec!["一".to_string(), "二".to_string(), "三".to_string()];
+        let body = vtt_segment(&segments, &lines, 6.0, 12.0);
+        assert!(body.starts_with("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:126000"));
+        assert!(!body.contains("一"));
+        assert!(body.contains("00:00:05.500 --> 00:00:07.000\n二"));
+        assert!(body.contains("三"));
+
+        let playlist = subtitle_playlist(&[6.0, 4.5], "subs_zh");
+        assert!(playlist.contains("#EXT-X-TARGETDURATION:6"));
+        assert!(playlist.contains("#EXTINF:4.500000,\nsubs_zh_00001.vtt"));
+
+        let tracks = [
+            SubtitleTrack {
+                lang: "zh-TW",
+                name: "繁體中文",
+                lines: &lines,
+            },
+            SubtitleTrack {
+                lang: "en",
+                name: "English",
+                lines: &lines,
+            },
+        ];
+        let master = master_playlist(1000, &tracks);
+        assert!(
+            master.contains("LANGUAGE=\"zh-TW\",DEFAULT=YES,AUTOSELECT=YES,URI=\"subs_zh.m3u8\"")
+        );
+        assert!(master.contains("LANGUAGE=\"en\",DEFAULT=NO,AUTOSELECT=YES,URI=\"subs_en.m3u8\""));
+    }
+}
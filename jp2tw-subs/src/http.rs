@@ -0,0 +1,79 @@
+//! The one HTTP client a run shares (OpenAI, downloads, uploads, webhooks,
+//! trace export), so connections and TLS sessions are pooled and reused
+//! across the many small translation requests instead of renegotiated.
+//! Configured once from `--http-*` at startup.
+
+use anyhow::{Context, Result};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Whole-request limit for API calls (uploads included)
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Idle connections kept open per host
+    pub pool_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(600),
+            connect_timeout: Duration::from_secs(30),
+            pool_size: 16,
+        }
+    }
+}
+
+static SHARED: OnceLock<(reqwest::Client, Options)> = OnceLock::new();
+
+/// Build the shared client; later calls keep the first one.
+pub fn init(opts: Options) -> Result<()> {
+    let client = build(&opts)?;
+    let _ = SHARED.set((client, opts));
+    Ok(())
+}
+
+fn build(opts: &Options) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(opts.connect_timeout)
+        .pool_max_idle_per_host(opts.pool_size)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+        .build()
+        .context("Build the HTTP client")
+}
+
+fn shared() -> &'static (reqwest::Client, Options) {
+    SHARED.get_or_init(|| {
+        let opts = Options::default();
+        (build(&opts).unwrap_or_default(), opts)
+    })
+}
+
+/// The shared client (a cheap handle onto the same pool). It sets no overall
+/// timeout, since downloads can run long; API calls add `timeout()`.
+pub fn client() -> reqwest::Client {
+    shared().0.clone()
+}
+
+/// `--http-timeout`.
+pub fn timeout() -> Duration {
+    shared().1.timeout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let opts = Options {
+            pool_size: 0,
+            ..Default::default()
+        };
+        assert!(build(&opts).is_ok());
+        assert_eq!(Options::default().timeout, Duration::from_secs(600));
+    }
+}
@@ -0,0 +1,81 @@
+//! `--language-guard`: the first [`PROBE_SECONDS`] of the first chunk are
+//! also sent to Whisper once without the Japanese hint so it reports the
+//! language it hears. Anything else (an English dub track, the commentary
+//! track) stops the run, or warns, before the rest of the audio is
+//! transcribed and "translated" at full cost. Whisper detects the language
+//! from its first 30 s window anyway, so a longer probe would only cost
+//! more. Only the detected language is taken from that pass; the chunk's
+//! cues come from the usual pass with the hint, which unhinted Whisper
+//! transcribes worse.
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+
+/// Audio sent without the hint, from the start of the first chunk
+pub const PROBE_SECONDS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LanguageGuard {
+    /// Stop when the first chunk is not Japanese
+    #[default]
+    Abort,
+    /// Warn and carry on
+    Warn,
+    /// Skip the check and always send the Japanese hint
+    Off,
+}
+
+impl LanguageGuard {
+    /// Whether the start of the first chunk gets the extra pass without
+    /// the hint.
+    pub fn detects(self) -> bool {
+        self != LanguageGuard::Off
+    }
+
+    /// Act on the language Whisper `detected` in the first chunk. Models
+    /// that do not report one pass.
+    pub fn check(self, detected: Option<&str>) -> Result<()> {
+        let Some(lang) = detected.map(str::trim).filter(|l| !l.is_empty()) else {
+            return Ok(());
+        };
+        if self == LanguageGuard::Off || is_japanese(lang) {
+            return Ok(());
+        }
+        let message = format!(
+            "Whisper hears {lang}, not Japanese, in the first chunk. If the input has several audio tracks, pick the Japanese one with --ffmpeg-extract-args \"-map 0:a:1\" (ffprobe lists them in order)"
+        );
+        match self {
+            LanguageGuard::Abort => Err(anyhow!(
+                "{message}; pass --language-guard warn to caption it anyway"
+            )),
+            _ => {
+                eprintln!(
+                    "\n*** Warning: {message}. Continuing because of --language-guard warn. ***\n"
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whisper reports full English names ("japanese"); accept ISO codes too.
+fn is_japanese(lang: &str) -> bool {
+    lang.eq_ignore_ascii_case("japanese") || lang.eq_ignore_ascii_case("ja")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert!(LanguageGuard::Abort.check(Some("japanese")).is_ok());
+        assert!(LanguageGuard::Abort.check(None).is_ok());
+        let err = LanguageGuard::Abort.check(Some("english")).unwrap_err();
+        assert!(err.to_string().contains("-map 0:a:1"));
+        assert!(LanguageGuard::Warn.check(Some("english")).is_ok());
+        assert!(LanguageGuard::Abort.detects());
+        assert!(LanguageGuard::Warn.detects());
+        assert!(!LanguageGuard::Off.detects());
+    }
+}
@@ -0,0 +1,529 @@
+//! Bilingual cue layout: which language comes first, what separates the two,
+//! and how the secondary language is sized and dimmed on burn-in. SRT and
+//! WebVTT get the plain text. On burn-in, stacked bilingual cues become two
+//! ASS events with their own `ZH` and `JA` styles (font, size, colour), since
+//! a good Japanese font and a good Traditional Chinese font are rarely the
+//! same file; one-line layouts use inline override tags instead. With
+//! `--bilingual-position split` the two events are placed apart, Japanese
+//! at the top of the frame and Chinese at the bottom.
+
+use clap::ValueEnum;
+
+use crate::{wrap, Args};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LineOrder {
+    /// Chinese first, Japanese under it
+    ZhJa,
+    /// Japanese first, Chinese under it
+    JaZh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Separator {
+    /// One language per line
+    Newline,
+    /// One line, "zh / ja"
+    Slash,
+    /// One line, "zh | ja"
+    Bar,
+}
+
+/// Where a burned-in bilingual cue sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Position {
+    /// Both languages stacked at the bottom
+    Stacked,
+    /// Japanese at the top of the frame, Chinese at the bottom
+    Split,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    Ja,
+    Zh,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub bilingual: bool,
+    pub order: LineOrder,
+    pub separator: Separator,
+    pub position: Position,
+    /// The language that is shrunk and dimmed
+    pub secondary: Lang,
+    /// Secondary font size, percent of the primary
+    pub secondary_scale: u32,
+    /// Secondary opacity, percent
+    pub secondary_opacity: u32,
+    /// `--max-line-chars`: wrap each language's text to this width
+    pub max_line_chars: Option<usize>,
+}
+
+/// Per-language burn-in style settings for stacked bilingual cues.
+#[derive(Debug, Clone)]
+pub struct LangStyle {
+    pub font: String,
+    pub size: u32,
+    /// 0xRRGGBB
+    pub rgb: u32,
+    pub opacity: u32,
+}
+
+/// The frame burned-in subtitles are laid out on, written as PlayResX and
+/// PlayResY: the video's resolution. Font sizes and margins are given for a
+/// 288-line script (what libass assumes without PlayRes) and scaled to it,
+/// so a style looks the same on 480p and 4K sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Canvas {
+    /// libass's default script resolution
+    fn default() -> Self {
+        Self {
+            width: 384,
+            height: 288,
+        }
+    }
+}
+
+impl Canvas {
+    /// The first video stream's display size from `ffprobe -show_entries
+    /// stream=width,height:stream_side_data=rotation -of json` output.
+    pub fn from_ffprobe(json: &str) -> Option<Self> {
+        let v: serde_json::Value = serde_json::from_str(json).ok()?;
+        let stream = v["streams"].get(0)?;
+        let width = u32::try_from(stream["width"].as_u64()?).ok()?;
+        let height = u32::try_from(stream["height"].as_u64()?).ok()?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        // Phone videos are stored sideways and rotated on playback
+        let rotated = stream["side_data_list"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|d| d["rotation"].as_i64())
+            .any(|r| r.rem_euclid(180) == 90);
+        Some(if rotated {
+            Self {
+                width: height,
+                height: width,
+            }
+        } else {
+            Self { width, height }
+        })
+    }
+
+    pub fn scale(&self) -> f64 {
+        f64::from(self.height) / f64::from(Canvas::default().height)
+    }
+
+    /// `size`, given for the default canvas, on this one.
+    pub fn scaled(&self, size: u32) -> u32 {
+        ((f64::from(size) * self.scale()).round() as u32).max(1)
+    }
+}
+
+/// One `[V4+ Styles]` entry: white-on-outline text like the default style.
+#[derive(Debug, Clone)]
+pub struct AssStyle {
+    pub name: &'static str,
+    pub style: LangStyle,
+    /// Numpad position: 2 bottom centre, 8 top centre
+    pub alignment: u8,
+}
+
+impl AssStyle {
+    /// The style line; `size` is already in `canvas` units, the outline and
+    /// margins are scaled here.
+    pub fn line(&self, canvas: Canvas) -> String {
+        let s = &self.style;
+        let outline = 2.0 * canvas.scale();
+        let (margin_h, margin_v) = (canvas.scaled(10), canvas.scaled(20));
+        let font = s.font.replace(',', " ");
+        let alpha = (100 - s.opacity.min(100)) * 255 / 100;
+        // ASS colours are &HAABBGGRR
+        let (r, g, b) = (s.rgb >> 16 & 0xFF, s.rgb >> 8 & 0xFF, s.rgb & 0xFF);
+        format!(
+            "Style: {},{font},{},&H{alpha:02X}{b:02X}{g:02X}{r:02X},&H000000FF,&H{alpha:02X}000000,&H64000000,0,0,0,0,100,100,0,0,1,{outline:.1},0,{},{margin_h},{margin_h},{margin_v},1",
+            self.name, s.size, self.alignment
+        )
+    }
+}
+
+/// Frame corner for the `--credit` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// ASS numpad alignment
+    fn alignment(self) -> u8 {
+        match self {
+            Corner::TopLeft => 7,
+            Corner::TopRight => 9,
+            Corner::BottomLeft => 1,
+            Corner::BottomRight => 3,
+        }
+    }
+}
+
+/// The longest a `--credit` line can be shown: the latest time ASS files
+/// can hold (9:59:59.99).
+const CREDIT_UNTIL_END: f64 = 35_999.99;
+
+/// The style and event for a `--credit` line: slightly dimmed, `size` high,
+/// in `corner` from the start for `seconds` (0 for the whole video).
+pub fn credit(
+    text: &str,
+    font: &str,
+    size: u32,
+    corner: Corner,
+    seconds: f64,
+) -> (AssStyle, AssEvent) {
+    let style = AssStyle {
+        name: "Credit",
+        style: LangStyle {
+            font: font.to_string(),
+            size: size.max(1),
+            rgb: 0xFFFFFF,
+            opacity: 80,
+        },
+        alignment: corner.alignment(),
+    };
+    let end = if seconds > 0.0 {
+        seconds.min(CREDIT_UNTIL_END)
+    } else {
+        CREDIT_UNTIL_END
+    };
+    let event = AssEvent {
+        start: 0.0,
+        end,
+        style: "Credit",
+        text: ass_escape(text),
+    };
+    (style, event)
+}
+
+/// Move bottom-aligned `styles` to the top of the frame (`--avoid-hardsubs`).
+/// Lines at the top stack downwards, so cues made of several events (one per
+/// language) have them reversed to keep their order on screen.
+pub fn move_to_top(styles: &mut [AssStyle], events: &mut [AssEvent]) {
+    for style in styles.iter_mut() {
+        if (1..=3).contains(&style.alignment) {
+            style.alignment += 6;
+        }
+    }
+    let mut start = 0;
+    while start < events.len() {
+        let (from, to) = (events[start].start, events[start].end);
+        let len = events[start..]
+            .iter()
+            .take_while(|e| e.start == from && e.end == to)
+            .count();
+        events[start..start + len].reverse();
+        start += len;
+    }
+}
+
+/// A Dialogue line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssEvent {
+    pub start: f64,
+    pub end: f64,
+    pub style: &'static str,
+    pub text: String,
+}
+
+impl Layout {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            bilingual: args.bilingual,
+            order: args.bilingual_order,
+            separator: args.bilingual_separator,
+            position: args.bilingual_position,
+            secondary: args.bilingual_secondary,
+            secondary_scale: args.secondary_scale,
+            secondary_opacity: args.secondary_opacity.min(100),
+            max_line_chars: args.max_line_chars.map(|n| n as usize),
+        }
+    }
+
+    fn wrapped(&self, text: &str) -> String {
+        match self.max_line_chars {
+            Some(width) => wrap::wrap(text, width),
+            None => text.to_string(),
+        }
+    }
+
+    /// Plain cue text for SRT and WebVTT.
+    pub fn text(&self, ja: &str, zh: &str) -> String {
+        let (ja, zh) = (&self.wrapped(ja), &self.wrapped(zh));
+        // Cues with no Japanese (--skip-range placeholders) show one line
+        if !self.bilingual || ja.trim().is_empty() {
+            return zh.to_string();
+        }
+        let (first, second) = self.ordered(ja, zh);
+        let sep = match self.separator {
+            Separator::Newline => "\n",
+            Separator::Slash => " / ",
+            Separator::Bar => " | ",
+        };
+        format!("{first}{sep}{second}")
+    }
+
+    /// ASS Dialogue text, with the secondary language resized and dimmed
+    /// relative to a primary size of `font_size`.
+    pub fn ass_text(&self, ja: &str, zh: &str, font_size: u32) -> String {
+        let (ja, zh) = (&self.wrapped(ja), &self.wrapped(zh));
+        if !self.bilingual || ja.trim().is_empty() {
+            return ass_escape(zh);
+        }
+        let secondary_first = (self.order == LineOrder::JaZh) == (self.secondary == Lang::Ja);
+        let (first, second) = self.ordered(ja, zh);
+        let (first, second) = (ass_escape(first), ass_escape(second));
+        let sep = match self.separator {
+            Separator::Newline => "\\N",
+            Separator::Slash => " / ",
+            Separator::Bar => " | ",
+        };
+        let styled = |text: &str| {
+            let mut tags = String::new();
+            if self.secondary_scale != 100 {
+                let size = (font_size * self.secondary_scale / 100).max(1);
+                tags.push_str(&format!("\\fs{size}"));
+            }
+            if self.secondary_opacity < 100 {
+                // ASS alpha is transparency, 00 opaque to FF invisible
+                let alpha = (100 - self.secondary_opacity) * 255 / 100;
+                tags.push_str(&format!("\\alpha&H{alpha:02X}&"));
+            }
+            if tags.is_empty() {
+                text.to_string()
+            } else {
+                format!("{{{tags}}}{text}{{\\r}}")
+            }
+        };
+        if secondary_first {
+            format!("{}{sep}{second}", styled(&first))
+        } else {
+            format!("{first}{sep}{}", styled(&second))
+        }
+    }
+
+    /// Whether burn-in renders the two languages as separate events.
+    pub fn split_events(&self) -> bool {
+        self.bilingual && (self.separator == Separator::Newline || self.position == Position::Split)
+    }
+
+    /// Styles and per-cue events for a two-event bilingual cue. libass
+    /// stacks colliding bottom events upwards in file order, so the lower
+    /// line is written first.
+    pub fn split_styles(&self, zh: LangStyle, ja: LangStyle) -> Vec<AssStyle> {
+        let ja_alignment = match self.position {
+            Position::Stacked => 2,
+            Position::Split => 8,
+        };
+        vec![
+            AssStyle {
+                name: "ZH",
+                style: zh,
+                alignment: 2,
+            },
+            AssStyle {
+                name: "JA",
+                style: ja,
+                alignment: ja_alignment,
+            },
+        ]
+    }
+
+    pub fn split_events_for(&self, start: f64, end: f64, ja: &str, zh: &str) -> Vec<AssEvent> {
+        let event = |style, text: &str| AssEvent {
+            start,
+            end,
+            style,
+            text: ass_escape(&self.wrapped(text)),
+        };
+        let (zh, ja) = (event("ZH", zh), event("JA", ja));
+        match (self.position, self.order) {
+            (Position::Split, _) | (_, LineOrder::JaZh) => vec![zh, ja],
+            (_, LineOrder::ZhJa) => vec![ja, zh],
+        }
+    }
+
+    /// Size and opacity of `lang`, whose base size is `size`.
+    pub fn scaled(&self, lang: Lang, size: u32) -> (u32, u32) {
+        if lang == self.secondary {
+            (
+                (size * self.secondary_scale / 100).max(1),
+                self.secondary_opacity,
+            )
+        } else {
+            (size, 100)
+        }
+    }
+
+    fn ordered<'a>(&self, ja: &'a str, zh: &'a str) -> (&'a str, &'a str) {
+        match self.order {
+            LineOrder::ZhJa => (zh, ja),
+            LineOrder::JaZh => (ja, zh),
+        }
+    }
+}
+
+/// `RRGGBB` or `#RRGGBB` as 0xRRGGBB.
+pub fn parse_rgb(s: &str) -> Result<u32, String> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("{s:?} is not an RRGGBB colour"));
+    }
+    u32::from_str_radix(hex, 16).map_err(|_| format!("{s:?} is not an RRGGBB colour"))
+}
+
+/// Cue text as ASS Dialogue text: line breaks become `\N`, and braces, which
+/// would open an override block, become parentheses.
+pub fn ass_escape(text: &str) -> String {
+    text.replace('\n', "\\N")
+        .replace('{', "(")
+        .replace('}', ")")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> Layout {
+        Layout {
+            bilingual: true,
+            order: LineOrder::ZhJa,
+            separator: Separator::Newline,
+            position: Position::Stacked,
+            secondary: Lang::Ja,
+            secondary_scale: 100,
+            secondary_opacity: 100,
+            max_line_chars: None,
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(layout().text("はい", "是"), "是\nはい");
+        let l = Layout {
+            order: LineOrder::JaZh,
+            separator: Separator::Slash,
+            ..layout()
+        };
+        assert_eq!(l.text("はい", "是"), "はい / 是");
+        let l = Layout {
+            bilingual: false,
+            ..layout()
+        };
+        assert_eq!(l.text("はい", "是"), "是");
+        let l = Layout {
+            max_line_chars: Some(4),
+            ..layout()
+        };
+        assert_eq!(l.text("はい", "我們走吧。好"), "我們走\n吧。好\nはい");
+    }
+
+    #[test]
+    fn test_ass_text_styles_secondary() {
+        assert_eq!(layout().ass_text("{はい}", "是", 30), "是\\N(はい)");
+        let l = Layout {
+            secondary_scale: 70,
+            secondary_opacity: 60,
+            ..layout()
+        };
+        assert_eq!(
+            l.ass_text("はい", "是", 30),
+            "是\\N{\\fs21\\alpha&H66&}はい{\\r}"
+        );
+        let l = Layout {
+            order: LineOrder::JaZh,
+            secondary_scale: 70,
+            ..layout()
+        };
+        assert_eq!(l.ass_text("はい", "是", 30), "{\\fs21}はい{\\r}\\N是");
+    }
+
+    #[test]
+    fn test_split_styles_and_events() {
+        let l = Layout {
+            secondary_scale: 80,
+            secondary_opacity: 50,
+            ..layout()
+        };
+        let (size, opacity) = l.scaled(Lang::Ja, 30);
+        let style = AssStyle {
+            name: "JA",
+            style: LangStyle {
+                font: "Noto Sans CJK JP".into(),
+                size,
+                rgb: parse_rgb("#FFE080").unwrap(),
+                opacity,
+            },
+            alignment: 2,
+        };
+        assert!(style
+            .line(Canvas::default())
+            .starts_with("Style: JA,Noto Sans CJK JP,24,&H7F80E0FF,&H000000FF,&H7F000000,"));
+        assert!(style
+            .line(Canvas::default())
+            .ends_with(",1,2.0,0,2,10,10,20,1"));
+        // A 1080p canvas: 3.75 times the 288-line sizes
+        let hd = Canvas::from_ffprobe(r#"{"streams": [{"width": 1920, "height": 1080}]}"#).unwrap();
+        assert_eq!(hd.scaled(24), 90);
+        assert!(style.line(hd).ends_with(",1,7.5,0,2,38,38,75,1"));
+        let portrait = Canvas::from_ffprobe(
+            r#"{"streams": [{"width": 1920, "height": 1080, "side_data_list": [{"rotation": -90}]}]}"#,
+        );
+        assert_eq!(
+            portrait,
+            Some(Canvas {
+                width: 1080,
+                height: 1920
+            })
+        );
+        assert_eq!(Canvas::from_ffprobe(r#"{"streams": []}"#), None);
+        assert!(parse_rgb("FFF").is_err());
+        // Japanese under Chinese: the lower line comes first
+        let events = l.split_events_for(1.0, 2.0, "はい", "是");
+        let styles: Vec<&str> = events.iter().map(|e| e.style).collect();
+        assert_eq!(styles, ["JA", "ZH"]);
+        // Split regions: Japanese moves to the top, even on one-line layouts
+        let l = Layout {
+            position: Position::Split,
+            separator: Separator::Slash,
+            ..layout()
+        };
+        assert!(l.split_events());
+        let zh = style.style.clone();
+        let alignments: Vec<u8> = l
+            .split_styles(zh.clone(), zh)
+            .iter()
+            .map(|s| s.alignment)
+            .collect();
+        assert_eq!(alignments, [2, 8]);
+
+        let mut styles = l.split_styles(style.style.clone(), style.style.clone());
+        let mut events = layout().split_events_for(1.0, 2.0, "はい", "是");
+        move_to_top(&mut styles, &mut events);
+        assert!(styles.iter().all(|s| s.alignment == 8));
+        let order: Vec<&str> = events.iter().map(|e| e.style).collect();
+        assert_eq!(order, ["ZH", "JA"]);
+
+        let (style, event) = credit("翻譯：小明", "Noto Sans CJK TC", 18, Corner::TopRight, 0.0);
+        assert_eq!(style.alignment, 9);
+        assert_eq!((event.start, event.end), (0.0, CREDIT_UNTIL_END));
+        assert_eq!(event.text, "翻譯：小明");
+    }
+}
@@ -0,0 +1,313 @@
+//! Append-only cost ledger: one JSON line per run with the input, audio
+//! minutes, tokens per model, and an estimated USD cost from list prices,
+//! summarized by the `costs` subcommand.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::openai::{self, ModelUsage};
+
+/// List prices in USD, matched by model name prefix (longest first).
+enum Price {
+    PerMinute(f64),
+    PerMillionTokens {
+        input: f64,
+        output: f64,
+    },
+    /// Per million characters of text
+    Characters(f64),
+}
+
+const PRICES: &[(&str, Price)] = &[
+    ("whisper-1", Price::PerMinute(0.006)),
+    ("gpt-4o-transcribe", Price::PerMinute(0.006)),
+    ("gpt-4o-mini-transcribe", Price::PerMinute(0.003)),
+    ("tts-1", Price::Characters(15.0)),
+    ("tts-1-hd", Price::Characters(30.0)),
+    // Billed by audio tokens, about $0.015 a minute of speech; only the
+    // input characters are recorded
+    ("gpt-4o-mini-tts", Price::Characters(12.0)),
+    (
+        "gpt-4o-mini",
+        Price::PerMillionTokens {
+            input: 0.15,
+            output: 0.60,
+        },
+    ),
+    (
+        "gpt-4o",
+        Price::PerMillionTokens {
+            input: 2.50,
+            output: 10.00,
+        },
+    ),
+    (
+        "gpt-4.1-nano",
+        Price::PerMillionTokens {
+            input: 0.10,
+            output: 0.40,
+        },
+    ),
+    (
+        "gpt-4.1-mini",
+        Price::PerMillionTokens {
+            input: 0.40,
+            output: 1.60,
+        },
+    ),
+    (
+        "gpt-4.1",
+        Price::PerMillionTokens {
+            input: 2.00,
+            output: 8.00,
+        },
+    ),
+];
+
+/// Estimated cost of `usage`, or None for models without a known price.
+/// Batch API usage is billed at half price.
+pub fn estimate_cost(model: &str, usage: &ModelUsage) -> Option<f64> {
+    let discount = if model.ends_with(openai::BATCH_SUFFIX) {
+        0.5
+    } else {
+        1.0
+    };
+    let (_, price) = PRICES
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())?;
+    Some(match price {
+        Price::PerMinute(p) => usage.audio_seconds / 60.0 * p,
+        Price::Characters(p) => usage.characters as f64 / 1_000_000.0 * p,
+        Price::PerMillionTokens { input, output } => {
+            (usage.prompt_tokens as f64 * input + usage.completion_tokens as f64 * output)
+                / 1_000_000.0
+                * discount
+        }
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: ModelUsage,
+    /// Missing when the model has no known price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    pub project: String,
+    pub input: String,
+    pub status: String,
+    pub duration_secs: f64,
+    pub audio_minutes: f64,
+    pub models: Vec<ModelEntry>,
+    pub cost_usd: f64,
+}
+
+impl Entry {
+    pub fn new(
+        project: &str,
+        input: &str,
+        status: &str,
+        duration_secs: f64,
+        usage: BTreeMap<String, ModelUsage>,
+    ) -> Self {
+        let timestamp = time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap_or_default();
+        let models: Vec<ModelEntry> = usage
+            .into_iter()
+            .map(|(model, usage)| ModelEntry {
+                cost_usd: estimate_cost(&model, &usage),
+                model,
+                usage,
+            })
+            .collect();
+        Self {
+            timestamp,
+            project: project.to_string(),
+            input: input.to_string(),
+            status: status.to_string(),
+            duration_secs,
+            audio_minutes: models.iter().map(|m| m.usage.audio_seconds).sum::<f64>() / 60.0,
+            cost_usd: models.iter().filter_map(|m| m.cost_usd).sum(),
+            models,
+        }
+    }
+}
+
+/// `JP2TW_LEDGER`, else `jp2tw-subs/ledger.jsonl` in the user data directory.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("JP2TW_LEDGER") {
+        return Some(PathBuf::from(p));
+    }
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))?;
+    Some(base.join("jp2tw-subs").join("ledger.jsonl"))
+}
+
+pub fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Create {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Open ledger {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Append to ledger {}", path.display()))
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Read ledger {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            serde_json::from_str(l).with_context(|| format!("{}:{}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Month,
+    Project,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Total {
+    pub runs: usize,
+    pub audio_minutes: f64,
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+pub fn summarize(entries: &[Entry], by: GroupBy) -> BTreeMap<String, Total> {
+    let mut out: BTreeMap<String, Total> = BTreeMap::new();
+    for e in entries {
+        let key = match by {
+            GroupBy::Month => e.timestamp.get(..7).unwrap_or("unknown").to_string(),
+            GroupBy::Project => e.project.clone(),
+        };
+        let t = out.entry(key).or_default();
+        t.runs += 1;
+        t.audio_minutes += e.audio_minutes;
+        t.tokens += e
+            .models
+            .iter()
+            .map(|m| m.usage.prompt_tokens + m.usage.completion_tokens)
+            .sum::<u64>();
+        t.cost_usd += e.cost_usd;
+    }
+    out
+}
+
+/// `costs` subcommand: spend per month or project as a plain table.
+pub fn print_summary(path: &Path, by: GroupBy) -> Result<()> {
+    if !path.exists() {
+        println!("No runs recorded yet ({})", path.display());
+        return Ok(());
+    }
+    let totals = summarize(&read(path)?, by);
+    let label = match by {
+        GroupBy::Month => "Month",
+        GroupBy::Project => "Project",
+    };
+    println!(
+        "{:<24} {:>5} {:>10} {:>12} {:>10}",
+        label, "Runs", "Audio min", "Tokens", "Cost USD"
+    );
+    let mut all = Total::default();
+    for (key, t) in &totals {
+        println!(
+            "{:<24} {:>5} {:>10.1} {:>12} {:>10.2}",
+            key, t.runs, t.audio_minutes, t.tokens, t.cost_usd
+        );
+        all.runs += t.runs;
+        all.audio_minutes += t.audio_minutes;
+        all.tokens += t.tokens;
+        all.cost_usd += t.cost_usd;
+    }
+    println!(
+        "{:<24} {:>5} {:>10.1} {:>12} {:>10.2}",
+        "Total", all.runs, all.audio_minutes, all.tokens, all.cost_usd
+    );
+    println!("Costs are estimates from list prices; unknown models count as 0.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost_by_prefix() {
+        let audio = ModelUsage {
+            requests: 1,
+            audio_seconds: 600.0,
+            ..Default::default()
+        };
+        assert!((estimate_cost("whisper-1", &audio).unwrap() - 0.06).abs() < 1e-9);
+        let chat = ModelUsage {
+            requests: 2,
+            prompt_tokens: 1_000_000,
+            completion_tokens: 1_000_000,
+            ..Default::default()
+        };
+        // gpt-4o-mini must not be priced as gpt-4o
+        let mini = estimate_cost("gpt-4o-mini-2024-07-18", &chat).unwrap();
+        assert!((mini - 0.75).abs() < 1e-9);
+        let batch = estimate_cost("gpt-4o-mini (batch)", &chat).unwrap();
+        assert!((batch - 0.375).abs() < 1e-9);
+        assert_eq!(estimate_cost("my-local-model", &chat), None);
+        // Speech is priced by characters, not as its chat model prefix
+        let speech = ModelUsage {
+            requests: 1,
+            characters: 1_000_000,
+            ..Default::default()
+        };
+        let tts = estimate_cost("gpt-4o-mini-tts", &speech).unwrap();
+        assert!((tts - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_append_and_summarize() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/ledger.jsonl");
+        let usage = BTreeMap::from([(
+            "whisper-1".to_string(),
+            ModelUsage {
+                requests: 1,
+                audio_seconds: 120.0,
+                ..Default::default()
+            },
+        )]);
+        for project in ["anime", "anime", "drama"] {
+            let mut e = Entry::new(project, "a.mp4", "ok", 1.0, usage.clone());
+            e.timestamp = "2026-10-01T00:00:00Z".into();
+            append(&path, &e).unwrap();
+        }
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+        let by_project = summarize(&entries, GroupBy::Project);
+        assert_eq!(by_project["anime"].runs, 2);
+        assert!((by_project["anime"].audio_minutes - 4.0).abs() < 1e-9);
+        let by_month = summarize(&entries, GroupBy::Month);
+        assert_eq!(by_month["2026-10"].runs, 3);
+    }
+}
@@ -0,0 +1,4197 @@
+//! The `jp2tw-subs` pipeline as a library, for programs that bring their own
+//! speech-to-text or translation provider: implement [`TranscriptionBackend`]
+//! or [`TranslationBackend`], register it on a [`Registry`] and run the
+//! command line with [`main_with`]. `--transcription-backend` and
+//! `--translation-backend` then pick it by name.
+
+use anyhow::{anyhow, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use tokio::sync::mpsc;
+
+mod anime;
+mod audio_cache;
+mod backend;
+mod batch;
+mod batch_api;
+mod bilibili;
+mod budget;
+mod cancel;
+mod cassette;
+mod chapters;
+mod checkpoint;
+mod concat;
+mod content_filter;
+mod doctor;
+mod download;
+mod dub;
+mod embedded;
+mod emoji;
+mod english;
+mod ensemble;
+mod entities;
+mod fonts;
+mod formats;
+mod hls;
+mod http;
+mod language_guard;
+mod layout;
+mod ledger;
+mod lint;
+mod live;
+#[cfg(feature = "native-ffmpeg")]
+mod native_ffmpeg;
+mod nle;
+mod notify;
+mod ocr;
+mod openai;
+mod plan;
+mod preset;
+mod preview;
+mod project;
+mod proofread;
+mod punctuate;
+mod qc;
+mod ratelimit;
+mod replace_rules;
+mod report;
+mod resegment;
+mod scc;
+mod sdh;
+mod segment_filter;
+mod sfx;
+mod skip;
+mod songs;
+mod stats;
+mod style_guide;
+mod subparse;
+#[cfg(feature = "symphonia-fallback")]
+mod symphonia_audio;
+mod sync;
+mod targets;
+mod telemetry;
+mod terms;
+mod themes;
+mod tm;
+mod tokens;
+mod vad;
+mod verify;
+mod wav;
+mod whisper_cpp;
+mod wrap;
+mod youtube;
+mod zh_tw;
+
+pub use backend::{BoxFuture, Config, Registry, TranscriptionBackend, TranslationBackend};
+use checkpoint::Stage;
+use openai::OpenAiClient;
+pub use openai::{
+    ApiError, ChatMessage, ChatRequest, ResponseFormat, TranscriptionRequest, TranscriptionResponse,
+};
+pub use whisper_cpp::Device;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "jp2tw-subs",
+    version,
+    about = "JP→TW subs: add Traditional Chinese subtitles (translated from Japanese audio) to MP4 videos using OpenAI",
+    subcommand_negates_reqs = true,
+    args_override_self = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Input MP4 video file, an http(s):// URL of one (downloaded to the work dir first, outputs named after it in the current directory), or a live HLS/RTMP URL with --live. Repeat to caption several parts of one recording as a single video: they are joined in order (ffmpeg concat, no re-encode) and outputs are named after the first
+    #[arg(short, long, required_unless_present = "jobs_file")]
+    input: Vec<PathBuf>,
+
+    /// CSV manifest of jobs to run in turn: a header row, then one row per input. Columns are option names (input, output_srt, output, font_size, target_lang, ...), plus glossary (= --replace-rules) and args (extra flags); they override the options given here
+    #[arg(long, conflicts_with = "input")]
+    jobs_file: Option<PathBuf>,
+
+    /// With --jobs-file: how many jobs run at once (transcription and translation overlap freely)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
+
+    /// With --jobs-file: how many burn-in encodes run at once; other jobs wait for a slot
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    encode_jobs: u32,
+
+    /// With --jobs-file: find the opening and ending themes repeated across the episodes (audio fingerprints, each compared with its neighbours in the manifest) and skip them, captioned （片頭曲）/（片尾曲）, or subtitle them as songs
+    #[arg(long, value_enum, default_value_t = themes::DetectOpEd::Off, requires = "jobs_file")]
+    detect_op_ed: themes::DetectOpEd,
+
+    /// Output SRT subtitle file (default: alongside input with .zh-TW.srt)
+    #[arg(long)]
+    output_srt: Option<PathBuf>,
+
+    /// Subtitle formats to write, comma-separated (srt, lrc, json, sbv, bcc, danmaku, fcpxml, premiere-xml, scc); extra formats sit next to the SRT
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "srt")]
+    format: Vec<formats::SubtitleFormat>,
+
+    /// Character set of SRT files: utf8, utf8-bom (for Windows players that misdetect plain UTF-8), or big5 (legacy Taiwanese players and set-top boxes)
+    #[arg(long, value_enum, default_value_t = formats::Charset::Utf8, conflicts_with = "live")]
+    encoding: formats::Charset,
+
+    /// Write SRT files with Windows (CRLF) line endings
+    #[arg(long, default_value_t = false, conflicts_with = "live")]
+    crlf: bool,
+
+    /// Drop segments whose Whisper no_speech_prob is above this (music/noise) when avg_logprob is also below --logprob-threshold; 1 disables
+    #[arg(long, default_value_t = 0.6)]
+    no_speech_threshold: f64,
+
+    /// avg_logprob below which a likely no-speech segment is dropped
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    logprob_threshold: f64,
+
+    /// Flag cues whose Whisper avg_logprob is below this as low confidence (JSON sidecar, review SRT)
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    review_threshold: f64,
+
+    /// Also write <name>.review.srt with low-confidence cues prefixed by a marker, for editors to re-check
+    #[arg(long, default_value_t = false)]
+    review_srt: bool,
+
+    /// Proofread the translations for likely zh-TW typos, wrong measure words, and mixed variant forms (flagged in the HTML report and review SRT; nothing is changed)
+    #[arg(long)]
+    proofread: bool,
+
+    /// TOML file of extra [[typo]] entries (wrong, right) for --proofread
+    #[arg(long, requires = "proofread")]
+    proofread_dict: Option<PathBuf>,
+
+    /// Also write <name>.report.html: a self-contained QC page with every cue's text, timing, reading speed, confidence, and QA flags, plus summary charts
+    #[arg(long, default_value_t = false)]
+    html_report: bool,
+
+    /// Re-translate, one line per request, lines whose translation is far longer or shorter than the Japanese (they are flagged for review either way). =false turns off a preset's
+    #[arg(long, num_args(0..=1), require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    retranslate_length_outliers: bool,
+
+    /// Output MP4 file path (default name if omitted). Can be passed without a value.
+    #[arg(long = "output", num_args(0..=1), default_missing_value = "__AUTO__")]
+    output: Option<String>,
+
+    /// Burn subtitles into the video (re-encode). Default: on.
+    #[arg(long, default_value_t = true)]
+    burn_in: bool,
+
+    /// Also write one SRT per chapter of the input, timed from zero (name.chNN.zh-TW.srt); with --output, burn one MP4 per chapter (name.chNN.mp4) instead of the whole video
+    #[arg(long, default_value_t = false)]
+    split_by_chapter: bool,
+
+    /// Output bilingual subtitles (ZH first line, JP second line unless --bilingual-order says otherwise). Default: on.
+    #[arg(long, default_value_t = true)]
+    bilingual: bool,
+
+    /// Bilingual line order
+    #[arg(long, value_enum, default_value_t = layout::LineOrder::ZhJa)]
+    bilingual_order: layout::LineOrder,
+
+    /// What separates the two languages of a bilingual cue
+    #[arg(long, value_enum, default_value_t = layout::Separator::Newline)]
+    bilingual_separator: layout::Separator,
+
+    /// Emoji handling per output: a mode (keep, strip, text) for all outputs and/or OUTPUT=MODE overrides for srt, vtt, lrc, json, sbv, burn-in. `text` swaps common emoji for a bracketed word
+    #[arg(long, default_value = "keep,burn-in=text", value_parser = emoji::Policy::parse)]
+    emoji: emoji::Policy,
+
+    /// Wrap subtitle lines longer than this many characters, following CJK line-breaking rules (default: no wrapping)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(4..))]
+    max_line_chars: Option<u32>,
+
+    /// Deliver to a professional style guide: cue timing is fixed to its duration and gap rules, Chinese lines are wrapped to its line length (unless --max-line-chars is given), and cues that still break it are reported
+    #[arg(long, value_enum)]
+    style_guide: Option<style_guide::StyleGuide>,
+
+    /// Burn-in placement of bilingual cues: stacked at the bottom, or split with Japanese at the top of the frame and Chinese at the bottom
+    #[arg(long, value_enum, default_value_t = layout::Position::Stacked)]
+    bilingual_position: layout::Position,
+
+    /// The bilingual language that --secondary-scale and --secondary-opacity apply to
+    #[arg(long, value_enum, default_value_t = layout::Lang::Ja)]
+    bilingual_secondary: layout::Lang,
+
+    /// Burn-in: secondary language font size, percent of the primary (e.g. 75)
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(10..=400))]
+    secondary_scale: u32,
+
+    /// Burn-in: secondary language opacity in percent, to dim it (e.g. 70)
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(0..=100))]
+    secondary_opacity: u32,
+
+    /// Subtitle languages, comma-separated; must include zh-TW. Each extra language (e.g. zh-TW,en) gets its own SRT and HLS track from the same transcription
+    #[arg(long, value_delimiter = ',', default_value = "zh-TW")]
+    target_lang: Vec<String>,
+
+    /// Directory containing fonts for burn-in (libass fontsdir)
+    #[arg(long, default_value = "./fonts")]
+    font_dir: Option<PathBuf>,
+
+    /// Preferred font family name for burn-in (e.g., "Noto Sans CJK TC")
+    #[arg(long, default_value = "Noto Sans CJK TC")]
+    font_name: Option<String>,
+
+    /// Font size for burn-in (ASS). If not set, uses 36 normally, 30 when --bilingual.
+    #[arg(long)]
+    font_size: Option<u32>,
+
+    /// Bilingual burn-in: font family for the Japanese line (default: --font-name)
+    #[arg(long)]
+    ja_font_name: Option<String>,
+
+    /// Bilingual burn-in: font size for the Japanese line (default: --font-size, scaled by --secondary-scale when Japanese is secondary)
+    #[arg(long)]
+    ja_font_size: Option<u32>,
+
+    /// Burn-in: colour of the Chinese line, RRGGBB (default: FFFFFF)
+    #[arg(long, default_value = "FFFFFF", value_parser = layout::parse_rgb)]
+    zh_color: u32,
+
+    /// Bilingual burn-in: colour of the Japanese line, RRGGBB (default: FFFFFF)
+    #[arg(long, default_value = "FFFFFF", value_parser = layout::parse_rgb)]
+    ja_color: u32,
+
+    /// Burn-in: a small credit line such as "翻譯：小明", shown at the start of the video
+    #[arg(long)]
+    credit: Option<String>,
+
+    /// Corner for --credit
+    #[arg(long, value_enum, default_value_t = layout::Corner::TopRight)]
+    credit_position: layout::Corner,
+
+    /// Seconds --credit stays on screen from the start; 0 keeps it for the whole video
+    #[arg(long, default_value_t = 5.0)]
+    credit_seconds: f64,
+
+    /// Settings bundle across the pipeline (models, review passes, encoder speed); flags given explicitly or in the project file still win
+    #[arg(long, value_enum)]
+    preset: Option<preset::Preset>,
+
+    /// Whisper model for transcription
+    #[arg(long, default_value = "whisper-1")]
+    whisper_model: String,
+
+    /// Check the language Whisper hears at the start of the first chunk (an extra pass over its first 30 s without the Japanese hint) and abort (default) or warn when it is not Japanese, e.g. an English dub track; off skips that pass
+    #[arg(long, value_enum, default_value_t = language_guard::LanguageGuard::Abort)]
+    language_guard: language_guard::LanguageGuard,
+
+    /// Max seconds per audio chunk for transcription
+    #[arg(long, default_value_t = 600)]
+    chunk_seconds: u32,
+
+    /// Chat model for translation
+    #[arg(long, default_value = "gpt-4o-mini")]
+    translate_model: String,
+
+    /// Whether --translate-model was given (also by a preset or the project
+    /// file) rather than left at its default
+    #[arg(skip)]
+    translate_model_given: bool,
+
+    /// Budget in USD for the run: pick the most capable translation model predicted to stay under it for the input's duration (replaces --translate-model, with a warning when one was given; --whisper-model is kept) and print the choice before starting
+    #[arg(long, value_name = "USD", conflicts_with = "live")]
+    target_cost: Option<f64>,
+
+    /// Translate through this language first (e.g. en): JA→pivot with --pivot-model, then pivot→zh-TW with --translate-model. The JSON sidecar keeps both texts
+    #[arg(long)]
+    pivot: Option<String>,
+
+    /// Chat model for the JA→pivot hop (default: --translate-model)
+    #[arg(long)]
+    pivot_model: Option<String>,
+
+    /// Request this many translations of each batch and have a judge model pick or merge the best line by line (about N+1 times the translation cost)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    candidates: u32,
+
+    /// Comma-separated models the --candidates cycle through (default: --translate-model)
+    #[arg(long, value_delimiter = ',')]
+    candidate_models: Vec<String>,
+
+    /// Chat model that judges --candidates (default: --translate-model)
+    #[arg(long)]
+    judge_model: Option<String>,
+
+    /// Sampling temperature for translation requests (default: the model's)
+    #[arg(long)]
+    translate_temperature: Option<f32>,
+
+    /// Nucleus sampling top_p for translation requests (default: the model's)
+    #[arg(long)]
+    top_p: Option<f32>,
+
+    /// Reply token limit for translation requests; raise it if long batches come back cut off (default: the model's)
+    #[arg(long)]
+    max_tokens: Option<u32>,
+
+    /// Ask for plain JSON objects instead of schema-checked structured outputs, for OpenAI-compatible servers that reject json_schema
+    #[arg(long, default_value_t = false)]
+    no_structured_output: bool,
+
+    /// Max subtitle lines per translation batch
+    #[arg(long, default_value_t = 60)]
+    translate_batch_size: usize,
+
+    /// Estimated tokens per translation request (instructions, Japanese lines, and the expected reply); a batch closes at this or --translate-batch-size lines, whichever comes first
+    #[arg(long, default_value_t = 4000)]
+    translate_batch_tokens: u64,
+
+    /// SDH (for deaf and hard-of-hearing viewers): keep music as （音樂） cues and speaker labels, turn sound annotations such as （笑） into zh-TW descriptions, and apply SDH timing (at least 1 s per cue, a gap between cues)
+    #[arg(long, default_value_t = false)]
+    sdh: bool,
+
+    /// Content type: dialog (default) or lyrics (phrase-per-line cues, poetic song translation)
+    #[arg(long, value_enum, default_value_t = Mode::Dialog)]
+    mode: Mode,
+
+    /// Sung sections (OP/ED, insert songs), found from Whisper's ♪ marks: off, style (italics with a ♪ prefix), or loose (styled and retranslated as lyrics)
+    #[arg(long, value_enum, default_value_t = songs::Songs::Off, conflicts_with = "live")]
+    songs: songs::Songs,
+
+    /// Mark a window as sung whatever Whisper's ♪ marks say: START-END, repeatable; styled as with --songs style (or loose)
+    #[arg(long, value_parser = songs::parse_range, conflicts_with = "live")]
+    song_range: Vec<skip::SkipRange>,
+
+    /// Shrink each cue to where speech starts and stops in the audio (local energy-based voice activity detection), trimming the silence Whisper leaves at either end; cues only get shorter
+    #[arg(long, conflicts_with = "live")]
+    tighten_cues: bool,
+
+    /// Pipe the cues as JSON through this command (read back from its stdout) before translation and again before writing, to edit, drop, or add cues; see the README for the format
+    #[arg(long, conflicts_with = "live")]
+    segment_filter: Option<String>,
+
+    /// Skip probing burned-in and joined videos with ffprobe afterwards (duration within tolerance of the source, video and audio streams present)
+    #[arg(long)]
+    no_verify_output: bool,
+
+    /// Synthesize the translation as a zh-TW voice track with OpenAI text-to-speech, fitted to the cues; added to the burned-in video as an extra audio track, else written as <name>.dub.wav
+    #[arg(long, conflicts_with_all = ["replay", "live"])]
+    dub: bool,
+
+    /// Text-to-speech model for --dub
+    #[arg(long, default_value = "tts-1")]
+    dub_model: String,
+
+    /// Voice for --dub (alloy, nova, onyx, shimmer, ...)
+    #[arg(long, default_value = "alloy")]
+    dub_voice: String,
+
+    /// Leave a window out of transcription and translation, e.g. the OP or a sponsor read: START-END, optionally =CAPTION to show one placeholder cue for it instead (`00:00-01:30=（片頭曲）`); repeatable
+    #[arg(long, value_parser = skip::parse, conflicts_with = "live")]
+    skip_range: Vec<skip::SkipRange>,
+
+    /// English words and acronyms in the Japanese (AI, YouTube, OK): keep them as written in the zh-TW line (checked and retried when dropped), or translate them like any other word
+    #[arg(long, value_enum, default_value_t = english::English::Keep)]
+    english: english::English,
+
+    /// How honorific suffixes on names are translated: translate (conveyed through wording), keep (田中さん stays 田中さん), or map (さん becomes 先生/小姐, 様 becomes 大人, ...)
+    #[arg(long, value_enum, default_value_t = anime::Honorifics::Translate)]
+    honorifics: anime::Honorifics,
+
+    /// Keep attack and technique names as called out in Japanese, with a zh-TW gloss in parentheses. =false turns off a preset's
+    #[arg(long, num_args(0..=1), require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    keep_techniques: bool,
+
+    /// Skip the run-level name table (proper nouns translated once and enforced across batches). =false keeps it when a preset skips it
+    #[arg(long, num_args(0..=1), require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    no_name_table: bool,
+
+    /// Keep Whisper's segmentation instead of regrouping transcribed dialog into one cue per sentence (at 。！？)
+    #[arg(long, default_value_t = false)]
+    no_sentence_split: bool,
+
+    /// Keep Whisper's cues but translate whole sentences: consecutive segments are grouped into sentences for translation and each translation is split back across its cues by length
+    #[arg(long, default_value_t = false, conflicts_with = "no_sentence_split")]
+    sentence_groups: bool,
+
+    /// Keep the translation's punctuation as the model wrote it instead of normalizing it to Taiwanese conventions (full-width marks, 「」 quotes, …… and ——)
+    #[arg(long, default_value_t = false)]
+    keep_punctuation: bool,
+
+    /// Before translating, have --translate-model re-punctuate the transcript and fix obvious mis-hearings (one extra request per 40 lines). =false turns off a preset's
+    #[arg(long, num_args(0..=1), require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    punctuate: bool,
+
+    /// Translation memory (TMX): reuse matches from earlier runs before calling the API, and store new pairs
+    #[arg(long)]
+    tm: Option<PathBuf>,
+
+    /// Merge these TMX files into --tm before translating (repeatable)
+    #[arg(long, requires = "tm")]
+    tm_import: Vec<PathBuf>,
+
+    /// Minimum similarity (0-1) for reusing a non-exact memory match; 1 = exact only
+    #[arg(long, default_value_t = 0.95)]
+    tm_min_similarity: f64,
+
+    /// TOML file of ordered regex find/replace rules applied to translations (and optionally the JA line) before writing
+    #[arg(long)]
+    replace_rules: Option<PathBuf>,
+
+    /// TOML file of compliance terms: [[banned]] terms (replaced when they have a replacement) and [[required]] brand spellings, enforced on translations; what cannot be fixed is reported
+    #[arg(long)]
+    terms: Option<PathBuf>,
+
+    /// TOML file of names to pin ([[name]] entries with ja, zh, and optional variants): given to every batch and enforced on the output, ahead of names the run finds itself
+    #[arg(long)]
+    names: Option<PathBuf>,
+
+    /// Post-process translations to mask or soften profanity and slurs
+    #[arg(long, value_enum, default_value_t = content_filter::FilterMode::Off)]
+    content_filter: content_filter::FilterMode,
+
+    /// Extra words for --content-filter, one per line (`word` or `word=replacement`)
+    #[arg(long)]
+    content_filter_words: Option<PathBuf>,
+
+    /// Onomatopoeia / sound-effect lines (ドキドキ, ガタン): translate, keep, bracket as （音效）, or drop
+    #[arg(long, value_enum, default_value_t = sfx::SfxMode::Translate)]
+    sfx: sfx::SfxMode,
+
+    /// Removed: the SRT is now always written chunk by chunk; rejected with a hint to drop it
+    #[arg(long, default_value_t = false, hide = true)]
+    stream: bool,
+
+    /// Retry only the chunks and translation batches that failed in the previous run, then rewrite the outputs
+    #[arg(long, default_value_t = false)]
+    repair: bool,
+
+    /// Send translation batches through the OpenAI Batch API (half price, results within 24 h): the run transcribes, submits one job, and stops; rerun the same command to collect the results and write the outputs
+    #[arg(long, default_value_t = false, conflicts_with_all = ["candidates", "pivot", "live"])]
+    batch_api: bool,
+
+    /// With --batch-api, wait for a submitted job to finish (checking every minute) instead of stopping
+    #[arg(long, default_value_t = false, requires = "batch_api")]
+    batch_wait: bool,
+
+    /// Project name recorded in the cost ledger (default: the input's parent directory name)
+    #[arg(long)]
+    project: Option<String>,
+
+    /// Cost ledger file, one JSON line per run (default: $JP2TW_LEDGER or jp2tw-subs/ledger.jsonl in the user data dir)
+    #[arg(long, global = true)]
+    ledger: Option<PathBuf>,
+
+    /// Export OpenTelemetry spans for pipeline stages and API calls to this OTLP/HTTP endpoint (e.g. http://localhost:4318); also read from OTEL_EXPORTER_OTLP_ENDPOINT
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// POST a JSON run summary (input, outputs, duration, cost, status) to this webhook when the run ends
+    #[arg(long)]
+    notify_url: Option<String>,
+
+    /// Webhook payload shape: plain JSON summary, or a Slack/Discord message
+    #[arg(long, value_enum, default_value_t = notify::NotifyFormat::Json)]
+    notify_format: notify::NotifyFormat,
+
+    /// Show a desktop notification with the output path and elapsed time when the run ends
+    #[arg(long, default_value_t = false)]
+    desktop_notify: bool,
+
+    /// Shift every cue by this many seconds (negative = earlier, clamped at 0) to fix player or source sync
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    offset: f64,
+
+    /// Correctly timed Japanese SRT for this video: cues matching it (text + time) adopt its timing
+    #[arg(long)]
+    sync_reference: Option<PathBuf>,
+
+    /// Rescale all timestamps for a copy played at a different speed: a ratio like 25/23.976 (PAL speed-up) or a plain factor; times are divided by it before --offset
+    #[arg(long, default_value = "1", value_parser = parse_ratio)]
+    speed_factor: f64,
+
+    /// Extra ffmpeg options for audio extraction, inserted before the output file (e.g. "-af loudnorm")
+    #[arg(long, allow_hyphen_values = true)]
+    ffmpeg_extract_args: Option<String>,
+
+    /// Extra ffmpeg options for the burn-in encode, inserted before the output file (e.g. "-tune animation -movflags +faststart")
+    #[arg(long, allow_hyphen_values = true)]
+    ffmpeg_encode_args: Option<String>,
+
+    /// Encoder threads for the burn-in (ffmpeg -threads); fewer leaves cores free for other work
+    #[arg(long)]
+    encode_threads: Option<u32>,
+
+    /// Run the burn-in encode at background priority (nice 10; below-normal priority on Windows) so the machine stays responsive
+    #[arg(long, default_value_t = false)]
+    nice: bool,
+
+    /// Print the ffmpeg commands, chunk layout, and API requests a run would make, then exit without running anything
+    #[arg(long, default_value_t = false)]
+    plan: bool,
+
+    /// Keep intermediate files (audio, chunks, ASS, API traces) in this directory instead of a temporary one
+    #[arg(long)]
+    work_dir: Option<PathBuf>,
+
+    /// Write every OpenAI request payload and raw response (API key redacted) to <work-dir>/api-traces
+    #[arg(long, default_value_t = false)]
+    save_api_traces: bool,
+
+    /// Save every OpenAI response, with the request it answered, to this cassette file for --replay
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer OpenAI requests from a --record cassette instead of the API: rerun offline (new styling or formats) without an API key or credits; a request it does not hold stops the run
+    #[arg(long, conflicts_with_all = ["batch_api", "live"])]
+    replay: Option<PathBuf>,
+
+    /// Speech-to-text provider, by name from the backend registry
+    #[arg(long, default_value = backend::DEFAULT)]
+    transcription_backend: String,
+
+    /// ggml model file for --transcription-backend whisper-cpp (e.g. ggml-large-v3.bin)
+    #[arg(long)]
+    whisper_cpp_model: Option<PathBuf>,
+
+    /// Where whisper.cpp runs: auto uses the GPU when one is found and falls back to the CPU when a GPU run fails
+    #[arg(long, value_enum, default_value_t = whisper_cpp::Device::Auto)]
+    whisper_device: whisper_cpp::Device,
+
+    /// Provider for translation and the other chat requests, by name from the backend registry
+    #[arg(long, default_value = backend::DEFAULT)]
+    translation_backend: String,
+
+    /// Base URL of an OpenAI-compatible API to call instead of api.openai.com (a proxy or a local server); OPENAI_API_KEY is then optional
+    #[arg(long)]
+    api_base_url: Option<String>,
+
+    /// Seconds an OpenAI or YouTube request (upload and response included) may take before it is retried
+    #[arg(long, default_value_t = 600)]
+    http_timeout: u64,
+
+    /// Seconds to wait for a connection to be established
+    #[arg(long, default_value_t = 30)]
+    http_connect_timeout: u64,
+
+    /// Idle connections kept open per host for reuse by later requests
+    #[arg(long, default_value_t = 16)]
+    http_pool_size: usize,
+
+    /// Translate an embedded Japanese text subtitle track instead of transcribing the audio
+    #[arg(long, default_value_t = false)]
+    use_embedded_subs: bool,
+
+    /// OCR burned-in Japanese subtitles (tesseract, jpn) instead of transcribing the audio
+    #[arg(long, default_value_t = false, conflicts_with = "use_embedded_subs")]
+    ocr_subs: bool,
+
+    /// Frames per second sampled for --ocr-subs
+    #[arg(long, default_value_t = 2.0)]
+    ocr_fps: f64,
+
+    /// Bottom fraction of the frame scanned for burned-in subtitles
+    #[arg(long, default_value_t = 0.25)]
+    ocr_region: f64,
+
+    /// Burn-in: OCR a few frames for subtitles already burned into the bottom of the picture (tesseract, jpn) and, when found, place the new ones at the top
+    #[arg(long, default_value_t = false)]
+    avoid_hardsubs: bool,
+
+    /// Also write an HLS package to this directory: source video segments (stream copy), WebVTT subtitle segments, and playlists
+    #[arg(long)]
+    hls_dir: Option<PathBuf>,
+
+    /// Target HLS segment duration in seconds
+    #[arg(long, default_value_t = 6)]
+    hls_time: u32,
+
+    /// Treat --input as a live stream (HLS/RTMP URL): caption it continuously, appending to SRT/VTT
+    #[arg(long, default_value_t = false)]
+    live: bool,
+
+    /// Seconds of audio per live transcription window (lower = less latency)
+    #[arg(long, default_value_t = 15)]
+    live_window: u32,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Summarize spend recorded in the cost ledger
+    Costs {
+        /// Group runs by calendar month or by project
+        #[arg(long, value_enum, default_value_t = ledger::GroupBy::Month)]
+        by: ledger::GroupBy,
+    },
+    /// Create a .jp2tw.toml project file (plus starter glossary.toml and names.toml) in the current directory; runs from that directory then use its settings
+    Init {
+        /// Overwrite an existing .jp2tw.toml
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Upload a subtitle file (SRT, SBV, WebVTT) to a YouTube video as a caption track, replacing the file of an existing track with the same language and name. Needs an OAuth token with the youtube.force-ssl scope: YOUTUBE_ACCESS_TOKEN, or --credentials
+    UploadCaptions {
+        /// The video's id (the v= part of its URL)
+        #[arg(long)]
+        video_id: String,
+        file: PathBuf,
+        /// Track language
+        #[arg(long, default_value = "zh-TW")]
+        language: String,
+        /// Track name shown in the caption menu
+        #[arg(long, default_value = "繁體中文")]
+        name: String,
+        /// Upload as a draft, hidden from viewers until published in YouTube Studio
+        #[arg(long, default_value_t = false)]
+        draft: bool,
+        /// JSON file with client_id, client_secret, and refresh_token for minting an access token
+        #[arg(long)]
+        credentials: Option<PathBuf>,
+    },
+    /// Render the frame at --at of --input, with its subtitle burned in as a run with the same style flags (given before the subcommand) would, to a PNG. Several --at times or --variant styles are tiled into one contact sheet
+    Preview {
+        /// Time of the frame: HH:MM:SS[.mmm], MM:SS, or seconds; repeat for a row per time
+        #[arg(long, required = true, value_parser = preview::parse_at)]
+        at: Vec<f64>,
+        /// Candidate style to compare, as comma-separated option=value pairs named like the flags (font-name=Noto Serif CJK TC,font-size=40); repeat for a labelled column per variant
+        #[arg(long)]
+        variant: Vec<String>,
+        /// Subtitles to draw: a JSON sidecar (both languages) or SRT/WebVTT/ASS. Defaults to the run's JSON sidecar, then its SRT
+        #[arg(long, conflicts_with = "text")]
+        subs: Option<PathBuf>,
+        /// Draw this Chinese text instead (\n for a line break)
+        #[arg(long)]
+        text: Option<String>,
+        /// Japanese text to pair with --text in bilingual layouts
+        #[arg(long, requires = "text")]
+        ja_text: Option<String>,
+        /// PNG to write; defaults to <input name>.preview.png
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Check the environment (ffmpeg and libass, fonts, the API key and network, temp disk space) and print fixes for what is missing; exits non-zero when a check fails
+    Doctor,
+    /// List the font families in the fonts directory and system font locations that cover Traditional Chinese, and show which font --font-name (given before the subcommand) resolves to for burn-in
+    Fonts,
+    /// Summarize subtitle files (SRT, WebVTT, ASS): cue count, speech time, characters-per-second and characters-per-line distributions, the longest and shortest cues, and gaps
+    Stats {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Check subtitle files (SRT, WebVTT, ASS) for timing, reading-speed, and character problems; exits non-zero on errors
+    Lint {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Max characters per second, whitespace excluded
+        #[arg(long, default_value_t = 9.0)]
+        max_cps: f64,
+        /// Max characters per line
+        #[arg(long, default_value_t = 16)]
+        max_line_chars: usize,
+        /// Max lines per cue
+        #[arg(long, default_value_t = 2)]
+        max_lines: usize,
+        /// Fail on warnings too
+        #[arg(long, default_value_t = false)]
+        strict: bool,
+    },
+}
+
+impl Args {
+    fn pivot_model(&self) -> &str {
+        self.pivot_model.as_deref().unwrap_or(&self.translate_model)
+    }
+
+    /// `--encoding` and `--crlf`, for SRT files.
+    fn srt_encoding(&self) -> formats::TextEncoding {
+        formats::TextEncoding {
+            charset: self.encoding,
+            crlf: self.crlf,
+        }
+    }
+
+    /// `--input`, which clap requires unless a subcommand or `--jobs-file`
+    /// is given.
+    fn input(&self) -> &Path {
+        self.input
+            .first()
+            .expect("--input is required without a subcommand or --jobs-file")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Spoken dialog
+    Dialog,
+    /// Songs and music videos
+    Lyrics,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct WhisperSegment {
+    pub id: Option<u32>,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// Whisper confidence fields; absent for embedded/OCR subtitles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_logprob: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f64>,
+}
+
+impl WhisperSegment {
+    /// Whether Whisper was unsure about this segment: average token log
+    /// probability below `threshold`. Segments without scores never are.
+    fn is_low_confidence(&self, threshold: f64) -> bool {
+        self.avg_logprob.is_some_and(|lp| lp < threshold)
+    }
+
+    /// Whisper's own silence heuristic: the model thinks there is no speech
+    /// and the text it produced anyway is improbable.
+    fn is_non_speech(&self, no_speech_threshold: f64, logprob_threshold: f64) -> bool {
+        self.no_speech_prob.is_some_and(|p| p > no_speech_threshold)
+            && self.avg_logprob.is_none_or(|lp| lp < logprob_threshold)
+    }
+}
+
+/// A test segment from `start` to `end` with `text`.
+#[cfg(test)]
+fn seg(start: f64, end: f64, text: &str) -> WhisperSegment {
+    WhisperSegment {
+        start,
+        end,
+        text: text.to_string(),
+        ..Default::default()
+    }
+}
+
+/// `--tighten-cues` for the segments of `chunk`, which starts `start` seconds
+/// into the input. Failing to read the audio only warns.
+fn tighten_cues(segments: &mut [WhisperSegment], chunk: &Path, start: f64) {
+    match vad::tighten(segments, chunk, start) {
+        Ok(0) => {}
+        Ok(n) => eprintln!(
+            "Tightened {n}/{} cues to the speech in {}",
+            segments.len(),
+            chunk.display()
+        ),
+        Err(e) => eprintln!("Warning: --tighten-cues skipped {}: {e:#}", chunk.display()),
+    }
+}
+
+/// Remove segments that are almost certainly music or noise instead of
+/// translating hallucinated lines.
+fn drop_non_speech(segments: &mut Vec<WhisperSegment>, args: &Args) {
+    let before = segments.len();
+    segments.retain_mut(|s| {
+        // SDH describes music instead of dropping it
+        !s.is_non_speech(args.no_speech_threshold, args.logprob_threshold)
+            || args.sdh && sdh::keep_as_music(s)
+    });
+    if segments.len() < before {
+        eprintln!("Dropped {} non-speech segments", before - segments.len());
+    }
+}
+
+/// Parse `argv` like `Args::parse_from`, also noting which settings were
+/// given rather than defaulted.
+fn parse_args(argv: Vec<OsString>) -> Result<Args, clap::Error> {
+    let matches = Args::command().try_get_matches_from(argv)?;
+    let mut args = Args::from_arg_matches(&matches)?;
+    args.translate_model_given =
+        matches.value_source("translate_model") == Some(ValueSource::CommandLine);
+    Ok(args)
+}
+
+/// The `jp2tw-subs` command line, transcribing and translating with the
+/// providers in `backends`.
+pub async fn main_with(backends: Registry) -> Result<()> {
+    backend::install(backends);
+    let mut args =
+        parse_args(preset::argv(project::argv(env::args_os())?)).unwrap_or_else(|e| e.exit());
+    project::expand_templates(&mut args);
+    if project::active() && !matches!(args.command, Some(Commands::Init { .. })) {
+        eprintln!("Using project settings from {}", project::FILE);
+    }
+    if let Some(preset) = args.preset {
+        eprintln!(
+            "Using the {} preset",
+            preset.to_possible_value().unwrap().get_name()
+        );
+    }
+    http::init(http::Options {
+        timeout: Duration::from_secs(args.http_timeout.max(1)),
+        connect_timeout: Duration::from_secs(args.http_connect_timeout.max(1)),
+        pool_size: args.http_pool_size,
+    })?;
+    let ledger_path = args.ledger.clone().or_else(ledger::default_path);
+    match &args.command {
+        Some(Commands::Costs { by }) => {
+            let path = ledger_path.context("No cost ledger location; pass --ledger")?;
+            return ledger::print_summary(&path, *by);
+        }
+        Some(Commands::Lint {
+            files,
+            max_cps,
+            max_line_chars,
+            max_lines,
+            strict,
+        }) => {
+            let limits = lint::Limits {
+                max_cps: *max_cps,
+                max_line_chars: *max_line_chars,
+                max_lines: *max_lines,
+            };
+            if !lint::run(files, &limits, *strict)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Stats { files }) => return stats::run(files),
+        Some(Commands::Fonts) => return fonts::run(&args),
+        Some(Commands::Doctor) => {
+            if !doctor::run(&args).await? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Init { force }) => return project::init(*force),
+        Some(Commands::Preview {
+            at,
+            variant,
+            subs,
+            text,
+            ja_text,
+            out,
+        }) => {
+            let req = preview::Request {
+                at,
+                variants: variant,
+                subs: subs.as_deref(),
+                text: text.as_deref(),
+                ja_text: ja_text.as_deref(),
+                out: out.as_deref(),
+            };
+            return preview::render(&args, &req);
+        }
+        Some(Commands::UploadCaptions {
+            video_id,
+            file,
+            language,
+            name,
+            draft,
+            credentials,
+        }) => {
+            let upload = youtube::Upload {
+                video_id,
+                file,
+                language,
+                name,
+                draft: *draft,
+            };
+            return youtube::upload(&upload, credentials.as_deref()).await;
+        }
+        None => {}
+    }
+    if let Some(path) = &args.jobs_file {
+        cancel::install();
+        // A plan runs nothing, OP/ED detection included
+        let detect = if args.plan {
+            themes::DetectOpEd::Off
+        } else {
+            args.detect_op_ed
+        };
+        let result = batch::run(path, args.jobs, args.encode_jobs, detect).await;
+        exit_if_cancelled();
+        return result;
+    }
+    if args.plan {
+        return plan::print(&args);
+    }
+
+    cancel::install();
+    let result = run_reported(args).await;
+    exit_if_cancelled();
+    result.map(|_| ())
+}
+
+fn exit_if_cancelled() {
+    if cancel::is_cancelled() {
+        match cancel::resume_hint() {
+            Some(hint) => eprintln!("Interrupted. {hint}"),
+            None => eprintln!("Interrupted."),
+        }
+        std::process::exit(130);
+    }
+}
+
+/// [`run`] plus what follows every run: the cost ledger entry, trace export,
+/// and notifications.
+async fn run_reported(args: Args) -> Result<Vec<PathBuf>> {
+    telemetry::scope(report(&args)).await
+}
+
+/// Body of [`run_reported`], inside its own trace collector.
+async fn report(args: &Args) -> Result<Vec<PathBuf>> {
+    let ledger_path = args.ledger.clone().or_else(ledger::default_path);
+    if let Some(url) = telemetry::endpoint(args.otlp_endpoint.as_deref()) {
+        telemetry::init(url);
+    }
+    let started = Instant::now();
+    let (result, usage) = openai::metered(run(args.clone())).await;
+    let status = if cancel::is_cancelled() {
+        "cancelled"
+    } else if result.is_ok() {
+        "ok"
+    } else {
+        "failed"
+    };
+    let error = result.as_ref().err().map(|e| format!("{e:#}"));
+    let entry = (!usage.is_empty()).then(|| {
+        let project = args.project.clone().unwrap_or_else(|| {
+            std::fs::canonicalize(args.input())
+                .ok()
+                .and_then(|p| Some(p.parent()?.file_name()?.to_string_lossy().into_owned()))
+                .unwrap_or_default()
+        });
+        ledger::Entry::new(
+            &project,
+            &args.input().to_string_lossy(),
+            status,
+            started.elapsed().as_secs_f64(),
+            usage,
+        )
+    });
+    if let Some(entry) = &entry {
+        eprintln!("Estimated cost: ${:.4}", entry.cost_usd);
+        if let Some(path) = &ledger_path {
+            if let Err(e) = ledger::append(path, entry) {
+                eprintln!("Warning: could not record cost ledger entry: {e:#}");
+            }
+        }
+    }
+
+    let mut root_attrs: Vec<(String, telemetry::AttrValue)> = vec![
+        (
+            "input".into(),
+            args.input().to_string_lossy().into_owned().into(),
+        ),
+        ("whisper_model".into(), args.whisper_model.clone().into()),
+        (
+            "translate_model".into(),
+            args.translate_model.clone().into(),
+        ),
+    ];
+    if let Some(entry) = &entry {
+        root_attrs.push(("cost_usd".into(), entry.cost_usd.into()));
+    }
+    if let Err(e) = telemetry::export(root_attrs, error.clone()).await {
+        eprintln!("Warning: could not export traces: {e:#}");
+    }
+
+    if args.notify_url.is_some() || args.desktop_notify {
+        let summary = notify::Summary {
+            input: args.input().to_string_lossy().into_owned(),
+            status: status.to_string(),
+            outputs: result
+                .as_ref()
+                .map(|paths| paths.iter().map(|p| p.display().to_string()).collect())
+                .unwrap_or_default(),
+            duration_secs: started.elapsed().as_secs_f64(),
+            cost_usd: entry.as_ref().map(|e| e.cost_usd),
+            error,
+        };
+        if let Some(url) = &args.notify_url {
+            if let Err(e) = notify::send(url, args.notify_format, &summary).await {
+                eprintln!("Warning: could not send notification: {e:#}");
+            }
+        }
+        if args.desktop_notify {
+            if let Err(e) = notify::desktop(&summary) {
+                eprintln!("Warning: could not show desktop notification: {e:#}");
+            }
+        }
+    }
+    result
+}
+
+/// One run of the pipeline; returns the files it wrote.
+async fn run(mut args: Args) -> Result<Vec<PathBuf>> {
+    if args.stream {
+        return Err(anyhow!(
+            "--stream was removed: every run now translates chunk by chunk and rewrites the SRT after each one, so drop the flag (for live stream URLs, use --live)"
+        ));
+    }
+    budget::apply(&mut args)?;
+    let style_rules = args.style_guide.map(style_guide::StyleGuide::rules);
+    if let Some(rules) = &style_rules {
+        args.max_line_chars
+            .get_or_insert(rules.max_line_chars as u32);
+    }
+    // Validate input
+    let extra_targets = targets::extras(&args.target_lang)?;
+    if args.format.contains(&formats::SubtitleFormat::Scc) {
+        eprintln!(
+            "Note: CEA-608 cannot carry Chinese; --format scc writes SCC for Latin-script --target-lang languages only"
+        );
+    }
+    if args.live {
+        if !live::is_stream_url(args.input()) {
+            eprintln!("Warning: --live input does not look like a stream URL; proceeding anyway");
+        }
+        if args.output.is_some() {
+            return Err(anyhow!("--output (burn-in) is not supported with --live"));
+        }
+        if args.hls_dir.is_some() {
+            return Err(anyhow!("--hls-dir is not supported with --live"));
+        }
+        if args.sync_reference.is_some() {
+            return Err(anyhow!("--sync-reference is not supported with --live"));
+        }
+        if !extra_targets.is_empty() {
+            return Err(anyhow!(
+                "--target-lang other than zh-TW is not supported with --live"
+            ));
+        }
+        if args.input.len() > 1 {
+            return Err(anyhow!("--live takes a single --input"));
+        }
+        if args.split_by_chapter {
+            return Err(anyhow!("--split-by-chapter is not supported with --live"));
+        }
+    } else {
+        for input in &args.input {
+            if !(input.exists() || download::is_url(input)) {
+                return Err(anyhow!("Input file not found: {}", input.display()));
+            }
+        }
+        if download::local_name(args.input())
+            .extension()
+            .and_then(|s| s.to_str())
+            != Some("mp4")
+        {
+            eprintln!("Warning: input is not .mp4; proceeding anyway");
+        }
+    }
+
+    // Load .env if present, then read API key
+    let _ = dotenvy::dotenv();
+    // A replayed run makes no API calls
+    let api_key = match env::var("OPENAI_API_KEY") {
+        Err(_) if args.replay.is_some() || args.api_base_url.is_some() => String::new(),
+        key => key.context("Set OPENAI_API_KEY environment variable for OpenAI access")?,
+    };
+
+    // Intermediate files go to --work-dir, else a temporary directory
+    let tmp = tempdir()?;
+    let work_dir = match &args.work_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Create work dir {}", dir.display()))?;
+            dir.clone()
+        }
+        // Traces must outlive the run
+        None if args.save_api_traces => tmp.keep(),
+        None => tmp.path().to_path_buf(),
+    };
+    let backend_config = backend::Config {
+        api_key: api_key.clone(),
+        base_url: args.api_base_url.clone(),
+        whisper_cpp_model: args.whisper_cpp_model.clone(),
+        whisper_device: args.whisper_device,
+    };
+    let mut client = OpenAiClient::new(api_key);
+    if let Some(url) = &args.api_base_url {
+        client = client.with_base_url(url);
+    }
+    let backends = backend::installed();
+    if args.transcription_backend != backend::DEFAULT {
+        client = client.with_transcriber(
+            backends.transcription(&args.transcription_backend, &backend_config)?,
+        );
+    }
+    if args.translation_backend != backend::DEFAULT {
+        client = client
+            .with_translator(backends.translation(&args.translation_backend, &backend_config)?);
+    }
+    let traces_dir = work_dir.join("api-traces");
+    if args.save_api_traces {
+        client = client
+            .with_traces(&traces_dir)
+            .with_context(|| format!("Create {}", traces_dir.display()))?;
+        eprintln!("Saving API traces to {}", traces_dir.display());
+    }
+    if let Some(path) = &args.record {
+        client = client.with_cassette(cassette::Cassette::record(path));
+        eprintln!("Recording API responses to {}", path.display());
+    } else if let Some(path) = &args.replay {
+        client = client.with_cassette(cassette::Cassette::replay(path)?);
+        eprintln!("Replaying API responses from {}", path.display());
+    }
+
+    // Prepare outputs
+    let output_srt = args
+        .output_srt
+        .clone()
+        .unwrap_or_else(|| default_srt_path(args.input()));
+    let output_mp4 = output_video_path(&args);
+    let extract_extra = split_args(args.ffmpeg_extract_args.as_deref().unwrap_or(""))?;
+    let encode_extra = encode_args(&args)?;
+    if cfg!(feature = "native-ffmpeg") && !(extract_extra.is_empty() && encode_extra.is_empty()) {
+        eprintln!(
+            "Warning: --ffmpeg-extract-args/--ffmpeg-encode-args/--encode-threads are ignored with native-ffmpeg"
+        );
+    }
+    if cfg!(feature = "native-ffmpeg") && args.nice {
+        eprintln!(
+            "Warning: --nice applies to the ffmpeg CLI only and is ignored with native-ffmpeg"
+        );
+    }
+
+    let translate_opts = TranslateOptions::from_args(&args)?;
+    let reference = match &args.sync_reference {
+        Some(path) => Some(sync::load_reference(path, args.speed_factor, args.offset)?),
+        None => None,
+    };
+
+    if args.live {
+        ensure_ffmpeg_cli().context("--live captures the stream with the ffmpeg binary")?;
+        live::run(&args, &client, &translate_opts, &output_srt).await?;
+        return Ok(vec![output_srt.clone(), output_srt.with_extension("vtt")]);
+    }
+
+    // Ensure ffmpeg exists; SRT-only runs can decode audio in-process instead
+    let burn = output_mp4.is_some() && args.burn_in;
+    let needs_ffmpeg = burn
+        || args.hls_dir.is_some()
+        || args.ocr_subs
+        || args.input.len() > 1
+        || args.split_by_chapter;
+    let have_ffmpeg = match ensure_ffmpeg(ffmpeg_cli_use(&args, burn)) {
+        Ok(()) => true,
+        Err(e) if !needs_ffmpeg && cfg!(feature = "symphonia-fallback") => {
+            eprintln!("Warning: {e:#}; decoding audio in-process (burn-in still needs ffmpeg)");
+            false
+        }
+        Err(e) => return Err(e),
+    };
+    // Outputs are already named after the URL or the first part
+    for input in &mut args.input {
+        if download::is_url(input) {
+            *input = download::fetch(&input.to_string_lossy(), &work_dir).await?;
+        }
+    }
+    if args.input.len() > 1 {
+        eprintln!("Concatenating {} inputs...", args.input.len());
+        let parts: Vec<verify::Media> = args
+            .input
+            .iter()
+            .map_while(|part| verify_source(&args, part))
+            .collect();
+        let joined = concat::join(&args.input, &work_dir)?;
+        if parts.len() == args.input.len() {
+            let total = parts.iter().map(|p| p.duration).sum::<Option<f64>>();
+            verify::check(&joined, &parts[0], total)?;
+        }
+        args.input = vec![joined];
+    }
+    let chapters = if args.split_by_chapter {
+        let chapters = chapters::probe(args.input())?;
+        if chapters.is_empty() {
+            return Err(anyhow!(
+                "--split-by-chapter: {} has no chapters",
+                args.input().display()
+            ));
+        }
+        eprintln!("Splitting outputs into {} chapters", chapters.len());
+        Some(chapters)
+    } else {
+        None
+    };
+
+    let progress = ProgressBar::new_spinner();
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} {msg}")
+            .unwrap()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "),
+    );
+
+    // 0) An embedded Japanese text subtitle track or OCR of burned-in
+    //    subtitles can replace transcription
+    let mut subtitles = embedded_transcript(&args, have_ffmpeg, &work_dir)?;
+    if args.ocr_subs {
+        let opts = ocr::OcrOptions {
+            fps: args.ocr_fps.max(0.1),
+            region: args.ocr_region,
+        };
+        let _span = telemetry::span("ocr_hardsubs");
+        let segments = ocr::transcribe_hardsubs(args.input(), &work_dir, &opts, |i, n| {
+            progress.set_message(format!("OCR of burned-in subtitles... frame {i}/{n}"))
+        })?;
+        if segments.is_empty() {
+            return Err(anyhow!(
+                "OCR found no Japanese subtitles in the caption region"
+            ));
+        }
+        subtitles = Some(segments);
+    }
+
+    // Rewrite the SRT as cues are translated, so an interrupted run still
+    // leaves a usable partial file
+    let partial_srt = (args.format.contains(&formats::SubtitleFormat::Srt) && !args.batch_api)
+        .then_some(output_srt.as_path());
+    let source = match &subtitles {
+        None => "audio",
+        Some(_) if args.ocr_subs => "ocr",
+        Some(_) => "embedded",
+    };
+    let checkpoint = checkpoint::Checkpoint::open(
+        &formats::sibling_path(&output_srt, "state", "json"),
+        checkpoint::RunKey::from_args(&args, source),
+    )?;
+    if args.repair {
+        let failed = checkpoint.failure_count();
+        if failed == 0 {
+            return Err(anyhow!(
+                "Nothing to repair: no failed chunks or batches recorded in {}",
+                formats::sibling_path(&output_srt, "state", "json").display()
+            ));
+        }
+        eprintln!("Repairing {failed} failed chunk(s)/batch(es); finished work is reused");
+    }
+    if let Some(collector) = &translate_opts.batch_api {
+        if !batch_api::collect(&client, &checkpoint, collector, args.batch_wait).await? {
+            progress.finish_and_clear();
+            return Ok(Vec::new());
+        }
+    }
+    let (mut segments, mut zh_lines) = if let Some(mut segments) = subtitles {
+        skip::drop_cues(&mut segments, &args.skip_range);
+        sfx::drop_cues(&mut segments, args.sfx);
+        progress.set_message("Translating Japanese subtitles (OpenAI GPT)...");
+        let groups = group_by_window(segments, args.chunk_seconds);
+        let (tx, rx) = mpsc::channel(groups.len().max(1));
+        for group in groups.into_iter().enumerate() {
+            tx.send(group).await?;
+        }
+        drop(tx);
+        translate_stages(
+            rx,
+            &client,
+            &args,
+            &translate_opts,
+            &checkpoint,
+            partial_srt,
+        )
+        .await?
+    } else {
+        // 1) Extract audio
+        progress.set_message("Extracting audio with ffmpeg...");
+        let wav_path = work_dir.join("audio_16k_mono.wav");
+        let extractor = match (have_ffmpeg, cfg!(feature = "native-ffmpeg")) {
+            (true, true) => "libav",
+            (true, false) => "ffmpeg",
+            (false, _) => "symphonia",
+        };
+        let source = audio_cache::Source::of(args.input(), extractor, &extract_extra)?;
+        let cached = audio_cache::lookup(&work_dir, &wav_path, &source, args.chunk_seconds);
+        // The ffmpeg CLI cuts each chunk straight from the container
+        let seek_duration = (matches!(cached, audio_cache::Cached::Nothing)
+            && extractor == "ffmpeg")
+            .then(|| probe_duration(args.input()))
+            .flatten();
+        let chunks = if let audio_cache::Cached::Chunks(paths) = cached {
+            eprintln!(
+                "Reusing the extracted audio and its {} chunks in {}",
+                paths.len(),
+                work_dir.display()
+            );
+            AudioChunks::Files(paths)
+        } else if let Some(duration) = seek_duration {
+            audio_cache::clear(&work_dir);
+            let _ = std::fs::remove_file(&wav_path);
+            remove_chunks(&work_dir);
+            let chunks = AudioChunks::seek(
+                args.input(),
+                &work_dir,
+                duration,
+                args.chunk_seconds,
+                &extract_extra,
+                args.work_dir.is_some(),
+            );
+            audio_cache::save(&work_dir, &source, Some(args.chunk_seconds), chunks.paths())?;
+            chunks
+        } else {
+            if let audio_cache::Cached::Wav = cached {
+                eprintln!("Reusing the extracted audio {}", wav_path.display());
+            } else {
+                audio_cache::clear(&work_dir);
+                let span = telemetry::span("extract_audio");
+                if have_ffmpeg {
+                    extract_audio(args.input(), &wav_path, &extract_extra, |f| {
+                        progress.set_message(format!("Extracting audio... {:.0}%", f * 100.0))
+                    })?;
+                } else {
+                    extract_audio_fallback(args.input(), &wav_path)?;
+                }
+                drop(span);
+                audio_cache::save(&work_dir, &source, None, &[])?;
+            }
+            let paths = segment_audio(&wav_path, args.chunk_seconds)?;
+            audio_cache::save(&work_dir, &source, Some(args.chunk_seconds), &paths)?;
+            AudioChunks::Files(paths)
+        };
+
+        // 2) Transcribe (Japanese) with Whisper (chunked for long videos) and
+        // 3) translate to Traditional Chinese using GPT, as overlapping stages
+        progress.set_message("Transcribing (Whisper) + translating (GPT) chunk by chunk...");
+        transcribe_translate_pipelined(
+            chunks,
+            &client,
+            &args,
+            &translate_opts,
+            &checkpoint,
+            partial_srt,
+        )
+        .await?
+    };
+    if let Some(collector) = translate_opts.batch_api.as_ref().filter(|c| c.queued() > 0) {
+        batch_api::submit(&client, &checkpoint, collector).await?;
+        progress.finish_and_clear();
+        return Ok(Vec::new());
+    }
+    let failed = checkpoint.failure_count();
+    if segments.is_empty() {
+        if failed > 0 {
+            return Err(failed_pieces_error(failed));
+        }
+        return Err(anyhow!("Whisper returned zero segments"));
+    }
+    for (mut seg, label) in skip::placeholders(&args.skip_range) {
+        retime(&mut seg, args.speed_factor, args.offset);
+        let at = segments.partition_point(|s| s.start < seg.start);
+        segments.insert(at, seg);
+        zh_lines.insert(at, label);
+    }
+    if let Some(command) = &args.segment_filter {
+        let filter = segment_filter::SegmentFilter::parse(command)?;
+        (segments, zh_lines) = filter.translated(segments, zh_lines).await?;
+    }
+    if args.sdh {
+        sdh::retime(&mut segments);
+    }
+    if let Some(rules) = &style_rules {
+        style_guide::retime(rules, &mut segments);
+    }
+    let mut ja_lines: Vec<String> = segments
+        .iter()
+        .map(|s| translate_opts.replace_rules.ja(&s.text))
+        .collect();
+    if zh_lines.len() != ja_lines.len() {
+        return Err(anyhow!(
+            "Translation count mismatch: {} vs {}",
+            zh_lines.len(),
+            ja_lines.len()
+        ));
+    }
+    if let Some(reference) = &reference {
+        let matched = sync::align(&mut segments, reference);
+        eprintln!(
+            "Synced {matched} of {} cues to the reference timing",
+            segments.len()
+        );
+    }
+    let sung = args.songs.detect(&segments, &args.song_range);
+    let sung_count = sung.iter().filter(|&&s| s).count();
+    if sung_count > 0 {
+        for i in (0..sung.len()).filter(|&i| sung[i]) {
+            ja_lines[i] = songs::strip_notes(&ja_lines[i]);
+            zh_lines[i] = songs::strip_notes(&zh_lines[i]);
+        }
+        if args.songs == songs::Songs::Loose && args.mode == Mode::Dialog {
+            progress.set_message("Retranslating sung lines as lyrics...");
+            retranslate_songs(&ja_lines, &mut zh_lines, &sung, &client, &translate_opts).await?;
+        }
+        eprintln!("{sung_count} of {} cues are sung", segments.len());
+    }
+    let mut qa_issues = style_rules
+        .as_ref()
+        .map(|rules| style_guide::report(rules, &segments, &zh_lines))
+        .unwrap_or_default();
+    if let Some(guide) = args.style_guide {
+        let issues = &qa_issues;
+        let name = guide.name();
+        let shown = 20;
+        if issues.is_empty() {
+            eprintln!("Style guide {name}: all cues comply");
+        } else {
+            for issue in issues.iter().take(shown) {
+                eprintln!("Style guide {name}: {issue}");
+            }
+            if issues.len() > shown {
+                eprintln!("... and {} more", issues.len() - shown);
+            }
+            eprintln!(
+                "Style guide {name}: {} issue(s) left to fix by hand (check the SRT with `jp2tw-subs lint`)",
+                issues.len()
+            );
+        }
+    }
+    if args.terms.is_some() {
+        // Again here for lines a --segment-filter or checkpoint supplied
+        for zh in zh_lines.iter_mut() {
+            *zh = translate_opts.terms.fix(zh);
+        }
+        let issues = translate_opts.terms.report(&segments, &zh_lines);
+        let path = formats::sibling_path(&output_srt, "terms", "txt");
+        if issues.is_empty() {
+            eprintln!("Terms: all cues comply");
+            std::fs::remove_file(&path).ok();
+        } else {
+            let text: String = issues.iter().map(|issue| format!("{issue}\n")).collect();
+            std::fs::write(&path, text).with_context(|| format!("Write {}", path.display()))?;
+            eprintln!(
+                "Terms: {} violation(s) could not be fixed automatically; see {}",
+                issues.len(),
+                path.display()
+            );
+        }
+        qa_issues.extend(issues);
+        qa_issues.sort_by_key(|issue| issue.cue);
+    }
+    let mut proofread_cues = vec![false; segments.len()];
+    if args.proofread {
+        let proofreader = proofread::Proofreader::new(args.proofread_dict.as_deref())?;
+        let issues = proofreader.check(&segments, &zh_lines);
+        for issue in &issues {
+            proofread_cues[issue.cue - 1] = true;
+        }
+        eprintln!(
+            "Proofreading: {} note(s) on {} of {} cues",
+            issues.len(),
+            proofread_cues.iter().filter(|&&b| b).count(),
+            segments.len()
+        );
+        qa_issues.extend(issues);
+        qa_issues.sort_by_key(|issue| issue.cue);
+    }
+    // Build display lines (bilingual or zh-only)
+    let display_lines =
+        build_display_lines(&ja_lines, &zh_lines, &layout::Layout::from_args(&args));
+
+    // 4) Write SRT (and any other requested formats)
+    progress.set_message("Writing subtitles...");
+    let mut outputs: Vec<PathBuf> = Vec::new();
+    let srt_lines: Vec<String> = args
+        .emoji
+        .lines(emoji::Output::Srt, &display_lines)
+        .into_iter()
+        .zip(&sung)
+        .map(|(line, &sung)| if sung { songs::srt(&line) } else { line })
+        .collect();
+    if args.format.contains(&formats::SubtitleFormat::Srt) {
+        write_srt_atomic(&output_srt, &segments, &srt_lines, args.srt_encoding())?;
+        outputs.push(output_srt.clone());
+    }
+    for (i, chapter) in chapters.iter().flatten().enumerate() {
+        let (segs, lines) = chapters::slice(&segments, &srt_lines, chapter);
+        let path = chapters::path(&output_srt, i + 1);
+        write_srt_atomic(&path, &segs, &lines, args.srt_encoding())?;
+        eprintln!(
+            "Chapter {}{}: {}",
+            i + 1,
+            chapter
+                .title
+                .as_deref()
+                .map(|t| format!(" ({t})"))
+                .unwrap_or_default(),
+            path.display()
+        );
+        outputs.push(path);
+    }
+    if args.format.contains(&formats::SubtitleFormat::Lrc) {
+        for path in formats::write_lrc_outputs(
+            &output_srt,
+            &segments,
+            &args.emoji.lines(emoji::Output::Lrc, &ja_lines),
+            &args.emoji.lines(emoji::Output::Lrc, &zh_lines),
+            args.bilingual,
+        )? {
+            eprintln!("LRC: {}", path.display());
+            outputs.push(path);
+        }
+    }
+    if args.format.contains(&formats::SubtitleFormat::Sbv) {
+        let path = formats::sibling_path(&output_srt, "zh-TW", "sbv");
+        formats::write_sbv(
+            &path,
+            &segments,
+            &args.emoji.lines(emoji::Output::Sbv, &display_lines),
+        )?;
+        eprintln!("SBV: {}", path.display());
+        outputs.push(path);
+    }
+    if args.format.contains(&formats::SubtitleFormat::Bcc) {
+        let path = formats::sibling_path(&output_srt, "zh-TW", "bcc");
+        bilibili::write_bcc(
+            &path,
+            &segments,
+            &args.emoji.lines(emoji::Output::Bcc, &display_lines),
+        )?;
+        eprintln!("Bilibili subtitles: {}", path.display());
+        outputs.push(path);
+    }
+    if args.format.contains(&formats::SubtitleFormat::Danmaku) {
+        let path = formats::sibling_path(&output_srt, "danmaku", "xml");
+        bilibili::write_danmaku(
+            &path,
+            &segments,
+            &args.emoji.lines(emoji::Output::Danmaku, &zh_lines),
+        )?;
+        eprintln!("Danmaku: {}", path.display());
+        outputs.push(path);
+    }
+    let nle_formats = [
+        formats::SubtitleFormat::Fcpxml,
+        formats::SubtitleFormat::PremiereXml,
+    ];
+    if nle_formats.iter().any(|f| args.format.contains(f)) {
+        let video = nle::Video::probe(args.input()).unwrap_or_else(|| {
+            eprintln!("Warning: could not probe the video's frame rate; timeline exports assume 1080p at 29.97");
+            Default::default()
+        });
+        let title = output_srt
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if args.format.contains(&formats::SubtitleFormat::Fcpxml) {
+            let path = formats::sibling_path(&output_srt, "zh-TW", "fcpxml");
+            nle::write_fcpxml(&path, &title, &video, &segments, &srt_lines)?;
+            eprintln!("FCPXML: {}", path.display());
+            outputs.push(path);
+        }
+        if args.format.contains(&formats::SubtitleFormat::PremiereXml) {
+            let path = formats::sibling_path(&output_srt, "markers", "xml");
+            nle::write_markers(
+                &path,
+                &title,
+                &video,
+                &segments,
+                &ja_lines,
+                &args.emoji.lines(emoji::Output::Markers, &zh_lines),
+            )?;
+            eprintln!("Markers: {}", path.display());
+            outputs.push(path);
+        }
+    }
+    if args.format.contains(&formats::SubtitleFormat::Json) {
+        let path = formats::sibling_path(&output_srt, "zh-TW", "json");
+        let pivot_lines: Vec<Option<String>> = {
+            let pivots = translate_opts.pivots.lock().unwrap();
+            segments
+                .iter()
+                .map(|s| pivots.get(&s.text).cloned())
+                .collect()
+        };
+        formats::write_json(
+            &path,
+            &segments,
+            &args.emoji.lines(emoji::Output::Json, &ja_lines),
+            &args.emoji.lines(emoji::Output::Json, &zh_lines),
+            args.pivot
+                .as_deref()
+                .map(|lang| (lang, pivot_lines.as_slice())),
+            args.review_threshold,
+        )?;
+        eprintln!("JSON: {}", path.display());
+        outputs.push(path);
+    }
+    let low_confidence = segments
+        .iter()
+        .filter(|s| s.is_low_confidence(args.review_threshold))
+        .count();
+    if low_confidence > 0 {
+        eprintln!(
+            "{} of {} cues are low confidence (avg_logprob < {})",
+            low_confidence,
+            segments.len(),
+            args.review_threshold
+        );
+    }
+    let length_outliers: Vec<bool> = ja_lines
+        .iter()
+        .zip(&zh_lines)
+        .map(|(ja, zh)| qc::length_outlier(ja, zh).is_some())
+        .collect();
+    let outliers = length_outliers.iter().filter(|&&b| b).count();
+    if outliers > 0 {
+        eprintln!(
+            "{} of {} cues have a translation far longer or shorter than the Japanese",
+            outliers,
+            segments.len()
+        );
+    }
+    if args.review_srt {
+        let path = formats::sibling_path(&output_srt, "review", "srt");
+        let marked: Vec<String> = segments
+            .iter()
+            .zip(srt_lines.iter().zip(&length_outliers))
+            .zip(&proofread_cues)
+            .map(|((seg, (line, &outlier)), &proofread)| {
+                if seg.is_low_confidence(args.review_threshold) || outlier || proofread {
+                    format!("{}{}", formats::REVIEW_MARKER, line)
+                } else {
+                    line.clone()
+                }
+            })
+            .collect();
+        write_srt(&path, &segments, &marked, args.srt_encoding())?;
+        eprintln!("Review SRT: {}", path.display());
+        outputs.push(path);
+    }
+    if args.html_report {
+        let path = formats::sibling_path(&output_srt, "report", "html");
+        let title = output_srt
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        report::write(
+            &path,
+            &report::Report {
+                title: &title,
+                segments: &segments,
+                ja_lines: &ja_lines,
+                zh_lines: &zh_lines,
+                review_threshold: args.review_threshold,
+                issues: &qa_issues,
+            },
+        )?;
+        eprintln!("HTML report: {}", path.display());
+        outputs.push(path);
+    }
+
+    // Extra target languages, translated from the same Japanese lines
+    let mut extra_lines = Vec::new();
+    for target in &extra_targets {
+        progress.set_message(format!("Translating into {}...", target.code));
+        let mut span = telemetry::span("translate_extra");
+        span.attr("language", target.code.clone());
+        let lines = targets::translate(
+            &ja_lines,
+            target,
+            &client,
+            &args.translate_model,
+            args.translate_batch_size,
+            args.mode,
+            translate_opts.sampling,
+        )
+        .await?;
+        drop(span);
+        let display = build_display_lines(&ja_lines, &lines, &layout::Layout::from_args(&args));
+        let path = formats::sibling_path(&output_srt, &target.code, "srt");
+        write_srt_atomic(
+            &path,
+            &segments,
+            &args.emoji.lines(emoji::Output::Srt, &display),
+            args.srt_encoding(),
+        )?;
+        eprintln!("{} SRT: {}", target.code, path.display());
+        outputs.push(path);
+        if args.format.contains(&formats::SubtitleFormat::Scc) {
+            let lines = args.emoji.lines(emoji::Output::Srt, &lines);
+            if scc::coverage(&lines) < 0.9 {
+                eprintln!(
+                    "Warning: {} is not written in a script CEA-608 can carry; no SCC written",
+                    target.code
+                );
+            } else {
+                let path = formats::sibling_path(&output_srt, &target.code, "scc");
+                let unknown = scc::write(&path, &segments, &lines)?;
+                if unknown > 0 {
+                    eprintln!(
+                        "Warning: {unknown} character(s) in the {} SCC have no CEA-608 code and were written as ?",
+                        target.code
+                    );
+                }
+                eprintln!("{} SCC: {}", target.code, path.display());
+                outputs.push(path);
+            }
+        }
+        extra_lines.push(display);
+    }
+
+    // Subtitles are written with what succeeded; stop before the slow outputs
+    if failed > 0 {
+        return Err(failed_pieces_error(failed));
+    }
+
+    // HLS package: source video segments plus segmented WebVTT subtitles
+    if let Some(dir) = &args.hls_dir {
+        progress.set_message("Packaging HLS (video copy + WebVTT segments)...");
+        let _span = telemetry::span("hls_package");
+        let vtt_lines: Vec<String> = args
+            .emoji
+            .lines(emoji::Output::Vtt, &display_lines)
+            .into_iter()
+            .zip(&sung)
+            .map(|(line, &sung)| if sung { songs::srt(&line) } else { line })
+            .collect();
+        let extra_vtt: Vec<Vec<String>> = extra_lines
+            .iter()
+            .map(|lines| args.emoji.lines(emoji::Output::Vtt, lines))
+            .collect();
+        let mut tracks = vec![hls::SubtitleTrack {
+            lang: targets::PRIMARY,
+            name: "繁體中文",
+            lines: &vtt_lines,
+        }];
+        for (target, lines) in extra_targets.iter().zip(&extra_vtt) {
+            tracks.push(hls::SubtitleTrack {
+                lang: &target.code,
+                name: target.native_name(),
+                lines,
+            });
+        }
+        hls::write_package(args.input(), dir, args.hls_time, &segments, &tracks)?;
+        eprintln!("HLS package: {}", dir.join("master.m3u8").display());
+        outputs.push(dir.join("master.m3u8"));
+    }
+
+    // The dub is muxed into a single burned-in video, else kept beside the SRT
+    let mux_dub = burn && chapters.is_none();
+    let dub_track = if args.dub {
+        let path = if mux_dub {
+            work_dir.join("dub.wav")
+        } else {
+            formats::sibling_path(&output_srt, "dub", "wav")
+        };
+        let voice = dub::Voice {
+            model: &args.dub_model,
+            voice: &args.dub_voice,
+            duration: probe_duration(args.input())
+                .unwrap_or_else(|| segments.last().map_or(0.0, |s| s.end) + 1.0),
+        };
+        let _span = telemetry::span("dub");
+        let stats = dub::synthesize(
+            &client,
+            &voice,
+            &segments,
+            &zh_lines,
+            &sung,
+            &path,
+            |i, n| progress.set_message(format!("Dubbing... {i}/{n}")),
+        )
+        .await?;
+        eprintln!(
+            "Dubbed {} lines ({} sped up, {} cut short to fit their cue)",
+            stats.lines, stats.sped_up, stats.cut
+        );
+        if !mux_dub {
+            eprintln!("Dub track: {}", path.display());
+            outputs.push(path.clone());
+        }
+        Some(path)
+    } else {
+        None
+    };
+
+    // 5) Produce MP4 only when --output is provided (and burn-in enabled)
+    if burn {
+        let out_mp4 = output_mp4.unwrap_or_else(|| default_output_video_path(args.input()));
+        // Default behavior is burn-in, even if --burn-in not explicitly set
+        progress.set_message("Burning subtitles into video (re-encode with ffmpeg)...");
+        // Prepare an ASS file with an explicit font to avoid missing glyphs
+        let ass_path = work_dir.join("subs.ass");
+        let canvas = probe_canvas(args.input());
+        let (mut styles, mut events) = burn_in_ass(&args, canvas, &segments, &ja_lines, &zh_lines);
+        if args.avoid_hardsubs {
+            progress.set_message("Checking for burned-in subtitles...");
+            // --ocr-subs already read the burned-in subtitles
+            let found = if args.ocr_subs {
+                Ok(true)
+            } else {
+                match probe_duration(args.input()) {
+                    Some(duration) => tokio::task::block_in_place(|| {
+                        ocr::hardsubs_at_bottom(args.input(), &work_dir, duration, args.ocr_region)
+                    }),
+                    None => Err(anyhow!("the video's duration is unknown")),
+                }
+            };
+            match found {
+                Ok(true) => {
+                    eprintln!("Burned-in subtitles found at the bottom; placing subtitles at the top");
+                    layout::move_to_top(&mut styles, &mut events);
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!(
+                    "Warning: --avoid-hardsubs could not check for burned-in subtitles ({e:#}); subtitles stay in place"
+                ),
+            }
+        }
+
+        // Try provided fonts dir or detect common/project fonts locations
+        let fonts_dir = resolve_fonts_dir(args.font_dir.as_deref());
+        if let Some(ref d) = fonts_dir {
+            eprintln!("Using fonts dir: {}", d.display());
+        } else {
+            eprintln!("Warning: no fonts dir found; relying on system fallback. You can run scripts/prepare_fonts.sh");
+        }
+        // Batch jobs share a small number of encode slots
+        let source = verify_source(&args, args.input());
+        let slot = batch::encode_slot().await;
+        let span = telemetry::span("burn_in");
+        if let Some(chapters) = &chapters {
+            // One encode per chapter instead of the whole video
+            for (i, chapter) in chapters.iter().enumerate() {
+                progress.set_message(format!(
+                    "Burning subtitles... chapter {}/{}",
+                    i + 1,
+                    chapters.len()
+                ));
+                let events: Vec<layout::AssEvent> = events
+                    .iter()
+                    .filter_map(|e| {
+                        let (start, end) = chapter.clip(e.start, e.end)?;
+                        Some(layout::AssEvent {
+                            start,
+                            end,
+                            ..e.clone()
+                        })
+                    })
+                    .collect();
+                let ass_path = work_dir.join(format!("subs.ch{:02}.ass", i + 1));
+                write_ass_events(&ass_path, canvas, &styles, &events)?;
+                let filter = subtitles_filter(&ass_path, fonts_dir.as_deref(), None);
+                let path = chapters::path(&out_mp4, i + 1);
+                tokio::task::block_in_place(|| {
+                    chapters::burn_in(
+                        args.input(),
+                        chapter,
+                        &filter,
+                        &path,
+                        &encode_extra,
+                        args.nice,
+                    )
+                })?;
+                if let Some(source) = &source {
+                    verify::check(&path, source, Some(chapter.end - chapter.start))?;
+                }
+                outputs.push(path);
+            }
+            progress.finish_with_message(format!(
+                "Done. SRT: {} | {} chapter videos next to {}",
+                output_srt.display(),
+                chapters.len(),
+                out_mp4.display()
+            ));
+        } else {
+            write_ass_events(&ass_path, canvas, &styles, &events)?;
+            tokio::task::block_in_place(|| {
+                burn_in_subtitles(
+                    args.input(),
+                    &ass_path,
+                    &out_mp4,
+                    fonts_dir.as_deref(),
+                    &encode_extra,
+                    args.nice,
+                    |f| progress.set_message(format!("Burning subtitles... {:.0}%", f * 100.0)),
+                )
+            })?;
+            if let Some(source) = &source {
+                verify::check(&out_mp4, source, source.duration)?;
+            }
+            if let Some(dub) = dub_track.as_deref().filter(|_| mux_dub) {
+                progress.set_message("Adding the dubbed track...");
+                tokio::task::block_in_place(|| dub::mux(&out_mp4, dub))?;
+            }
+            progress.finish_with_message(format!(
+                "Done. SRT: {} | Video: {}",
+                output_srt.display(),
+                out_mp4.display()
+            ));
+            outputs.push(out_mp4);
+        }
+        drop(span);
+        drop(slot);
+    } else if output_srt.exists() {
+        progress.finish_with_message(format!("Done. SRT written to {}", output_srt.display()));
+    } else {
+        progress.finish_with_message("Done.");
+    }
+    checkpoint.finish()?;
+
+    Ok(outputs)
+}
+
+/// Burn-in ASS styles and events for the cues, as `--font-*`, the bilingual
+/// layout, and `--emoji` set them up.
+fn burn_in_ass(
+    args: &Args,
+    canvas: layout::Canvas,
+    segments: &[WhisperSegment],
+    ja_lines: &[String],
+    zh_lines: &[String],
+) -> (Vec<layout::AssStyle>, Vec<layout::AssEvent>) {
+    // Prefer Noto to avoid platform-private font issues
+    let chosen_font = args.font_name.as_deref().unwrap_or(fonts::DEFAULT_FONT);
+    let font_size = canvas.scaled(
+        args.font_size
+            .unwrap_or(if args.bilingual { 30 } else { 36 }),
+    );
+    let layout = layout::Layout::from_args(args);
+    let sung = args.songs.detect(segments, &args.song_range);
+    let ja_lines = args.emoji.lines(emoji::Output::BurnIn, ja_lines);
+    let zh_lines = args.emoji.lines(emoji::Output::BurnIn, zh_lines);
+    let (mut styles, mut events) = if layout.split_events() {
+        let (zh_size, zh_opacity) = layout.scaled(layout::Lang::Zh, font_size);
+        let (ja_size, ja_opacity) = layout.scaled(layout::Lang::Ja, font_size);
+        let styles = layout.split_styles(
+            layout::LangStyle {
+                font: chosen_font.to_string(),
+                size: zh_size,
+                rgb: args.zh_color,
+                opacity: zh_opacity,
+            },
+            layout::LangStyle {
+                font: args
+                    .ja_font_name
+                    .clone()
+                    .unwrap_or_else(|| chosen_font.to_string()),
+                size: args.ja_font_size.map_or(ja_size, |s| canvas.scaled(s)),
+                rgb: args.ja_color,
+                opacity: ja_opacity,
+            },
+        );
+        let events: Vec<layout::AssEvent> = segments
+            .iter()
+            .zip(ja_lines.iter().zip(&zh_lines))
+            .zip(&sung)
+            .flat_map(|((seg, (ja, zh)), &sung)| {
+                let mut events = layout.split_events_for(seg.start, seg.end, ja, zh);
+                if sung {
+                    for event in &mut events {
+                        event.text = songs::ass(&event.text);
+                    }
+                }
+                events
+            })
+            .collect();
+        (styles, events)
+    } else {
+        let ass_lines: Vec<String> = ja_lines
+            .iter()
+            .zip(&zh_lines)
+            .zip(&sung)
+            .map(|((ja, zh), &sung)| {
+                let text = layout.ass_text(ja, zh, font_size);
+                if sung {
+                    songs::ass(&text)
+                } else {
+                    text
+                }
+            })
+            .collect();
+        let style = layout::AssStyle {
+            name: "Default",
+            style: layout::LangStyle {
+                font: chosen_font.to_string(),
+                size: font_size,
+                rgb: args.zh_color,
+                opacity: 100,
+            },
+            alignment: 2,
+        };
+        let events: Vec<layout::AssEvent> = segments
+            .iter()
+            .zip(ass_lines)
+            .map(|(seg, text)| layout::AssEvent {
+                start: seg.start,
+                end: seg.end,
+                style: "Default",
+                text,
+            })
+            .collect();
+        (vec![style], events)
+    };
+    if let Some(text) = &args.credit {
+        let (style, event) = layout::credit(
+            text,
+            chosen_font,
+            font_size * 3 / 5,
+            args.credit_position,
+            args.credit_seconds,
+        );
+        styles.push(style);
+        events.insert(0, event);
+    }
+    (styles, events)
+}
+
+fn failed_pieces_error(failed: usize) -> anyhow::Error {
+    anyhow!(
+        "{failed} chunk(s)/batch(es) still failed after retries; subtitles are partial (untranslated lines kept in Japanese). Rerun with --repair to retry only those"
+    )
+}
+
+/// What in this run spawns the ffmpeg/ffprobe binaries even when libav is
+/// linked (native-ffmpeg covers audio extraction and burn-in only).
+fn ffmpeg_cli_use(args: &Args, burn: bool) -> Option<&'static str> {
+    [
+        (args.hls_dir.is_some(), "--hls-dir"),
+        (args.input.len() > 1, "joining several --input files"),
+        (args.split_by_chapter, "--split-by-chapter"),
+        (args.ocr_subs, "--ocr-subs"),
+        (args.use_embedded_subs, "--use-embedded-subs"),
+        (burn && args.avoid_hardsubs, "--avoid-hardsubs"),
+        (burn && args.dub, "--dub"),
+    ]
+    .into_iter()
+    .find_map(|(used, what)| used.then_some(what))
+}
+
+#[cfg(feature = "native-ffmpeg")]
+fn ensure_ffmpeg(cli_use: Option<&str>) -> Result<()> {
+    // Linked libav replaces the ffmpeg binary for extraction and burn-in
+    native_ffmpeg::init()?;
+    match cli_use {
+        Some(what) => ensure_ffmpeg_cli()
+            .with_context(|| format!("{what} runs the ffmpeg binary, also with native-ffmpeg")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(feature = "native-ffmpeg"))]
+fn ensure_ffmpeg(_cli_use: Option<&str>) -> Result<()> {
+    ensure_ffmpeg_cli()
+}
+
+/// The ffmpeg binary is on `PATH`.
+fn ensure_ffmpeg_cli() -> Result<()> {
+    let out = Command::new("ffmpeg")
+        .arg("-version")
+        .output()
+        .context("ffmpeg is required (install via brew/apt/choco)")?;
+    if !out.status.success() {
+        return Err(anyhow!("ffmpeg not available in PATH"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "native-ffmpeg")]
+fn extract_audio(
+    input: &Path,
+    wav_out: &Path,
+    _extra: &[String],
+    on_progress: impl FnMut(f64),
+) -> Result<()> {
+    native_ffmpeg::extract_audio(input, wav_out, on_progress)
+}
+
+#[cfg(not(feature = "native-ffmpeg"))]
+fn extract_audio(
+    input: &Path,
+    wav_out: &Path,
+    extra: &[String],
+    _on_progress: impl FnMut(f64),
+) -> Result<()> {
+    let status =
+        cancel::status(Command::new("ffmpeg").args(extract_audio_args(input, wav_out, extra)))
+            .context("Failed to run ffmpeg to extract audio")?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg audio extraction failed"));
+    }
+    Ok(())
+}
+
+/// ffmpeg arguments that extract 16kHz mono PCM WAV; `extra` goes last
+/// before the output so it can override the defaults.
+fn extract_audio_args(input: &Path, wav_out: &Path, extra: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-i"].map(String::from).into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-vn", "-acodec", "pcm_s16le", "-ar", "16000", "-ac", "1"].map(String::from));
+    args.extend_from_slice(extra);
+    args.push(wav_out.to_string_lossy().into_owned());
+    args
+}
+
+/// `extract_audio_args` for `seconds` of the input from `start`, seeking in
+/// the container so nothing before it is decoded.
+fn chunk_audio_args(
+    input: &Path,
+    start: f64,
+    seconds: u32,
+    wav_out: &Path,
+    extra: &[String],
+) -> Vec<String> {
+    let mut args = extract_audio_args(input, wav_out, extra);
+    let at = args.iter().position(|a| a == "-i").unwrap_or(0);
+    let seek = [
+        "-v",
+        "error",
+        "-ss",
+        &format!("{start:.3}"),
+        "-t",
+        &seconds.to_string(),
+    ]
+    .map(String::from);
+    args.splice(at..at, seek);
+    args
+}
+
+/// The input's duration in seconds, from ffprobe.
+fn probe_duration(input: &Path) -> Option<f64> {
+    let out = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(input)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+/// `input` as probed for verifying what is encoded from it; `None` with
+/// `--no-verify-output`, or (with a warning) when ffprobe cannot read it.
+fn verify_source(args: &Args, input: &Path) -> Option<verify::Media> {
+    if args.no_verify_output {
+        return None;
+    }
+    verify::probe(input)
+        .map_err(|e| eprintln!("Warning: the output will not be verified: {e:#}"))
+        .ok()
+}
+
+/// The ASS canvas for burning into `input`: its display resolution, or the
+/// default canvas when ffprobe cannot tell.
+fn probe_canvas(input: &Path) -> layout::Canvas {
+    let out = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=width,height:stream_side_data=rotation",
+            "-of",
+            "json",
+        ])
+        .arg(input)
+        .output();
+    out.ok()
+        .and_then(|o| layout::Canvas::from_ffprobe(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default()
+}
+
+/// Segments from an embedded Japanese text subtitle track when
+/// `--use-embedded-subs` is set; otherwise only hints that one exists.
+fn embedded_transcript(
+    args: &Args,
+    have_ffmpeg: bool,
+    work_dir: &Path,
+) -> Result<Option<Vec<WhisperSegment>>> {
+    if !have_ffmpeg {
+        if args.use_embedded_subs {
+            return Err(anyhow!("--use-embedded-subs requires ffmpeg/ffprobe"));
+        }
+        return Ok(None);
+    }
+    let streams = match embedded::probe_subtitle_streams(args.input()) {
+        Ok(s) => s,
+        Err(e) if args.use_embedded_subs => return Err(e),
+        Err(_) => return Ok(None),
+    };
+    match embedded::pick_japanese(&streams) {
+        Some(stream) if args.use_embedded_subs => {
+            eprintln!(
+                "Using embedded Japanese subtitles (stream #{}, {}); skipping transcription",
+                stream.index, stream.codec
+            );
+            let segments = embedded::extract(args.input(), stream, work_dir)?;
+            if segments.is_empty() {
+                return Err(anyhow!(
+                    "Embedded subtitle stream #{} is empty",
+                    stream.index
+                ));
+            }
+            Ok(Some(segments))
+        }
+        Some(stream) => {
+            eprintln!(
+                "Hint: input has an embedded Japanese subtitle track (stream #{}); pass --use-embedded-subs to skip transcription",
+                stream.index
+            );
+            Ok(None)
+        }
+        None if args.use_embedded_subs => Err(anyhow!(
+            "No Japanese text subtitle stream found in {}",
+            args.input().display()
+        )),
+        None => Ok(None),
+    }
+}
+
+#[cfg(feature = "symphonia-fallback")]
+fn extract_audio_fallback(input: &Path, wav_out: &Path) -> Result<()> {
+    symphonia_audio::extract_audio(input, wav_out)
+}
+
+#[cfg(not(feature = "symphonia-fallback"))]
+fn extract_audio_fallback(_input: &Path, _wav_out: &Path) -> Result<()> {
+    Err(anyhow!(
+        "ffmpeg is required (built without symphonia-fallback)"
+    ))
+}
+
+async fn transcribe_whisper_verbose(
+    client: &OpenAiClient,
+    wav_path: &Path,
+    model: &str,
+    language: Option<&'static str>,
+) -> Result<openai::TranscriptionResponse> {
+    let req = openai::TranscriptionRequest {
+        model: model.to_string(),
+        audio: wav_path.to_path_buf(),
+        language,
+    };
+    Ok(client.transcribe(&req).await?)
+}
+
+/// Split the audio into `chunk_NNNNN.wav` files next to `wav_path`, sorted.
+/// The WAV is always 16kHz mono PCM, so this runs in-process for every backend.
+fn segment_audio(wav_path: &Path, chunk_seconds: u32) -> Result<Vec<PathBuf>> {
+    let out_dir = wav_path.parent().unwrap_or_else(|| Path::new("."));
+    remove_chunks(out_dir);
+    let chunks = wav::segment_wav(wav_path, out_dir, chunk_seconds)?;
+    if chunks.is_empty() {
+        return Err(anyhow!("No audio chunks were produced"));
+    }
+    Ok(chunks)
+}
+
+/// Remove any prior `chunk_NNNNN.wav` files from `dir`.
+fn remove_chunks(dir: &Path) {
+    // Best-effort cleanup; ignore errors
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for e in entries.flatten() {
+            let p = e.path();
+            if let Some(name) = p.file_name().and_then(|s| s.to_str()) {
+                if name.starts_with("chunk_") && name.ends_with(".wav") {
+                    let _ = std::fs::remove_file(p);
+                }
+            }
+        }
+    }
+}
+
+/// The audio chunks the transcription stage uploads, in order.
+enum AudioChunks {
+    /// Already on disk
+    Files(Vec<PathBuf>),
+    /// Cut from the input container one at a time as they are transcribed,
+    /// so no full-length WAV is written; each is deleted after upload
+    /// unless `keep`
+    Seek {
+        input: PathBuf,
+        paths: Vec<PathBuf>,
+        chunk_seconds: u32,
+        extra: Vec<String>,
+        keep: bool,
+    },
+}
+
+impl AudioChunks {
+    fn seek(
+        input: &Path,
+        dir: &Path,
+        duration: f64,
+        chunk_seconds: u32,
+        extra: &[String],
+        keep: bool,
+    ) -> Self {
+        let n = (duration / chunk_seconds.max(1) as f64).ceil().max(1.0) as usize;
+        AudioChunks::Seek {
+            input: input.to_path_buf(),
+            paths: (0..n)
+                .map(|i| dir.join(format!("chunk_{i:05}.wav")))
+                .collect(),
+            chunk_seconds,
+            extra: extra.to_vec(),
+            keep,
+        }
+    }
+
+    fn paths(&self) -> &[PathBuf] {
+        match self {
+            AudioChunks::Files(paths) | AudioChunks::Seek { paths, .. } => paths,
+        }
+    }
+
+    /// Make chunk `i` ready to upload; false when it holds no audio (the
+    /// container can run longer than its audio track).
+    async fn fetch(&self, i: usize) -> Result<bool> {
+        let AudioChunks::Seek {
+            input,
+            paths,
+            chunk_seconds,
+            extra,
+            ..
+        } = self
+        else {
+            return Ok(true);
+        };
+        let path = paths[i].clone();
+        let start = i as f64 * *chunk_seconds as f64;
+        let ffmpeg_args = chunk_audio_args(input, start, *chunk_seconds, &path, extra);
+        // Blocking, so translation keeps running meanwhile
+        tokio::task::spawn_blocking(move || {
+            let status = cancel::status(Command::new("ffmpeg").args(&ffmpeg_args))
+                .context("Failed to run ffmpeg to cut an audio chunk")?;
+            if !status.success() {
+                return Err(anyhow!("ffmpeg failed to cut {}", path.display()));
+            }
+            let mut file = File::open(&path)?;
+            Ok(wav::seek_to_data(&mut file)? > 0)
+        })
+        .await?
+    }
+
+    /// Done uploading chunk `i`.
+    fn release(&self, i: usize) {
+        if let AudioChunks::Seek {
+            paths, keep: false, ..
+        } = self
+        {
+            let _ = std::fs::remove_file(&paths[i]);
+        }
+    }
+}
+
+/// Transcribe one chunk (the client retries transient errors) and shift its
+/// segments to absolute time. A chunk the API rejects as too large is split
+/// in half, and the halves are sent instead.
+async fn transcribe_chunk(
+    chunk: &Path,
+    index: usize,
+    chunk_seconds: u32,
+    client: &OpenAiClient,
+    model: &str,
+    language: Option<&'static str>,
+) -> Result<(Vec<WhisperSegment>, Option<String>)> {
+    /// A 600 s chunk goes down to 37.5 s pieces at most
+    const MAX_SPLITS: u32 = 4;
+
+    // (piece, its start in the chunk, times split), taken in time order
+    let mut pieces = vec![(chunk.to_path_buf(), 0.0, 0)];
+    let mut out = Vec::new();
+    let mut detected = None;
+    while let Some((piece, start, splits)) = pieces.pop() {
+        let result = transcribe_whisper_verbose(client, &piece, model, language).await;
+        if let Err(e) = &result {
+            if splits < MAX_SPLITS && matches!(e.downcast_ref(), Some(ApiError::TooLarge { .. })) {
+                let (first, second, seconds) = wav::split_in_half(&piece)?;
+                eprintln!(
+                    "Chunk {}: {} is too large for the API; sending it as two {seconds:.0} s halves",
+                    index + 1,
+                    piece.display()
+                );
+                if piece != chunk {
+                    let _ = std::fs::remove_file(&piece);
+                }
+                pieces.push((second, start + seconds, splits + 1));
+                pieces.push((first, start, splits + 1));
+                continue;
+            }
+        }
+        if piece != chunk {
+            let _ = std::fs::remove_file(&piece);
+        }
+        let response = result?;
+        if detected.is_none() {
+            detected = response.language;
+        }
+        let mut segs = response.segments.ok_or_else(|| {
+            anyhow!(
+                "No segments returned by Whisper (verbose_json) for chunk {}",
+                index
+            )
+        })?;
+        let offset = (index as f64) * (chunk_seconds as f64) + start;
+        for s in segs.iter_mut() {
+            s.start += offset;
+            s.end += offset;
+        }
+        out.extend(segs);
+    }
+    Ok((out, detected))
+}
+
+/// Transcription, translation, and subtitle building run as concurrent stages
+/// connected by channels, so chunk N is translated while chunk N+1 is being
+/// transcribed.
+async fn transcribe_translate_pipelined(
+    chunks: AudioChunks,
+    client: &OpenAiClient,
+    args: &Args,
+    translate_opts: &TranslateOptions,
+    checkpoint: &checkpoint::Checkpoint,
+    partial_srt: Option<&Path>,
+) -> Result<(Vec<WhisperSegment>, Vec<String>)> {
+    // One chunk buffered per stage is enough to keep both APIs busy
+    let (seg_tx, seg_rx) = mpsc::channel::<(usize, Vec<WhisperSegment>)>(1);
+
+    let transcribe = async move {
+        // The first chunk transcribed in this run is checked by --language-guard
+        let mut verify = true;
+        for (i, chunk) in chunks.paths().iter().enumerate() {
+            let chunk_start = i as f64 * args.chunk_seconds as f64;
+            let chunk_end = chunk_start + args.chunk_seconds as f64;
+            if skip::covers(&args.skip_range, chunk_start, chunk_end) {
+                eprintln!(
+                    "Skipping chunk {}/{} (--skip-range)",
+                    i + 1,
+                    chunks.paths().len()
+                );
+                continue;
+            }
+            let mut span = telemetry::span("transcribe_chunk");
+            span.attr("chunk", i);
+            span.attr("cached", checkpoint.segments(i).is_some());
+            let mut segs = match checkpoint.segments(i) {
+                Some(mut saved) => {
+                    verify = false;
+                    // Reused chunks are cut from the input again
+                    if args.tighten_cues && chunks.fetch(i).await? {
+                        tighten_cues(&mut saved, chunk, chunk_start);
+                        chunks.release(i);
+                    }
+                    saved
+                }
+                None => {
+                    cancel::check()?;
+                    if !chunks.fetch(i).await? {
+                        chunks.release(i);
+                        continue;
+                    }
+                    // Skipped audio at the chunk's ends is not uploaded at all
+                    let trimmed = chunk.with_extension("skip.wav");
+                    let (upload, lead) = if args.skip_range.is_empty() {
+                        (chunk.as_path(), 0.0)
+                    } else {
+                        match skip::trim_chunk(&args.skip_range, chunk, chunk_start, &trimmed)? {
+                            Some(lead) => (trimmed.as_path(), lead),
+                            None => {
+                                chunks.release(i);
+                                continue;
+                            }
+                        }
+                    };
+                    eprintln!(
+                        "Transcribing chunk {}/{}: {}",
+                        i + 1,
+                        chunks.paths().len(),
+                        chunk.display()
+                    );
+                    // --language-guard: an unhinted pass over the chunk's
+                    // start only to detect the language; the cues always
+                    // come from the hinted pass
+                    let mut transcribed = Ok(Default::default());
+                    if verify && args.language_guard.detects() {
+                        let probe = chunk.with_extension("probe.wav");
+                        wav::write_head(upload, language_guard::PROBE_SECONDS, &probe)?;
+                        transcribed = transcribe_chunk(
+                            &probe,
+                            i,
+                            args.chunk_seconds,
+                            client,
+                            &args.whisper_model,
+                            None,
+                        )
+                        .await;
+                        let _ = std::fs::remove_file(&probe);
+                        if let Ok((_, language)) = &transcribed {
+                            if let Err(e) = args.language_guard.check(language.as_deref()) {
+                                if upload != chunk {
+                                    let _ = std::fs::remove_file(upload);
+                                }
+                                return Err(e);
+                            }
+                            verify = false;
+                        }
+                    }
+                    if transcribed.is_ok() {
+                        transcribed = transcribe_chunk(
+                            upload,
+                            i,
+                            args.chunk_seconds,
+                            client,
+                            &args.whisper_model,
+                            Some("ja"),
+                        )
+                        .await;
+                    }
+                    if upload != chunk {
+                        let _ = std::fs::remove_file(upload);
+                    }
+                    let mut segs = match transcribed {
+                        Ok((mut segs, _)) => {
+                            for s in &mut segs {
+                                s.start += lead;
+                                s.end += lead;
+                            }
+                            checkpoint.record_segments(i, &segs)?;
+                            segs
+                        }
+                        Err(e) if ApiError::is_fatal(&e) => return Err(e),
+                        Err(e) => {
+                            // Keep going; --repair retries this chunk later
+                            span.fail(format!("{e:#}"));
+                            checkpoint.record_failure(i, Stage::Transcribe, &e)?;
+                            chunks.release(i);
+                            continue;
+                        }
+                    };
+                    // The checkpoint keeps Whisper's times; tightening is redone on reuse
+                    if args.tighten_cues {
+                        tighten_cues(&mut segs, chunk, chunk_start);
+                    }
+                    chunks.release(i);
+                    segs
+                }
+            };
+            skip::drop_cues(&mut segs, &args.skip_range);
+            drop_non_speech(&mut segs, args);
+            if args.punctuate && !segs.is_empty() {
+                let _span = telemetry::span("punctuate");
+                match punctuate::clean(&mut segs, client, &args.translate_model).await {
+                    Ok(n) => eprintln!("Punctuation pass: {n}/{} lines cleaned", segs.len()),
+                    Err(e) if ApiError::is_fatal(&e) => return Err(e),
+                    // The raw transcript still translates
+                    Err(e) => eprintln!("Warning: punctuation pass failed: {e:#}"),
+                }
+            }
+            if args.mode == Mode::Lyrics {
+                segs = split_lyric_phrases(segs);
+            } else if !args.no_sentence_split && !args.sentence_groups {
+                segs = resegment::by_sentence(segs);
+            }
+            sfx::drop_cues(&mut segs, args.sfx);
+            span.attr("segments", segs.len());
+            drop(span);
+            if seg_tx.send((i, segs)).await.is_err() {
+                break; // a later stage failed; its error is reported
+            }
+        }
+        anyhow::Ok(())
+    };
+
+    let ((), out) = tokio::try_join!(
+        transcribe,
+        translate_stages(
+            seg_rx,
+            client,
+            args,
+            translate_opts,
+            checkpoint,
+            partial_srt
+        )
+    )?;
+    Ok(out)
+}
+
+/// Translate numbered groups of cues as they arrive (reusing checkpointed
+/// translations) and collect them in order. With `partial_srt` the SRT is
+/// rewritten (atomically) after each group, so the first minutes are usable,
+/// and survive a crash, while the rest runs.
+async fn translate_stages(
+    mut seg_rx: mpsc::Receiver<(usize, Vec<WhisperSegment>)>,
+    client: &OpenAiClient,
+    args: &Args,
+    translate_opts: &TranslateOptions,
+    checkpoint: &checkpoint::Checkpoint,
+    partial_srt: Option<&Path>,
+) -> Result<(Vec<WhisperSegment>, Vec<String>)> {
+    let (cue_tx, mut cue_rx) = mpsc::channel::<(Vec<WhisperSegment>, Vec<String>)>(1);
+
+    let filter = args
+        .segment_filter
+        .as_deref()
+        .map(segment_filter::SegmentFilter::parse)
+        .transpose()?;
+    let translate = async move {
+        while let Some((i, mut segs)) = seg_rx.recv().await {
+            if let Some(filter) = &filter {
+                segs = filter.transcribed(segs).await?;
+            }
+            let ja: Vec<String> = segs.iter().map(|s| s.text.clone()).collect();
+            let mut span = telemetry::span("translate_chunk");
+            span.attr("chunk", i);
+            span.attr("lines", ja.len());
+            span.attr("cached", checkpoint.translations(i, &ja).is_some());
+            let zh = match checkpoint.translations(i, &ja) {
+                Some(saved) => saved,
+                None => {
+                    cancel::check()?;
+                    let translated = if args.sentence_groups && args.mode == Mode::Dialog {
+                        translate_sentence_groups(&segs, client, translate_opts).await
+                    } else {
+                        translate_lines_zh_tw(&ja, client, translate_opts).await
+                    };
+                    match translated {
+                        Ok(zh) => {
+                            checkpoint.record_translations(i, &ja, &zh)?;
+                            zh
+                        }
+                        // Placeholders until the job's replies are collected
+                        Err(e) if e.is::<batch_api::Deferred>() => ja.clone(),
+                        Err(e) if ApiError::is_fatal(&e) => return Err(e),
+                        Err(e) => {
+                            // Untranslated until --repair; the cues keep their timing
+                            span.fail(format!("{e:#}"));
+                            checkpoint.record_failure(i, Stage::Translate, &e)?;
+                            ja.clone()
+                        }
+                    }
+                }
+            };
+            drop(span);
+            if cue_tx.send((segs, zh)).await.is_err() {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    };
+
+    let build = async move {
+        let mut segments: Vec<WhisperSegment> = Vec::new();
+        let mut zh_lines: Vec<String> = Vec::new();
+        while let Some((mut segs, zh)) = cue_rx.recv().await {
+            // Saved checkpoints keep source timing; outputs get the adjusted one
+            for s in &mut segs {
+                retime(s, args.speed_factor, args.offset);
+            }
+            segments.extend(segs);
+            zh_lines.extend(zh);
+            let Some(path) = partial_srt else { continue };
+            let ja_all: Vec<String> = segments
+                .iter()
+                .map(|s| translate_opts.replace_rules.ja(&s.text))
+                .collect();
+            let display = build_display_lines(&ja_all, &zh_lines, &layout::Layout::from_args(args));
+            let display = args.emoji.lines(emoji::Output::Srt, &display);
+            write_srt_atomic(path, &segments, &display, args.srt_encoding())?;
+            eprintln!(
+                "Partial SRT: {} cues up to {} -> {}",
+                segments.len(),
+                format_srt_time(segments.last().map(|s| s.end).unwrap_or(0.0)),
+                path.display()
+            );
+        }
+        anyhow::Ok((segments, zh_lines))
+    };
+
+    let ((), out) = tokio::try_join!(translate, build)?;
+    Ok(out)
+}
+
+/// Split cues into consecutive groups spanning `seconds` each, mirroring audio
+/// chunks for sources that are already timed (embedded or OCR subtitles).
+fn group_by_window(segments: Vec<WhisperSegment>, seconds: u32) -> Vec<Vec<WhisperSegment>> {
+    let window = f64::from(seconds.max(1));
+    let mut groups: Vec<Vec<WhisperSegment>> = Vec::new();
+    let mut current_window = None;
+    for seg in segments {
+        let w = (seg.start / window).floor() as i64;
+        if current_window != Some(w) {
+            groups.push(Vec::new());
+            current_window = Some(w);
+        }
+        groups.last_mut().unwrap().push(seg);
+    }
+    groups
+}
+
+/// Lyrics mode: Whisper tends to run several sung phrases into one segment.
+/// Split each segment at spaces and sentence punctuation into one cue per
+/// phrase, sharing the segment's time span in proportion to phrase length.
+/// Phrases are never merged back across segments.
+fn split_lyric_phrases(segments: Vec<WhisperSegment>) -> Vec<WhisperSegment> {
+    let mut out = Vec::with_capacity(segments.len());
+    for seg in segments {
+        let mut phrases: Vec<String> = Vec::new();
+        let mut cur = String::new();
+        for c in seg.text.trim().chars() {
+            if c.is_whitespace() {
+                if !cur.is_empty() {
+                    phrases.push(std::mem::take(&mut cur));
+                }
+                continue;
+            }
+            cur.push(c);
+            if matches!(c, '、' | '。' | '！' | '？' | '!' | '?') {
+                phrases.push(std::mem::take(&mut cur));
+            }
+        }
+        if !cur.is_empty() {
+            phrases.push(cur);
+        }
+        // Fold tiny fragments (interjections, stray punctuation) into the previous phrase
+        let mut merged: Vec<String> = Vec::new();
+        for p in phrases {
+            match merged.last_mut() {
+                Some(last) if p.chars().count() <= 2 => last.push_str(&p),
+                _ => merged.push(p),
+            }
+        }
+        if merged.len() <= 1 {
+            out.push(seg);
+            continue;
+        }
+        let total: usize = merged.iter().map(|p| p.chars().count()).sum();
+        let span = seg.end - seg.start;
+        let mut t = seg.start;
+        for p in merged {
+            let end = t + span * p.chars().count() as f64 / total as f64;
+            out.push(WhisperSegment {
+                id: None,
+                start: t,
+                end,
+                text: p,
+                avg_logprob: seg.avg_logprob,
+                no_speech_prob: seg.no_speech_prob,
+            });
+            t = end;
+        }
+    }
+    out
+}
+
+fn build_display_lines(
+    ja_lines: &[String],
+    zh_lines: &[String],
+    layout: &layout::Layout,
+) -> Vec<String> {
+    ja_lines
+        .iter()
+        .zip(zh_lines.iter())
+        .map(|(ja, zh)| layout.text(ja, zh))
+        .collect()
+}
+
+// (Removed unused ChatResponse/ChatChoice/ChatMessage)
+
+/// Translation settings shared by every batch of a run.
+struct TranslateOptions {
+    model: String,
+    batch_size: usize,
+    /// `--translate-batch-tokens`, lowered for the rest of the run when a
+    /// request exceeds the context or reply limit
+    batch_tokens: std::sync::atomic::AtomicU64,
+    mode: Mode,
+    sfx: sfx::SfxMode,
+    content_filter: content_filter::ContentFilter,
+    replace_rules: replace_rules::ReplaceRules,
+    /// `--terms`
+    terms: terms::Terms,
+    name_table: bool,
+    tm: Option<std::sync::Mutex<tm::TranslationMemory>>,
+    /// Grows as new lines are translated (streaming/live see one chunk at a time)
+    names: std::sync::Mutex<entities::NameTable>,
+    /// `--pivot` language and the model for the first hop
+    pivot: Option<(targets::Target, String)>,
+    /// Pivot text by Japanese line, for the JSON sidecar
+    pivots: std::sync::Mutex<std::collections::HashMap<String, String>>,
+    ensemble: ensemble::Ensemble,
+    /// `--translate-temperature`, `--top-p`, `--max-tokens`
+    sampling: openai::Sampling,
+    /// `--retranslate-length-outliers`
+    retry_length: bool,
+    /// `--sdh`
+    sdh: bool,
+    /// `--honorifics`, `--keep-techniques`
+    honorifics: anime::Honorifics,
+    keep_techniques: bool,
+    /// `--english`
+    english: english::English,
+    /// Normalize zh-TW punctuation (off with `--keep-punctuation`)
+    normalize_punctuation: bool,
+    /// Structured outputs; cleared for the run if the server rejects them
+    structured: std::sync::atomic::AtomicBool,
+    /// `--batch-api`: requests queued for, and replies from, Batch API jobs
+    batch_api: Option<batch_api::Collector>,
+}
+
+impl TranslateOptions {
+    /// Tokens a batch's lines may use: `--translate-batch-tokens` less the
+    /// instructions every request carries.
+    fn line_budget(&self) -> u64 {
+        let overhead = tokens::count(&self.system_prompt())
+            + tokens::count(&translation_user(&[], None, self).to_string());
+        self.batch_tokens
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .saturating_sub(overhead)
+    }
+
+    /// Halve the batch token budget after `err`, a context-length error.
+    fn shrink_batches(&self, err: &anyhow::Error) {
+        const MIN: u64 = 500;
+        let old = self.batch_tokens.load(std::sync::atomic::Ordering::Relaxed);
+        let new = (old / 2).max(MIN);
+        if new < old {
+            self.batch_tokens
+                .store(new, std::sync::atomic::Ordering::Relaxed);
+            eprintln!(
+                "{err:#}; translation batches shrunk to ~{new} tokens for the rest of the run"
+            );
+        }
+    }
+
+    fn from_args(args: &Args) -> Result<Self> {
+        if args
+            .translate_temperature
+            .is_some_and(|t| !(0.0..=2.0).contains(&t))
+        {
+            return Err(anyhow!("--translate-temperature must be between 0 and 2"));
+        }
+        if args.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            return Err(anyhow!("--top-p must be between 0 and 1"));
+        }
+        Ok(Self {
+            model: args.translate_model.clone(),
+            batch_size: args.translate_batch_size,
+            batch_tokens: args.translate_batch_tokens.into(),
+            mode: args.mode,
+            sfx: args.sfx,
+            content_filter: content_filter::ContentFilter::new(
+                args.content_filter,
+                args.content_filter_words.as_deref(),
+            )?,
+            replace_rules: match &args.replace_rules {
+                Some(path) => {
+                    let rules = replace_rules::ReplaceRules::load(path)?;
+                    eprintln!(
+                        "Loaded {} replace rules from {}",
+                        rules.len(),
+                        path.display()
+                    );
+                    rules
+                }
+                None => Default::default(),
+            },
+            terms: match &args.terms {
+                Some(path) => {
+                    let terms = terms::Terms::load(path)?;
+                    eprintln!("Loaded {} terms from {}", terms.len(), path.display());
+                    terms
+                }
+                None => Default::default(),
+            },
+            name_table: !args.no_name_table,
+            tm: match &args.tm {
+                Some(path) => {
+                    let mut memory = tm::TranslationMemory::open(path, args.tm_min_similarity)?;
+                    for tmx in &args.tm_import {
+                        let n = memory.import(tmx)?;
+                        eprintln!("Imported {} units from {}", n, tmx.display());
+                    }
+                    memory.save()?;
+                    eprintln!("Translation memory: {} entries", memory.len());
+                    Some(std::sync::Mutex::new(memory))
+                }
+                None => None,
+            },
+            names: std::sync::Mutex::new(match &args.names {
+                Some(path) => {
+                    let mut table = entities::NameTable::default();
+                    table.merge(entities::load(path)?);
+                    eprintln!("Pinned {} names from {}", table.len(), path.display());
+                    table
+                }
+                None => Default::default(),
+            }),
+            pivot: match &args.pivot {
+                Some(code) => {
+                    let target = targets::Target { code: code.clone() };
+                    if target.code.eq_ignore_ascii_case(targets::PRIMARY) {
+                        return Err(anyhow!("--pivot must differ from zh-TW"));
+                    }
+                    Some((target, args.pivot_model().to_string()))
+                }
+                None => None,
+            },
+            pivots: Default::default(),
+            ensemble: ensemble::Ensemble::from_args(args),
+            sampling: openai::Sampling {
+                temperature: args.translate_temperature,
+                top_p: args.top_p,
+                max_tokens: args.max_tokens,
+            },
+            retry_length: args.retranslate_length_outliers,
+            sdh: args.sdh,
+            honorifics: args.honorifics,
+            keep_techniques: args.keep_techniques,
+            english: args.english,
+            normalize_punctuation: !args.keep_punctuation,
+            structured: (!args.no_structured_output).into(),
+            batch_api: args.batch_api.then(Default::default),
+        })
+    }
+
+    /// Add the proper nouns in `lines` to the run's name table. Failures only
+    /// cost consistency, so they are reported and translation continues.
+    async fn learn_names(&self, lines: &[String], client: &OpenAiClient) {
+        if !self.name_table {
+            return;
+        }
+        match entities::extract(lines, client, &self.model).await {
+            Ok(found) => {
+                let mut names = self.names.lock().unwrap();
+                let before = names.len();
+                names.merge(found);
+                if names.len() > before {
+                    eprintln!("Name table: {} entries", names.len());
+                }
+            }
+            Err(e) => eprintln!("Warning: name table extraction failed: {e:#}"),
+        }
+    }
+
+    /// A translated line as written out: names enforced, punctuation
+    /// normalized, replace rules, `--terms`, and the content filter applied.
+    fn finish(&self, names: &entities::NameTable, ja: &str, zh: &str) -> String {
+        let mut zh = names.enforce(ja, zh);
+        if self.english == english::English::Keep {
+            zh = english::restore(ja, &zh);
+        }
+        if self.normalize_punctuation {
+            zh = zh_tw::normalize_punctuation(&zh);
+        }
+        let zh = self.terms.fix(&self.replace_rules.zh(&zh));
+        self.content_filter.apply(&zh)
+    }
+
+    /// System prompt for batch translation.
+    fn system_prompt(&self) -> String {
+        let base = match self.mode {
+            Mode::Dialog => "You are a professional translator. Translate Japanese to Traditional Chinese (Taiwan). Keep meaning, tone, and honorific nuance. Do not add explanations.",
+            Mode::Lyrics => "You are a lyricist translating Japanese song lyrics into Traditional Chinese (Taiwan). Favor natural, poetic phrasing, imagery, and rhythm over literal word-for-word rendering, but keep the meaning of each line. Translate every line on its own: never merge, split, or reorder lines. Do not add explanations.",
+        };
+        let mut prompt = base.to_string();
+        if self.english == english::English::Keep {
+            prompt = format!("{prompt} {}", english::PROMPT);
+        }
+        if self.sdh {
+            prompt = format!("{prompt} {}", sdh::PROMPT);
+        }
+        if let Some(anime) = anime::prompt(self.honorifics, self.keep_techniques) {
+            prompt = format!("{prompt} {anime}");
+        }
+        prompt
+    }
+}
+
+async fn translate_lines_zh_tw(
+    lines: &[String],
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<Vec<String>> {
+    if lines.is_empty() {
+        return Ok(vec![]);
+    }
+    opts.learn_names(lines, client).await;
+
+    // SFX lines handled per --sfx and translation memory hits skip the API
+    let mut found: Vec<Option<String>> = lines
+        .iter()
+        .map(|l| {
+            sfx::fixed_line(l, opts.sfx).or_else(|| opts.sdh.then(|| sdh::fixed_line(l)).flatten())
+        })
+        .collect();
+    let fixed = found.iter().filter(|f| f.is_some()).count();
+    if let Some(tm) = &opts.tm {
+        let tm = tm.lock().unwrap();
+        for (slot, line) in found.iter_mut().zip(lines) {
+            if slot.is_none() {
+                *slot = tm.lookup(line).map(str::to_string);
+            }
+        }
+    }
+    let pending: Vec<usize> = (0..lines.len()).filter(|&i| found[i].is_none()).collect();
+    let reused = lines.len() - pending.len() - fixed;
+    if reused > 0 {
+        eprintln!(
+            "Translation memory: reused {}/{} lines",
+            reused,
+            lines.len()
+        );
+    }
+
+    // Dense dialog gets fewer lines per request, sparse content more
+    let costs: Vec<u64> = pending
+        .iter()
+        .map(|&i| tokens::line_cost(&lines[i]))
+        .collect();
+    let budget = opts.line_budget();
+    let mut deferred = false;
+    for range in tokens::batches(&costs, opts.batch_size, budget) {
+        let batch_idx = &pending[range];
+        let batch: Vec<String> = batch_idx.iter().map(|&i| lines[i].clone()).collect();
+        let mut translated = if let Some(collector) = &opts.batch_api {
+            let id = batch_api::request_id(&opts.model, &batch);
+            let Some(content) = collector.result(&id) else {
+                let user = translation_user(&batch, None, opts);
+                collector.queue(id, translation_request(&user, &opts.model, None, opts));
+                deferred = true;
+                continue;
+            };
+            // A garbled reply is retried directly, halving as usual
+            match parse_translations(&content) {
+                Ok(v) if v.len() == batch.len() => v,
+                _ => translate_batch_strict(&batch, None, client, opts).await?,
+            }
+        } else {
+            let pivot = match &opts.pivot {
+                Some((target, model)) => {
+                    let mid = targets::translate(
+                        &batch,
+                        target,
+                        client,
+                        model,
+                        opts.batch_size,
+                        opts.mode,
+                        opts.sampling,
+                    )
+                    .await?;
+                    let mut pivots = opts.pivots.lock().unwrap();
+                    for (ja, p) in batch.iter().zip(&mid) {
+                        pivots.insert(ja.clone(), p.clone());
+                    }
+                    Some(mid)
+                }
+                None => None,
+            };
+            translate_batch_strict(&batch, pivot.as_deref(), client, opts).await?
+        };
+        retry_suspect_lines(&batch, &mut translated, client, opts).await?;
+        let mut tm = opts.tm.as_ref().map(|tm| tm.lock().unwrap());
+        for (&i, zh) in batch_idx.iter().zip(translated) {
+            if let Some(tm) = tm.as_mut() {
+                tm.insert(&lines[i], &zh);
+            }
+            found[i] = Some(zh);
+        }
+    }
+    if deferred {
+        return Err(batch_api::Deferred.into());
+    }
+    if let Some(tm) = &opts.tm {
+        tm.lock().unwrap().save()?;
+    }
+
+    let names = opts.names.lock().unwrap();
+    Ok(lines
+        .iter()
+        .zip(found)
+        .map(|(ja, zh)| opts.finish(&names, ja, &zh.unwrap_or_default()))
+        .collect())
+}
+
+/// `--songs loose`: retranslate the sung lines as lyrics, in place. A failed
+/// request keeps the dialog translation.
+async fn retranslate_songs(
+    ja_lines: &[String],
+    zh_lines: &mut [String],
+    sung: &[bool],
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<()> {
+    let idx: Vec<usize> = (0..sung.len()).filter(|&i| sung[i]).collect();
+    let lines: Vec<String> = idx.iter().map(|&i| ja_lines[i].clone()).collect();
+    let target = targets::Target {
+        code: targets::PRIMARY.to_string(),
+    };
+    let lyrics = match targets::translate(
+        &lines,
+        &target,
+        client,
+        &opts.model,
+        opts.batch_size,
+        Mode::Lyrics,
+        opts.sampling,
+    )
+    .await
+    {
+        Ok(lyrics) => lyrics,
+        Err(e) if ApiError::is_fatal(&e) => return Err(e),
+        Err(e) => {
+            eprintln!("Warning: lyric translation of sung lines failed: {e:#}");
+            return Ok(());
+        }
+    };
+    let names = opts.names.lock().unwrap();
+    for (&i, zh) in idx.iter().zip(lyrics) {
+        zh_lines[i] = songs::strip_notes(&opts.finish(&names, &ja_lines[i], &zh));
+    }
+    Ok(())
+}
+
+/// Re-translate, one line per request, lines the model echoed back in
+/// Japanese or that lost an English term under `--english keep` (and, with
+/// `--retranslate-length-outliers`, lines of a suspect length); lines that
+/// fail the check again are reported and kept.
+async fn retry_suspect_lines(
+    lines: &[String],
+    translated: &mut [String],
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<()> {
+    let problem = |ja: &str, zh: &str| {
+        if qc::untranslated(zh) {
+            return Some("untranslated");
+        }
+        if opts.english == english::English::Keep && english::missing(ja, zh).is_some() {
+            return Some("missing an English term");
+        }
+        opts.retry_length
+            .then(|| qc::length_outlier(ja, zh))
+            .flatten()
+            .map(|_| "suspect length")
+    };
+    for (ja, zh) in lines.iter().zip(translated.iter_mut()) {
+        if problem(ja, zh).is_none() {
+            continue;
+        }
+        match translate_single_fallback(ja, client, opts).await {
+            Ok(retry) => match problem(ja, &retry) {
+                None => *zh = retry,
+                Some(what) => eprintln!("Warning: still {what} after retry: {}", ja.trim()),
+            },
+            Err(e) if ApiError::is_fatal(&e) => return Err(e),
+            Err(e) => eprintln!("Warning: retry of a suspect line failed: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// `--sentence-groups`: translate the sentences that runs of `segments` make
+/// up, then spread each translation back over its segments.
+async fn translate_sentence_groups(
+    segments: &[WhisperSegment],
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<Vec<String>> {
+    let groups = resegment::sentence_groups(segments);
+    let sentences: Vec<String> = groups
+        .iter()
+        .map(|g| segments[g.clone()].iter().map(|s| s.text.trim()).collect())
+        .collect();
+    let zh = translate_lines_zh_tw(&sentences, client, opts).await?;
+    Ok(groups
+        .into_iter()
+        .zip(zh)
+        .flat_map(|(g, zh)| {
+            let weights: Vec<usize> = segments[g]
+                .iter()
+                .map(|s| s.text.trim().chars().count())
+                .collect();
+            resegment::redistribute(&zh, &weights)
+        })
+        .collect())
+}
+
+/// Translate `lines`, halving batches the model garbles down to single
+/// lines. With `pivot`, each line's pivot-language text is translated and the
+/// Japanese is context.
+async fn translate_batch_strict(
+    lines: &[String],
+    pivot: Option<&[String]>,
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<Vec<String>> {
+    let n = lines.len();
+    let mut out: Vec<Option<String>> = vec![None; n];
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    if n > 0 {
+        stack.push((0, n));
+    }
+
+    while let Some((start, end)) = stack.pop() {
+        let len = end - start;
+        if len == 0 {
+            continue;
+        }
+        let pivot = pivot.map(|p| &p[start..end]);
+        match translate_batch(&lines[start..end], pivot, client, opts).await {
+            Ok(v) if v.len() == len => {
+                for (i, t) in v.into_iter().enumerate() {
+                    out[start + i] = Some(t);
+                }
+            }
+            Err(e)
+                if len > 1 && matches!(e.downcast_ref(), Some(ApiError::ContextLength { .. })) =>
+            {
+                // Straight to batches that fit, rather than halving down to them
+                opts.shrink_batches(&e);
+                let budget = opts.line_budget();
+                let costs: Vec<u64> = lines[start..end]
+                    .iter()
+                    .map(|l| tokens::line_cost(l))
+                    .collect();
+                for range in tokens::batches(&costs, len.div_ceil(2), budget)
+                    .into_iter()
+                    .rev()
+                {
+                    stack.push((start + range.start, start + range.end));
+                }
+            }
+            Ok(_) | Err(_) => {
+                if len == 1 {
+                    let t = translate_single_fallback(&lines[start], client, opts).await?;
+                    out[start] = Some(t);
+                } else {
+                    let mid = start + len / 2;
+                    // Process right later, left first
+                    stack.push((mid, end));
+                    stack.push((start, mid));
+                }
+            }
+        }
+    }
+
+    // Collect and ensure all present
+    let mut result = Vec::with_capacity(n);
+    for (i, slot) in out.iter_mut().enumerate() {
+        if let Some(t) = slot.take() {
+            result.push(t);
+        } else {
+            return Err(anyhow!("Failed to translate line {}", i));
+        }
+    }
+    Ok(result)
+}
+
+async fn translate_batch(
+    lines: &[String],
+    pivot: Option<&[String]>,
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<Vec<String>> {
+    let user = translation_user(lines, pivot, opts);
+    let ensemble = &opts.ensemble;
+    if !ensemble.enabled() {
+        return request_translations(&user, &opts.model, None, client, opts).await;
+    }
+    // Candidates of the wrong length cannot be judged line by line
+    let mut candidates = Vec::new();
+    let mut last_err = None;
+    for i in 0..ensemble.candidates {
+        let temperature = ensemble.temperature(i);
+        match request_translations(&user, ensemble.model(i), temperature, client, opts).await {
+            Ok(v) if v.len() == lines.len() => candidates.push(v),
+            Ok(v) => last_err = Some(anyhow!("Candidate {} returned {} lines", i + 1, v.len())),
+            Err(e) if ApiError::is_fatal(&e) => return Err(e),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    match candidates.len() {
+        0 => Err(last_err.unwrap_or_else(|| anyhow!("No translation candidates"))),
+        1 => Ok(candidates.remove(0)),
+        _ => match ensemble::judge(lines, &candidates, client, &ensemble.judge_model).await {
+            Err(e) if !ApiError::is_fatal(&e) => {
+                eprintln!("Judge failed ({e:#}); keeping the first candidate");
+                Ok(candidates.remove(0))
+            }
+            judged => judged,
+        },
+    }
+}
+
+/// The user message of a batch translation request.
+fn translation_user(
+    lines: &[String],
+    pivot: Option<&[String]>,
+    opts: &TranslateOptions,
+) -> serde_json::Value {
+    let mut user = match (pivot, &opts.pivot) {
+        (Some(items), Some((target, _))) => json!({
+            "instruction": format!("Translate each item from {} to Traditional Chinese. The Japanese original of each item is in originals; follow it for names, honorifics, and tone where the {} lost them. Return strict JSON with {{\"translations\": string[]}} matching the input length.", target.english_name(), target.english_name()),
+            "source_language": target.code,
+            "target_language": "zh-TW",
+            "items": items,
+            "originals": lines,
+        }),
+        _ => json!({
+            "instruction": "Translate each item to Traditional Chinese. Return strict JSON with {\"translations\": string[]} matching the input length.",
+            "source_language": "ja",
+            "target_language": "zh-TW",
+            "items": lines,
+        }),
+    };
+    let names = opts.names.lock().unwrap().relevant(lines);
+    if !names.is_empty() {
+        user["names"] = names
+            .iter()
+            .map(|(ja, zh)| json!({"ja": ja, "zh": zh}))
+            .collect();
+        user["names_instruction"] =
+            "Render these proper nouns exactly as given in names, in every item.".into();
+    }
+    user
+}
+
+/// One batch translation request for the prepared `user` message.
+async fn request_translations(
+    user: &serde_json::Value,
+    model: &str,
+    temperature: Option<f32>,
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<Vec<String>> {
+    let req = translation_request(user, model, temperature, opts);
+    let structured = req
+        .response_format
+        .as_ref()
+        .is_some_and(|f| f.kind == "json_schema");
+    let content = match client.chat(&req).await {
+        Err(ApiError::BadRequest { message, .. })
+            if structured
+                && (message.contains("response_format") || message.contains("json_schema")) =>
+        {
+            eprintln!("Structured outputs rejected ({message}); using JSON mode for this run");
+            opts.structured
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+            client.chat(&req.json_object()).await
+        }
+        other => other,
+    }
+    .context("OpenAI translation request failed")?;
+    parse_translations(&content)
+}
+
+/// The chat request for a prepared `user` message: structured outputs where
+/// the model takes them, JSON mode otherwise.
+fn translation_request(
+    user: &serde_json::Value,
+    model: &str,
+    temperature: Option<f32>,
+    opts: &TranslateOptions,
+) -> openai::ChatRequest {
+    let req = openai::ChatRequest::new(model)
+        .system(opts.system_prompt())
+        .user(user.to_string())
+        .sampling(opts.sampling)
+        .temperature(temperature.or(opts.sampling.temperature));
+    let items = user["items"].as_array().map_or(0, Vec::len);
+    if opts.structured.load(std::sync::atomic::Ordering::Relaxed)
+        && openai::supports_json_schema(model)
+    {
+        // The schema pins the reply to exactly one string per item
+        req.json_schema("translations", translations_schema(items))
+    } else {
+        // Instruct model to return strict JSON
+        req.json_object()
+    }
+}
+
+/// The translations in a reply's content.
+fn parse_translations(content: &str) -> Result<Vec<String>> {
+    // Be tolerant: try content directly, then strip code fences, then find braces
+    if let Some(v) = try_parse_translations_json(content) {
+        return Ok(v);
+    }
+    // Fallback: try to slice out the first {...} block
+    let json_obj = extract_first_json_object(content).and_then(|s| try_parse_translations_json(&s));
+    if let Some(v) = json_obj {
+        return Ok(v);
+    }
+
+    Err(anyhow!("Translation JSON missing 'translations' array"))
+}
+
+/// JSON schema for a reply of exactly `n` translations.
+fn translations_schema(n: usize) -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "translations": {
+                "type": "array",
+                "items": { "type": "string" },
+                "minItems": n,
+                "maxItems": n,
+            }
+        },
+        "required": ["translations"],
+        "additionalProperties": false,
+    })
+}
+
+fn try_parse_translations_json(s: &str) -> Option<Vec<String>> {
+    let trimmed = s.trim();
+    let candidate = if trimmed.starts_with("```") {
+        // Possible fenced code block
+        trimmed
+            .trim_start_matches("```json")
+            .trim_start_matches("```JSON")
+            .trim_start_matches("```) ")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim()
+            .to_string()
+    } else {
+        trimmed.to_string()
+    };
+    match serde_json::from_str::<serde_json::Value>(&candidate) {
+        Ok(v) => v["translations"].as_array().map(|arr| {
+            arr.iter()
+                .map(|x| x.as_str().unwrap_or("").to_string())
+                .collect::<Vec<_>>()
+        }),
+        Err(_) => None,
+    }
+}
+
+fn extract_first_json_object(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut depth = 0i32;
+    let mut start: Option<usize> = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'{' {
+            if depth == 0 {
+                start = Some(i);
+            }
+            depth += 1;
+        } else if b == b'}' {
+            depth -= 1;
+            if depth == 0 {
+                if let Some(st) = start {
+                    return Some(s[st..=i].to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+async fn translate_single_fallback(
+    text: &str,
+    client: &OpenAiClient,
+    opts: &TranslateOptions,
+) -> Result<String> {
+    let system = match opts.mode {
+        Mode::Dialog => "You are a professional translator. Translate Japanese to Traditional Chinese (Taiwan). Output only the translated text without quotes or explanations.",
+        Mode::Lyrics => "You are a lyricist. Translate this Japanese song lyric line into natural, poetic Traditional Chinese (Taiwan). Output only the translated line without quotes or explanations.",
+    };
+    let system = match opts.english {
+        english::English::Keep => format!("{system} {}", english::PROMPT),
+        english::English::Translate => system.to_string(),
+    };
+    let req = openai::ChatRequest::new(&opts.model)
+        .system(system)
+        .user(text)
+        .sampling(opts.sampling);
+    let content = client
+        .chat(&req)
+        .await
+        .context("OpenAI translation request failed")?;
+    // Strip surrounding quotes if any
+    Ok(content.trim().trim_matches('"').to_string())
+}
+
+fn write_srt(
+    path: &Path,
+    segments: &[WhisperSegment],
+    lines: &[String],
+    encoding: formats::TextEncoding,
+) -> Result<()> {
+    use std::fmt::Write;
+    let mut text = String::new();
+    for (i, (seg, line)) in segments.iter().zip(lines.iter()).enumerate() {
+        let idx = i + 1;
+        let start = format_srt_time(seg.start);
+        let end = format_srt_time(seg.end);
+        writeln!(text, "{}\n{} --> {}\n{}\n", idx, start, end, line)?;
+    }
+    let (bytes, unmappable) = encoding.encode(&text);
+    if unmappable > 0 {
+        eprintln!(
+            "Warning: {unmappable} character(s) in {} have no {:?} code and were written as ?",
+            path.display(),
+            encoding.charset
+        );
+    }
+    std::fs::write(path, bytes).with_context(|| format!("Create SRT at {}", path.display()))
+}
+
+/// Append cues to an existing SRT, numbering from `first_index`.
+fn append_srt(
+    path: &Path,
+    first_index: usize,
+    segments: &[WhisperSegment],
+    lines: &[String],
+) -> Result<()> {
+    use std::io::Write;
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("Append SRT at {}", path.display()))?;
+    for (i, (seg, text)) in segments.iter().zip(lines.iter()).enumerate() {
+        let start = format_srt_time(seg.start);
+        let end = format_srt_time(seg.end);
+        writeln!(f, "{}\n{} --> {}\n{}\n", first_index + i, start, end, text)?;
+    }
+    Ok(())
+}
+
+/// Append cues to a WebVTT file whose `WEBVTT` header is already written.
+fn append_vtt(path: &Path, segments: &[WhisperSegment], lines: &[String]) -> Result<()> {
+    use std::io::Write;
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("Append VTT at {}", path.display()))?;
+    for (seg, text) in segments.iter().zip(lines.iter()) {
+        let start = format_vtt_time(seg.start);
+        let end = format_vtt_time(seg.end);
+        writeln!(f, "{} --> {}\n{}\n", start, end, text)?;
+    }
+    Ok(())
+}
+
+/// Apply `--speed-factor` and then `--offset` to a cue, clamping at zero.
+fn retime(seg: &mut WhisperSegment, speed: f64, offset: f64) {
+    seg.start = (seg.start / speed + offset).max(0.0);
+    seg.end = (seg.end / speed + offset).max(0.0);
+}
+
+/// `25/23.976` or `1.0427`; must be positive.
+fn parse_ratio(s: &str) -> std::result::Result<f64, String> {
+    let num = |t: &str| t.trim().parse::<f64>().map_err(|e| format!("{t:?}: {e}"));
+    let v = match s.split_once('/') {
+        Some((a, b)) => num(a)? / num(b)?,
+        None => num(s)?,
+    };
+    if !v.is_finite() || v <= 0.0 {
+        return Err(format!("{s} is not a positive ratio"));
+    }
+    Ok(v)
+}
+
+fn format_vtt_time(seconds: f64) -> String {
+    // HH:MM:SS.mmm
+    format_srt_time(seconds).replace(',', ".")
+}
+
+/// Write the SRT to a sibling temp file and rename it into place, so readers
+/// (players, tail -f users) never observe a half-written file.
+fn write_srt_atomic(
+    path: &Path,
+    segments: &[WhisperSegment],
+    lines: &[String],
+    encoding: formats::TextEncoding,
+) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".part");
+    let tmp = PathBuf::from(tmp);
+    write_srt(&tmp, segments, lines, encoding)?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Move {} into place", tmp.display()))?;
+    Ok(())
+}
+
+fn format_srt_time(seconds: f64) -> String {
+    // HH:MM:SS,mmm
+    let total_ms = (seconds * 1000.0).round() as i64;
+    let ms = total_ms % 1000;
+    let total_secs = total_ms / 1000;
+    let s = total_secs % 60;
+    let total_mins = total_secs / 60;
+    let m = total_mins % 60;
+    let h = total_mins / 60;
+    format!("{:02}:{:02}:{:02},{:03}", h, m, s, ms)
+}
+
+/// 1 - normalized Levenshtein distance over chars.
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    1.0 - prev[b.len()] as f64 / longest as f64
+}
+
+fn default_srt_path(input: &Path) -> PathBuf {
+    let input = &download::local_name(input);
+    let mut p = input.to_path_buf();
+    p.set_extension("");
+    let base = p.file_name().and_then(|s| s.to_str()).unwrap_or("output");
+    let mut out = input
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    out.push(format!("{}.zh-TW.srt", base));
+    out
+}
+
+/// `--output` path; passed without a value, a default derived from the input.
+fn output_video_path(args: &Args) -> Option<PathBuf> {
+    match args.output.as_deref() {
+        None => None,
+        Some("__AUTO__") | Some("") => Some(default_output_video_path(args.input())),
+        Some(s) => Some(PathBuf::from(s)),
+    }
+}
+
+fn default_output_video_path(input: &Path) -> PathBuf {
+    let input = &download::local_name(input);
+    let mut p = input.to_path_buf();
+    p.set_extension("");
+    let base = p.file_name().and_then(|s| s.to_str()).unwrap_or("output");
+    let mut out = input
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    out.push(format!("{}.zh.mp4", base));
+    out
+}
+
+// (Removed unused mux_subtitles)
+
+fn burn_in_subtitles(
+    input: &Path,
+    subs: &Path,
+    out: &Path,
+    fonts_dir: Option<&Path>,
+    extra: &[String],
+    nice: bool,
+    on_progress: impl FnMut(f64),
+) -> Result<()> {
+    // Burn subtitles using subtitles filter (requires libass). Re-encodes video.
+    let filter = subtitles_filter(subs, fonts_dir, None);
+
+    #[cfg(feature = "native-ffmpeg")]
+    {
+        let _ = (extra, nice);
+        native_ffmpeg::burn_in(input, out, &filter, on_progress)
+    }
+
+    #[cfg(not(feature = "native-ffmpeg"))]
+    {
+        let _ = on_progress;
+        let status =
+            cancel::status(encode_command(nice).args(burn_in_args(input, &filter, out, extra)))
+                .context("ffmpeg burn-in subtitles failed")?;
+        if !status.success() {
+            return Err(anyhow!("ffmpeg burn-in failed"));
+        }
+        Ok(())
+    }
+}
+
+/// Extra burn-in encoder arguments: `--encode-threads`, then
+/// `--ffmpeg-encode-args` so it can override.
+fn encode_args(args: &Args) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    if let Some(n) = args.encode_threads {
+        out.extend(["-threads".to_string(), n.to_string()]);
+    }
+    out.extend(split_args(
+        args.ffmpeg_encode_args.as_deref().unwrap_or(""),
+    )?);
+    Ok(out)
+}
+
+/// The burn-in encoder process, at background priority with `--nice`.
+fn encode_command(nice: bool) -> Command {
+    if nice && cfg!(unix) {
+        let mut cmd = Command::new("nice");
+        cmd.args(["-n", "10", "ffmpeg"]);
+        return cmd;
+    }
+    #[allow(unused_mut)]
+    let mut cmd = Command::new("ffmpeg");
+    #[cfg(windows)]
+    if nice {
+        use std::os::windows::process::CommandExt;
+        const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+        cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+    }
+    cmd
+}
+
+/// ffmpeg arguments that re-encode `input` with the subtitles filter; `extra`
+/// goes last before the output.
+fn burn_in_args(input: &Path, filter: &str, out: &Path, extra: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-i"].map(String::from).into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-vf", filter, "-c:a", "copy"].map(String::from));
+    args.extend_from_slice(extra);
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+/// Split a command-line fragment into arguments the way a POSIX shell would:
+/// whitespace separates, quotes group, and backslash escapes outside single
+/// quotes.
+fn split_args(s: &str) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    let mut cur: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => out.extend(cur.take()),
+            '\'' => {
+                let word = cur.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(anyhow!("Unterminated ' in {s:?}")),
+                    }
+                }
+            }
+            '"' => {
+                let word = cur.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(c) => word.push(c),
+                        None => return Err(anyhow!("Unterminated \" in {s:?}")),
+                    }
+                }
+            }
+            '\\' => cur.get_or_insert_with(String::new).extend(chars.next()),
+            c => cur.get_or_insert_with(String::new).push(c),
+        }
+    }
+    out.extend(cur);
+    Ok(out)
+}
+
+fn subtitles_filter(subs: &Path, fonts_dir: Option<&Path>, font_name: Option<&str>) -> String {
+    let mut filter = format!("subtitles={}", escape_for_ffmpeg(subs));
+    if let Some(dir) = fonts_dir {
+        filter.push_str(":fontsdir=");
+        filter.push_str(&escape_for_ffmpeg(dir));
+    }
+    // If an ASS file was generated with a Style font, don't override via force_style.
+    // Only apply force_style for plain SRT inputs when an explicit font is requested.
+    if subs
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|e| e.eq_ignore_ascii_case("ass"))
+        == Some(false)
+    {
+        if let Some(name) = font_name {
+            let safe = name.replace("'", "\\'");
+            filter.push_str(":force_style=");
+            filter.push_str(&format!("'FontName={}'", safe));
+        }
+    }
+    filter
+}
+
+fn escape_for_ffmpeg(path: &Path) -> String {
+    // Basic escaping for spaces and special chars in filter args
+    let s = path.to_string_lossy();
+    s.replace("\\", "\\\\")
+        .replace(":", "\\:")
+        .replace("=", "\\=")
+}
+
+/// Write an ASS file with `styles` and `events`.
+fn write_ass_events(
+    path: &Path,
+    canvas: layout::Canvas,
+    styles: &[layout::AssStyle],
+    events: &[layout::AssEvent],
+) -> Result<()> {
+    use std::io::Write;
+    let mut f =
+        std::fs::File::create(path).with_context(|| format!("Create ASS at {}", path.display()))?;
+
+    writeln!(f, "[Script Info]")?;
+    writeln!(f, "ScriptType: v4.00+")?;
+    writeln!(f, "PlayResX: {}", canvas.width)?;
+    writeln!(f, "PlayResY: {}", canvas.height)?;
+    writeln!(f, "WrapStyle: 0")?;
+    writeln!(f, "ScaledBorderAndShadow: yes")?;
+    writeln!(f, "YCbCr Matrix: TV.601")?;
+    writeln!(f)?;
+    writeln!(f, "[V4+ Styles]")?;
+    writeln!(f, "Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding")?;
+    for style in styles {
+        writeln!(f, "{}", style.line(canvas))?;
+    }
+    writeln!(f)?;
+    writeln!(f, "[Events]")?;
+    writeln!(
+        f,
+        "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+    )?;
+
+    for e in events {
+        let start = format_ass_time(e.start);
+        let end = format_ass_time(e.end);
+        writeln!(
+            f,
+            "Dialogue: 0,{start},{end},{},,0,0,0,,{}",
+            e.style, e.text
+        )?;
+    }
+    Ok(())
+}
+
+fn format_ass_time(seconds: f64) -> String {
+    // h:mm:ss.cs (centiseconds)
+    let total_cs = (seconds * 100.0).round() as i64;
+    let cs = total_cs % 100;
+    let total_secs = total_cs / 100;
+    let s = total_secs % 60;
+    let total_mins = total_secs / 60;
+    let m = total_mins % 60;
+    let h = total_mins / 60;
+    format!("{}:{:02}:{:02}.{:02}", h, m, s, cs)
+}
+
+fn detect_default_fonts_dir() -> Option<PathBuf> {
+    // Try common system fonts directories to help libass find CJK glyphs
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    // Highest priority: env override (new name), fallback to legacy var
+    if let Ok(env_dir) = std::env::var("JP2TW_subs_FONTS_DIR") {
+        let p = PathBuf::from(env_dir);
+        if p.exists() {
+            return Some(p);
+        }
+    }
+    if let Ok(env_dir) = std::env::var("VIDEO_TRANSLATOR_FONTS_DIR") {
+        let p = PathBuf::from(env_dir);
+        if p.exists() {
+            return Some(p);
+        }
+    }
+
+    // Project-local fonts folder next
+    if let Ok(cwd) = std::env::current_dir() {
+        let project_fonts = cwd.join("fonts");
+        if project_fonts.exists() {
+            return Some(project_fonts);
+        }
+    }
+    if cfg!(target_os = "macos") {
+        candidates.push(PathBuf::from("/System/Library/Fonts"));
+        candidates.push(PathBuf::from("/Library/Fonts"));
+        if let Ok(home) = std::env::var("HOME") {
+            candidates.push(PathBuf::from(format!("{home}/Library/Fonts")));
+        }
+    } else if cfg!(target_os = "windows") {
+        candidates.push(PathBuf::from("C:/Windows/Fonts"));
+    } else {
+        candidates.push(PathBuf::from("/usr/share/fonts"));
+        candidates.push(PathBuf::from("/usr/local/share/fonts"));
+        candidates.push(PathBuf::from("/usr/share/fonts/truetype"));
+    }
+
+    candidates.into_iter().find(|pb| pb.exists())
+}
+
+fn resolve_fonts_dir(preferred: Option<&Path>) -> Option<PathBuf> {
+    if let Some(p) = preferred {
+        if p.exists() {
+            return Some(p.to_path_buf());
+        }
+    }
+    // Prefer project-local ./fonts if present
+    if let Ok(cwd) = std::env::current_dir() {
+        let p = cwd.join("fonts");
+        if p.exists() {
+            return Some(p);
+        }
+    }
+    // Fall back to env/system detection
+    detect_default_fonts_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_srt_time() {
+        assert_eq!(format_srt_time(0.0), "00:00:00,000");
+        assert_eq!(format_srt_time(1.234), "00:00:01,234");
+        assert_eq!(format_srt_time(3661.234), "01:01:01,234");
+    }
+
+    #[test]
+    fn test_append_srt_and_vtt() {
+        let dir = tempfile::tempdir().unwrap();
+        let srt = dir.path().join("live.srt");
+        let vtt = dir.path().join("live.vtt");
+        std::fs::write(&vtt, "WEBVTT\n\n").unwrap();
+        append_srt(&srt, 1, &[seg(0.0, 1.0, "JA")], &["一".to_string()]).unwrap();
+        append_srt(&srt, 2, &[seg(15.0, 16.5, "JA")], &["二".to_string()]).unwrap();
+        append_vtt(&vtt, &[seg(15.0, 16.5, "JA")], &["二".to_string()]).unwrap();
+        let content = std::fs::read_to_string(&srt).unwrap();
+        assert!(content.ends_with("2\n00:00:15,000 --> 00:00:16,500\n二\n\n"));
+        let content = std::fs::read_to_string(&vtt).unwrap();
+        assert_eq!(content, "WEBVTT\n\n00:00:15.000 --> 00:00:16.500\n二\n\n");
+    }
+
+    #[test]
+    fn test_parse_srt_time() {
+        assert_eq!(subparse::parse_timestamp("00:00:01,234"), Some(1.234));
+        assert_eq!(subparse::parse_timestamp("01:01:01.500"), Some(3661.5));
+        assert_eq!(subparse::parse_timestamp("garbage"), None);
+    }
+
+    #[test]
+    fn test_format_ass_time() {
+        assert_eq!(format_ass_time(0.0), "0:00:00.00");
+        assert_eq!(format_ass_time(1.23), "0:00:01.23");
+        assert_eq!(format_ass_time(3661.23), "1:01:01.23");
+    }
+
+    #[test]
+    fn test_default_paths() {
+        let input = PathBuf::from("/tmp/sample.mp4");
+        let srt = default_srt_path(&input);
+        assert_eq!(srt, PathBuf::from("/tmp/sample.zh-TW.srt"));
+
+        let mp4 = default_output_video_path(&input);
+        assert_eq!(mp4, PathBuf::from("/tmp/sample.zh.mp4"));
+    }
+
+    #[test]
+    fn test_escape_for_ffmpeg() {
+        let p = PathBuf::from("/a:b=c\\ d");
+        let esc = escape_for_ffmpeg(&p);
+        // ":" -> "\\:", "=" -> "\\=", "\\" -> "\\\\"
+        assert!(esc.contains("\\:"));
+        assert!(esc.contains("\\="));
+        assert!(esc.contains("\\\\"));
+    }
+
+    #[test]
+    fn test_write_srt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.srt");
+        let segments = vec![
+            WhisperSegment {
+                id: Some(0),
+                start: 0.0,
+                end: 1.0,
+                text: "JA0".into(),
+                ..Default::default()
+            },
+            WhisperSegment {
+                id: Some(1),
+                start: 2.5,
+                end: 3.75,
+                text: "JA1".into(),
+                ..Default::default()
+            },
+        ];
+        let lines = vec!["你好".to_string(), "世界".to_string()];
+        write_srt(&path, &segments, &lines, Default::default()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let expected =
+            "1\n00:00:00,000 --> 00:00:01,000\n你好\n\n2\n00:00:02,500 --> 00:00:03,750\n世界\n\n";
+        assert_eq!(content, expected);
+    }
+
+    #[test]
+    fn test_write_ass() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.ass");
+        let style = layout::AssStyle {
+            name: "Default",
+            style: layout::LangStyle {
+                font: "My, Font".into(),
+                size: 30,
+                rgb: 0xFFFFFF,
+                opacity: 100,
+            },
+            alignment: 2,
+        };
+        let event = |start: f64, end: f64, text: &str| layout::AssEvent {
+            start,
+            end,
+            style: "Default",
+            text: layout::ass_escape(text),
+        };
+        let events = vec![event(0.0, 1.0, "你好"), event(2.5, 3.75, "世界\n{x}")];
+        write_ass_events(&path, Default::default(), &[style], &events).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("Style: Default,My  Font,30,&H00FFFFFF,"));
+        assert!(content.contains("PlayResX: 384\nPlayResY: 288\n"));
+        assert!(content.contains(",Default,,0,0,0,,你好"));
+        // Newlines become \N and braces parentheses in Dialogue text
+        assert!(content.contains("世界\\N(x)"));
+        assert!(content.contains("0:00:00.00"));
+        assert!(content.contains("0:00:01.00"));
+        assert!(content.contains("0:00:02.50"));
+        assert!(content.contains("0:00:03.75"));
+    }
+
+    #[test]
+    fn test_build_display_lines() {
+        let ja = vec!["こんにちは".to_string()];
+        let zh = vec!["你好".to_string()];
+        let args = Args::parse_from(["jp2tw-subs", "-i", "x.mp4"]);
+        let mut layout = layout::Layout::from_args(&args);
+        assert_eq!(
+            build_display_lines(&ja, &zh, &layout),
+            vec!["你好\nこんにちは"]
+        );
+        layout.bilingual = false;
+        assert_eq!(build_display_lines(&ja, &zh, &layout), vec!["你好"]);
+    }
+
+    #[test]
+    fn test_group_by_window() {
+        let cue = |start: f64| seg(start, start + 1.0, "x");
+        let groups = group_by_window(vec![cue(1.0), cue(59.0), cue(61.0), cue(200.0)], 60);
+        let sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+        assert!(group_by_window(Vec::new(), 60).is_empty());
+    }
+
+    #[test]
+    fn test_split_lyric_phrases() {
+        let segs = vec![WhisperSegment {
+            id: Some(0),
+            ..seg(10.0, 16.0, "君の声が 聞こえる あぁ 夜空の向こう")
+        }];
+        let out = split_lyric_phrases(segs);
+        let texts: Vec<&str> = out.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["君の声が", "聞こえるあぁ", "夜空の向こう"]);
+        assert_eq!(out[0].start, 10.0);
+        assert_eq!(out[2].end, 16.0);
+        assert!(out[0].end <= out[1].start + 1e-9);
+    }
+
+    #[test]
+    fn test_is_non_speech() {
+        let scored = |no_speech: f64, lp: f64| WhisperSegment {
+            no_speech_prob: Some(no_speech),
+            avg_logprob: Some(lp),
+            ..seg(0.0, 1.0, "")
+        };
+        assert!(scored(0.9, -1.5).is_non_speech(0.6, -1.0));
+        // Confident text wins over a high no-speech probability
+        assert!(!scored(0.9, -0.3).is_non_speech(0.6, -1.0));
+        assert!(!scored(0.1, -1.5).is_non_speech(0.6, -1.0));
+        assert!(!WhisperSegment::default().is_non_speech(0.6, -1.0));
+    }
+
+    #[test]
+    fn test_write_srt_atomic_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.srt");
+        std::fs::write(&path, "old").unwrap();
+        let segments = vec![seg(0.0, 1.0, "JA")];
+        write_srt_atomic(&path, &segments, &["你好".to_string()], Default::default()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("1\n00:00:00,000 --> 00:00:01,000\n你好"));
+        assert!(!dir.path().join("out.srt.part").exists());
+    }
+
+    #[test]
+    fn test_json_helpers() {
+        // Plain JSON
+        let s = r#"{"translations":["a","b"]}"#;
+        let v = try_parse_translations_json(s).unwrap();
+        assert_eq!(v, vec!["a", "b"]);
+
+        // Fenced JSON
+        let s2 = "```json\n{\n  \"translations\":[\"x\",\"y\"]\n}\n```";
+        let v2 = try_parse_translations_json(s2).unwrap();
+        assert_eq!(v2, vec!["x", "y"]);
+
+        // Embedded JSON
+        let s3 = "Here is your result:\n{\"translations\":[\"m\",\"n\"]}\nThanks";
+        let obj = extract_first_json_object(s3).unwrap();
+        let v3 = try_parse_translations_json(&obj).unwrap();
+        assert_eq!(v3, vec!["m", "n"]);
+    }
+
+    #[test]
+    fn test_resolve_fonts_dir_prefers_provided() {
+        let dir = tempfile::tempdir().unwrap();
+        let chosen = resolve_fonts_dir(Some(dir.path()));
+        assert_eq!(chosen.unwrap(), dir.path());
+    }
+
+    #[test]
+    fn test_split_args() {
+        let args = split_args(r#" -tune animation -metadata title="My Show" -vf 'a=b c' x\ y "#);
+        assert_eq!(
+            args.unwrap(),
+            [
+                "-tune",
+                "animation",
+                "-metadata",
+                "title=My Show",
+                "-vf",
+                "a=b c",
+                "x y"
+            ]
+        );
+        assert_eq!(split_args("''").unwrap(), [""]);
+        assert!(split_args("-vf 'oops").is_err());
+    }
+
+    #[test]
+    fn test_chunk_audio_args() {
+        let args = chunk_audio_args(
+            Path::new("in.mp4"),
+            1200.0,
+            600,
+            Path::new("chunk_00002.wav"),
+            &["-af".to_string(), "highpass=f=80".to_string()],
+        );
+        // Seeks on the input side, so earlier audio is never decoded
+        let line = args.join(" ");
+        assert!(line.starts_with("-nostdin -y -v error -ss 1200.000 -t 600 -i in.mp4 -vn"));
+        assert!(line.ends_with("-af highpass=f=80 chunk_00002.wav"));
+    }
+
+    #[test]
+    fn test_retime_clamps_at_zero() {
+        let mut cue = seg(0.2, 1.5, "");
+        retime(&mut cue, 1.0, -0.3);
+        assert_eq!((cue.start, cue.end), (0.0, 1.2));
+    }
+
+    #[test]
+    fn test_speed_factor() {
+        let pal = parse_ratio("25/23.976").unwrap();
+        assert!((pal - 1.04271).abs() < 1e-5);
+        assert!(parse_ratio("0").is_err() && parse_ratio("1/0").is_err());
+        let mut cue = seg(2502.5, 2505.0, "");
+        retime(&mut cue, 1.25, 1.0);
+        assert_eq!((cue.start, cue.end), (2003.0, 2005.0));
+    }
+}
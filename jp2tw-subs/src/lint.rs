@@ -0,0 +1,226 @@
+//! `lint` subcommand: check subtitle files for ordering, overlaps, bad
+//! durations, reading speed, line length, characters the format cannot
+//! carry, and Simplified characters in zh-TW text. Errors (and warnings with
+//! `--strict`) make the command exit non-zero, for CI-style gating.
+
+use anyhow::Result;
+use std::fmt;
+use std::path::Path;
+
+use crate::subparse::{self, Cue, SubtitleKind};
+use crate::{format_srt_time, formats, zh_tw};
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Characters per second, whitespace excluded
+    pub max_cps: f64,
+    pub max_line_chars: usize,
+    pub max_lines: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    /// 1-based cue number
+    pub cue: usize,
+    pub start: f64,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(
+            f,
+            "cue {} [{}] {level}: {}",
+            self.cue,
+            format_srt_time(self.start),
+            self.message
+        )
+    }
+}
+
+pub fn check(cues: &[Cue], kind: SubtitleKind, limits: &Limits) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut prev: Option<&Cue> = None;
+    for (i, cue) in cues.iter().enumerate() {
+        let mut report = |severity, message: String| {
+            issues.push(Issue {
+                cue: i + 1,
+                start: cue.start,
+                severity,
+                message,
+            })
+        };
+        let duration = cue.end - cue.start;
+        if duration <= 0.0 {
+            report(
+                Severity::Error,
+                format!("duration is {duration:.3}s (end not after start)"),
+            );
+        }
+        if let Some(p) = prev {
+            if cue.start < p.start {
+                report(
+                    Severity::Error,
+                    format!(
+                        "starts before the previous cue ({})",
+                        format_srt_time(p.start)
+                    ),
+                );
+            } else if cue.start < p.end {
+                report(
+                    Severity::Warning,
+                    format!("overlaps the previous cue by {:.3}s", p.end - cue.start),
+                );
+            }
+        }
+        prev = Some(cue);
+
+        let chars: usize = cue
+            .lines
+            .iter()
+            .map(|l| l.chars().filter(|c| !c.is_whitespace()).count())
+            .sum();
+        if chars == 0 {
+            report(Severity::Warning, "empty cue".into());
+        }
+        if duration > 0.0 && chars as f64 / duration > limits.max_cps {
+            report(
+                Severity::Warning,
+                format!(
+                    "{:.1} characters/s (max {})",
+                    chars as f64 / duration,
+                    limits.max_cps
+                ),
+            );
+        }
+        if cue.lines.len() > limits.max_lines {
+            report(
+                Severity::Warning,
+                format!("{} lines (max {})", cue.lines.len(), limits.max_lines),
+            );
+        }
+        for line in &cue.lines {
+            let len = line.chars().count();
+            if len > limits.max_line_chars {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "line of {len} characters (max {}): {line}",
+                        limits.max_line_chars
+                    ),
+                );
+            }
+            if let Some(c) = line
+                .chars()
+                .find(|c| (c.is_control() && *c != '\t') || *c == '\u{fffd}')
+            {
+                report(
+                    Severity::Error,
+                    format!("illegal character U+{:04X}", c as u32),
+                );
+            }
+            if kind != SubtitleKind::Ass && line.contains("-->") {
+                report(
+                    Severity::Error,
+                    "\"-->\" in cue text breaks SRT/WebVTT parsing".into(),
+                );
+            }
+            let simplified = zh_tw::simplified_chars(line);
+            if !simplified.is_empty() {
+                let list: Vec<String> =
+                    simplified.iter().map(|(s, t)| format!("{s}→{t}")).collect();
+                report(
+                    Severity::Warning,
+                    format!("Simplified characters: {}", list.join(" ")),
+                );
+            }
+        }
+    }
+    issues
+}
+
+/// Lint each file and print its issues; true when none fail the run.
+pub fn run(files: &[impl AsRef<Path>], limits: &Limits, strict: bool) -> Result<bool> {
+    let mut ok = true;
+    for path in files {
+        let path = path.as_ref();
+        let text = formats::read_text(path)?;
+        let kind = SubtitleKind::detect(path, &text);
+        let cues = subparse::parse(&text, kind)?;
+        let issues = check(&cues, kind, limits);
+        for issue in &issues {
+            println!("{}: {issue}", path.display());
+        }
+        let errors = issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .count();
+        let warnings = issues.len() - errors;
+        println!(
+            "{}: {} cues, {errors} error(s), {warnings} warning(s)",
+            path.display(),
+            cues.len()
+        );
+        if errors > 0 || (strict && warnings > 0) {
+            ok = false;
+        }
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            lines: text.lines().map(str::to_string).collect(),
+            style: None,
+        }
+    }
+
+    #[test]
+    fn test_check_finds_each_problem() {
+        let limits = Limits {
+            max_cps: 9.0,
+            max_line_chars: 16,
+            max_lines: 2,
+        };
+        let cues = [
+            cue(1.0, 3.0, "你好"),
+            cue(2.5, 4.0, "重疊了"),
+            cue(2.0, 2.0, "這是什么"),
+            cue(5.0, 6.0, "這一行實在是太長了而且說得太快根本來不及看完"),
+        ];
+        let issues = check(&cues, SubtitleKind::Srt, &limits);
+        let on = |n: usize| -> Vec<String> {
+            issues
+                .iter()
+                .filter(|i| i.cue == n)
+                .map(|i| i.to_string())
+                .collect()
+        };
+        assert!(on(1).is_empty());
+        assert_eq!(on(2).len(), 1);
+        assert!(on(2)[0].contains("warning: overlaps"));
+        let third = on(3).join("\n");
+        assert!(third.contains("error: duration"));
+        assert!(third.contains("error: starts before"));
+        assert!(third.contains("么→麼"));
+        let fourth = on(4).join("\n");
+        assert!(fourth.contains("characters/s") && fourth.contains("line of 22"));
+    }
+}
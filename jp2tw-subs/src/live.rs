@@ -0,0 +1,189 @@
+//! Live input captioning: ffmpeg captures a live HLS/RTMP stream into rolling
+//! WAV windows; each finished window is transcribed, translated, and appended
+//! to the growing SRT/VTT files.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use tempfile::tempdir;
+use tokio::time::{sleep, Duration};
+
+use crate::cancel;
+use crate::emoji;
+use crate::layout::Layout;
+use crate::openai::OpenAiClient;
+use crate::resegment;
+use crate::sfx::{self, SfxMode};
+use crate::{
+    append_srt, append_vtt, build_display_lines, drop_non_speech, retime, split_lyric_phrases,
+    transcribe_chunk, translate_lines_zh_tw, wav, Args, Mode, TranslateOptions,
+};
+
+/// Whether `input` names a network stream rather than a local file.
+pub fn is_stream_url(input: &Path) -> bool {
+    let s = input.to_string_lossy().to_ascii_lowercase();
+    [
+        "http://", "https://", "rtmp://", "rtmps://", "rtsp://", "srt://", "udp://",
+    ]
+    .iter()
+    .any(|p| s.starts_with(p))
+}
+
+/// Kills the capture process if the live loop bails out with an error.
+struct Capture(Child);
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+pub async fn run(
+    args: &Args,
+    client: &OpenAiClient,
+    translate_opts: &TranslateOptions,
+    output_srt: &Path,
+) -> Result<()> {
+    let tmp = tempdir()?;
+    let window = args.live_window.max(2);
+    let pattern = tmp.path().join("live_%05d.wav");
+    let child = Command::new("ffmpeg")
+        .args([
+            "-nostdin",
+            "-loglevel",
+            "error",
+            "-i",
+            &args.input().to_string_lossy(),
+            "-vn",
+            "-acodec",
+            "pcm_s16le",
+            "-ar",
+            "16000",
+            "-ac",
+            "1",
+            "-f",
+            "segment",
+            "-segment_time",
+            &window.to_string(),
+            "-reset_timestamps",
+            "1",
+            pattern.to_str().unwrap(),
+        ])
+        .stdout(Stdio::null())
+        .spawn()
+        .context("ffmpeg is required for --live capture")?;
+    let mut capture = Capture(child);
+
+    let output_vtt = output_srt.with_extension("vtt");
+    std::fs::write(output_srt, "")
+        .with_context(|| format!("Create SRT at {}", output_srt.display()))?;
+    std::fs::write(&output_vtt, "WEBVTT\n\n")
+        .with_context(|| format!("Create VTT at {}", output_vtt.display()))?;
+    eprintln!(
+        "Live captioning {} in {}s windows -> {} / {} (Ctrl-C to stop)",
+        args.input().display(),
+        window,
+        output_srt.display(),
+        output_vtt.display()
+    );
+
+    let window_path = |i: usize| -> PathBuf { tmp.path().join(format!("live_{:05}.wav", i)) };
+    let mut next = 0usize;
+    let mut offset = 0.0f64;
+    let mut cue_index = 1usize;
+    loop {
+        if cancel::is_cancelled() {
+            break;
+        }
+        let exited = capture.0.try_wait()?;
+        let current = window_path(next);
+        // A window is complete once ffmpeg has moved on to the next one
+        if window_path(next + 1).exists() || (exited.is_some() && current.exists()) {
+            let secs = window_seconds(&current)?;
+            if secs > 0.5 {
+                let mut segs =
+                    transcribe_chunk(&current, 0, 0, client, &args.whisper_model, Some("ja"))
+                        .await?
+                        .0;
+                drop_non_speech(&mut segs, args);
+                let segs = if args.mode == Mode::Lyrics {
+                    split_lyric_phrases(segs)
+                } else if !args.no_sentence_split {
+                    resegment::by_sentence(segs)
+                } else {
+                    segs
+                };
+                let segs: Vec<_> = segs
+                    .into_iter()
+                    .map(|mut s| {
+                        s.start += offset;
+                        s.end += offset;
+                        retime(&mut s, args.speed_factor, args.offset);
+                        s
+                    })
+                    .filter(|s| !s.text.trim().is_empty())
+                    .filter(|s| args.sfx != SfxMode::Drop || !sfx::is_sfx(&s.text))
+                    .collect();
+                if !segs.is_empty() {
+                    let ja: Vec<String> = segs.iter().map(|s| s.text.clone()).collect();
+                    let zh = translate_lines_zh_tw(&ja, client, translate_opts).await?;
+                    let ja: Vec<String> = ja
+                        .iter()
+                        .map(|l| translate_opts.replace_rules.ja(l))
+                        .collect();
+                    let display = build_display_lines(&ja, &zh, &Layout::from_args(args));
+                    let srt = args.emoji.lines(emoji::Output::Srt, &display);
+                    append_srt(output_srt, cue_index, &segs, &srt)?;
+                    let vtt = args.emoji.lines(emoji::Output::Vtt, &display);
+                    append_vtt(&output_vtt, &segs, &vtt)?;
+                    cue_index += segs.len();
+                    for line in &zh {
+                        eprintln!("  {}", line);
+                    }
+                }
+            }
+            offset += secs;
+            let _ = std::fs::remove_file(&current);
+            next += 1;
+            continue;
+        }
+        if let Some(status) = exited {
+            if !status.success() && next == 0 {
+                return Err(anyhow!(
+                    "ffmpeg could not capture {}",
+                    args.input().display()
+                ));
+            }
+            break;
+        }
+        sleep(Duration::from_millis(500)).await;
+    }
+    eprintln!("Live stream ended after {:.0}s", offset);
+    Ok(())
+}
+
+/// Duration of a finished 16kHz mono window. Uses the bytes actually on disk
+/// rather than the header size field, which a live muxer may not patch.
+fn window_seconds(path: &Path) -> Result<f64> {
+    use std::io::Seek;
+    let mut f = std::fs::File::open(path)?;
+    wav::seek_to_data(&mut f)?;
+    let data_start = f.stream_position()?;
+    let len = f.metadata()?.len().saturating_sub(data_start);
+    Ok(len as f64 / (wav::SAMPLE_RATE as f64 * 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_stream_url() {
+        assert!(is_stream_url(Path::new("rtmp://live.example.com/app/key")));
+        assert!(is_stream_url(Path::new(
+            "https://example.com/live/index.m3u8"
+        )));
+        assert!(!is_stream_url(Path::new("/videos/ep01.mp4")));
+    }
+}
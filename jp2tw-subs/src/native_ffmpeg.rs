@@ -0,0 +1,348 @@
+//! In-process media handling via libav (ffmpeg-next), enabled with the
+//! `native-ffmpeg` cargo feature. Mirrors the subprocess helpers in main.rs
+//! (extract, burn-in) without requiring the ffmpeg binary in PATH.
+
+use anyhow::{anyhow, Context, Result};
+use ffmpeg::{codec, encoder, format, frame, media, ChannelLayout, Packet, Rational};
+use ffmpeg_next as ffmpeg;
+use std::path::Path;
+
+use crate::cancel;
+use crate::wav::{WavWriter, SAMPLE_RATE};
+
+pub fn init() -> Result<()> {
+    ffmpeg::init().map_err(|e| anyhow!("Failed to initialize libav: {}", e))?;
+    ffmpeg::log::set_level(ffmpeg::log::Level::Error);
+    Ok(())
+}
+
+/// Total duration of the input container in seconds (0.0 if unknown).
+fn input_duration_secs(ictx: &format::context::Input) -> f64 {
+    let d = ictx.duration();
+    if d <= 0 {
+        0.0
+    } else {
+        d as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE)
+    }
+}
+
+/// Decode the best audio stream and write 16kHz mono PCM WAV.
+/// `on_progress` receives the processed fraction (0.0..=1.0).
+pub fn extract_audio(input: &Path, wav_out: &Path, mut on_progress: impl FnMut(f64)) -> Result<()> {
+    let mut ictx = format::input(&input)
+        .with_context(|| format!("libav could not open {}", input.display()))?;
+    let total = input_duration_secs(&ictx);
+    let stream = ictx
+        .streams()
+        .best(media::Type::Audio)
+        .ok_or_else(|| anyhow!("No audio stream found in {}", input.display()))?;
+    let stream_index = stream.index();
+    let time_base = stream.time_base();
+    let mut decoder = codec::context::Context::from_parameters(stream.parameters())
+        .context("Create audio decoder context")?
+        .decoder()
+        .audio()
+        .context("Open audio decoder")?;
+    if decoder.channel_layout().is_empty() {
+        decoder.set_channel_layout(ChannelLayout::default(i32::from(decoder.channels())));
+    }
+    let mut resampler = decoder
+        .resampler(
+            format::Sample::I16(format::sample::Type::Packed),
+            ChannelLayout::MONO,
+            SAMPLE_RATE,
+        )
+        .context("Create resampler (16kHz mono s16)")?;
+
+    let mut out = WavWriter::create(wav_out)?;
+    let write_frame = |resampled: &frame::Audio, out: &mut WavWriter| -> Result<()> {
+        let bytes = resampled.samples() * 2;
+        if bytes > 0 {
+            out.write_pcm(&resampled.data(0)[..bytes])?;
+        }
+        Ok(())
+    };
+
+    let mut decoded = frame::Audio::empty();
+    for (s, packet) in ictx.packets() {
+        cancel::check()?;
+        if s.index() != stream_index {
+            continue;
+        }
+        decoder
+            .send_packet(&packet)
+            .context("libav audio decode failed")?;
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            let mut resampled = frame::Audio::empty();
+            resampler
+                .run(&decoded, &mut resampled)
+                .context("libav resample failed")?;
+            write_frame(&resampled, &mut out)?;
+            if total > 0.0 {
+                if let Some(pts) = decoded.pts() {
+                    on_progress((f64::from(time_base) * pts as f64 / total).clamp(0.0, 1.0));
+                }
+            }
+        }
+    }
+    decoder
+        .send_eof()
+        .context("libav audio decoder flush failed")?;
+    while decoder.receive_frame(&mut decoded).is_ok() {
+        let mut resampled = frame::Audio::empty();
+        resampler
+            .run(&decoded, &mut resampled)
+            .context("libav resample failed")?;
+        write_frame(&resampled, &mut out)?;
+    }
+    loop {
+        let mut resampled = frame::Audio::new(
+            format::Sample::I16(format::sample::Type::Packed),
+            4096,
+            ChannelLayout::MONO,
+        );
+        let delay = resampler
+            .flush(&mut resampled)
+            .context("libav resampler flush failed")?;
+        write_frame(&resampled, &mut out)?;
+        if delay.is_none() || resampled.samples() == 0 {
+            break;
+        }
+    }
+
+    out.finish()?;
+    on_progress(1.0);
+    Ok(())
+}
+
+/// Re-encode the best video stream through `filter_spec` (the same
+/// `subtitles=...` string the subprocess path uses) and copy audio streams.
+pub fn burn_in(
+    input: &Path,
+    out: &Path,
+    filter_spec: &str,
+    mut on_progress: impl FnMut(f64),
+) -> Result<()> {
+    let mut ictx = format::input(&input)
+        .with_context(|| format!("libav could not open {}", input.display()))?;
+    let mut octx = format::output(&out)
+        .with_context(|| format!("libav could not create {}", out.display()))?;
+    let total = input_duration_secs(&ictx);
+
+    let video_index = ictx
+        .streams()
+        .best(media::Type::Video)
+        .map(|s| s.index())
+        .ok_or_else(|| anyhow!("No video stream found in {}", input.display()))?;
+
+    let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut ist_time_bases = vec![Rational(0, 1); ictx.nb_streams() as usize];
+    let mut video: Option<(codec::decoder::Video, encoder::Video, ffmpeg::filter::Graph)> = None;
+    let mut ost_index = 0usize;
+
+    for ist in ictx.streams() {
+        let medium = ist.parameters().medium();
+        if medium != media::Type::Audio && medium != media::Type::Video {
+            continue;
+        }
+        if medium == media::Type::Video && ist.index() != video_index {
+            continue;
+        }
+        stream_mapping[ist.index()] = Some(ost_index);
+        ist_time_bases[ist.index()] = ist.time_base();
+        if ist.index() == video_index {
+            let decoder = codec::context::Context::from_parameters(ist.parameters())
+                .context("Create video decoder context")?
+                .decoder()
+                .video()
+                .context("Open video decoder")?;
+            let h264 = encoder::find(codec::Id::H264)
+                .ok_or_else(|| anyhow!("libav was built without an H.264 encoder"))?;
+            let mut ost = octx.add_stream(h264).context("Add output video stream")?;
+            let mut enc = codec::context::Context::new_with_codec(h264)
+                .encoder()
+                .video()
+                .context("Create H.264 encoder")?;
+            enc.set_width(decoder.width());
+            enc.set_height(decoder.height());
+            enc.set_aspect_ratio(decoder.aspect_ratio());
+            enc.set_format(decoder.format());
+            enc.set_frame_rate(decoder.frame_rate());
+            enc.set_time_base(ist.time_base());
+            if global_header {
+                enc.set_flags(codec::Flags::GLOBAL_HEADER);
+            }
+            let enc = enc.open_as(h264).context("Open H.264 encoder")?;
+            ost.set_parameters(&enc);
+            let graph = subtitles_graph(&decoder, ist.time_base(), filter_spec)?;
+            video = Some((decoder, enc, graph));
+        } else {
+            let mut ost = octx
+                .add_stream(encoder::find(codec::Id::None))
+                .context("Add output audio stream")?;
+            ost.set_parameters(ist.parameters());
+            // Let the muxer pick a codec tag compatible with the container
+            unsafe {
+                (*ost.parameters().as_mut_ptr()).codec_tag = 0;
+            }
+        }
+        ost_index += 1;
+    }
+    let (mut decoder, mut enc, mut graph) =
+        video.ok_or_else(|| anyhow!("Failed to set up video transcoder"))?;
+    let video_ost = stream_mapping[video_index].unwrap_or(0);
+
+    octx.set_metadata(ictx.metadata().to_owned());
+    octx.write_header().context("Write output header")?;
+    let ost_time_bases: Vec<Rational> = (0..octx.nb_streams() as usize)
+        .map(|i| {
+            octx.stream(i)
+                .map(|s| s.time_base())
+                .unwrap_or(Rational(1, 1))
+        })
+        .collect();
+    let video_tb = ist_time_bases[video_index];
+
+    let drain = |graph: &mut ffmpeg::filter::Graph,
+                 enc: &mut encoder::Video,
+                 octx: &mut format::context::Output|
+     -> Result<()> {
+        let mut filtered = frame::Video::empty();
+        while graph
+            .get("out")
+            .expect("buffersink")
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            enc.send_frame(&filtered).context("libav encode failed")?;
+            write_encoded(enc, octx, video_ost, video_tb, ost_time_bases[video_ost])?;
+        }
+        Ok(())
+    };
+
+    let mut decoded = frame::Video::empty();
+    for (ist, mut packet) in ictx.packets() {
+        cancel::check()?;
+        let Some(ost) = stream_mapping[ist.index()] else {
+            continue;
+        };
+        if ist.index() == video_index {
+            decoder
+                .send_packet(&packet)
+                .context("libav video decode failed")?;
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                let ts = decoded.timestamp();
+                decoded.set_pts(ts);
+                graph
+                    .get("in")
+                    .expect("buffer source")
+                    .source()
+                    .add(&decoded)
+                    .context("libav filter (subtitles) failed")?;
+                drain(&mut graph, &mut enc, &mut octx)?;
+                if total > 0.0 {
+                    if let Some(ts) = ts {
+                        on_progress((f64::from(video_tb) * ts as f64 / total).clamp(0.0, 1.0));
+                    }
+                }
+            }
+        } else {
+            packet.rescale_ts(ist_time_bases[ist.index()], ost_time_bases[ost]);
+            packet.set_position(-1);
+            packet.set_stream(ost);
+            packet
+                .write_interleaved(&mut octx)
+                .context("libav mux (audio copy) failed")?;
+        }
+    }
+
+    decoder
+        .send_eof()
+        .context("libav video decoder flush failed")?;
+    while decoder.receive_frame(&mut decoded).is_ok() {
+        let ts = decoded.timestamp();
+        decoded.set_pts(ts);
+        graph
+            .get("in")
+            .expect("buffer source")
+            .source()
+            .add(&decoded)
+            .context("libav filter (subtitles) failed")?;
+        drain(&mut graph, &mut enc, &mut octx)?;
+    }
+    graph
+        .get("in")
+        .expect("buffer source")
+        .source()
+        .flush()
+        .context("libav filter flush failed")?;
+    drain(&mut graph, &mut enc, &mut octx)?;
+    enc.send_eof().context("libav encoder flush failed")?;
+    write_encoded(
+        &mut enc,
+        &mut octx,
+        video_ost,
+        video_tb,
+        ost_time_bases[video_ost],
+    )?;
+
+    octx.write_trailer().context("Write output trailer")?;
+    on_progress(1.0);
+    Ok(())
+}
+
+fn subtitles_graph(
+    decoder: &codec::decoder::Video,
+    time_base: Rational,
+    filter_spec: &str,
+) -> Result<ffmpeg::filter::Graph> {
+    let mut graph = ffmpeg::filter::Graph::new();
+    let pix_fmt: ffmpeg::ffi::AVPixelFormat = decoder.format().into();
+    let aspect = decoder.aspect_ratio();
+    let args = format!(
+        "video_size={}x{}:pix_fmt={}:time_base={}:pixel_aspect={}/{}",
+        decoder.width(),
+        decoder.height(),
+        pix_fmt as i32,
+        time_base,
+        aspect.numerator().max(1),
+        aspect.denominator().max(1),
+    );
+    let buffer =
+        ffmpeg::filter::find("buffer").ok_or_else(|| anyhow!("libav lacks buffer filter"))?;
+    let sink = ffmpeg::filter::find("buffersink")
+        .ok_or_else(|| anyhow!("libav lacks buffersink filter"))?;
+    graph.add(&buffer, "in", &args)?;
+    graph.add(&sink, "out", "")?;
+    graph
+        .get("out")
+        .expect("buffersink")
+        .set_pixel_format(decoder.format());
+    graph
+        .output("in", 0)?
+        .input("out", 0)?
+        .parse(filter_spec)
+        .with_context(|| format!("Invalid filter (is libass enabled?): {}", filter_spec))?;
+    graph.validate().context("Validate filter graph")?;
+    Ok(graph)
+}
+
+fn write_encoded(
+    enc: &mut encoder::Video,
+    octx: &mut format::context::Output,
+    ost: usize,
+    in_tb: Rational,
+    out_tb: Rational,
+) -> Result<()> {
+    let mut encoded = Packet::empty();
+    while enc.receive_packet(&mut encoded).is_ok() {
+        encoded.set_stream(ost);
+        encoded.rescale_ts(in_tb, out_tb);
+        encoded
+            .write_interleaved(octx)
+            .context("libav mux (video) failed")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,258 @@
+//! Timeline exports for editors (`--format fcpxml`, `--format premiere-xml`),
+//! so a translation lands on the edit timeline instead of being re-imported
+//! from SRT and restyled:
+//!
+//! - `<name>.fcpxml`: a Final Cut Pro caption roll (iTT captions on a gap,
+//!   one per cue), also read by DaVinci Resolve
+//! - `<name>.markers.xml`: a Final Cut Pro 7 XML sequence whose markers
+//!   span each cue, named with the translation and commented with the
+//!   Japanese; Premiere Pro and Resolve import it as sequence markers
+//!
+//! Times are snapped to frames of the input's frame rate.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+
+use crate::formats::xml_escape;
+use crate::WhisperSegment;
+
+/// The input video's frame size and rate (`num/den` frames per second).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Video {
+    pub width: u32,
+    pub height: u32,
+    pub num: u32,
+    pub den: u32,
+}
+
+impl Default for Video {
+    /// 1080p at 29.97, when the input cannot be probed
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            num: 30000,
+            den: 1001,
+        }
+    }
+}
+
+impl Video {
+    pub fn probe(input: &Path) -> Option<Self> {
+        let out = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height,r_frame_rate",
+                "-of",
+                "json",
+            ])
+            .arg(input)
+            .output()
+            .ok()?;
+        Self::from_ffprobe(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    fn from_ffprobe(json: &str) -> Option<Self> {
+        let v: serde_json::Value = serde_json::from_str(json).ok()?;
+        let stream = v["streams"].get(0)?;
+        let (num, den) = stream["r_frame_rate"].as_str()?.split_once('/')?;
+        let (num, den) = (num.parse().ok()?, den.parse().ok()?);
+        if num == 0 || den == 0 {
+            return None;
+        }
+        Some(Self {
+            width: stream["width"].as_u64()? as u32,
+            height: stream["height"].as_u64()? as u32,
+            num,
+            den,
+        })
+    }
+
+    /// The frame `seconds` falls on.
+    fn frame(&self, seconds: f64) -> u64 {
+        (seconds.max(0.0) * f64::from(self.num) / f64::from(self.den)).round() as u64
+    }
+
+    /// `frames` as an FCPXML rational time.
+    fn time(&self, frames: u64) -> String {
+        if frames == 0 {
+            return "0s".into();
+        }
+        format!("{}/{}s", frames * u64::from(self.den), self.num)
+    }
+
+    /// FCP 7 XML `<rate>`: whole frames per second, NTSC for the /1001 rates.
+    fn rate(&self) -> String {
+        let timebase = (f64::from(self.num) / f64::from(self.den)).round();
+        let ntsc = if self.den == 1001 { "TRUE" } else { "FALSE" };
+        format!("<rate><timebase>{timebase}</timebase><ntsc>{ntsc}</ntsc></rate>")
+    }
+}
+
+/// Each cue's first and end frame; every cue lasts at least one frame.
+fn frames(video: &Video, segments: &[WhisperSegment]) -> Vec<(u64, u64)> {
+    segments
+        .iter()
+        .map(|s| {
+            let start = video.frame(s.start);
+            (start, video.frame(s.end).max(start + 1))
+        })
+        .collect()
+}
+
+pub fn write_fcpxml(
+    path: &Path,
+    title: &str,
+    video: &Video,
+    segments: &[WhisperSegment],
+    lines: &[String],
+) -> Result<()> {
+    std::fs::write(path, fcpxml(title, video, segments, lines))
+        .with_context(|| format!("Write FCPXML at {}", path.display()))
+}
+
+fn fcpxml(title: &str, video: &Video, segments: &[WhisperSegment], lines: &[String]) -> String {
+    let frames = frames(video, segments);
+    let total = frames.iter().map(|&(_, end)| end).max().unwrap_or(1);
+    let title = xml_escape(title);
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE fcpxml>
+<fcpxml version="1.9">
+  <resources>
+    <format id="r1" frameDuration="{frame}" width="{width}" height="{height}"/>
+  </resources>
+  <library>
+    <event name="{title}">
+      <project name="{title}">
+        <sequence format="r1" duration="{duration}" tcStart="0s" tcFormat="NDF">
+          <spine>
+            <gap name="Gap" offset="0s" start="0s" duration="{duration}">
+"#,
+        frame = video.time(1),
+        width = video.width,
+        height = video.height,
+        duration = video.time(total),
+    );
+    for (i, (&(start, end), line)) in frames.iter().zip(lines).enumerate() {
+        let text = xml_escape(line.trim()).replace('\n', "&#10;");
+        let _ = write!(
+            out,
+            r#"              <caption lane="1" offset="{offset}" start="0s" duration="{duration}" name="{name}" role="iTT?captionFormat=ITT.zh-Hant">
+                <text placement="bottom"><text-style ref="ts{n}">{text}</text-style></text>
+                <text-style-def id="ts{n}"><text-style font=".AppleSystemUIFont" fontSize="13" fontFace="Regular" fontColor="1 1 1 1" backgroundColor="0 0 0 1"/></text-style-def>
+              </caption>
+"#,
+            offset = video.time(start),
+            duration = video.time(end - start),
+            name = text.replace("&#10;", " "),
+            n = i + 1,
+        );
+    }
+    out.push_str(
+        "            </gap>\n          </spine>\n        </sequence>\n      </project>\n    </event>\n  </library>\n</fcpxml>\n",
+    );
+    out
+}
+
+pub fn write_markers(
+    path: &Path,
+    title: &str,
+    video: &Video,
+    segments: &[WhisperSegment],
+    ja_lines: &[String],
+    zh_lines: &[String],
+) -> Result<()> {
+    std::fs::write(path, markers(title, video, segments, ja_lines, zh_lines))
+        .with_context(|| format!("Write markers at {}", path.display()))
+}
+
+fn markers(
+    title: &str,
+    video: &Video,
+    segments: &[WhisperSegment],
+    ja_lines: &[String],
+    zh_lines: &[String],
+) -> String {
+    let frames = frames(video, segments);
+    let total = frames.iter().map(|&(_, end)| end).max().unwrap_or(1);
+    let rate = video.rate();
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE xmeml>
+<xmeml version="4">
+  <sequence>
+    <name>{title}</name>
+    <duration>{total}</duration>
+    {rate}
+    <timecode>{rate}<string>00:00:00:00</string><frame>0</frame><displayformat>NDF</displayformat></timecode>
+    <media>
+      <video><format><samplecharacteristics>{rate}<width>{width}</width><height>{height}</height></samplecharacteristics></format><track/></video>
+      <audio><track/></audio>
+    </media>
+"#,
+        title = xml_escape(title),
+        width = video.width,
+        height = video.height,
+    );
+    for ((&(start, end), ja), zh) in frames.iter().zip(ja_lines).zip(zh_lines) {
+        let _ = writeln!(
+            out,
+            "    <marker><name>{}</name><comment>{}</comment><in>{start}</in><out>{end}</out></marker>",
+            xml_escape(&zh.trim().replace('\n', " ")),
+            xml_escape(&ja.trim().replace('\n', " ")),
+        );
+    }
+    out.push_str("  </sequence>\n</xmeml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_nle_exports() {
+        let video = Video::from_ffprobe(
+            r#"{"streams":[{"width":1280,"height":720,"r_frame_rate":"24000/1001"}]}"#,
+        )
+        .unwrap();
+        assert_eq!((video.width, video.num, video.den), (1280, 24000, 1001));
+        let segs = [seg(1.0, 2.5, "")];
+        let xml = fcpxml("ep1", &video, &segs, &["你好\n<こんにちは>".to_string()]);
+        // 1 s is frame 24 at 23.976
+        assert!(xml.contains(r#"offset="24024/24000s""#), "{xml}");
+        assert!(xml.contains("你好&#10;&lt;こんにちは&gt;"));
+        let parse = |xml: &str| {
+            let opts = roxmltree::ParsingOptions {
+                allow_dtd: true,
+                ..Default::default()
+            };
+            roxmltree::Document::parse_with_options(xml, opts).map(|_| ())
+        };
+        parse(&xml).unwrap();
+        let xml = markers(
+            "ep1",
+            &video,
+            &segs,
+            &["こんにちは".to_string()],
+            &["你好".to_string()],
+        );
+        assert!(xml.contains("<timebase>24</timebase><ntsc>TRUE</ntsc>"));
+        assert!(xml.contains(
+            "<marker><name>你好</name><comment>こんにちは</comment><in>24</in><out>60</out></marker>"
+        ));
+        parse(&xml).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+//! Run-end notifications: a webhook (`--notify-url`) that receives the run
+//! summary as plain JSON or as a Slack/Discord-compatible message, and a
+//! native desktop notification (`--desktop-notify`) for long runs left in
+//! the background.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NotifyFormat {
+    /// The summary object as-is
+    Json,
+    /// `{"text": ...}` for Slack incoming webhooks
+    Slack,
+    /// `{"content": ...}` for Discord webhooks
+    Discord,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub input: String,
+    /// ok, failed, or cancelled
+    pub status: String,
+    pub outputs: Vec<String>,
+    pub duration_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Summary {
+    fn message(&self) -> String {
+        let icon = match self.status.as_str() {
+            "ok" => "✅",
+            "cancelled" => "⏹",
+            _ => "❌",
+        };
+        let mut msg = format!(
+            "{icon} jp2tw-subs {}: {} ({:.0}s",
+            self.status, self.input, self.duration_secs
+        );
+        if let Some(cost) = self.cost_usd {
+            msg.push_str(&format!(", ~${cost:.2}"));
+        }
+        msg.push(')');
+        for out in &self.outputs {
+            msg.push_str(&format!("\n• {out}"));
+        }
+        if let Some(err) = &self.error {
+            msg.push_str(&format!("\nError: {err}"));
+        }
+        msg
+    }
+}
+
+/// Show a desktop notification with the status, first output, and elapsed
+/// time, using the platform's own notifier (notify-send, osascript, or a
+/// PowerShell toast).
+pub fn desktop(summary: &Summary) -> Result<()> {
+    let title = format!("jp2tw-subs: {}", summary.status);
+    let mut body = match summary.outputs.first() {
+        Some(out) => out.clone(),
+        None => summary.input.clone(),
+    };
+    body.push_str(&format!(" ({:.0}s)", summary.duration_secs));
+    if let Some(err) = &summary.error {
+        body.push_str(&format!("\n{err}"));
+    }
+    let status = desktop_command(&title, &body)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Run desktop notifier")?;
+    if !status.success() {
+        return Err(anyhow!("desktop notifier exited with {status}"));
+    }
+    Ok(())
+}
+
+fn desktop_command(title: &str, body: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_quote(body),
+            applescript_quote(title)
+        ));
+        cmd
+    } else if cfg!(windows) {
+        let q = |s: &str| format!("'{}'", s.replace('\'', "''"));
+        let script = format!(
+            "$t=[Windows.UI.Notifications.ToastNotificationManager,Windows.UI.Notifications,ContentType=WindowsRuntime]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02);\
+             $x=$t.GetElementsByTagName('text');\
+             $x.Item(0).AppendChild($t.CreateTextNode({}))>$null;\
+             $x.Item(1).AppendChild($t.CreateTextNode({}))>$null;\
+             [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('jp2tw-subs').Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+            q(title),
+            q(body)
+        );
+        let mut cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-Command", &script]);
+        cmd
+    } else {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=jp2tw-subs", title, body]);
+        cmd
+    }
+}
+
+fn applescript_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn payload(summary: &Summary, format: NotifyFormat) -> Value {
+    match format {
+        NotifyFormat::Json => json!(summary),
+        NotifyFormat::Slack => json!({"text": summary.message()}),
+        // Discord rejects content over 2000 characters
+        NotifyFormat::Discord => {
+            json!({"content": summary.message().chars().take(2000).collect::<String>()})
+        }
+    }
+}
+
+pub async fn send(url: &str, format: NotifyFormat, summary: &Summary) -> Result<()> {
+    let resp = crate::http::client()
+        .post(url)
+        .timeout(Duration::from_secs(30))
+        .json(&payload(summary, format))
+        .send()
+        .await
+        .with_context(|| format!("POST {url}"))?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("webhook returned {}", resp.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> Summary {
+        Summary {
+            input: "ep01.mp4".into(),
+            status: "failed".into(),
+            outputs: vec!["ep01.zh-TW.srt".into()],
+            duration_secs: 61.2,
+            cost_usd: Some(0.1234),
+            error: Some("quota exhausted".into()),
+        }
+    }
+
+    #[test]
+    fn test_applescript_quote() {
+        assert_eq!(applescript_quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+
+    #[test]
+    fn test_payload_formats() {
+        let s = summary();
+        let plain = payload(&s, NotifyFormat::Json);
+        assert_eq!(plain["status"], "failed");
+        assert_eq!(plain["outputs"][0], "ep01.zh-TW.srt");
+        let slack = payload(&s, NotifyFormat::Slack);
+        let text = slack["text"].as_str().unwrap();
+        assert!(text.starts_with("❌ jp2tw-subs failed: ep01.mp4 (61s, ~$0.12)"));
+        assert!(text.contains("• ep01.zh-TW.srt") && text.contains("quota exhausted"));
+        assert_eq!(payload(&s, NotifyFormat::Discord)["content"], text);
+    }
+}
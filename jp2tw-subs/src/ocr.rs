@@ -0,0 +1,272 @@
+//! Hardcoded-subtitle OCR: sample frames from the caption band with ffmpeg,
+//! OCR them with tesseract (jpn), and merge runs of matching text into timed
+//! Japanese cues that feed the translation stage like a transcript. The same
+//! sampling, on a few frames, tells `--avoid-hardsubs` whether the bottom of
+//! the picture is already taken by burned-in subtitles.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{cancel, text_similarity, WhisperSegment};
+
+/// Minimum similarity for two consecutive samples to count as the same cue.
+const SAME_CUE_SIMILARITY: f64 = 0.7;
+
+/// Frames checked for burned-in subtitles, spread over the video.
+pub const HARDSUB_SAMPLES: usize = 12;
+
+/// Share of sampled frames with text in the band for it to count as
+/// hardsubbed; captions are off screen between lines, so this is low.
+const HARDSUB_HIT_RATE: f64 = 0.25;
+
+pub struct OcrOptions {
+    /// Frames sampled per second
+    pub fps: f64,
+    /// Bottom fraction of the frame that holds the captions (0 < r <= 1)
+    pub region: f64,
+}
+
+pub fn transcribe_hardsubs(
+    input: &Path,
+    work_dir: &Path,
+    opts: &OcrOptions,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<Vec<WhisperSegment>> {
+    ensure_tesseract_jpn()?;
+    let frames_dir = work_dir.join("ocr_frames");
+    std::fs::create_dir_all(&frames_dir)?;
+    let status = cancel::status(Command::new("ffmpeg").args(sample_args(input, &frames_dir, opts)))
+        .context("ffmpeg frame sampling for OCR failed")?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg could not sample frames for OCR"));
+    }
+
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(&frames_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("png"))
+        .collect();
+    frames.sort();
+
+    let mut samples = Vec::with_capacity(frames.len());
+    for (i, frame) in frames.iter().enumerate() {
+        on_progress(i + 1, frames.len());
+        let text = normalize(&ocr_frame(frame)?);
+        samples.push((i as f64 / opts.fps, text));
+    }
+    let _ = std::fs::remove_dir_all(&frames_dir);
+    Ok(build_cues(&samples, 1.0 / opts.fps))
+}
+
+/// ffmpeg arguments that sample grayscale caption-region frames into `frames_dir`.
+pub fn sample_args(input: &Path, frames_dir: &Path, opts: &OcrOptions) -> Vec<String> {
+    let region = opts.region.clamp(0.05, 1.0);
+    let filter = format!(
+        "fps={},crop=iw:ih*{r}:0:ih*(1-{r}),format=gray",
+        opts.fps,
+        r = region
+    );
+    let mut args: Vec<String> = ["-nostdin", "-y", "-loglevel", "error", "-i"]
+        .map(String::from)
+        .into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-vf".to_string(), filter]);
+    args.push(
+        frames_dir
+            .join("ocr_%06d.png")
+            .to_string_lossy()
+            .into_owned(),
+    );
+    args
+}
+
+/// Whether the bottom `region` of the picture carries burned-in subtitles:
+/// OCR hits on at least a quarter of `HARDSUB_SAMPLES` frames.
+pub fn hardsubs_at_bottom(
+    input: &Path,
+    work_dir: &Path,
+    duration: f64,
+    region: f64,
+) -> Result<bool> {
+    ensure_tesseract_jpn()?;
+    let frame = work_dir.join("hardsub_probe.png");
+    let mut hits = 0;
+    for at in probe_times(duration) {
+        cancel::check()?;
+        let status =
+            cancel::status(Command::new("ffmpeg").args(band_frame_args(input, at, region, &frame)))
+                .context("ffmpeg frame sampling for --avoid-hardsubs failed")?;
+        if !status.success() || !frame.exists() {
+            continue;
+        }
+        if !normalize(&ocr_frame(&frame)?).is_empty() {
+            hits += 1;
+        }
+        let _ = std::fs::remove_file(&frame);
+    }
+    Ok(hits as f64 >= HARDSUB_SAMPLES as f64 * HARDSUB_HIT_RATE)
+}
+
+/// Midpoints of `HARDSUB_SAMPLES` equal slices of the video.
+fn probe_times(duration: f64) -> Vec<f64> {
+    (0..HARDSUB_SAMPLES)
+        .map(|i| duration * (i as f64 + 0.5) / HARDSUB_SAMPLES as f64)
+        .collect()
+}
+
+/// ffmpeg arguments that write the grayscale caption band of the frame at
+/// `at` to `out`.
+pub fn band_frame_args(input: &Path, at: f64, region: f64, out: &Path) -> Vec<String> {
+    let region = region.clamp(0.05, 1.0);
+    let mut args: Vec<String> = ["-nostdin", "-y", "-loglevel", "error", "-ss"]
+        .map(String::from)
+        .into();
+    args.push(format!("{at:.3}"));
+    args.push("-i".to_string());
+    args.push(input.to_string_lossy().into_owned());
+    args.extend([
+        "-vf".to_string(),
+        format!("crop=iw:ih*{region}:0:ih*(1-{region}),format=gray"),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ]);
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+fn ensure_tesseract_jpn() -> Result<()> {
+    let out = Command::new("tesseract")
+        .arg("--list-langs")
+        .output()
+        .context("tesseract is required for --ocr-subs (brew/apt install tesseract)")?;
+    let langs = String::from_utf8_lossy(&out.stdout);
+    if !langs.lines().any(|l| l.trim() == "jpn") {
+        return Err(anyhow!(
+            "tesseract has no Japanese model; install tesseract-ocr-jpn (apt) or tesseract-lang (brew)"
+        ));
+    }
+    Ok(())
+}
+
+fn ocr_frame(frame: &Path) -> Result<String> {
+    let out = Command::new("tesseract")
+        .args([frame.to_str().unwrap(), "stdout", "-l", "jpn", "--psm", "6"])
+        .output()
+        .context("Failed to run tesseract")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "tesseract failed on {}: {}",
+            frame.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Drop whitespace (tesseract spaces out CJK) and lines without Japanese,
+/// which are almost always noise from the picture behind the captions.
+fn normalize(raw: &str) -> String {
+    raw.lines()
+        .map(|l| l.chars().filter(|c| !c.is_whitespace()).collect::<String>())
+        .filter(|l| l.chars().filter(|&c| is_japanese(c)).count() >= 2)
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn is_japanese(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // hiragana + katakana
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xFF66..=0xFF9F) // half-width katakana
+}
+
+/// Merge consecutive samples with similar text into cues; the most frequent
+/// reading in a run wins, since OCR noise varies from frame to frame.
+fn build_cues(samples: &[(f64, String)], frame_dur: f64) -> Vec<WhisperSegment> {
+    let mut cues: Vec<WhisperSegment> = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut run_start = 0.0;
+    let mut run_end = 0.0;
+
+    let mut flush = |run: &mut Vec<&str>, start: f64, end: f64| {
+        if run.is_empty() {
+            return;
+        }
+        let mut best = run[0];
+        let mut best_count = 0;
+        for cand in run.iter() {
+            let count = run.iter().filter(|t| *t == cand).count();
+            if count > best_count || (count == best_count && cand.len() > best.len()) {
+                best = cand;
+                best_count = count;
+            }
+        }
+        cues.push(WhisperSegment {
+            id: Some(cues.len() as u32),
+            start,
+            end,
+            text: best.to_string(),
+            ..Default::default()
+        });
+        run.clear();
+    };
+
+    for (t, text) in samples {
+        if text.is_empty() {
+            flush(&mut run, run_start, run_end);
+            continue;
+        }
+        let same = run
+            .last()
+            .map(|prev| text_similarity(prev, text) >= SAME_CUE_SIMILARITY)
+            .unwrap_or(false);
+        if !same {
+            flush(&mut run, run_start, run_end);
+            run_start = *t;
+        }
+        run.push(text);
+        run_end = t + frame_dur;
+    }
+    flush(&mut run, run_start, run_end);
+    cues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_drops_noise() {
+        assert_eq!(normalize("今 日 は\n|| ~ -\n"), "今日は");
+        assert_eq!(normalize("abc 12\n"), "");
+    }
+
+    #[test]
+    fn test_band_frame_args() {
+        let args = band_frame_args(Path::new("in.mp4"), 42.0, 0.25, Path::new("f.png"));
+        assert_eq!(&args[4..7], ["-ss", "42.000", "-i"]);
+        assert!(args.contains(&"crop=iw:ih*0.25:0:ih*(1-0.25),format=gray".to_string()));
+        let times = probe_times(120.0);
+        assert_eq!(times.len(), HARDSUB_SAMPLES);
+        assert_eq!((times[0], times[11]), (5.0, 115.0));
+    }
+
+    #[test]
+    fn test_build_cues_merges_noisy_runs() {
+        let s = |t: f64, x: &str| (t, x.to_string());
+        let samples = vec![
+            s(0.0, ""),
+            s(0.5, "今日はいい天気"),
+            s(1.0, "今日はいい天気"),
+            s(1.5, "今日はいぃ天気"),
+            s(2.0, ""),
+            s(2.5, "ありがとう"),
+        ];
+        let cues = build_cues(&samples, 0.5);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text, "今日はいい天気");
+        assert_eq!((cues[0].start, cues[0].end), (0.5, 2.0));
+        assert_eq!((cues[1].start, cues[1].end), (2.5, 3.0));
+    }
+}
@@ -0,0 +1,948 @@
+//! Typed OpenAI client: request/response structs for the two endpoints the
+//! tool uses (audio transcriptions, chat completions), an error enum that
+//! tells auth, quota, rate-limit, server, and context-length failures apart,
+//! and one retry policy shared by every call. With `--save-api-traces`
+//! every attempt's request and raw response is written as JSON for bug
+//! reports. Billable usage of successful calls is tallied per model for the
+//! cost ledger. Chat requests can also go through the Batch API (files and
+//! batches endpoints) at half price.
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::time::sleep;
+
+use crate::backend::{TranscriptionBackend, TranslationBackend};
+use crate::cassette::{self, Cassette};
+use crate::{cancel, http, ratelimit, telemetry, wav, WhisperSegment};
+
+const BASE_URL: &str = "https://api.openai.com/v1";
+const MAX_ATTEMPTS: u32 = 5;
+/// Appended to the model name of usage billed at Batch API prices.
+pub const BATCH_SUFFIX: &str = " (batch)";
+
+/// Billable usage of one model during this run.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub requests: u64,
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub audio_seconds: f64,
+    /// Text sent to speech synthesis
+    #[serde(default)]
+    pub characters: u64,
+}
+
+tokio::task_local! {
+    /// Usage of the run being metered; task-local so concurrent batch jobs
+    /// are costed apart.
+    static USAGE: RefCell<BTreeMap<String, ModelUsage>>;
+}
+
+/// Run `f`, returning its output with the usage of every model it called.
+pub async fn metered<F: Future>(f: F) -> (F::Output, BTreeMap<String, ModelUsage>) {
+    USAGE
+        .scope(RefCell::default(), async {
+            let out = f.await;
+            (out, USAGE.with(RefCell::take))
+        })
+        .await
+}
+
+fn record_usage(model: &str, update: impl FnOnce(&mut ModelUsage)) {
+    let _ = USAGE.try_with(|all| {
+        let mut all = all.borrow_mut();
+        let u = all.entry(model.to_string()).or_default();
+        u.requests += 1;
+        update(u);
+    });
+}
+
+/// Record usage of one request answered through the Batch API.
+pub fn record_batch_usage(model: &str, prompt_tokens: u64, completion_tokens: u64) {
+    record_usage(&format!("{model}{BATCH_SUFFIX}"), |u| {
+        u.prompt_tokens += prompt_tokens;
+        u.completion_tokens += completion_tokens;
+    });
+}
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("OpenAI authentication failed ({status}): {message}")]
+    Auth { status: u16, message: String },
+    #[error("OpenAI quota exhausted: {message}")]
+    Quota { message: String },
+    #[error("OpenAI rate limit reached: {message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("OpenAI server error {status}: {message}")]
+    Server { status: u16, message: String },
+    #[error("Request exceeds the model's context length: {message}")]
+    ContextLength { message: String },
+    #[error("Upload too large for the API: {message}")]
+    TooLarge { message: String },
+    #[error("OpenAI rejected the request ({status}): {message}")]
+    BadRequest { status: u16, message: String },
+    #[error("OpenAI request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("Unexpected OpenAI response: {0}")]
+    Decode(String),
+    #[error("Transcription backend failed: {0}")]
+    Backend(String),
+    #[error("Could not read the file to upload: {0}")]
+    Upload(#[from] std::io::Error),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("The --replay cassette has no {endpoint} response for this request; the input or options changed since it was recorded")]
+    NotRecorded { endpoint: String },
+}
+
+impl ApiError {
+    /// Transient failures worth retrying with backoff.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } | ApiError::Server { .. } => true,
+            ApiError::Transport(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Failures that will not go away by moving on to the next chunk, so the
+    /// whole run should stop.
+    pub fn is_fatal(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(
+                ApiError::Auth { .. }
+                    | ApiError::Quota { .. }
+                    | ApiError::Cancelled
+                    | ApiError::NotRecorded { .. }
+            )
+        ) || cancel::is_cancelled()
+    }
+
+    /// Classify a non-success response from its status and error body
+    /// (`{"error": {"message", "type", "code"}}`).
+    pub fn from_response(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        #[derive(Deserialize, Default)]
+        struct Body {
+            #[serde(default)]
+            error: Detail,
+        }
+        #[derive(Deserialize, Default)]
+        struct Detail {
+            message: Option<String>,
+            code: Option<String>,
+            #[serde(rename = "type")]
+            kind: Option<String>,
+        }
+        let detail = serde_json::from_str::<Body>(body).unwrap_or_default().error;
+        let message = detail.message.unwrap_or_else(|| body.trim().to_string());
+        let code = detail.code.or(detail.kind).unwrap_or_default();
+        match status.as_u16() {
+            401 | 403 => ApiError::Auth {
+                status: status.as_u16(),
+                message,
+            },
+            429 if code == "insufficient_quota" => ApiError::Quota { message },
+            429 => ApiError::RateLimited {
+                message,
+                retry_after: headers
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<f64>().ok())
+                    .map(Duration::from_secs_f64),
+            },
+            413 => ApiError::TooLarge { message },
+            s if s >= 500 => ApiError::Server { status: s, message },
+            _ if code == "context_length_exceeded" => ApiError::ContextLength { message },
+            s => ApiError::BadRequest { status: s, message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChatMessage {
+    pub role: &'static str,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// Decoding parameters for chat requests; unset fields keep the API defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sampling {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl ChatRequest {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            messages: Vec::new(),
+            response_format: None,
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+        }
+    }
+
+    pub fn system(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(ChatMessage {
+            role: "system",
+            content: content.into(),
+        });
+        self
+    }
+
+    pub fn user(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(ChatMessage {
+            role: "user",
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Ask for a JSON object reply (supported by newer models).
+    pub fn json_object(mut self) -> Self {
+        self.response_format = Some(ResponseFormat {
+            kind: "json_object",
+            json_schema: None,
+        });
+        self
+    }
+
+    /// Ask for a reply that matches `schema` exactly (structured outputs).
+    pub fn json_schema(mut self, name: &str, schema: serde_json::Value) -> Self {
+        self.response_format = Some(ResponseFormat {
+            kind: "json_schema",
+            json_schema: Some(json!({ "name": name, "strict": true, "schema": schema })),
+        });
+        self
+    }
+
+    pub fn temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.temperature = sampling.temperature;
+        self.top_p = sampling.top_p;
+        self.max_tokens = sampling.max_tokens;
+        self
+    }
+
+    /// Budget estimate: the prompt plus a reply about as long as the user input.
+    fn estimated_tokens(&self) -> u64 {
+        self.messages
+            .iter()
+            .map(|m| {
+                let n = ratelimit::estimate_tokens(&m.content);
+                if m.role == "user" {
+                    n * 2
+                } else {
+                    n
+                }
+            })
+            .sum()
+    }
+}
+
+/// Whether `model` accepts `json_schema` response formats. Unknown names
+/// (fine-tunes excepted) and OpenAI-compatible servers get `json_object`.
+pub fn supports_json_schema(model: &str) -> bool {
+    let model = model.strip_prefix("ft:").unwrap_or(model);
+    if model == "gpt-4o-2024-05-13"
+        || model.starts_with("o1-mini")
+        || model.starts_with("o1-preview")
+    {
+        return false;
+    }
+    [
+        "gpt-4o",
+        "chatgpt-4o",
+        "gpt-4.1",
+        "gpt-4.5",
+        "gpt-5",
+        "o1",
+        "o3",
+        "o4",
+    ]
+    .iter()
+    .any(|p| model.starts_with(p))
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatReply,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatReply {
+    content: Option<String>,
+}
+
+/// Whisper `verbose_json` transcription request for one audio file. The
+/// file is streamed from disk on each attempt, so memory stays flat however
+/// large the chunks and however many uploads run at once.
+pub struct TranscriptionRequest {
+    pub model: String,
+    pub audio: PathBuf,
+    /// None lets Whisper detect the language (and report it)
+    pub language: Option<&'static str>,
+}
+
+impl TranscriptionRequest {
+    fn audio_bytes(&self) -> u64 {
+        std::fs::metadata(&self.audio).map_or(0, |m| m.len())
+    }
+
+    fn file_name(&self) -> String {
+        self.audio
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audio.wav")
+            .to_string()
+    }
+
+    /// Length of the 16kHz mono 16-bit WAV being sent.
+    fn audio_seconds(&self) -> f64 {
+        const WAV_HEADER_BYTES: u64 = 44;
+        self.audio_bytes().saturating_sub(WAV_HEADER_BYTES) as f64 / f64::from(wav::SAMPLE_RATE * 2)
+    }
+
+    /// What a cassette matches the request by: the audio by content.
+    fn cassette_request(&self) -> Result<serde_json::Value, ApiError> {
+        Ok(json!({
+            "model": self.model,
+            "language": self.language,
+            "audio_hash": cassette::file_hash(&self.audio)?,
+        }))
+    }
+
+    /// Trace summary; the audio itself is represented by its size.
+    fn trace(&self) -> serde_json::Value {
+        json!({
+            "model": self.model,
+            "file_name": self.file_name(),
+            "audio_bytes": self.audio_bytes(),
+            "language": self.language,
+            "response_format": "verbose_json",
+        })
+    }
+
+    fn form(&self) -> Result<Form, ApiError> {
+        let file = std::fs::File::open(&self.audio)?;
+        let len = file.metadata()?.len();
+        let body = reqwest::Body::from(tokio::fs::File::from_std(file));
+        let part = Part::stream_with_length(body, len)
+            .file_name(self.file_name())
+            .mime_str("audio/wav")?;
+        let form = Form::new()
+            .part("file", part)
+            .text("model", self.model.clone())
+            .text("response_format", "verbose_json")
+            // Ask for segment timestamps if supported
+            .text("timestamp_granularities[]", "segment");
+        Ok(match self.language {
+            Some(language) => form.text("language", language),
+            None => form,
+        })
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranscriptionResponse {
+    pub text: Option<String>,
+    pub segments: Option<Vec<WhisperSegment>>, // Some SDKs omit this unless requested
+    /// Spoken language as Whisper names it ("japanese")
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// A text-to-speech request, answered as raw PCM.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub voice: String,
+    pub input: String,
+    response_format: &'static str,
+    /// Delivery instructions; only the gpt-4o TTS models take them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+}
+
+impl SpeechRequest {
+    pub fn new(model: &str, voice: &str, input: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            voice: voice.to_string(),
+            input: input.to_string(),
+            response_format: "pcm",
+            instructions: model
+                .starts_with("gpt-")
+                .then(|| "Speak Mandarin with a natural Taiwanese accent.".to_string()),
+        }
+    }
+}
+
+/// A Batch API job, as returned by the batches endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    /// validating, in_progress, finalizing, completed, failed, expired, cancelling, cancelled
+    pub status: String,
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub request_counts: Option<BatchCounts>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BatchCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+/// Writes one numbered JSON file per API attempt.
+struct Tracer {
+    dir: PathBuf,
+    seq: AtomicUsize,
+}
+
+impl Tracer {
+    fn write(&self, label: &str, api_key: &str, entry: serde_json::Value) {
+        let n = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let path = self.dir.join(format!("{n:04}-{label}.json"));
+        let text = serde_json::to_string_pretty(&entry).unwrap_or_default();
+        if let Err(e) = std::fs::write(&path, redact(&text, api_key)) {
+            eprintln!("Warning: could not write API trace {}: {e}", path.display());
+        }
+    }
+}
+
+/// Remove the API key from trace text, should a payload or error echo it.
+fn redact(text: &str, api_key: &str) -> String {
+    if api_key.is_empty() {
+        return text.to_string();
+    }
+    text.replace(api_key, "[REDACTED]")
+}
+
+pub struct OpenAiClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    tracer: Option<Tracer>,
+    cassette: Option<Cassette>,
+    /// `--transcription-backend` / `--translation-backend` other than OpenAI
+    transcriber: Option<Arc<dyn TranscriptionBackend>>,
+    translator: Option<Arc<dyn TranslationBackend>>,
+}
+
+impl OpenAiClient {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            http: http::client(),
+            api_key: api_key.into(),
+            base_url: BASE_URL.to_string(),
+            tracer: None,
+            cassette: None,
+            transcriber: None,
+            translator: None,
+        }
+    }
+
+    /// Send requests to an OpenAI-compatible server instead of OpenAI.
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Transcribe with `backend` instead of Whisper.
+    pub fn with_transcriber(mut self, backend: Arc<dyn TranscriptionBackend>) -> Self {
+        self.transcriber = Some(backend);
+        self
+    }
+
+    /// Answer chat requests with `backend` instead of OpenAI.
+    pub fn with_translator(mut self, backend: Arc<dyn TranslationBackend>) -> Self {
+        self.translator = Some(backend);
+        self
+    }
+
+    /// Record responses to, or replay them from, `cassette`.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// The response to `request`: from the cassette when replaying, else
+    /// from `send` (recorded when recording). True when it was replayed.
+    async fn recorded(
+        &self,
+        endpoint: &str,
+        request: Option<serde_json::Value>,
+        send: impl Future<Output = Result<String, ApiError>>,
+    ) -> Result<(String, bool), ApiError> {
+        let (Some(cassette), Some(request)) = (&self.cassette, request) else {
+            return Ok((send.await?, false));
+        };
+        if cassette.replaying() {
+            return cassette
+                .play(endpoint, &request)
+                .map(|body| (body, true))
+                .ok_or_else(|| ApiError::NotRecorded {
+                    endpoint: endpoint.to_string(),
+                });
+        }
+        let body = send.await?;
+        cassette.keep(endpoint, &request, &body);
+        Ok((body, false))
+    }
+
+    /// Save every request payload and raw response under `dir`.
+    pub fn with_traces(mut self, dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        self.tracer = Some(Tracer {
+            dir: dir.to_path_buf(),
+            seq: AtomicUsize::new(0),
+        });
+        Ok(self)
+    }
+
+    /// Run a chat completion and return the first choice's content.
+    pub async fn chat(&self, req: &ChatRequest) -> Result<String, ApiError> {
+        if let Some(backend) = &self.translator {
+            let request = self.cassette.as_ref().map(|_| json!(req));
+            return Ok(self.recorded("chat", request, backend.chat(req)).await?.0);
+        }
+        let url = format!("{}/chat/completions", self.base_url);
+        let trace = self.tracer.as_ref().map(|_| json!(req));
+        let send = self.send_with_retry("chat", &req.model, req.estimated_tokens(), trace, || {
+            Ok(self.http.post(&url).json(req))
+        });
+        let (body, replayed) = self
+            .recorded("chat", self.cassette.as_ref().map(|_| json!(req)), send)
+            .await?;
+        let parsed: ChatResponse = serde_json::from_str(&body)
+            .map_err(|e| ApiError::Decode(format!("chat response: {e}")))?;
+        // Replayed responses cost nothing
+        if !replayed {
+            record_usage(&req.model, |u| {
+                if let Some(usage) = &parsed.usage {
+                    u.prompt_tokens += usage.prompt_tokens;
+                    u.completion_tokens += usage.completion_tokens;
+                }
+            });
+        }
+        let choice = parsed.choices.into_iter().next();
+        // A reply cut off at the token limit is unusable JSON; callers shrink
+        // the request as for a context-length error
+        if choice.as_ref().and_then(|c| c.finish_reason.as_deref()) == Some("length") {
+            return Err(ApiError::ContextLength {
+                message: match req.max_tokens {
+                    Some(max) => format!("the {} reply stopped at --max-tokens {max}", req.model),
+                    None => format!("the {} reply reached its output limit", req.model),
+                },
+            });
+        }
+        choice
+            .and_then(|c| c.message.content)
+            .ok_or_else(|| ApiError::Decode("chat response has no content".into()))
+    }
+
+    pub async fn transcribe(
+        &self,
+        req: &TranscriptionRequest,
+    ) -> Result<TranscriptionResponse, ApiError> {
+        let request = match &self.cassette {
+            Some(_) => Some(req.cassette_request()?),
+            None => None,
+        };
+        if let Some(backend) = &self.transcriber {
+            // Kept as JSON so cassettes hold the same shape for every backend
+            let send = async {
+                let response = backend.transcribe(req).await?;
+                serde_json::to_string(&response).map_err(|e| ApiError::Decode(e.to_string()))
+            };
+            let (body, _) = self.recorded("transcription", request, send).await?;
+            return serde_json::from_str(&body)
+                .map_err(|e| ApiError::Decode(format!("transcription response: {e}")));
+        }
+        let url = format!("{}/audio/transcriptions", self.base_url);
+        let trace = self.tracer.as_ref().map(|_| req.trace());
+        let send = self.send_with_retry("transcription", &req.model, 0, trace, || {
+            Ok(self.http.post(&url).multipart(req.form()?))
+        });
+        let (body, replayed) = self.recorded("transcription", request, send).await?;
+        let parsed = serde_json::from_str(&body)
+            .map_err(|e| ApiError::Decode(format!("Whisper response: {e}")))?;
+        if !replayed {
+            record_usage(&req.model, |u| u.audio_seconds += req.audio_seconds());
+        }
+        Ok(parsed)
+    }
+
+    /// Upload `jsonl` (one chat request per line) and start a Batch API job
+    /// with a 24 h completion window.
+    pub async fn create_batch(&self, jsonl: &[u8]) -> Result<Batch, ApiError> {
+        let url = format!("{}/files", self.base_url);
+        let body = self
+            .send_with_retry("batch_upload", "batch", 0, None, || {
+                let part = Part::bytes(jsonl.to_vec())
+                    .file_name("translations.jsonl")
+                    .mime_str("application/jsonl")?;
+                let form = Form::new().text("purpose", "batch").part("file", part);
+                Ok(self.http.post(&url).multipart(form))
+            })
+            .await?;
+        let file: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| ApiError::Decode(format!("file upload response: {e}")))?;
+        let file_id = file["id"]
+            .as_str()
+            .ok_or_else(|| ApiError::Decode("file upload response has no id".into()))?;
+        let url = format!("{}/batches", self.base_url);
+        let req = json!({
+            "input_file_id": file_id,
+            "endpoint": "/v1/chat/completions",
+            "completion_window": "24h",
+        });
+        let body = self
+            .send_with_retry("batch", "batch", 0, Some(req.clone()), || {
+                Ok(self.http.post(&url).json(&req))
+            })
+            .await?;
+        serde_json::from_str(&body).map_err(|e| ApiError::Decode(format!("batch response: {e}")))
+    }
+
+    pub async fn batch(&self, id: &str) -> Result<Batch, ApiError> {
+        let url = format!("{}/batches/{id}", self.base_url);
+        let body = self
+            .send_with_retry("batch", "batch", 0, None, || Ok(self.http.get(&url)))
+            .await?;
+        serde_json::from_str(&body).map_err(|e| ApiError::Decode(format!("batch response: {e}")))
+    }
+
+    /// Contents of an uploaded or generated file.
+    pub async fn file_content(&self, id: &str) -> Result<String, ApiError> {
+        let url = format!("{}/files/{id}/content", self.base_url);
+        self.send_with_retry("batch_output", "batch", 0, None, || Ok(self.http.get(&url)))
+            .await
+    }
+
+    /// Ids of the models the key can use. One attempt, without retries: a
+    /// cheap check that the server is reachable and the key accepted.
+    pub async fn models(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/models", self.base_url);
+        let resp = self
+            .http
+            .get(&url)
+            .bearer_auth(&self.api_key)
+            .timeout(http::timeout())
+            .send()
+            .await?;
+        let (status, headers) = (resp.status(), resp.headers().clone());
+        let body = resp.text().await?;
+        if !status.is_success() {
+            return Err(ApiError::from_response(status, &headers, &body));
+        }
+        let list: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| ApiError::Decode(format!("models response: {e}")))?;
+        Ok(list["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["id"].as_str().map(String::from))
+            .collect())
+    }
+
+    /// Synthesize `req.input` as 24 kHz mono s16le PCM.
+    pub async fn speech(&self, req: &SpeechRequest) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/audio/speech", self.base_url);
+        let trace = self.tracer.as_ref().map(|_| json!(req));
+        let pcm = self
+            .send_bytes_with_retry("speech", &req.model, 0, trace, || {
+                Ok(self.http.post(&url).json(req))
+            })
+            .await?;
+        record_usage(&req.model, |u| {
+            u.characters += req.input.chars().count() as u64
+        });
+        Ok(pcm)
+    }
+
+    /// Send with rate-limit pacing, retrying transient failures with
+    /// exponential backoff (or the server's Retry-After), and return the
+    /// successful response body.
+    async fn send_with_retry(
+        &self,
+        label: &str,
+        model: &str,
+        est_tokens: u64,
+        trace: Option<serde_json::Value>,
+        build: impl Fn() -> Result<RequestBuilder, ApiError>,
+    ) -> Result<String, ApiError> {
+        let body = self
+            .send_bytes_with_retry(label, model, est_tokens, trace, build)
+            .await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// `send_with_retry` for a binary response body.
+    async fn send_bytes_with_retry(
+        &self,
+        label: &str,
+        model: &str,
+        est_tokens: u64,
+        trace: Option<serde_json::Value>,
+        build: impl Fn() -> Result<RequestBuilder, ApiError>,
+    ) -> Result<Vec<u8>, ApiError> {
+        let mut span = telemetry::span_of_kind(&format!("openai.{label}"), telemetry::Kind::Client);
+        span.attr("model", model);
+        let mut attempt = 0;
+        loop {
+            span.attr("attempts", attempt as usize + 1);
+            if cancel::is_cancelled() {
+                span.fail("cancelled");
+                return Err(ApiError::Cancelled);
+            }
+            ratelimit::acquire(model, est_tokens).await;
+            let started = Instant::now();
+            let request = build()?.bearer_auth(&self.api_key).timeout(http::timeout());
+            let outcome = match request.send().await {
+                Ok(resp) => {
+                    ratelimit::observe(model, resp.headers());
+                    let status = resp.status();
+                    let headers = resp.headers().clone();
+                    match resp.bytes().await {
+                        Ok(body) => Ok((status, headers, body.to_vec())),
+                        Err(e) => Err(ApiError::Transport(e)),
+                    }
+                }
+                Err(e) => Err(ApiError::Transport(e)),
+            };
+            if let (Some(tracer), Some(request)) = (&self.tracer, &trace) {
+                let response = match &outcome {
+                    Ok((status, _, body)) => json!({
+                        "status": status.as_u16(),
+                        // Raw text, parsed when it is JSON so traces stay readable
+                        "body": match std::str::from_utf8(body) {
+                            Ok(text) => serde_json::from_str::<serde_json::Value>(text)
+                                .unwrap_or_else(|_| json!(text)),
+                            Err(_) => json!(format!("<{} bytes>", body.len())),
+                        },
+                    }),
+                    Err(e) => json!({ "error": e.to_string() }),
+                };
+                tracer.write(
+                    label,
+                    &self.api_key,
+                    json!({
+                        "endpoint": label,
+                        "attempt": attempt + 1,
+                        "elapsed_ms": started.elapsed().as_millis() as u64,
+                        "request": request,
+                        "response": response,
+                    }),
+                );
+            }
+            let result = match outcome {
+                Ok((status, _, body)) if status.is_success() => {
+                    span.attr("retries", attempt as usize);
+                    return Ok(body);
+                }
+                Ok((status, headers, body)) => {
+                    ApiError::from_response(status, &headers, &String::from_utf8_lossy(&body))
+                }
+                Err(e) => e,
+            };
+            attempt += 1;
+            if !result.is_retryable() || attempt >= MAX_ATTEMPTS {
+                span.attr("retries", attempt as usize - 1);
+                span.fail(&result);
+                return Err(result);
+            }
+            let backoff = match &result {
+                ApiError::RateLimited {
+                    retry_after: Some(d),
+                    ..
+                } => *d,
+                _ => Duration::from_millis(2u64.pow(attempt) * 1000),
+            };
+            eprintln!(
+                "OpenAI {} error (attempt {}/{}): {}. Retrying in {}ms...",
+                label,
+                attempt,
+                MAX_ATTEMPTS,
+                result,
+                backoff.as_millis()
+            );
+            sleep(backoff).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_serializes() {
+        let req = ChatRequest::new("gpt-4o-mini")
+            .system("sys")
+            .user("hi")
+            .json_object();
+        let v = serde_json::to_value(&req).unwrap();
+        assert_eq!(v["model"], "gpt-4o-mini");
+        assert_eq!(v["messages"][0]["role"], "system");
+        assert_eq!(v["messages"][1]["content"], "hi");
+        assert_eq!(v["response_format"]["type"], "json_object");
+        let plain = serde_json::to_value(ChatRequest::new("m").user("x")).unwrap();
+        assert!(plain.get("response_format").is_none());
+        assert!(plain.get("temperature").is_none() && plain.get("max_tokens").is_none());
+        let sampled = ChatRequest::new("m").sampling(Sampling {
+            temperature: Some(0.5),
+            top_p: None,
+            max_tokens: Some(4096),
+        });
+        let v = serde_json::to_value(sampled).unwrap();
+        assert_eq!(v["temperature"], 0.5);
+        assert_eq!(v["max_tokens"], 4096);
+        assert!(v.get("top_p").is_none());
+        let strict = ChatRequest::new("m").json_schema("t", json!({"type": "object"}));
+        let v = serde_json::to_value(strict).unwrap();
+        assert_eq!(v["response_format"]["type"], "json_schema");
+        assert_eq!(v["response_format"]["json_schema"]["strict"], true);
+        assert!(supports_json_schema("gpt-4o-mini"));
+        assert!(supports_json_schema("ft:gpt-4.1-mini:acme::abc"));
+        assert!(!supports_json_schema("gpt-4o-2024-05-13"));
+        assert!(!supports_json_schema("gpt-3.5-turbo"));
+    }
+
+    #[test]
+    fn test_traces_redact_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = OpenAiClient::new("sk-secret")
+            .with_traces(&dir.path().join("api-traces"))
+            .unwrap();
+        let tracer = client.tracer.as_ref().unwrap();
+        tracer.write("chat", "sk-secret", json!({"echo": "key sk-secret leaked"}));
+        tracer.write("chat", "sk-secret", json!({}));
+        let first = std::fs::read_to_string(dir.path().join("api-traces/0001-chat.json")).unwrap();
+        assert!(first.contains("key [REDACTED] leaked"));
+        assert!(!first.contains("sk-secret"));
+        assert!(dir.path().join("api-traces/0002-chat.json").exists());
+    }
+
+    #[test]
+    fn test_transcription_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk_00000.wav");
+        let mut wav = Vec::new();
+        wav::write_header(&mut wav, 32_000).unwrap();
+        wav.resize(44 + 32_000, 0);
+        std::fs::write(&path, wav).unwrap();
+        let req = TranscriptionRequest {
+            model: "whisper-1".into(),
+            audio: path,
+            language: Some("ja"),
+        };
+        assert_eq!(req.audio_seconds(), 1.0);
+        assert_eq!(req.trace()["file_name"], "chunk_00000.wav");
+        assert!(req.form().is_ok());
+        let missing = TranscriptionRequest {
+            audio: dir.path().join("gone.wav"),
+            ..req
+        };
+        assert!(matches!(missing.form(), Err(ApiError::Upload(_))));
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let h = HeaderMap::new();
+        let err = |status: u16, body: &str| {
+            ApiError::from_response(StatusCode::from_u16(status).unwrap(), &h, body)
+        };
+        assert!(matches!(err(401, "{}"), ApiError::Auth { .. }));
+        let quota = err(
+            429,
+            r#"{"error":{"message":"no credit","code":"insufficient_quota"}}"#,
+        );
+        assert!(matches!(quota, ApiError::Quota { .. }));
+        assert!(!quota.is_retryable());
+        assert!(err(429, "slow down").is_retryable());
+        assert!(err(503, "").is_retryable());
+        let ctx = err(
+            400,
+            r#"{"error":{"message":"too long","type":"invalid_request_error","code":"context_length_exceeded"}}"#,
+        );
+        assert!(matches!(ctx, ApiError::ContextLength { ref message } if message == "too long"));
+        assert!(matches!(
+            err(400, "bad"),
+            ApiError::BadRequest { status: 400, .. }
+        ));
+        assert!(matches!(
+            err(
+                413,
+                r#"{"error":{"message":"Maximum content size limit (26214400) exceeded"}}"#
+            ),
+            ApiError::TooLarge { .. }
+        ));
+    }
+}
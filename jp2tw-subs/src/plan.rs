@@ -0,0 +1,433 @@
+//! `--plan`: print what a run would do (every ffmpeg command with its full
+//! arguments, the audio chunk layout, and the OpenAI requests) without
+//! running any of it. Only ffprobe is called, to read the duration and
+//! subtitle streams.
+
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+
+use crate::{
+    budget, burn_in_args, chapters, chunk_audio_args, concat, default_srt_path, download, embedded,
+    encode_args, ensemble, extract_audio_args, formats, hls, ocr, output_video_path,
+    probe_duration, resolve_fonts_dir, skip, songs, split_args, subtitles_filter, targets, wav,
+    Args, Mode,
+};
+
+pub fn print(args: &Args) -> Result<()> {
+    let mut args = args.clone();
+    budget::apply(&mut args)?;
+    let args = &args;
+    let extract_extra = split_args(args.ffmpeg_extract_args.as_deref().unwrap_or(""))?;
+    let encode_extra = encode_args(args)?;
+    if args.live {
+        return Err(anyhow!("--plan is not supported with --live"));
+    }
+    let extra_targets = targets::extras(&args.target_lang)?;
+    for input in &args.input {
+        if !(input.exists() || download::is_url(input)) {
+            return Err(anyhow!("Input file not found: {}", input.display()));
+        }
+    }
+    let output_srt = args
+        .output_srt
+        .clone()
+        .unwrap_or_else(|| default_srt_path(args.input()));
+    let burn = args.burn_in.then(|| output_video_path(args)).flatten();
+    let work_dir = match &args.work_dir {
+        Some(dir) => dir.clone(),
+        None => {
+            println!("Work dir: a new temporary directory (shown as ./work; pass --work-dir to pick one)");
+            PathBuf::from("work")
+        }
+    };
+    let duration: Option<f64> = args.input.iter().map(|p| probe_duration(p)).sum();
+    let names: Vec<String> = args.input.iter().map(|p| p.display().to_string()).collect();
+    match duration {
+        Some(d) => println!("Input: {} ({})", names.join(" + "), clock(d)),
+        None => println!(
+            "Input: {} (duration unknown: ffprobe failed)",
+            names.join(" + ")
+        ),
+    }
+    if cfg!(feature = "native-ffmpeg") {
+        println!(
+            "Note: built with native-ffmpeg; these steps run in-process with the same settings"
+        );
+    }
+
+    let mut local = Vec::new();
+    for input in &args.input {
+        if download::is_url(input) {
+            let path = work_dir.join(download::local_name(input));
+            println!("\n# Download (resumable)");
+            println!("{} -> {}", input.display(), path.display());
+            local.push(path);
+        } else {
+            local.push(input.clone());
+        }
+    }
+    let joined = work_dir.join("concat.mp4");
+    let input = if local.len() > 1 {
+        println!("\n# Concatenate {} inputs", args.input.len());
+        let list_path = work_dir.join("concat.txt");
+        println!("{}: {}", list_path.display(), names.join(", "));
+        println!(
+            "{}",
+            command_line("ffmpeg", &concat::ffmpeg_args(&list_path, &joined))
+        );
+        joined.as_path()
+    } else {
+        &local[0]
+    };
+
+    println!("\n# Japanese source");
+    let mut chunks = None;
+    let mut transcription = None;
+    if args.use_embedded_subs {
+        let streams = embedded::probe_subtitle_streams(input)?;
+        let stream = embedded::pick_japanese(&streams).ok_or_else(|| {
+            anyhow!(
+                "No Japanese text subtitle stream found in {}",
+                input.display()
+            )
+        })?;
+        let srt = work_dir.join("embedded_ja.srt");
+        println!(
+            "Embedded subtitle stream #{} ({})",
+            stream.index, stream.codec
+        );
+        println!(
+            "{}",
+            command_line("ffmpeg", &embedded::extract_args(input, stream, &srt))
+        );
+    } else if args.ocr_subs {
+        let opts = ocr::OcrOptions {
+            fps: args.ocr_fps.max(0.1),
+            region: args.ocr_region,
+        };
+        let frames_dir = work_dir.join("ocr_frames");
+        println!(
+            "{}",
+            command_line("ffmpeg", &ocr::sample_args(input, &frames_dir, &opts))
+        );
+        match duration {
+            Some(d) => println!(
+                "tesseract <frame> stdout -l jpn, once per frame (~{} frames)",
+                (d * opts.fps).ceil()
+            ),
+            None => println!("tesseract <frame> stdout -l jpn, once per frame"),
+        }
+    } else {
+        // Without the duration, the whole track is extracted and then split
+        let seek = duration.is_some() && !cfg!(feature = "native-ffmpeg");
+        let wav_path = work_dir.join("audio_16k_mono.wav");
+        if !seek {
+            println!(
+                "{}",
+                command_line(
+                    "ffmpeg",
+                    &extract_audio_args(input, &wav_path, &extract_extra)
+                )
+            );
+        }
+        println!("\n# Chunks ({} s each)", args.chunk_seconds);
+        let mut total = 0;
+        match duration {
+            Some(d) => {
+                let layout = chunk_layout(d, args.chunk_seconds);
+                let mut sent = 0;
+                for (i, (start, end)) in layout.iter().enumerate() {
+                    if skip::covers(&args.skip_range, *start, *end) {
+                        println!(
+                            "chunk_{i:05}  {} - {}  skipped (--skip-range)",
+                            clock(*start),
+                            clock(*end)
+                        );
+                        continue;
+                    }
+                    sent += 1;
+                    let bytes = wav_bytes(end - start);
+                    total += bytes;
+                    let chunk = work_dir.join(format!("chunk_{i:05}.wav"));
+                    println!(
+                        "{}  {} - {}  {:.1} MB",
+                        chunk.display(),
+                        clock(*start),
+                        clock(*end),
+                        bytes as f64 / 1e6
+                    );
+                    if seek {
+                        let cut = chunk_audio_args(
+                            input,
+                            *start,
+                            args.chunk_seconds,
+                            &chunk,
+                            &extract_extra,
+                        );
+                        println!("  {}", command_line("ffmpeg", &cut));
+                    }
+                }
+                chunks = Some(sent);
+            }
+            None => println!("Unknown without the duration"),
+        }
+        transcription = Some(match chunks {
+            Some(n) => format!(
+                "{n} transcription request(s) to {} ({:.1} MB uploaded in total)",
+                args.whisper_model,
+                total as f64 / 1e6
+            ),
+            None => format!(
+                "One transcription request per chunk to {}",
+                args.whisper_model
+            ),
+        });
+    }
+
+    println!("\n# OpenAI requests");
+    if let Some(line) = &transcription {
+        println!("{line}");
+    }
+    println!(
+        "At least {} chat request(s) to {}: one per {} lines (or ~{} tokens, whichever comes first) of each {} s window; the line count is known only after transcription",
+        chunks.unwrap_or(1),
+        args.translate_model,
+        args.translate_batch_size,
+        args.translate_batch_tokens,
+        args.chunk_seconds
+    );
+    if !args.no_name_table {
+        println!(
+            "Plus one name-table request to {} per chunk with new lines",
+            args.translate_model
+        );
+    }
+    if args.candidates > 1 {
+        println!(
+            "Each zh-TW batch is requested {} times ({}) plus one judge request to {}",
+            args.candidates,
+            ensemble::Ensemble::from_args(args).models.join(", "),
+            args.judge_model.as_deref().unwrap_or(&args.translate_model)
+        );
+    }
+    if let Some(pivot) = &args.pivot {
+        println!(
+            "Plus one {pivot} pivot request to {} per {} lines before each zh-TW batch",
+            args.pivot_model(),
+            args.translate_batch_size
+        );
+    }
+    if args.songs == songs::Songs::Loose && args.mode == Mode::Dialog {
+        println!(
+            "Plus one lyrics request to {} per {} sung lines, if Whisper marks any",
+            args.translate_model, args.translate_batch_size
+        );
+    }
+    if args.punctuate && transcription.is_some() {
+        println!(
+            "Plus one punctuation request to {} per 40 transcribed lines",
+            args.translate_model
+        );
+    }
+    if args.tm.is_some() {
+        println!("Lines found in the translation memory skip the API");
+    }
+    if args.batch_api {
+        println!("The zh-TW batches are submitted as one Batch API job at half price; rerun the same command to collect them");
+    }
+    for target in &extra_targets {
+        println!(
+            "{}: one chat request to {} per {} lines, after zh-TW",
+            target.code, args.translate_model, args.translate_batch_size
+        );
+    }
+
+    if let Some(dir) = &args.hls_dir {
+        println!("\n# HLS package");
+        println!(
+            "{}",
+            command_line("ffmpeg", &hls::ffmpeg_args(input, dir, args.hls_time))
+        );
+    }
+    // Joined inputs do not exist yet; their first part is probed instead
+    let chapters = if args.split_by_chapter {
+        let chapters = chapters::probe(args.input())?;
+        println!("\n# Chapters");
+        for (i, ch) in chapters.iter().enumerate() {
+            let title = ch.title.as_deref().unwrap_or("");
+            println!(
+                "{:02}: {}-{} {title}",
+                i + 1,
+                clock(ch.start),
+                clock(ch.end)
+            );
+        }
+        chapters
+    } else {
+        Vec::new()
+    };
+    if let Some(out_mp4) = &burn {
+        println!("\n# Burn-in");
+        if args.avoid_hardsubs && !args.ocr_subs {
+            let frame = work_dir.join("hardsub_probe.png");
+            println!(
+                "{}",
+                command_line(
+                    "ffmpeg",
+                    &ocr::band_frame_args(input, 0.0, args.ocr_region, &frame)
+                )
+            );
+            println!(
+                "tesseract <frame> stdout -l jpn, for {} frames spread over the video (subtitles move to the top if text shows in a quarter of them)",
+                ocr::HARDSUB_SAMPLES
+            );
+        }
+        let fonts_dir = resolve_fonts_dir(args.font_dir.as_deref());
+        let encoder = if args.nice && cfg!(unix) {
+            "nice -n 10 ffmpeg"
+        } else {
+            "ffmpeg"
+        };
+        if chapters.is_empty() {
+            let filter = subtitles_filter(&work_dir.join("subs.ass"), fonts_dir.as_deref(), None);
+            println!(
+                "{}",
+                command_line(
+                    encoder,
+                    &burn_in_args(input, &filter, out_mp4, &encode_extra)
+                )
+            );
+        }
+        for (i, ch) in chapters.iter().enumerate() {
+            let ass = work_dir.join(format!("subs.ch{:02}.ass", i + 1));
+            let filter = subtitles_filter(&ass, fonts_dir.as_deref(), None);
+            let out = chapters::path(out_mp4, i + 1);
+            println!(
+                "{}",
+                command_line(
+                    encoder,
+                    &chapters::burn_in_args(input, ch, &filter, &out, &encode_extra)
+                )
+            );
+        }
+    }
+
+    println!("\n# Outputs");
+    if args.format.contains(&formats::SubtitleFormat::Srt) {
+        println!("{}", output_srt.display());
+    }
+    if args.format.contains(&formats::SubtitleFormat::Sbv) {
+        println!(
+            "{}",
+            formats::sibling_path(&output_srt, "zh-TW", "sbv").display()
+        );
+    }
+    if args.format.contains(&formats::SubtitleFormat::Json) {
+        println!(
+            "{}",
+            formats::sibling_path(&output_srt, "zh-TW", "json").display()
+        );
+    }
+    if args.review_srt {
+        println!(
+            "{}",
+            formats::sibling_path(&output_srt, "review", "srt").display()
+        );
+    }
+    if args.html_report {
+        println!(
+            "{}",
+            formats::sibling_path(&output_srt, "report", "html").display()
+        );
+    }
+    for target in &extra_targets {
+        println!(
+            "{}",
+            formats::sibling_path(&output_srt, &target.code, "srt").display()
+        );
+    }
+    if let Some(dir) = &args.hls_dir {
+        println!("{}", dir.join("master.m3u8").display());
+    }
+    for i in 1..=chapters.len() {
+        println!("{}", chapters::path(&output_srt, i).display());
+    }
+    match &burn {
+        Some(out_mp4) if chapters.is_empty() => println!("{}", out_mp4.display()),
+        Some(out_mp4) => {
+            for i in 1..=chapters.len() {
+                println!("{}", chapters::path(out_mp4, i).display());
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// (start, end) seconds of each chunk `wav::segment_wav` would write.
+fn chunk_layout(duration: f64, chunk_seconds: u32) -> Vec<(f64, f64)> {
+    let step = chunk_seconds.max(1) as f64;
+    let mut out = Vec::new();
+    let mut start = 0.0;
+    while start < duration {
+        out.push((start, (start + step).min(duration)));
+        start += step;
+    }
+    out
+}
+
+/// Size of a 16-bit mono WAV of `seconds`, header included.
+fn wav_bytes(seconds: f64) -> u64 {
+    (seconds * wav::SAMPLE_RATE as f64) as u64 * 2 + 44
+}
+
+pub fn clock(seconds: f64) -> String {
+    let s = seconds as u64;
+    format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
+
+/// `program` and `args` as one line that can be pasted into a POSIX shell.
+fn command_line(program: &str, args: &[String]) -> String {
+    let mut line = program.to_string();
+    for a in args {
+        line.push(' ');
+        line.push_str(&shell_quote(a));
+    }
+    line
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+%@".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_layout() {
+        assert_eq!(
+            chunk_layout(1250.5, 600),
+            vec![(0.0, 600.0), (600.0, 1200.0), (1200.0, 1250.5)]
+        );
+        assert!(chunk_layout(0.0, 600).is_empty());
+        assert_eq!(wav_bytes(1.0), 32_044);
+    }
+
+    #[test]
+    fn test_command_line_quotes_filters() {
+        let args = [
+            "-vf".to_string(),
+            r"subtitles=C\:/a b/subs.ass".to_string(),
+            "it's".to_string(),
+        ];
+        assert_eq!(
+            command_line("ffmpeg", &args),
+            r"ffmpeg -vf 'subtitles=C\:/a b/subs.ass' 'it'\''s'"
+        );
+    }
+}
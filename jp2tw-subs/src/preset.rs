@@ -0,0 +1,182 @@
+//! `--preset`: bundles of settings across the whole pipeline (models, review
+//! passes, encoder speed) so a run gets sensible trade-offs without tuning
+//! each flag. A preset's flags go in front of the project file's and the
+//! command line's, so anything set explicitly still wins.
+
+use clap::ValueEnum;
+use std::ffi::OsString;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Quickest and cheapest: no name table, large translation batches, a fast x264 encode
+    Fast,
+    /// The default models with a retry pass for lines of suspect length and a medium x264 encode
+    Balanced,
+    /// gpt-4o translation with transcript cleanup, 3 judged candidates per batch, and a slow, high-quality x264 encode
+    Quality,
+    /// Anime dialog: honorifics kept on names, attack and technique names kept with a zh-TW gloss, OP/ED styled as songs, and an x264 encode tuned for animation
+    Anime,
+}
+
+impl Preset {
+    /// The flags the preset stands for. Transcription stays on whisper-1,
+    /// the only model returning the segment timing subtitles need.
+    fn flags(self) -> &'static [&'static str] {
+        match self {
+            Preset::Fast => &[
+                "--whisper-model=whisper-1",
+                "--translate-model=gpt-4o-mini",
+                "--no-name-table",
+                "--translate-batch-size=100",
+                "--ffmpeg-encode-args=-preset veryfast -crf 23",
+            ],
+            Preset::Balanced => &[
+                "--whisper-model=whisper-1",
+                "--translate-model=gpt-4o-mini",
+                "--retranslate-length-outliers",
+                "--ffmpeg-encode-args=-preset medium -crf 20",
+            ],
+            Preset::Quality => &[
+                "--whisper-model=whisper-1",
+                "--translate-model=gpt-4o",
+                "--punctuate",
+                "--retranslate-length-outliers",
+                "--candidates=3",
+                "--ffmpeg-encode-args=-preset slow -crf 18",
+            ],
+            Preset::Anime => &[
+                "--whisper-model=whisper-1",
+                "--translate-model=gpt-4o-mini",
+                "--honorifics=keep",
+                "--keep-techniques",
+                "--songs=style",
+                "--retranslate-length-outliers",
+                "--ffmpeg-encode-args=-preset medium -crf 20 -tune animation",
+            ],
+        }
+    }
+}
+
+/// The arguments with the flags of the (last) `--preset` inserted after the
+/// program name. An unknown preset name is left for clap to report.
+pub fn argv(mut args: Vec<OsString>) -> Vec<OsString> {
+    let Some(preset) = chosen(&args).and_then(|name| Preset::from_str(&name, true).ok()) else {
+        return args;
+    };
+    let batch_api = args.iter().any(|a| a == "--batch-api");
+    let flags = preset
+        .flags()
+        .iter()
+        // --batch-api takes one reply per batch
+        .filter(|f| !(batch_api && f.starts_with("--candidates")))
+        .map(OsString::from);
+    let at = args.len().min(1);
+    args.splice(at..at, flags);
+    args
+}
+
+fn chosen(args: &[OsString]) -> Option<String> {
+    let mut found = None;
+    let mut iter = args.iter().skip(1).map(|a| a.to_string_lossy());
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--preset" {
+            found = iter.next().map(|v| v.into_owned());
+        } else if let Some(v) = arg.strip_prefix("--preset=") {
+            found = Some(v.to_string());
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Args;
+    use clap::Parser;
+
+    fn parse(cli: &[&str]) -> Args {
+        Args::try_parse_from(argv(cli.iter().map(OsString::from).collect())).unwrap()
+    }
+
+    #[test]
+    fn test_preset_flags() {
+        let args = parse(&["jp2tw-subs", "--input", "a.mp4", "--preset", "quality"]);
+        assert_eq!(args.translate_model, "gpt-4o");
+        assert_eq!(args.candidates, 3);
+        assert!(args.punctuate);
+        // Explicit flags win, wherever they appear
+        let args = parse(&[
+            "jp2tw-subs",
+            "--translate-model",
+            "gpt-4.1",
+            "--input",
+            "a.mp4",
+            "--preset=fast",
+        ]);
+        assert_eq!(args.translate_model, "gpt-4.1");
+        assert!(args.no_name_table);
+        let args = parse(&[
+            "jp2tw-subs",
+            "--input",
+            "a.mp4",
+            "--preset",
+            "quality",
+            "--batch-api",
+        ]);
+        assert_eq!(args.candidates, 1);
+        assert_eq!(parse(&["jp2tw-subs", "--input", "a.mp4"]).candidates, 1);
+        let args = parse(&[
+            "jp2tw-subs",
+            "--input",
+            "a.mp4",
+            "--preset",
+            "anime",
+            "--honorifics",
+            "map",
+        ]);
+        assert_eq!(args.honorifics, crate::anime::Honorifics::Map);
+        assert!(args.keep_techniques && !args.no_name_table);
+    }
+
+    #[test]
+    fn test_preset_switches_off() {
+        let args = parse(&[
+            "jp2tw-subs",
+            "--preset",
+            "quality",
+            "--punctuate=false",
+            "--retranslate-length-outliers=false",
+            "--input",
+            "a.mp4",
+        ]);
+        assert!(!args.punctuate && !args.retranslate_length_outliers);
+        assert_eq!(args.translate_model, "gpt-4o");
+        let args = parse(&[
+            "jp2tw-subs",
+            "--preset=fast",
+            "--no-name-table=false",
+            "-i",
+            "a.mp4",
+        ]);
+        assert!(!args.no_name_table);
+        let args = parse(&[
+            "jp2tw-subs",
+            "--preset=anime",
+            "--keep-techniques=false",
+            "-i",
+            "a.mp4",
+        ]);
+        assert!(!args.keep_techniques);
+        // Without a value they still only switch on
+        let args = parse(&["jp2tw-subs", "--punctuate", "--input", "a.mp4"]);
+        assert!(args.punctuate);
+        let row = vec![("punctuate".to_string(), "no".to_string())];
+        assert_eq!(
+            crate::batch::option_flags(&row).unwrap(),
+            ["--punctuate=false"]
+        );
+    }
+}
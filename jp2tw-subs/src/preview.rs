@@ -0,0 +1,372 @@
+//! `preview`: one frame of the input with the burn-in subtitle drawn over it,
+//! written as a PNG. The subtitle is styled exactly as a burn-in run with the
+//! same flags would style it, so fonts, sizes, colors, and bilingual layouts
+//! can be tried in seconds instead of encoding test clips.
+//!
+//! With several `--at` times or `--variant` styles the frames are tiled into
+//! one contact sheet, a row per time and a labelled column per variant, for
+//! comparing candidate fonts side by side.
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::batch::{self, Row};
+use crate::layout::{self, AssEvent, AssStyle, LangStyle};
+use crate::{
+    burn_in_ass, default_srt_path, formats, resolve_fonts_dir, subparse, subtitles_filter,
+    write_ass_events, Args, WhisperSegment,
+};
+
+pub struct Request<'a> {
+    pub at: &'a [f64],
+    /// Style overrides, one column each, like `font-name=Noto Serif CJK TC,font-size=40`
+    pub variants: &'a [String],
+    pub subs: Option<&'a Path>,
+    /// Sample Chinese text shown instead of a subtitle file
+    pub text: Option<&'a str>,
+    pub ja_text: Option<&'a str>,
+    pub out: Option<&'a Path>,
+}
+
+/// `HH:MM:SS[.mmm]`, `MM:SS`, or plain seconds.
+pub fn parse_at(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .or_else(|| subparse::parse_timestamp(s))
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .ok_or_else(|| format!("expected HH:MM:SS[.mmm], MM:SS, or seconds, got {s:?}"))
+}
+
+pub fn render(args: &Args, req: &Request) -> Result<()> {
+    let input = args
+        .input
+        .first()
+        .context("preview needs the video: pass --input before the subcommand")?;
+    let (segments, ja_lines, zh_lines) = cues(args, input, req)?;
+    let mut args = args.clone();
+    if ja_lines.iter().all(|l| l.is_empty()) && args.bilingual {
+        eprintln!("No Japanese text to preview; showing the Chinese lines only (use the JSON sidecar or --ja-text for bilingual layouts)");
+        args.bilingual = false;
+    }
+    let columns: Vec<(Option<&str>, Args)> = if req.variants.is_empty() {
+        vec![(None, args)]
+    } else {
+        req.variants
+            .iter()
+            .map(|spec| Ok((Some(spec.as_str()), variant(&args, spec)?)))
+            .collect::<Result<_>>()?
+    };
+
+    let work = tempfile::tempdir()?;
+    let mut tiles = Vec::new();
+    for &at in req.at {
+        for (label, args) in &columns {
+            let tile = work.path().join(format!("tile{}.png", tiles.len()));
+            let cues = (
+                segments.as_slice(),
+                ja_lines.as_slice(),
+                zh_lines.as_slice(),
+            );
+            frame(args, input, at, cues, *label, work.path(), &tile)?;
+            tiles.push(tile);
+        }
+    }
+    let out = req
+        .out
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| default_path(input));
+    if let [tile] = &tiles[..] {
+        std::fs::copy(tile, &out).with_context(|| format!("Write {}", out.display()))?;
+    } else {
+        ffmpeg(&sheet_args(&tiles, columns.len(), &out))?;
+    }
+    eprintln!("Preview: {}", out.display());
+    Ok(())
+}
+
+/// `args` with a variant's options applied, as if given on the command line.
+fn variant(args: &Args, spec: &str) -> Result<Args> {
+    let row: Row = spec
+        .split(',')
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Variant {spec:?}: expected option=value, got {pair:?}"))?;
+            let key = key.trim().to_ascii_lowercase().replace('-', "_");
+            Ok((key, value.trim().to_string()))
+        })
+        .collect::<Result<_>>()?;
+    let mut argv = vec!["jp2tw-subs".to_string()];
+    argv.extend(batch::option_flags(&row).with_context(|| format!("Variant {spec:?}"))?);
+    // Updates are checked like a full command line, which needs --input
+    for input in &args.input {
+        argv.push("--input".to_string());
+        argv.push(input.to_string_lossy().into_owned());
+    }
+    let mut args = args.clone();
+    args.try_update_from(argv)
+        .map_err(|e| anyhow!("Variant {spec:?}: {e}"))?;
+    Ok(args)
+}
+
+/// One frame at `at` with its subtitle, and `label` in the top-left corner.
+fn frame(
+    args: &Args,
+    input: &Path,
+    at: f64,
+    (segments, ja_lines, zh_lines): (&[WhisperSegment], &[String], &[String]),
+    label: Option<&str>,
+    work: &Path,
+    out: &Path,
+) -> Result<()> {
+    let canvas = crate::probe_canvas(input);
+    let (mut styles, events) = burn_in_ass(args, canvas, segments, ja_lines, zh_lines);
+    let mut events: Vec<AssEvent> = events
+        .into_iter()
+        .filter(|e| e.start <= at && at < e.end)
+        .map(|e| AssEvent {
+            // The frame is cut first, so it sits at time zero
+            start: 0.0,
+            end: 1.0,
+            ..e
+        })
+        .collect();
+    if events.is_empty() {
+        eprintln!("No subtitle at {}", crate::format_srt_time(at));
+    }
+    if let Some(label) = label {
+        styles.push(AssStyle {
+            name: "Label",
+            style: LangStyle {
+                font: "Noto Sans CJK TC".to_string(),
+                size: canvas.scaled(18),
+                rgb: 0xFFE066,
+                opacity: 100,
+            },
+            alignment: 7,
+        });
+        events.push(AssEvent {
+            start: 0.0,
+            end: 1.0,
+            style: "Label",
+            text: layout::ass_escape(label),
+        });
+    }
+
+    let ass_path = work.join("preview.ass");
+    write_ass_events(&ass_path, canvas, &styles, &events)?;
+    let fonts_dir = resolve_fonts_dir(args.font_dir.as_deref());
+    let filter = subtitles_filter(&ass_path, fonts_dir.as_deref(), None);
+    ffmpeg(&frame_args(input, at, &filter, out))?;
+    if !out.exists() {
+        return Err(anyhow!(
+            "No frame at {}; is it past the end of the video?",
+            crate::format_srt_time(at)
+        ));
+    }
+    Ok(())
+}
+
+fn ffmpeg(args: &[String]) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(args)
+        .output()
+        .context("ffmpeg is required to render a preview")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg preview failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// The cues to draw from: `--text`, the given file, or the outputs of an
+/// earlier run (JSON sidecar first, for its separate Japanese lines).
+fn cues(
+    args: &Args,
+    input: &Path,
+    req: &Request,
+) -> Result<(Vec<WhisperSegment>, Vec<String>, Vec<String>)> {
+    if let Some(text) = req.text {
+        // Shown at every --at
+        let seg = WhisperSegment {
+            start: 0.0,
+            end: f64::INFINITY,
+            ..Default::default()
+        };
+        let ja = req.ja_text.unwrap_or_default().to_string();
+        return Ok((vec![seg], vec![ja], vec![text.replace("\\n", "\n")]));
+    }
+    let path = match req.subs {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let srt = args
+                .output_srt
+                .clone()
+                .unwrap_or_else(|| default_srt_path(input));
+            let json = formats::sibling_path(&srt, "zh-TW", "json");
+            if json.exists() {
+                json
+            } else if srt.exists() {
+                srt
+            } else {
+                return Err(anyhow!(
+                    "No subtitles found at {}; pass --subs or --text",
+                    srt.display()
+                ));
+            }
+        }
+    };
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        return read_json(&path);
+    }
+    let cues = subparse::read(&path)?;
+    let segments = subparse::to_segments(&cues);
+    // Same cues to_segments keeps, with their line breaks
+    let zh = cues
+        .iter()
+        .filter(|c| c.lines.iter().any(|l| !l.trim().is_empty()))
+        .map(|c| c.lines.join("\n"))
+        .collect();
+    let ja = vec![String::new(); segments.len()];
+    Ok((segments, ja, zh))
+}
+
+fn read_json(path: &Path) -> Result<(Vec<WhisperSegment>, Vec<String>, Vec<String>)> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Read {}", path.display()))?;
+    let v: serde_json::Value =
+        serde_json::from_str(&text).with_context(|| format!("Parse {}", path.display()))?;
+    let mut out = (Vec::new(), Vec::new(), Vec::new());
+    for cue in v["cues"].as_array().into_iter().flatten() {
+        let text = |key: &str| cue[key].as_str().unwrap_or_default().to_string();
+        out.0.push(WhisperSegment {
+            start: cue["start"].as_f64().unwrap_or_default(),
+            end: cue["end"].as_f64().unwrap_or_default(),
+            ..Default::default()
+        });
+        out.1.push(text("ja"));
+        out.2.push(text("zh"));
+    }
+    Ok(out)
+}
+
+fn default_path(input: &Path) -> PathBuf {
+    crate::download::local_name(input).with_extension("preview.png")
+}
+
+/// ffmpeg arguments that seek to `at` and write one filtered frame.
+fn frame_args(input: &Path, at: f64, filter: &str, out: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-v", "error", "-ss"]
+        .map(String::from)
+        .into();
+    args.push(format!("{at:.3}"));
+    args.push("-i".to_string());
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-vf", filter, "-frames:v", "1"].map(String::from));
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+/// ffmpeg arguments that tile `tiles` (row by row, `columns` wide) into
+/// one image.
+fn sheet_args(tiles: &[PathBuf], columns: usize, out: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-v", "error"].map(String::from).into();
+    for tile in tiles {
+        args.push("-i".to_string());
+        args.push(tile.to_string_lossy().into_owned());
+    }
+    let stack = |inputs: &[String], how: &str, to: &str| match inputs {
+        [one] => format!("{one}null{to}"),
+        _ => format!("{}{how}=inputs={}{to}", inputs.concat(), inputs.len()),
+    };
+    let mut graph = Vec::new();
+    let mut rows = Vec::new();
+    for (r, row) in (0..tiles.len())
+        .collect::<Vec<_>>()
+        .chunks(columns)
+        .enumerate()
+    {
+        let inputs: Vec<String> = row.iter().map(|i| format!("[{i}:v]")).collect();
+        let to = format!("[r{r}]");
+        graph.push(stack(&inputs, "hstack", &to));
+        rows.push(to);
+    }
+    graph.push(stack(&rows, "vstack", "[sheet]"));
+    args.extend(["-filter_complex".to_string(), graph.join(";")]);
+    args.extend(["-map", "[sheet]", "-frames:v", "1"].map(String::from));
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_at_and_frame_args() {
+        assert_eq!(parse_at("00:12:34"), Ok(754.0));
+        assert_eq!(parse_at("12:34.5"), Ok(754.5));
+        assert_eq!(parse_at("90"), Ok(90.0));
+        assert!(parse_at("soon").is_err());
+        assert_eq!(
+            frame_args(
+                Path::new("ep.mp4"),
+                754.0,
+                "subtitles=p.ass",
+                Path::new("p.png")
+            ),
+            [
+                "-nostdin",
+                "-y",
+                "-v",
+                "error",
+                "-ss",
+                "754.000",
+                "-i",
+                "ep.mp4",
+                "-vf",
+                "subtitles=p.ass",
+                "-frames:v",
+                "1",
+                "p.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variants_and_sheet() {
+        let args = Args::parse_from([
+            "jp2tw-subs",
+            "-i",
+            "ep.mp4",
+            "--font-size",
+            "30",
+            "preview",
+            "--at",
+            "1",
+        ]);
+        let v = variant(&args, "font-name=Noto Serif CJK TC, font_size=44").unwrap();
+        assert_eq!(v.font_name.as_deref(), Some("Noto Serif CJK TC"));
+        assert_eq!(v.font_size, Some(44));
+        assert_eq!(v.input, args.input);
+        assert!(variant(&args, "font-size").is_err());
+        assert!(variant(&args, "typeface=Foo").is_err());
+
+        let tiles: Vec<PathBuf> = (0..4).map(|i| format!("t{i}.png").into()).collect();
+        let sheet = sheet_args(&tiles, 2, Path::new("s.png"));
+        let graph = &sheet[sheet.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        assert_eq!(
+            graph,
+            "[0:v][1:v]hstack=inputs=2[r0];[2:v][3:v]hstack=inputs=2[r1];[r0][r1]vstack=inputs=2[sheet]"
+        );
+        let sheet = sheet_args(&tiles[..2], 1, Path::new("s.png"));
+        assert!(sheet
+            .contains(&"[0:v]null[r0];[1:v]null[r1];[r0][r1]vstack=inputs=2[sheet]".to_string()));
+    }
+}
@@ -0,0 +1,215 @@
+//! Project mode: a `.jp2tw.toml` in the working directory pins the settings
+//! of a series (glossary, name table, style, models, output names) so every
+//! episode run from that directory is captioned the same way.
+//! `jp2tw-subs init` writes a starter file.
+//!
+//! ```toml
+//! [options]
+//! translate-model = "gpt-4o-mini"
+//! replace-rules = "glossary.toml"
+//! names = "names.toml"
+//! font-size = 32
+//! output = "out/{stem}.zh-TW.mp4"
+//! ```
+//!
+//! Options are named as on the command line and go in front of it, so flags
+//! given there still win. `{stem}` in `output-srt` and `output` is the input
+//! file name without its extension.
+
+use anyhow::{anyhow, Context, Result};
+use clap::CommandFactory;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::batch::{self, Row};
+use crate::{download, Args};
+
+pub const FILE: &str = ".jp2tw.toml";
+
+const GLOSSARY: &str = r#"# Ordered find/replace rules applied to translations (--replace-rules).
+#
+# [[rule]]
+# pattern = "(\\p{Han}+)桑"
+# replace = "${1}先生"
+#
+# [[rule]]
+# pattern = "ポケモン"
+# replace = "Pokémon"
+# target = "both"   # zh (default) | ja | both
+"#;
+
+const NAMES: &str = r#"# Names pinned for every episode (--names): the Japanese form, its zh-TW
+# rendering, and other renderings to rewrite to it.
+#
+# [[name]]
+# ja = "田中"
+# zh = "田中"
+# variants = ["塔納卡"]
+"#;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectFile {
+    #[serde(default)]
+    options: toml::Table,
+}
+
+/// Whether the working directory holds a project file.
+pub fn active() -> bool {
+    Path::new(FILE).is_file()
+}
+
+/// The process arguments with the project's options inserted after the
+/// program name; unchanged outside a project.
+pub fn argv(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    if !active() {
+        return Ok(args);
+    }
+    let text = std::fs::read_to_string(FILE).with_context(|| format!("Read {FILE}"))?;
+    let flags = flags(&text).with_context(|| format!("Parse {FILE}"))?;
+    let at = args.len().min(1);
+    args.splice(at..at, flags.into_iter().map(OsString::from));
+    Ok(args)
+}
+
+fn flags(text: &str) -> Result<Vec<String>> {
+    let file: ProjectFile = toml::from_str(text)?;
+    let row = file
+        .options
+        .iter()
+        .map(|(key, v)| Ok((key.to_ascii_lowercase().replace('-', "_"), value(key, v)?)))
+        .collect::<Result<Row>>()?;
+    batch::option_flags(&row)
+}
+
+/// An option value as it would be typed; arrays become comma lists.
+fn value(key: &str, v: &toml::Value) -> Result<String> {
+    Ok(match v {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| value(key, item))
+            .collect::<Result<Vec<_>>>()?
+            .join(","),
+        _ => return Err(anyhow!("Option {key:?} takes a string, number, or list")),
+    })
+}
+
+/// Fill `{stem}` in `--output-srt` and `--output` from the input name.
+pub fn expand_templates(args: &mut Args) {
+    let Some(stem) = args
+        .input
+        .first()
+        .map(|p| download::local_name(p))
+        .and_then(|p| Some(p.file_stem()?.to_owned()))
+        .map(|s| s.to_string_lossy().into_owned())
+    else {
+        return;
+    };
+    if let Some(path) = &mut args.output_srt {
+        *path = path.to_string_lossy().replace("{stem}", &stem).into();
+    }
+    if let Some(out) = &mut args.output {
+        *out = out.replace("{stem}", &stem);
+    }
+}
+
+/// Write a starter project file, glossary, and name table into the working
+/// directory; existing glossary and name files are kept.
+pub fn init(force: bool) -> Result<()> {
+    if active() && !force {
+        return Err(anyhow!(
+            "{FILE} already exists; pass --force to overwrite it"
+        ));
+    }
+    std::fs::write(FILE, template()).with_context(|| format!("Write {FILE}"))?;
+    eprintln!("Wrote {FILE}");
+    for (path, text) in [("glossary.toml", GLOSSARY), ("names.toml", NAMES)] {
+        if !Path::new(path).exists() {
+            std::fs::write(path, text).with_context(|| format!("Write {path}"))?;
+            eprintln!("Wrote {path}");
+        }
+    }
+    Ok(())
+}
+
+fn template() -> String {
+    let command = Args::command();
+    let default = |id: &str| {
+        command
+            .get_arguments()
+            .find(|a| a.get_id() == id)
+            .and_then(|a| a.get_default_values().first())
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    format!(
+        r#"# jp2tw-subs project settings, applied to every run in this directory.
+# Options are named as on the command line (translate-model for
+# --translate-model); flags given on the command line still win.
+
+[options]
+whisper-model = "{whisper}"
+translate-model = "{translate}"
+replace-rules = "glossary.toml"
+names = "names.toml"
+
+# Burn-in style
+# font-name = "Noto Sans CJK TC"
+# font-size = 32
+# bilingual-order = "zh-ja"
+
+# Output names; {{stem}} is the input file name without its extension
+# output-srt = "subs/{{stem}}.zh-TW.srt"
+# output = "out/{{stem}}.zh-TW.mp4"
+"#,
+        whisper = default("whisper_model"),
+        translate = default("translate_model"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_project_flags_and_templates() {
+        let text = r#"
+            [options]
+            translate-model = "gpt-4o"
+            font_size = 32
+            burn-in = true
+            target-lang = ["zh-TW", "en"]
+            output = "out/{stem}.mp4"
+        "#;
+        assert_eq!(
+            flags(text).unwrap(),
+            [
+                "--burn-in",
+                "--font-size",
+                "32",
+                "--output",
+                "out/{stem}.mp4",
+                "--target-lang",
+                "zh-TW,en",
+                "--translate-model",
+                "gpt-4o"
+            ]
+        );
+        assert!(flags("[options]\ncolour = \"red\"").is_err());
+        assert!(flags("[option]\nfont-size = 1").is_err());
+        // The starter file parses
+        assert!(flags(&template()).is_ok());
+
+        let mut args =
+            Args::parse_from(["jp2tw-subs", "--output", "out/{stem}.mp4", "-i", "ep01.mp4"]);
+        expand_templates(&mut args);
+        assert_eq!(args.output.as_deref(), Some("out/ep01.mp4"));
+    }
+}
@@ -0,0 +1,240 @@
+//! `--proofread`: a zh-TW proofreading pass over the translated lines. It
+//! flags, without changing anything, what the model and later edits tend to
+//! get wrong: commonly miswritten words (因該 for 應該), a measure word that
+//! does not fit its noun (一隻車), and a run mixing two accepted forms of
+//! the same word (台灣 and 臺灣). Flags go to the HTML report and the review
+//! SRT. `--proofread-dict` adds entries to the built-in dictionary.
+//!
+//! ```toml
+//! [[typo]]
+//! wrong = "帳蓬"
+//! right = "帳篷"
+//! ```
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::lint::{Issue, Severity};
+use crate::WhisperSegment;
+
+/// Miswritten words and their correct form in Taiwan usage.
+const TYPOS: &[(&str, &str)] = &[
+    ("因該", "應該"),
+    ("以經", "已經"),
+    ("即然", "既然"),
+    ("必竟", "畢竟"),
+    ("一昧", "一味"),
+    ("真象", "真相"),
+    ("想象", "想像"),
+    ("重覆", "重複"),
+    ("坐位", "座位"),
+    ("部份", "部分"),
+    ("做為", "作為"),
+    ("好象", "好像"),
+    ("份外", "分外"),
+    ("松了一口氣", "鬆了一口氣"),
+    ("迫不急待", "迫不及待"),
+    ("一股作氣", "一鼓作氣"),
+    ("再接再勵", "再接再厲"),
+    ("按步就班", "按部就班"),
+    ("穿流不息", "川流不息"),
+    ("甘敗下風", "甘拜下風"),
+    ("默守成規", "墨守成規"),
+    ("莫明其妙", "莫名其妙"),
+    ("變本加利", "變本加厲"),
+    ("針貶", "針砭"),
+    ("既使", "即使"),
+    ("不徑而走", "不脛而走"),
+    ("談笑風聲", "談笑風生"),
+];
+
+/// Nouns, their measure word, and measure words often used wrongly for them.
+/// 個 is left out: it also starts compounds (一個車站, 這個書包).
+const MEASURE_WORDS: &[(&str, &str, &str)] = &[
+    ("車", "輛", "隻條"),
+    ("書", "本", "隻"),
+    ("電影", "部", "本"),
+    ("衣服", "件", "張"),
+    ("樹", "棵", "隻"),
+    ("傘", "把", "隻"),
+    ("河", "條", "隻"),
+    ("橋", "座", "隻"),
+    ("椅子", "張", "隻"),
+];
+
+/// Words that start with a listed noun but take their own measure word.
+const COMPOUNDS: &[&str] = &[
+    "車道",
+    "車票",
+    "書單",
+    "書桌",
+    "書架",
+    "書櫃",
+    "書籤",
+    "書蟲",
+    "電影票",
+    "樹懶",
+    "樹枝",
+    "河馬",
+    "河豚",
+    "傘蜥",
+];
+
+/// Numerals and determiners a measure word follows.
+const COUNTERS: &str = "一二兩三四五六七八九十百千萬幾這那哪每某半0-9";
+
+/// Equally valid forms a run should not mix.
+const VARIANTS: &[(&str, &str)] = &[
+    ("台灣", "臺灣"),
+    ("台北", "臺北"),
+    ("什麼", "甚麼"),
+    ("裡", "裏"),
+    ("著", "着"),
+    ("峰", "峯"),
+    ("群", "羣"),
+    ("線", "綫"),
+    ("癡", "痴"),
+    ("麵", "麪"),
+];
+
+#[derive(Deserialize)]
+struct DictFile {
+    #[serde(default)]
+    typo: Vec<Typo>,
+}
+
+#[derive(Deserialize)]
+struct Typo {
+    wrong: String,
+    right: String,
+}
+
+pub struct Proofreader {
+    typos: Vec<(String, String)>,
+    /// A wrong measure word before a noun, the noun, and the right one
+    measure_words: Vec<(Regex, &'static str, &'static str)>,
+}
+
+impl Proofreader {
+    /// The built-in dictionary plus `dict`'s `[[typo]]` entries.
+    pub fn new(dict: Option<&Path>) -> Result<Self> {
+        let mut typos: Vec<(String, String)> = TYPOS
+            .iter()
+            .map(|&(w, r)| (w.to_string(), r.to_string()))
+            .collect();
+        if let Some(path) = dict {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Read proofreading dictionary {}", path.display()))?;
+            let file: DictFile = toml::from_str(&text)
+                .with_context(|| format!("Parse proofreading dictionary {}", path.display()))?;
+            typos.extend(
+                file.typo
+                    .into_iter()
+                    .filter(|t| !t.wrong.is_empty())
+                    .map(|t| (t.wrong, t.right)),
+            );
+        }
+        let measure_words = MEASURE_WORDS
+            .iter()
+            .map(|&(noun, right, wrong)| {
+                let re = Regex::new(&format!("[{COUNTERS}][{wrong}]{noun}"))?;
+                Ok((re, noun, right))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            typos,
+            measure_words,
+        })
+    }
+
+    /// Warnings for the translated lines, numbered by cue.
+    pub fn check(&self, segments: &[WhisperSegment], zh_lines: &[String]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for (i, (seg, zh)) in segments.iter().zip(zh_lines).enumerate() {
+            let mut report = |message: String| {
+                issues.push(Issue {
+                    cue: i + 1,
+                    start: seg.start,
+                    severity: Severity::Warning,
+                    message,
+                })
+            };
+            for (wrong, right) in self.typos.iter().filter(|(w, _)| zh.contains(w.as_str())) {
+                report(format!("typo? {wrong} → {right}"));
+            }
+            for &(ref re, noun, right) in &self.measure_words {
+                let compound = |end: usize| {
+                    let rest = &zh[end - noun.len()..];
+                    COMPOUNDS.iter().any(|c| rest.starts_with(c))
+                };
+                for m in re.find_iter(zh).filter(|m| !compound(m.end())) {
+                    report(format!("measure word? {} → {right}", m.as_str()));
+                }
+            }
+        }
+        // The form used less often is the odd one out
+        for &(a, b) in VARIANTS {
+            let count = |form: &str| zh_lines.iter().filter(|l| l.contains(form)).count();
+            let (na, nb) = (count(a), count(b));
+            if na == 0 || nb == 0 {
+                continue;
+            }
+            let (odd, usual, n) = if na < nb { (a, b, nb) } else { (b, a, na) };
+            for i in (0..zh_lines.len()).filter(|&i| zh_lines[i].contains(odd)) {
+                issues.push(Issue {
+                    cue: i + 1,
+                    start: segments.get(i).map_or(0.0, |s| s.start),
+                    severity: Severity::Warning,
+                    message: format!("mixed variants: {odd} here, {usual} in {n} other cue(s)"),
+                });
+            }
+        }
+        issues.sort_by_key(|issue| issue.cue);
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let proofreader = Proofreader::new(None).unwrap();
+        let lines: Vec<String> = [
+            "我因該開兩隻車去",
+            "台灣很熱",
+            "台灣的夏天",
+            "去臺灣吧",
+            "買了一張電影票和一張書桌",
+            "這張書單裡有一隻河馬",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let segs: Vec<WhisperSegment> = (0..lines.len())
+            .map(|i| WhisperSegment {
+                start: i as f64,
+                ..Default::default()
+            })
+            .collect();
+        let messages: Vec<(usize, String)> = proofreader
+            .check(&segs, &lines)
+            .into_iter()
+            .map(|issue| (issue.cue, issue.message))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (1, "typo? 因該 → 應該".to_string()),
+                (1, "measure word? 兩隻車 → 輛".to_string()),
+                (
+                    4,
+                    "mixed variants: 臺灣 here, 台灣 in 2 other cue(s)".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! `--punctuate`: an optional pass between transcription and translation
+//! in which the model re-punctuates the raw Whisper lines and fixes obvious
+//! mis-hearings. Lines map one to one, so timing is untouched; a line the
+//! model rewrote beyond recognition is kept as transcribed.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::openai::{ChatRequest, OpenAiClient};
+use crate::{text_similarity, WhisperSegment};
+
+/// Lines per request.
+const BATCH_LINES: usize = 40;
+/// Minimum similarity of the bare text (punctuation removed) for a cleaned
+/// line to replace the original.
+const MIN_SIMILARITY: f64 = 0.6;
+
+const SYSTEM: &str = "You clean up Japanese speech-recognition transcripts before they are translated. For each line, add natural Japanese punctuation (、。！？「」) and fix obvious mis-recognitions such as wrong kanji for a homophone, only when you are confident. Keep the speaker's wording, including fillers and casual speech. Never translate, summarize, merge, split, or reorder lines.";
+
+/// Replace each segment's text with its cleaned-up form; returns how many
+/// lines changed.
+pub async fn clean(
+    segments: &mut [WhisperSegment],
+    client: &OpenAiClient,
+    model: &str,
+) -> Result<usize> {
+    let mut changed = 0;
+    for batch in segments.chunks_mut(BATCH_LINES) {
+        let lines: Vec<String> = batch.iter().map(|s| s.text.trim().to_string()).collect();
+        let cleaned = clean_lines(&lines, client, model).await?;
+        for (seg, (old, new)) in batch.iter_mut().zip(lines.iter().zip(cleaned)) {
+            if accept(old, &new) && new != *old {
+                seg.text = new;
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+async fn clean_lines(lines: &[String], client: &OpenAiClient, model: &str) -> Result<Vec<String>> {
+    let user = json!({
+        "instruction": "Return strict JSON {\"lines\": string[]} with exactly one cleaned line per input line, in order.",
+        "lines": lines,
+    });
+    let req = ChatRequest::new(model)
+        .system(SYSTEM)
+        .user(user.to_string())
+        .json_object();
+    let content = client
+        .chat(&req)
+        .await
+        .context("OpenAI punctuation request failed")?;
+    #[derive(Deserialize)]
+    struct Lines {
+        lines: Vec<String>,
+    }
+    let parsed: Lines = serde_json::from_str(content.trim()).context("Parse punctuation JSON")?;
+    if parsed.lines.len() != lines.len() {
+        return Err(anyhow!(
+            "Punctuation pass returned {} lines for {}",
+            parsed.lines.len(),
+            lines.len()
+        ));
+    }
+    Ok(parsed.lines)
+}
+
+/// Whether `new` is still the same words as `old`, give or take punctuation
+/// and a few corrected characters.
+fn accept(old: &str, new: &str) -> bool {
+    let bare = |s: &str| -> String { s.chars().filter(|c| c.is_alphanumeric()).collect() };
+    let (old, new) = (bare(old), bare(new));
+    !new.is_empty() && text_similarity(&old, &new) >= MIN_SIMILARITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_keeps_wording() {
+        assert!(accept(
+            "今日は暑いですねそうですね",
+            "今日は暑いですね。そうですね。"
+        ));
+        // One corrected homophone
+        assert!(accept("会議に以降します", "会議に移行します。"));
+        assert!(!accept("はい", ""));
+        assert!(!accept("今日は暑いですね", "天気の話をしている。"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Checks on translated lines that catch model failures the JSON shape does
+//! not: output that is still Japanese, and output far longer or shorter than
+//! its source (added or dropped content).
+
+/// Kana share of the letters above which a line counts as untranslated.
+const MAX_KANA_SHARE: f64 = 0.3;
+/// Sources lighter than this are too short for a meaningful ratio.
+const MIN_SOURCE_WEIGHT: f64 = 4.0;
+/// Translation weight over source weight outside this range is flagged.
+const MIN_RATIO: f64 = 0.25;
+const MAX_RATIO: f64 = 2.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthFlag {
+    TooLong,
+    TooShort,
+}
+
+impl LengthFlag {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LengthFlag::TooLong => "too_long",
+            LengthFlag::TooShort => "too_short",
+        }
+    }
+}
+
+/// Whether `zh` still reads as Japanese: at least two kana, making up a good
+/// part of its letters. Kanji-only lines cannot be told apart from Chinese
+/// and pass.
+pub fn untranslated(zh: &str) -> bool {
+    let kana = zh.chars().filter(|c| is_kana(*c)).count();
+    let letters = zh.chars().filter(|c| c.is_alphanumeric()).count();
+    kana >= 2 && kana as f64 >= letters as f64 * MAX_KANA_SHARE
+}
+
+/// Whether `zh` is wildly longer or shorter than `ja`. Kana and Latin
+/// letters count half, since Chinese says the same in fewer of them.
+pub fn length_outlier(ja: &str, zh: &str) -> Option<LengthFlag> {
+    let (source, target) = (weight(ja), weight(zh));
+    if source < MIN_SOURCE_WEIGHT {
+        return None;
+    }
+    let ratio = target / source;
+    if ratio > MAX_RATIO {
+        Some(LengthFlag::TooLong)
+    } else if ratio < MIN_RATIO {
+        Some(LengthFlag::TooShort)
+    } else {
+        None
+    }
+}
+
+fn weight(s: &str) -> f64 {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| if is_kana(c) || c.is_ascii() { 0.5 } else { 1.0 })
+        .sum()
+}
+
+/// Hiragana and katakana, without the long-vowel mark and middle dot that
+/// Chinese text borrows.
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untranslated() {
+        assert!(untranslated("そうですね、行きましょう"));
+        assert!(untranslated("我知道了ですよね"));
+        assert!(!untranslated("好，我們走吧"));
+        // A stylized の or a kanji-only line is not an echo
+        assert!(!untranslated("東京の夜景真漂亮"));
+        assert!(!untranslated("東京"));
+        assert!(!untranslated("ー・"));
+    }
+
+    #[test]
+    fn test_length_outlier() {
+        assert_eq!(length_outlier("ありがとうございます", "謝謝"), None);
+        assert_eq!(
+            length_outlier("はい", "是的，我完全同意你剛才說的每一句話"),
+            None
+        );
+        assert_eq!(
+            length_outlier(
+                "明日は雨が降る",
+                "明天會下雨，所以我們應該取消野餐並改在室內舉辦派對"
+            ),
+            Some(LengthFlag::TooLong)
+        );
+        assert_eq!(
+            length_outlier("昨日の会議で決まった予算案について説明します", "好"),
+            Some(LengthFlag::TooShort)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Adaptive rate limiting: every OpenAI response's `x-ratelimit-*` headers
+//! update a shared per-model budget of requests and tokens, and callers wait
+//! for the reset window when the budget runs dry instead of hitting 429s.
+
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Requests kept in reserve so concurrent callers do not overshoot together.
+const REQUEST_RESERVE: u64 = 1;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Window {
+    remaining: Option<u64>,
+    reset_at: Option<Instant>,
+}
+
+impl Window {
+    /// How long to wait before `need` units fit, if the server said they don't.
+    fn wait_for(&self, need: u64, now: Instant) -> Option<Duration> {
+        match (self.remaining, self.reset_at) {
+            (Some(left), Some(at)) if left < need && at > now => Some(at - now),
+            _ => None,
+        }
+    }
+
+    fn spend(&mut self, n: u64, now: Instant) {
+        if self.reset_at.is_some_and(|at| at <= now) {
+            // Window rolled over; unknown until the next response says otherwise
+            *self = Window::default();
+        }
+        if let Some(left) = self.remaining.as_mut() {
+            *left = left.saturating_sub(n);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Budget {
+    requests: Window,
+    tokens: Window,
+}
+
+static BUDGETS: LazyLock<Mutex<HashMap<String, Budget>>> = LazyLock::new(Default::default);
+
+/// Wait until `model` has budget for one request of about `est_tokens`, then
+/// reserve it.
+pub async fn acquire(model: &str, est_tokens: u64) {
+    loop {
+        let wait = {
+            let mut budgets = BUDGETS.lock().unwrap();
+            let b = budgets.entry(model.to_string()).or_default();
+            let now = Instant::now();
+            let wait = b
+                .requests
+                .wait_for(1 + REQUEST_RESERVE, now)
+                .max(b.tokens.wait_for(est_tokens, now));
+            if wait.is_none() {
+                b.requests.spend(1, now);
+                b.tokens.spend(est_tokens, now);
+            }
+            wait
+        };
+        match wait {
+            None => return,
+            Some(d) => {
+                eprintln!(
+                    "Rate limit budget for {} is low; pausing {}ms until it resets",
+                    model,
+                    d.as_millis()
+                );
+                sleep(d).await;
+                // Budget is stale once the window passes
+                let mut budgets = BUDGETS.lock().unwrap();
+                if let Some(b) = budgets.get_mut(model) {
+                    let now = Instant::now();
+                    b.requests.spend(0, now);
+                    b.tokens.spend(0, now);
+                }
+            }
+        }
+    }
+}
+
+/// Update `model`'s budget from a response's rate limit headers.
+pub fn observe(model: &str, headers: &HeaderMap) {
+    let now = Instant::now();
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let window = |kind: &str| Window {
+        remaining: header(&format!("x-ratelimit-remaining-{kind}")).and_then(|v| v.parse().ok()),
+        reset_at: header(&format!("x-ratelimit-reset-{kind}"))
+            .and_then(parse_reset)
+            .map(|d| now + d),
+    };
+    let (requests, tokens) = (window("requests"), window("tokens"));
+    if requests.remaining.is_none() && tokens.remaining.is_none() {
+        return;
+    }
+    let mut budgets = BUDGETS.lock().unwrap();
+    let b = budgets.entry(model.to_string()).or_default();
+    if requests.remaining.is_some() {
+        b.requests = requests;
+    }
+    if tokens.remaining.is_some() {
+        b.tokens = tokens;
+    }
+}
+
+/// Rough token count for budgeting: one per character overestimates ASCII
+/// and is close for Japanese/Chinese, which dominate these requests.
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count() as u64
+}
+
+/// Parse OpenAI reset durations such as `1s`, `6m0s`, `59.5s`, `20ms`, `1h2m`.
+fn parse_reset(s: &str) -> Option<Duration> {
+    let mut total = 0.0f64;
+    let mut num = String::new();
+    let mut chars = s.trim().chars().peekable();
+    let mut seen = false;
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            num.push(c);
+            continue;
+        }
+        let value: f64 = num.parse().ok()?;
+        num.clear();
+        let unit = match c {
+            'h' => 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                0.001
+            }
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return None,
+        };
+        total += value * unit;
+        seen = true;
+    }
+    if !num.is_empty() {
+        // Bare number: seconds
+        total += num.parse::<f64>().ok()?;
+        seen = true;
+    }
+    seen.then(|| Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_parse_reset() {
+        assert_eq!(parse_reset("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_reset("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_reset("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_reset("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_reset("soon"), None);
+    }
+
+    #[test]
+    fn test_observe_sets_wait() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("0"),
+        );
+        headers.insert("x-ratelimit-reset-requests", HeaderValue::from_static("2s"));
+        headers.insert(
+            "x-ratelimit-remaining-tokens",
+            HeaderValue::from_static("5000"),
+        );
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("1s"));
+        observe("test-model-observe", &headers);
+        let budgets = BUDGETS.lock().unwrap();
+        let b = &budgets["test-model-observe"];
+        let now = Instant::now();
+        assert!(b.requests.wait_for(2, now).unwrap() > Duration::from_secs(1));
+        assert!(b.tokens.wait_for(1000, now).is_none());
+        assert!(b.tokens.wait_for(6000, now).is_some());
+    }
+}
@@ -0,0 +1,143 @@
+//! User find/replace rules (`--replace-rules rules.toml`): ordered regex
+//! substitutions applied to translations, and optionally to the Japanese
+//! line, right before subtitles are written.
+//!
+//! ```toml
+//! [[rule]]
+//! pattern = "(\\p{Han}+)桑"
+//! replace = "${1}先生"
+//!
+//! [[rule]]
+//! pattern = "ポケモン"
+//! replace = "Pokémon"
+//! target = "both"   # zh (default) | ja | both
+//! ```
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Target {
+    #[default]
+    Zh,
+    Ja,
+    Both,
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+struct RawRule {
+    pattern: String,
+    replace: String,
+    #[serde(default)]
+    target: Target,
+}
+
+struct Rule {
+    re: Regex,
+    replace: String,
+    target: Target,
+}
+
+#[derive(Default)]
+pub struct ReplaceRules {
+    rules: Vec<Rule>,
+}
+
+impl ReplaceRules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Read replace rules {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Parse replace rules {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(text)?;
+        let rules = file
+            .rule
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| {
+                Ok(Rule {
+                    re: Regex::new(&r.pattern)
+                        .with_context(|| format!("Rule #{}: invalid pattern", i + 1))?,
+                    replace: r.replace,
+                    target: r.target,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Apply the rules targeting translations, in file order.
+    pub fn zh(&self, line: &str) -> String {
+        self.apply(line, Target::Zh)
+    }
+
+    /// Apply the rules targeting the Japanese line, in file order.
+    pub fn ja(&self, line: &str) -> String {
+        self.apply(line, Target::Ja)
+    }
+
+    fn apply(&self, line: &str, side: Target) -> String {
+        let mut out = line.to_string();
+        for r in self
+            .rules
+            .iter()
+            .filter(|r| r.target == side || r.target == Target::Both)
+        {
+            out = r.re.replace_all(&out, r.replace.as_str()).into_owned();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_apply_in_order_per_target() {
+        let rules = ReplaceRules::parse(
+            r#"
+[[rule]]
+pattern = "(\\p{Han}+)桑"
+replace = "${1}先生"
+
+[[rule]]
+pattern = "先生"
+replace = "老師"
+target = "ja"
+
+[[rule]]
+pattern = "ピカチュウ|皮卡丘"
+replace = "Pikachu"
+target = "both"
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules.zh("山田桑和皮卡丘"), "山田先生和Pikachu");
+        assert_eq!(rules.ja("先生とピカチュウ"), "老師とPikachu");
+    }
+
+    #[test]
+    fn test_invalid_pattern_names_rule() {
+        let err = ReplaceRules::parse("[[rule]]\npattern = \"(\"\nreplace = \"\"\n")
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("Rule #1"));
+    }
+}
@@ -0,0 +1,251 @@
+//! `--html-report`: one self-contained HTML page per run (inline CSS and
+//! SVG, no scripts or external assets) with every cue's Japanese and Chinese
+//! text, timing, reading speed, Whisper confidence, and the flags raised by
+//! the QA passes, so reviewers can audit a run in a browser.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::formats::xml_escape;
+use crate::lint::Issue;
+use crate::{format_srt_time, qc, zh_tw, WhisperSegment};
+
+pub struct Report<'a> {
+    pub title: &'a str,
+    pub segments: &'a [WhisperSegment],
+    pub ja_lines: &'a [String],
+    pub zh_lines: &'a [String],
+    pub review_threshold: f64,
+    /// `--style-guide`, `--terms`, and `--proofread` issues, numbered by cue
+    pub issues: &'a [Issue],
+}
+
+struct Row {
+    cps: f64,
+    flags: Vec<String>,
+}
+
+const CPS_BUCKETS: [f64; 6] = [3.0, 6.0, 9.0, 12.0, 15.0, 20.0];
+const LOGPROB_BUCKETS: [f64; 5] = [-1.5, -1.0, -0.6, -0.3, -0.1];
+
+pub fn write(path: &Path, report: &Report) -> Result<()> {
+    std::fs::write(path, html(report)).with_context(|| format!("Write {}", path.display()))
+}
+
+fn rows(report: &Report) -> Vec<Row> {
+    report
+        .segments
+        .iter()
+        .zip(report.ja_lines.iter().zip(report.zh_lines))
+        .enumerate()
+        .map(|(i, (seg, (ja, zh)))| {
+            let mut flags = Vec::new();
+            if seg.is_low_confidence(report.review_threshold) {
+                flags.push("low confidence".to_string());
+            }
+            if let Some(flag) = qc::length_outlier(ja, zh) {
+                flags.push(flag.as_str().replace('_', " "));
+            }
+            if qc::untranslated(zh) {
+                flags.push("untranslated".to_string());
+            }
+            let simplified = zh_tw::simplified_chars(zh);
+            if !simplified.is_empty() {
+                let pairs: Vec<String> =
+                    simplified.iter().map(|(s, t)| format!("{s}→{t}")).collect();
+                flags.push(format!("simplified: {}", pairs.join(" ")));
+            }
+            flags.extend(
+                report
+                    .issues
+                    .iter()
+                    .filter(|issue| issue.cue == i + 1)
+                    .map(|issue| issue.message.clone()),
+            );
+            let chars = zh.chars().filter(|c| !c.is_whitespace()).count();
+            let duration = (seg.end - seg.start).max(0.001);
+            Row {
+                cps: chars as f64 / duration,
+                flags,
+            }
+        })
+        .collect()
+}
+
+fn html(report: &Report) -> String {
+    let rows = rows(report);
+    let flagged = rows.iter().filter(|r| !r.flags.is_empty()).count();
+    let low_confidence = report
+        .segments
+        .iter()
+        .filter(|s| s.is_low_confidence(report.review_threshold))
+        .count();
+    let duration = report.segments.last().map_or(0.0, |s| s.end);
+    let max_cps = rows.iter().map(|r| r.cps).fold(0.0, f64::max);
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        r#"<!DOCTYPE html>
+<html lang="zh-Hant">
+<head>
+<meta charset="utf-8">
+<title>{title} — subtitle QC</title>
+<style>
+body {{ font-family: system-ui, "Noto Sans CJK TC", sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; vertical-align: top; }}
+th {{ background: #f4f4f4; position: sticky; top: 0; }}
+td.num {{ text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }}
+tr.flagged {{ background: #fff4e0; }}
+.flag {{ display: inline-block; background: #e8590c; color: #fff; border-radius: 3px; padding: 0 4px; margin: 1px; font-size: 85%; }}
+.charts {{ display: flex; gap: 2em; flex-wrap: wrap; }}
+.summary td {{ border: none; padding: 2px 12px 2px 0; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table class="summary">
+<tr><td>Cues</td><td>{cues}</td></tr>
+<tr><td>Duration</td><td>{duration}</td></tr>
+<tr><td>Flagged cues</td><td>{flagged}</td></tr>
+<tr><td>Low confidence (avg_logprob &lt; {threshold})</td><td>{low_confidence}</td></tr>
+<tr><td>Highest reading speed</td><td>{max_cps:.1} characters/s</td></tr>
+</table>
+<div class="charts">
+"#,
+        title = escape(report.title),
+        cues = rows.len(),
+        duration = format_srt_time(duration),
+        threshold = report.review_threshold,
+    );
+    out.push_str(&bar_chart(
+        "Reading speed (characters/s)",
+        &histogram(rows.iter().map(|r| r.cps), &CPS_BUCKETS),
+    ));
+    out.push_str(&bar_chart(
+        "Whisper confidence (avg_logprob)",
+        &histogram(
+            report.segments.iter().filter_map(|s| s.avg_logprob),
+            &LOGPROB_BUCKETS,
+        ),
+    ));
+    out.push_str(
+        "</div>\n<h2>Cues</h2>\n<table>\n<tr><th>#</th><th>Start</th><th>End</th><th>Dur.</th><th>CPS</th><th>avg_logprob</th><th>no_speech</th><th>Japanese</th><th>Chinese</th><th>Flags</th></tr>\n",
+    );
+    for (i, (row, seg)) in rows.iter().zip(report.segments).enumerate() {
+        let score = |v: Option<f64>| v.map_or(String::new(), |v| format!("{v:.2}"));
+        let flags: String = row
+            .flags
+            .iter()
+            .map(|f| format!(r#"<span class="flag">{}</span>"#, escape(f)))
+            .collect();
+        let _ = writeln!(
+            out,
+            r#"<tr{class}><td class="num">{n}</td><td class="num">{start}</td><td class="num">{end}</td><td class="num">{dur:.2}</td><td class="num">{cps:.1}</td><td class="num">{logprob}</td><td class="num">{no_speech}</td><td>{ja}</td><td>{zh}</td><td>{flags}</td></tr>"#,
+            class = if row.flags.is_empty() {
+                ""
+            } else {
+                r#" class="flagged""#
+            },
+            n = i + 1,
+            start = format_srt_time(seg.start),
+            end = format_srt_time(seg.end),
+            dur = seg.end - seg.start,
+            cps = row.cps,
+            logprob = score(seg.avg_logprob),
+            no_speech = score(seg.no_speech_prob),
+            ja = escape(&report.ja_lines[i]),
+            zh = escape(&report.zh_lines[i]),
+        );
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Counts per bucket: below each bound in turn, then the rest.
+fn histogram(values: impl Iterator<Item = f64>, bounds: &[f64]) -> Vec<(String, usize)> {
+    let mut counts = vec![0; bounds.len() + 1];
+    for v in values {
+        counts[bounds.iter().position(|&b| v < b).unwrap_or(bounds.len())] += 1;
+    }
+    let mut labels = Vec::with_capacity(counts.len());
+    labels.push(format!("< {}", bounds[0]));
+    labels.extend(bounds.windows(2).map(|w| format!("{}–{}", w[0], w[1])));
+    labels.push(format!("≥ {}", bounds[bounds.len() - 1]));
+    labels.into_iter().zip(counts).collect()
+}
+
+/// A horizontal bar chart as inline SVG.
+fn bar_chart(title: &str, bars: &[(String, usize)]) -> String {
+    const ROW: usize = 22;
+    const WIDTH: f64 = 240.0;
+    let max = bars.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1) as f64;
+    let height = bars.len() * ROW + 4;
+    let mut svg = format!(
+        "<figure>\n<figcaption>{}</figcaption>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\" height=\"{height}\" font-size=\"12\">\n",
+        escape(title)
+    );
+    for (i, (label, n)) in bars.iter().enumerate() {
+        let y = i * ROW;
+        let w = *n as f64 / max * WIDTH;
+        let _ = writeln!(
+            svg,
+            r##"<text x="86" y="{ty}" text-anchor="end">{label}</text><rect x="92" y="{y}" width="{w:.1}" height="16" fill="#4c6ef5"/><text x="{tx:.1}" y="{ty}">{n}</text>"##,
+            ty = y + 13,
+            label = escape(label),
+            tx = 96.0 + w,
+        );
+    }
+    svg.push_str("</svg>\n</figure>\n");
+    svg
+}
+
+/// HTML-escaped, with line breaks kept as `<br>`.
+fn escape(text: &str) -> String {
+    xml_escape(text).replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_report_html() {
+        let segments = vec![
+            WhisperSegment {
+                avg_logprob: Some(-1.4),
+                ..seg(1.0, 3.0, "")
+            },
+            WhisperSegment {
+                avg_logprob: Some(-0.2),
+                ..seg(4.0, 6.0, "")
+            },
+        ];
+        let ja = vec!["<えっ>".to_string(), "ありがとう".to_string()];
+        let zh = vec!["咦？".to_string(), "谢谢".to_string()];
+        let page = html(&Report {
+            title: "ep01 & ep02",
+            segments: &segments,
+            ja_lines: &ja,
+            zh_lines: &zh,
+            review_threshold: -1.0,
+            issues: &[],
+        });
+        assert!(page.contains("<h1>ep01 &amp; ep02</h1>"));
+        assert!(page.contains("&lt;えっ&gt;"));
+        assert!(page.contains(r#"<span class="flag">low confidence</span>"#));
+        assert!(page.contains("simplified: 谢→謝"));
+        assert_eq!(page.matches(r#"<tr class="flagged">"#).count(), 2);
+        assert_eq!(
+            histogram([1.0, 4.0, 25.0].into_iter(), &[3.0, 6.0]),
+            [
+                ("< 3".to_string(), 1),
+                ("3–6".to_string(), 1),
+                ("≥ 6".to_string(), 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Sentence re-segmentation for dialog: Whisper often cuts a sentence in
+//! the middle, and a translator given half a sentence guesses at the rest.
+//! Segments are cut at 。！？ and the pieces regrouped into one cue per
+//! sentence; each piece's time is its share of the segment by character
+//! count. A pause, or a sentence grown too long to read as one cue, also
+//! ends a cue so that run-on transcripts still get split.
+//!
+//! `--sentence-groups` keeps Whisper's cues instead: consecutive segments
+//! are grouped into sentences only for translation, and each translated
+//! sentence is cut back across its segments in proportion to their length.
+
+use std::ops::Range;
+
+use crate::WhisperSegment;
+
+/// A silence (seconds) longer than this always ends a cue.
+const MAX_GAP: f64 = 1.0;
+/// Merged cues stop growing at this length...
+const MAX_SECONDS: f64 = 8.0;
+/// ...or this many characters.
+const MAX_CHARS: usize = 48;
+/// A translation group is closed once it has this many characters.
+const MAX_GROUP_CHARS: usize = 120;
+/// How far (characters) a cut may move from its proportional position to
+/// land after punctuation.
+const CUT_SLACK: usize = 3;
+
+/// Closing brackets and quotes that stay with the sentence they end.
+fn is_closer(c: char) -> bool {
+    matches!(c, '」' | '』' | '）' | ')' | '】' | '”' | '’')
+}
+
+fn is_terminal(c: char) -> bool {
+    matches!(c, '。' | '！' | '？' | '!' | '?' | '…')
+}
+
+struct Piece {
+    start: f64,
+    end: f64,
+    text: String,
+    /// Ends with sentence punctuation
+    terminal: bool,
+    avg_logprob: Option<f64>,
+    no_speech_prob: Option<f64>,
+}
+
+pub fn by_sentence(segments: Vec<WhisperSegment>) -> Vec<WhisperSegment> {
+    let pieces: Vec<Piece> = segments.iter().flat_map(split_segment).collect();
+    let mut out: Vec<WhisperSegment> = Vec::new();
+    let mut open = false;
+    for p in pieces {
+        let extend = open
+            && out.last().is_some_and(|last| {
+                p.start - last.end <= MAX_GAP
+                    && p.end - last.start <= MAX_SECONDS
+                    && last.text.chars().count() + p.text.chars().count() <= MAX_CHARS
+            });
+        match out.last_mut() {
+            Some(last) if extend => {
+                last.end = p.end;
+                last.text.push_str(&p.text);
+                // The cue is as unsure as its least sure part
+                last.avg_logprob = min_opt(last.avg_logprob, p.avg_logprob);
+                last.no_speech_prob = max_opt(last.no_speech_prob, p.no_speech_prob);
+            }
+            _ => out.push(WhisperSegment {
+                id: None,
+                start: p.start,
+                end: p.end,
+                text: p.text,
+                avg_logprob: p.avg_logprob,
+                no_speech_prob: p.no_speech_prob,
+            }),
+        }
+        open = !p.terminal;
+    }
+    out
+}
+
+/// Cut a segment after each sentence end, sharing its span by length.
+fn split_segment(seg: &WhisperSegment) -> Vec<Piece> {
+    let text = seg.text.trim();
+    let mut parts: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        cur.push(c);
+        if is_terminal(c) {
+            while let Some(&n) = chars.peek() {
+                if is_terminal(n) || is_closer(n) {
+                    cur.push(n);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            parts.push(std::mem::take(&mut cur).trim().to_string());
+        }
+    }
+    if !cur.trim().is_empty() {
+        parts.push(cur.trim().to_string());
+    }
+    let total: usize = parts.iter().map(|p| p.chars().count()).sum();
+    let span = seg.end - seg.start;
+    let mut t = seg.start;
+    parts.retain(|p| !p.is_empty());
+    let last = parts.len().saturating_sub(1);
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let end = if i == last {
+                seg.end
+            } else {
+                t + span * p.chars().count() as f64 / total.max(1) as f64
+            };
+            let piece = Piece {
+                start: t,
+                end,
+                terminal: p
+                    .chars()
+                    .rev()
+                    .find(|c| !is_closer(*c))
+                    .is_some_and(is_terminal),
+                text: p,
+                avg_logprob: seg.avg_logprob,
+                no_speech_prob: seg.no_speech_prob,
+            };
+            t = end;
+            piece
+        })
+        .collect()
+}
+
+/// Runs of consecutive segments that make up one sentence, for translation.
+pub fn sentence_groups(segments: &[WhisperSegment]) -> Vec<Range<usize>> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (i, seg) in segments.iter().enumerate() {
+        chars += seg.text.trim().chars().count();
+        let ends = ends_sentence(&seg.text)
+            || segments
+                .get(i + 1)
+                .is_none_or(|n| n.start - seg.end > MAX_GAP)
+            || chars >= MAX_GROUP_CHARS;
+        if ends {
+            groups.push(start..i + 1);
+            start = i + 1;
+            chars = 0;
+        }
+    }
+    groups
+}
+
+/// Cut `text` into `weights.len()` parts sized in proportion to `weights`,
+/// moving each cut a little to fall after punctuation where there is some.
+pub fn redistribute(text: &str, weights: &[usize]) -> Vec<String> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    let Some(last) = weights.len().checked_sub(1) else {
+        return Vec::new();
+    };
+    let total = weights.iter().sum::<usize>().max(1);
+    let mut out = Vec::with_capacity(weights.len());
+    let (mut start, mut acc) = (0, 0);
+    for (k, w) in weights[..last].iter().enumerate() {
+        acc += w;
+        // Leave at least a character for each later part when there are enough
+        let lo = (start + 1).min(chars.len());
+        let hi = chars.len().saturating_sub(last - k).max(lo);
+        let ideal = ((chars.len() * acc + total / 2) / total).clamp(lo, hi);
+        let cut = (0..=CUT_SLACK)
+            .flat_map(|d| [ideal.checked_sub(d), Some(ideal + d)])
+            .flatten()
+            .find(|&p| p > 0 && (lo..=hi).contains(&p) && is_break(chars[p - 1]))
+            .unwrap_or(ideal);
+        out.push(
+            chars[start..cut]
+                .iter()
+                .collect::<String>()
+                .trim()
+                .to_string(),
+        );
+        start = cut;
+    }
+    out.push(chars[start..].iter().collect::<String>().trim().to_string());
+    out
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.trim()
+        .chars()
+        .rev()
+        .find(|c| !is_closer(*c))
+        .is_some_and(is_terminal)
+}
+
+/// Characters a translated line can be cut after.
+fn is_break(c: char) -> bool {
+    is_terminal(c) || is_closer(c) || matches!(c, '，' | '、' | ',' | '；' | '：' | ' ')
+}
+
+fn min_opt(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn max_opt(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_merges_split_sentences_and_splits_joined_ones() {
+        let out = by_sentence(vec![
+            seg(0.0, 2.0, "今日は天気が"),
+            seg(2.0, 3.0, "いいですね。"),
+            seg(3.0, 6.0, "散歩しよう！「うん」と言った。はい"),
+        ]);
+        let texts: Vec<&str> = out.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "今日は天気がいいですね。",
+                "散歩しよう！",
+                "「うん」と言った。",
+                "はい"
+            ]
+        );
+        assert_eq!((out[0].start, out[0].end), (0.0, 3.0));
+        assert_eq!(out[1].start, 3.0);
+        assert_eq!(out[3].end, 6.0);
+        // Times stay contiguous inside the split segment
+        assert_eq!(out[1].end, out[2].start);
+    }
+
+    #[test]
+    fn test_pauses_and_length_end_a_cue() {
+        let out = by_sentence(vec![
+            seg(0.0, 1.0, "それで"),
+            seg(3.0, 4.0, "彼は"),
+            seg(4.0, 7.0, "駅まで走って"),
+            seg(7.0, 12.0, "電車に乗った"),
+        ]);
+        let texts: Vec<&str> = out.iter().map(|s| s.text.as_str()).collect();
+        // A 2 s pause splits; 12 s is too long for one cue
+        assert_eq!(texts, ["それで", "彼は駅まで走って", "電車に乗った"]);
+    }
+
+    #[test]
+    fn test_sentence_groups_and_redistribute() {
+        let segs = [
+            seg(0.0, 2.0, "今日は天気が"),
+            seg(2.0, 3.0, "いいですね。"),
+            seg(3.0, 4.0, "散歩しよう"),
+            seg(6.0, 7.0, "はい"),
+        ];
+        assert_eq!(sentence_groups(&segs), [0..2, 2..3, 3..4]);
+        assert_eq!(
+            redistribute("今天天氣真好，對吧。", &[6, 6]),
+            ["今天天氣真好，", "對吧。"]
+        );
+        // Without punctuation nearby the cut is proportional
+        assert_eq!(
+            redistribute("一二三四五六七八九十", &[1, 1]),
+            ["一二三四五", "六七八九十"]
+        );
+        assert_eq!(redistribute("好", &[2, 2, 2]), ["好", "", ""]);
+    }
+}
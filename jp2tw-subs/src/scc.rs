@@ -0,0 +1,289 @@
+//! `--format scc`: Scenarist SCC (CEA-608, line 21) caption files for
+//! broadcast and OTT deliveries that still require them. CEA-608 only
+//! carries a Latin character set (ASCII plus the special and extended
+//! Western European characters), so Chinese and Japanese cannot be written;
+//! SCC files are made for the Latin-script `--target-lang` languages.
+//! Characters without a 608 code are written as `?` and counted.
+//!
+//! Cues are pop-on captions on CC1: loaded into non-displayed memory ahead
+//! of time and flipped on screen at the cue's frame, at most four rows of
+//! 32 columns, centered at the bottom. Timecodes are 29.97 drop-frame.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::{wrap, WhisperSegment};
+
+const COLUMNS: usize = 32;
+const MAX_ROWS: usize = 4;
+
+/// Control codes (channel 1), without parity
+const RCL: [u8; 2] = [0x14, 0x20];
+const ENM: [u8; 2] = [0x14, 0x2E];
+const EDM: [u8; 2] = [0x14, 0x2C];
+const EOC: [u8; 2] = [0x14, 0x2F];
+
+/// Basic characters that differ from ASCII, by code
+const BASIC: [(u8, char); 10] = [
+    (0x2A, 'á'),
+    (0x5C, 'é'),
+    (0x5E, 'í'),
+    (0x5F, 'ó'),
+    (0x60, 'ú'),
+    (0x7B, 'ç'),
+    (0x7C, '÷'),
+    (0x7D, 'Ñ'),
+    (0x7E, 'ñ'),
+    (0x7F, '█'),
+];
+/// Special characters: 0x11 followed by 0x30 + position
+const SPECIAL: &str = "®°½¿™¢£♪à\u{a0}èâêîôû";
+/// Extended characters: 0x12 / 0x13 followed by 0x20 + position
+const EXTENDED_12: &str = "ÁÉÓÚÜü‘¡*’—©℠•“”ÀÂÇÈÊËëÎÏïÔÙùÛ«»";
+const EXTENDED_13: &str = "ÃãÍÌìÒòÕõ{}\\^_|~ÄäÖöß¥¤¦ÅåØø┌┐└┘";
+
+/// A 608 byte with its odd parity bit.
+fn odd(b: u8) -> u8 {
+    if b.count_ones().is_multiple_of(2) {
+        b | 0x80
+    } else {
+        b
+    }
+}
+
+fn word(pair: [u8; 2]) -> String {
+    format!("{:02x}{:02x}", odd(pair[0]), odd(pair[1]))
+}
+
+/// How one character is sent.
+enum Code {
+    Basic(u8),
+    /// A two-byte code; extended characters follow a basic fallback the
+    /// decoder replaces
+    Pair([u8; 2], Option<u8>),
+}
+
+fn code(c: char) -> Option<Code> {
+    if let Some(&(b, _)) = BASIC.iter().find(|&&(_, ch)| ch == c) {
+        return Some(Code::Basic(b));
+    }
+    if c.is_ascii() && (' '..='~').contains(&c) && !BASIC.iter().any(|&(b, _)| b == c as u8) {
+        return Some(Code::Basic(c as u8));
+    }
+    if let Some(i) = SPECIAL.chars().position(|s| s == c) {
+        return Some(Code::Pair([0x11, 0x30 + i as u8], None));
+    }
+    let fallback = |c: char| match c {
+        'Á' | 'À' | 'Â' | 'Ã' | 'Ä' | 'Å' => b'A',
+        'É' | 'È' | 'Ê' | 'Ë' => b'E',
+        'Í' | 'Ì' | 'Î' | 'Ï' => b'I',
+        'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => b'O',
+        'Ú' | 'Ù' | 'Û' | 'Ü' => b'U',
+        'ü' | 'ù' => b'u',
+        'ë' => b'e',
+        'ï' | 'ì' => b'i',
+        'ò' | 'õ' | 'ö' | 'ø' => b'o',
+        'ã' | 'ä' | 'å' => b'a',
+        'Ç' => b'C',
+        'ß' => b's',
+        '‘' | '’' => b'\'',
+        '“' | '”' | '«' | '»' => b'"',
+        '—' => b'-',
+        _ => b'.',
+    };
+    for (first, table) in [(0x12, EXTENDED_12), (0x13, EXTENDED_13)] {
+        if let Some(i) = table.chars().position(|s| s == c) {
+            return Some(Code::Pair([first, 0x20 + i as u8], Some(fallback(c))));
+        }
+    }
+    None
+}
+
+/// Common typography 608 lacks, spelled with what it has.
+fn simplify(text: &str) -> String {
+    text.replace('…', "...")
+        .replace(['–', '‐'], "-")
+        .replace(['\u{3000}', '\t'], " ")
+}
+
+/// Share of `lines`' non-space characters SCC can carry.
+pub fn coverage(lines: &[String]) -> f64 {
+    let (mut total, mut known) = (0usize, 0usize);
+    for c in lines
+        .iter()
+        .flat_map(|l| simplify(l).chars().collect::<Vec<_>>())
+    {
+        if !c.is_whitespace() {
+            total += 1;
+            known += usize::from(code(c).is_some());
+        }
+    }
+    if total == 0 {
+        1.0
+    } else {
+        known as f64 / total as f64
+    }
+}
+
+/// The words that draw `rows` (bottom-aligned, centered) into a caption.
+fn draw(rows: &[String], unknown: &mut usize) -> Vec<String> {
+    let mut words = Vec::new();
+    let first_row = 16 - rows.len();
+    for (i, text) in rows.iter().enumerate() {
+        let row = first_row + i;
+        let indent = (COLUMNS - text.chars().count().min(COLUMNS)) / 2;
+        // Preamble address code: row pair byte, then the row's half and a
+        // 4-column indent
+        let (hi, half) = match row {
+            12 => (0x13, 0x40),
+            13 => (0x13, 0x60),
+            14 => (0x14, 0x40),
+            _ => (0x14, 0x60),
+        };
+        let pac = word([hi, half + 0x10 + 2 * (indent / 4) as u8]);
+        words.extend([pac.clone(), pac]);
+        if !indent.is_multiple_of(4) {
+            let tab = word([0x17, 0x20 + (indent % 4) as u8]);
+            words.extend([tab.clone(), tab]);
+        }
+        let mut pending: Option<u8> = None;
+        let flush = |pending: &mut Option<u8>, words: &mut Vec<String>| {
+            if let Some(b) = pending.take() {
+                words.push(word([b, 0x00]));
+            }
+        };
+        for c in text.chars() {
+            match code(c) {
+                Some(Code::Basic(b)) => match pending.take() {
+                    Some(a) => words.push(word([a, b])),
+                    None => pending = Some(b),
+                },
+                Some(Code::Pair(pair, fallback)) => {
+                    if let Some(f) = fallback {
+                        match pending.take() {
+                            Some(a) => words.push(word([a, f])),
+                            None => words.push(word([f, 0x00])),
+                        }
+                    }
+                    flush(&mut pending, &mut words);
+                    let w = word(pair);
+                    words.extend([w.clone(), w]);
+                }
+                None => {
+                    *unknown += 1;
+                    match pending.take() {
+                        Some(a) => words.push(word([a, b'?'])),
+                        None => pending = Some(b'?'),
+                    }
+                }
+            }
+        }
+        flush(&mut pending, &mut words);
+    }
+    words
+}
+
+/// `frames` at 29.97 as drop-frame timecode.
+fn timecode(frames: u64) -> String {
+    const PER_10_MIN: u64 = 17982;
+    const PER_MIN: u64 = 1798;
+    let (tens, rest) = (frames / PER_10_MIN, frames % PER_10_MIN);
+    let dropped = 18 * tens
+        + if rest > 1 {
+            2 * ((rest - 2) / PER_MIN)
+        } else {
+            0
+        };
+    let n = frames + dropped;
+    format!(
+        "{:02}:{:02}:{:02};{:02}",
+        n / 108_000,
+        n / 1800 % 60,
+        n / 30 % 60,
+        n % 30
+    )
+}
+
+fn frame(seconds: f64) -> u64 {
+    (seconds.max(0.0) * 30000.0 / 1001.0).round() as u64
+}
+
+/// SCC text for the cues; also how many characters had no 608 code.
+fn scc(segments: &[WhisperSegment], lines: &[String]) -> (String, usize) {
+    let mut unknown = 0;
+    // (ideal frame, words): loading, flipping on, and erasing each cue
+    let mut events: Vec<(u64, Vec<String>)> = Vec::new();
+    for (i, (seg, line)) in segments.iter().zip(lines).enumerate() {
+        let mut rows: Vec<String> = wrap::wrap(&simplify(line), COLUMNS)
+            .lines()
+            .map(|l| l.trim().chars().take(COLUMNS).collect())
+            .filter(|l: &String| !l.is_empty())
+            .collect();
+        if rows.is_empty() {
+            continue;
+        }
+        rows.truncate(MAX_ROWS);
+        let mut load = vec![word(RCL), word(RCL), word(ENM), word(ENM)];
+        load.extend(draw(&rows, &mut unknown));
+        let (start, end) = (frame(seg.start), frame(seg.end));
+        // Loaded only once the previous cue is on screen
+        let earliest = i.checked_sub(1).map_or(0, |p| frame(segments[p].start) + 1);
+        events.push((
+            start.saturating_sub(load.len() as u64 + 2).max(earliest),
+            load,
+        ));
+        events.push((start, vec![word(EOC), word(EOC)]));
+        // The next cue flipping on replaces this one
+        let next = segments.get(i + 1).map(|n| frame(n.start));
+        if next.is_none_or(|n| n > end + 1) {
+            events.push((end, vec![word(EDM), word(EDM)]));
+        }
+    }
+    events.sort_by_key(|(at, _)| *at);
+    let mut out = String::from("Scenarist_SCC V1.0\n");
+    let mut cursor = 0;
+    for (at, words) in events {
+        let at = at.max(cursor);
+        out.push_str(&format!("\n{}\t{}\n", timecode(at), words.join(" ")));
+        cursor = at + words.len() as u64;
+    }
+    (out, unknown)
+}
+
+/// Write `lines` as SCC; returns how many characters became `?`.
+pub fn write(path: &Path, segments: &[WhisperSegment], lines: &[String]) -> Result<usize> {
+    let (text, unknown) = scc(segments, lines);
+    std::fs::write(path, text).with_context(|| format!("Write SCC at {}", path.display()))?;
+    Ok(unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_scc() {
+        assert_eq!(timecode(0), "00:00:00;00");
+        // Frames 00 and 01 of each minute are skipped, except every tenth
+        assert_eq!(timecode(1800), "00:01:00;02");
+        assert_eq!(timecode(17982), "00:10:00;00");
+        assert_eq!(word(RCL), "9420");
+        assert_eq!(word(EOC), "942f");
+
+        let segs = [seg(2.0, 4.0, "")];
+        let (text, unknown) = scc(&segs, &["Olé, 你".to_string()]);
+        assert_eq!(unknown, 1);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Scenarist_SCC V1.0");
+        // "Olé, ?" is 6 columns: indent 13 = PAC indent 12 + tab 1
+        assert!(
+            lines[2].ends_with("9420 9420 94ae 94ae 9476 9476 97a1 97a1 4fec dc2c 20bf"),
+            "{}",
+            lines[2]
+        );
+        assert!(lines[4].starts_with("00:00:02;00\t942f 942f"));
+        assert!(lines[6].starts_with("00:00:04;00\t942c 942c"));
+        assert!(coverage(&["Hello".into()]) == 1.0 && coverage(&["你好".into()]) == 0.0);
+    }
+}
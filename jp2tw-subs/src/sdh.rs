@@ -0,0 +1,127 @@
+//! `--sdh` (subtitles for the deaf and hard of hearing): music that the
+//! non-speech filter would drop is kept as a （音樂） cue, bracketed sound
+//! annotations in the transcript such as （笑） become zh-TW descriptions
+//! without a translation request, speaker labels are kept, and cue timing
+//! follows SDH conventions (a minimum duration and a gap between cues).
+
+use crate::WhisperSegment;
+
+/// Cues are held on screen at least this long (seconds)...
+const MIN_DURATION: f64 = 1.0;
+/// ...while leaving this much blank between consecutive cues.
+const MIN_GAP: f64 = 0.083;
+
+pub const MUSIC: &str = "（音樂）";
+
+/// Japanese annotation keywords and their zh-TW descriptions; the first
+/// keyword found in an annotation wins.
+const SOUNDS: &[(&str, &str)] = &[
+    ("拍手", "掌聲"),
+    ("歓声", "歡呼聲"),
+    ("笑", "笑聲"),
+    ("泣", "哭聲"),
+    ("ため息", "嘆氣"),
+    ("咳", "咳嗽聲"),
+    ("悲鳴", "尖叫聲"),
+    ("電話", "電話鈴聲"),
+    ("着信", "電話鈴聲"),
+    ("チャイム", "門鈴聲"),
+    ("ノック", "敲門聲"),
+    ("足音", "腳步聲"),
+    ("ドア", "開門聲"),
+    ("銃声", "槍聲"),
+    ("爆発", "爆炸聲"),
+    ("雨", "雨聲"),
+    ("風", "風聲"),
+    ("犬", "狗叫聲"),
+    ("鳥", "鳥叫聲"),
+    ("音楽", "音樂"),
+    ("BGM", "音樂"),
+];
+
+/// Instruction added to the translation prompt.
+pub const PROMPT: &str = "These are SDH subtitles: keep speaker labels (such as 田中： or （田中）) at the start of a line, translating the name, and translate bracketed sound descriptions as bracketed zh-TW descriptions.";
+
+/// Keep a non-speech segment as a music cue when Whisper heard music in it.
+pub fn keep_as_music(seg: &mut WhisperSegment) -> bool {
+    let text = seg.text.trim();
+    let music = text.contains(['♪', '♫', '♬']) || text.contains("音楽") || text.contains("BGM");
+    if music {
+        seg.text = "♪".to_string();
+    }
+    music
+}
+
+/// The zh-TW line for a cue that is only music or a sound annotation;
+/// `None` means translate.
+pub fn fixed_line(ja: &str) -> Option<String> {
+    let text = ja.trim();
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|c| matches!(c, '♪' | '♫' | '♬' | '～' | '〜' | '~') || c.is_whitespace())
+    {
+        return Some(MUSIC.to_string());
+    }
+    let inner = ["（）", "()", "［］", "[]", "【】"]
+        .iter()
+        .find_map(|pair| {
+            let mut p = pair.chars();
+            let (open, close) = (p.next()?, p.next()?);
+            let inner = text.strip_prefix(open)?.strip_suffix(close)?;
+            (!inner.contains([open, close])).then_some(inner)
+        })?;
+    SOUNDS
+        .iter()
+        .find(|(key, _)| inner.contains(key))
+        .map(|(_, zh)| format!("（{zh}）"))
+}
+
+/// Apply SDH timing: stretch short cues to the minimum duration where the
+/// next cue leaves room, and keep a small gap between cues.
+pub fn retime(segments: &mut [WhisperSegment]) {
+    for i in 0..segments.len() {
+        let next_start = segments.get(i + 1).map(|n| n.start);
+        let seg = &mut segments[i];
+        let limit = next_start.map_or(f64::INFINITY, |n| n - MIN_GAP);
+        if seg.end - seg.start < MIN_DURATION {
+            seg.end = (seg.start + MIN_DURATION).min(limit).max(seg.end);
+        }
+        if seg.end > limit {
+            seg.end = limit.max(seg.start + 0.001);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_fixed_line() {
+        assert_eq!(fixed_line("（笑）").as_deref(), Some("（笑聲）"));
+        assert_eq!(
+            fixed_line("[電話の着信音]").as_deref(),
+            Some("（電話鈴聲）")
+        );
+        assert_eq!(fixed_line("♪～").as_deref(), Some(MUSIC));
+        // Speech, and annotations nobody mapped, are translated
+        assert_eq!(fixed_line("（笑）そうだね"), None);
+        assert_eq!(fixed_line("（田中）"), None);
+    }
+
+    #[test]
+    fn test_retime() {
+        let mut segs = vec![
+            seg(0.0, 0.3, ""),
+            seg(0.6, 2.0, ""),
+            seg(2.0, 3.0, ""),
+            seg(9.0, 9.2, ""),
+        ];
+        retime(&mut segs);
+        let ends: Vec<f64> = segs.iter().map(|s| s.end).collect();
+        // Stretched up to the next cue; overlaps trimmed to leave a gap
+        assert_eq!(ends, [0.6 - MIN_GAP, 2.0 - MIN_GAP, 3.0, 10.0]);
+    }
+}
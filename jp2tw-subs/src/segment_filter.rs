@@ -0,0 +1,220 @@
+//! `--segment-filter CMD`: a plugin point between pipeline stages. The cue
+//! list is piped to the command as JSON and read back from its stdout, so it
+//! can edit, drop, split, or add cues (custom censoring, a house style, its
+//! own MT engine) without changes here.
+//!
+//! The command runs at two stages:
+//!
+//! - `transcribed`: Japanese cues before translation (one call per chunk)
+//! - `translated`: the whole run's cues with their `zh` line, before the
+//!   subtitles are written
+//!
+//! ```json
+//! {"stage": "translated", "segments": [
+//!   {"id": null, "start": 1.0, "end": 2.5, "text": "こんにちは", "zh": "你好"}
+//! ]}
+//! ```
+//!
+//! It answers with the same object or just the `segments` array; `start`,
+//! `end`, and `text` are required, and `zh` at the `translated` stage. The
+//! stage is also in `$JP2TW_STAGE`.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::{split_args, WhisperSegment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Transcribed,
+    Translated,
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Transcribed => "transcribed",
+            Stage::Translated => "translated",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cue {
+    #[serde(flatten)]
+    segment: WhisperSegment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zh: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    stage: &'a str,
+    segments: &'a [Cue],
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Wrapped { segments: Vec<Cue> },
+    Bare(Vec<Cue>),
+}
+
+pub struct SegmentFilter {
+    program: String,
+    args: Vec<String>,
+}
+
+impl SegmentFilter {
+    /// `--segment-filter`, split like a shell command line.
+    pub fn parse(command: &str) -> Result<Self> {
+        let mut words = split_args(command)?.into_iter();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow!("--segment-filter is empty"))?;
+        Ok(Self {
+            program,
+            args: words.collect(),
+        })
+    }
+
+    /// Japanese cues before translation.
+    pub async fn transcribed(&self, segments: Vec<WhisperSegment>) -> Result<Vec<WhisperSegment>> {
+        let cues = segments
+            .into_iter()
+            .map(|segment| Cue { segment, zh: None })
+            .collect();
+        let cues = self.exchange(Stage::Transcribed, cues).await?;
+        Ok(cues.into_iter().map(|c| c.segment).collect())
+    }
+
+    /// Translated cues before writing; every cue must come back with `zh`.
+    pub async fn translated(
+        &self,
+        segments: Vec<WhisperSegment>,
+        zh_lines: Vec<String>,
+    ) -> Result<(Vec<WhisperSegment>, Vec<String>)> {
+        let cues = segments
+            .into_iter()
+            .zip(zh_lines)
+            .map(|(segment, zh)| Cue {
+                segment,
+                zh: Some(zh),
+            })
+            .collect();
+        let cues = self.exchange(Stage::Translated, cues).await?;
+        let mut segments = Vec::with_capacity(cues.len());
+        let mut zh_lines = Vec::with_capacity(cues.len());
+        for (i, cue) in cues.into_iter().enumerate() {
+            let zh = cue
+                .zh
+                .ok_or_else(|| anyhow!("--segment-filter returned cue {} without \"zh\"", i + 1))?;
+            segments.push(cue.segment);
+            zh_lines.push(zh);
+        }
+        Ok((segments, zh_lines))
+    }
+
+    async fn exchange(&self, stage: Stage, cues: Vec<Cue>) -> Result<Vec<Cue>> {
+        let input = serde_json::to_vec(&Request {
+            stage: stage.name(),
+            segments: &cues,
+        })?;
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env("JP2TW_STAGE", stage.name())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to run --segment-filter {}", self.program))?;
+        let mut stdin = child.stdin.take().context("--segment-filter stdin")?;
+        // Written while the output is read, so large lists cannot deadlock
+        let write = async move {
+            let written = stdin.write_all(&input).await;
+            drop(stdin);
+            written
+        };
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        let output = output?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "--segment-filter {} failed at the {} stage ({})",
+                self.program,
+                stage.name(),
+                output.status
+            ));
+        }
+        // A filter that exits without reading its input is fine
+        if let Err(e) = written {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+        let reply: Reply = serde_json::from_slice(&output.stdout).with_context(|| {
+            format!(
+                "--segment-filter {} printed invalid JSON at the {} stage",
+                self.program,
+                stage.name()
+            )
+        })?;
+        let cues = match reply {
+            Reply::Wrapped { segments } | Reply::Bare(segments) => segments,
+        };
+        if let Some((i, cue)) = cues.iter().enumerate().find(|(_, c)| {
+            let s = &c.segment;
+            !(s.start.is_finite() && s.end.is_finite() && 0.0 <= s.start && s.start <= s.end)
+        }) {
+            return Err(anyhow!(
+                "--segment-filter returned cue {} with invalid times {}..{}",
+                i + 1,
+                cue.segment.start,
+                cue.segment.end
+            ));
+        }
+        Ok(cues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_filter_round_trip() {
+        let cue = |start: f64, text: &str| seg(start, start + 1.0, text);
+        // `cat` hands the list back unchanged
+        let filter = SegmentFilter::parse("cat").unwrap();
+        let (segs, zh) = filter
+            .translated(
+                vec![cue(0.0, "はい"), cue(2.0, "いいえ")],
+                vec!["是".into(), "不".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(segs[1].text, "いいえ");
+        assert_eq!(zh, ["是", "不"]);
+
+        // A bare array that drops the first cue
+        let filter = SegmentFilter::parse(
+            r#"sh -c 'cat >/dev/null; echo "[{\"start\":2,\"end\":3,\"text\":\"いいえ\"}]"'"#,
+        )
+        .unwrap();
+        let segs = filter
+            .transcribed(vec![cue(0.0, "はい"), cue(2.0, "いいえ")])
+            .await
+            .unwrap();
+        assert_eq!(segs.len(), 1);
+        assert_eq!(segs[0].start, 2.0);
+        assert!(SegmentFilter::parse("false")
+            .unwrap()
+            .transcribed(vec![])
+            .await
+            .is_err());
+    }
+}
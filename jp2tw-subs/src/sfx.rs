@@ -0,0 +1,141 @@
+//! Onomatopoeia / sound-effect cues (ドキドキ, ガタン): detect lines made only
+//! of SFX and handle them per `--sfx` instead of translating them literally.
+
+use clap::ValueEnum;
+
+use crate::WhisperSegment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SfxMode {
+    /// Translate like any other line
+    Translate,
+    /// Show the Japanese as-is on the zh line
+    Keep,
+    /// Replace with （音效）
+    Bracket,
+    /// Remove the cue
+    Drop,
+}
+
+pub const BRACKET: &str = "（音效）";
+
+/// Common SFX that are not reduplicated.
+const KNOWN: &[&str] = &[
+    "ガタン",
+    "バタン",
+    "ドン",
+    "バン",
+    "ドカン",
+    "ガシャン",
+    "パリン",
+    "ザー",
+    "ピンポン",
+    "ジャーン",
+    "チーン",
+    "ゴクリ",
+    "ゴクン",
+    "ガチャ",
+    "カチッ",
+    "ビリビリ",
+    "ズドン",
+    "バシャ",
+    "ポン",
+];
+
+/// Whether `text` consists only of onomatopoeia: reduplicated kana
+/// (ドキドキ, ぐるぐる, ゴゴゴ) or a known sound word, ignoring emphasis marks.
+pub fn is_sfx(text: &str) -> bool {
+    let words: Vec<String> = text
+        .split(|c: char| c.is_whitespace() || "、。,.!！?？…〜~♪".contains(c))
+        .map(|w| w.trim_end_matches(['ッ', 'っ']).to_string())
+        .filter(|w| !w.is_empty())
+        .collect();
+    !words.is_empty() && words.iter().all(|w| is_sfx_word(w))
+}
+
+fn is_sfx_word(word: &str) -> bool {
+    if !word.chars().all(is_kana) {
+        return false;
+    }
+    // Drawn-out sounds (ザー, ドーン) match their base form
+    let base = word.trim_end_matches('ー');
+    if KNOWN.contains(&word) || KNOWN.contains(&base) {
+        return true;
+    }
+    // XX / XXX repetition of a 1-3 kana unit
+    let chars: Vec<char> = base.chars().collect();
+    (1..=3).any(|unit| {
+        chars.len() >= unit * 2
+            && chars.len().is_multiple_of(unit)
+            && chars.chunks(unit).all(|c| c == &chars[..unit])
+            && (unit > 1 || chars.len() >= 3)
+    })
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c as u32, 0x3041..=0x3096 | 0x30A1..=0x30FA | 0x30FC)
+}
+
+/// Remove SFX cues when the mode is `Drop`.
+pub fn drop_cues(segments: &mut Vec<WhisperSegment>, mode: SfxMode) {
+    if mode == SfxMode::Drop {
+        segments.retain(|s| !is_sfx(&s.text));
+    }
+}
+
+/// The zh line for an SFX cue under `Keep`/`Bracket`; `None` means translate.
+pub fn fixed_line(ja: &str, mode: SfxMode) -> Option<String> {
+    match mode {
+        SfxMode::Keep if is_sfx(ja) => Some(ja.trim().to_string()),
+        SfxMode::Bracket if is_sfx(ja) => Some(BRACKET.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_is_sfx() {
+        for sfx in [
+            "ドキドキ",
+            "ドキドキ…",
+            "ガタン！",
+            "ゴゴゴゴ",
+            "ぐるぐる",
+            "ドンッ",
+            "ザーッ",
+        ] {
+            assert!(is_sfx(sfx), "{sfx}");
+        }
+        for line in [
+            "ルフィ！",
+            "コーヒー",
+            "ドキドキする",
+            "はい",
+            "ああ",
+            "行くぞ",
+        ] {
+            assert!(!is_sfx(line), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut segs = vec![seg(0.0, 1.0, "ガタン"), seg(0.0, 1.0, "誰だ？")];
+        drop_cues(&mut segs, SfxMode::Drop);
+        assert_eq!(segs.len(), 1);
+        assert_eq!(
+            fixed_line("ガタン", SfxMode::Bracket).as_deref(),
+            Some(BRACKET)
+        );
+        assert_eq!(
+            fixed_line("ガタン", SfxMode::Keep).as_deref(),
+            Some("ガタン")
+        );
+        assert_eq!(fixed_line("誰だ？", SfxMode::Keep), None);
+        assert_eq!(fixed_line("ガタン", SfxMode::Translate), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! `--skip-range`: windows of the input (OP/ED, sponsor reads) left out of
+//! transcription and translation. Chunks inside a window are never sent;
+//! a window at the head or tail of a chunk is cut from the upload and one
+//! in the middle silenced, and any cue Whisper still places there is
+//! dropped. A window can carry a placeholder caption shown for its length.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::{preview, wav, WhisperSegment};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipRange {
+    pub start: f64,
+    pub end: f64,
+    /// Caption for the window, e.g. （片頭曲）
+    pub label: Option<String>,
+}
+
+/// `START-END[=CAPTION]`, times as `MM:SS`, `HH:MM:SS[.mmm]`, or seconds.
+pub fn parse(s: &str) -> Result<SkipRange, String> {
+    let (span, label) = match s.split_once('=') {
+        Some((span, label)) => (span, Some(label.trim().to_string())),
+        None => (s, None),
+    };
+    let (start, end) = span
+        .split_once('-')
+        .ok_or_else(|| format!("expected START-END[=CAPTION], got {s:?}"))?;
+    let (start, end) = (
+        preview::parse_at(start.trim())?,
+        preview::parse_at(end.trim())?,
+    );
+    if end <= start {
+        return Err(format!("{s:?} ends before it starts"));
+    }
+    Ok(SkipRange {
+        start,
+        end,
+        label: label.filter(|l| !l.is_empty()),
+    })
+}
+
+/// The ranges as `start-end` seconds, for the checkpoint key.
+pub fn key(ranges: &[SkipRange]) -> String {
+    ranges
+        .iter()
+        .map(|r| format!("{:.3}-{:.3}", r.start, r.end))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Whether `start..end` lies inside one skipped range.
+pub fn covers(ranges: &[SkipRange], start: f64, end: f64) -> bool {
+    ranges.iter().any(|r| r.start <= start && end <= r.end)
+}
+
+/// Drop cues centered inside a skipped range.
+pub fn drop_cues(segments: &mut Vec<WhisperSegment>, ranges: &[SkipRange]) {
+    segments.retain(|s| {
+        let mid = (s.start + s.end) / 2.0;
+        !ranges.iter().any(|r| r.start <= mid && mid < r.end)
+    });
+}
+
+/// One cue per labelled range, with its caption; the cue's Japanese is empty.
+pub fn placeholders(ranges: &[SkipRange]) -> Vec<(WhisperSegment, String)> {
+    ranges
+        .iter()
+        .filter_map(|r| {
+            let label = r.label.clone()?;
+            let seg = WhisperSegment {
+                start: r.start,
+                end: r.end,
+                ..Default::default()
+            };
+            Some((seg, label))
+        })
+        .collect()
+}
+
+/// Write the unskipped part of `chunk`, which starts `start` seconds into
+/// the input, to `out`: skipped audio at either end is cut and skipped audio
+/// in between silenced. Returns the seconds cut from the head, or `None`
+/// when nothing is left to transcribe.
+pub fn trim_chunk(
+    ranges: &[SkipRange],
+    chunk: &Path,
+    start: f64,
+    out: &Path,
+) -> Result<Option<f64>> {
+    let mut r = BufReader::new(File::open(chunk).context("Open chunk for --skip-range")?);
+    let len = wav::seek_to_data(&mut r)?;
+    let mut pcm = Vec::with_capacity(len as usize);
+    r.take(len).read_to_end(&mut pcm)?;
+    let samples = pcm.len() / 2;
+    let rate = f64::from(wav::SAMPLE_RATE);
+    let to_sample = |t: f64| (((t - start) * rate).round().max(0.0) as usize).min(samples);
+
+    let mut keep = vec![true; samples];
+    for r in ranges {
+        keep[to_sample(r.start)..to_sample(r.end)].fill(false);
+    }
+    let (Some(first), Some(last)) = (keep.iter().position(|&k| k), keep.iter().rposition(|&k| k))
+    else {
+        return Ok(None);
+    };
+    for (i, _) in keep.iter().enumerate().filter(|(_, &k)| !k) {
+        pcm[i * 2] = 0;
+        pcm[i * 2 + 1] = 0;
+    }
+    let mut w = wav::WavWriter::create(out)?;
+    w.write_pcm(&pcm[first * 2..(last + 1) * 2])?;
+    w.finish()?;
+    Ok(Some(first as f64 / rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_parse_and_trim() {
+        let op = parse("00:00-01:30=（片頭曲）").unwrap();
+        assert_eq!((op.start, op.end), (0.0, 90.0));
+        assert_eq!(op.label.as_deref(), Some("（片頭曲）"));
+        assert_eq!(parse("22:40-24:00").unwrap().label, None);
+        assert!(parse("01:30-00:00").is_err());
+        let ranges = [op];
+        assert!(covers(&ranges, 10.0, 20.0));
+        assert!(!covers(&ranges, 80.0, 100.0));
+
+        let mut segs = vec![seg(85.0, 89.0, ""), seg(89.0, 95.0, "")];
+        drop_cues(&mut segs, &ranges);
+        assert_eq!(segs.len(), 1);
+
+        // A 4 s chunk at 88 s: the first 2 s fall in the OP and are cut
+        let dir = tempfile::tempdir().unwrap();
+        let (chunk, out) = (dir.path().join("c.wav"), dir.path().join("t.wav"));
+        let mut w = wav::WavWriter::create(&chunk).unwrap();
+        w.write_pcm(&vec![1u8; 4 * 32_000]).unwrap();
+        w.finish().unwrap();
+        assert_eq!(trim_chunk(&ranges, &chunk, 88.0, &out).unwrap(), Some(2.0));
+        assert_eq!(std::fs::metadata(&out).unwrap().len(), 44 + 2 * 32_000);
+        assert_eq!(trim_chunk(&ranges, &chunk, 10.0, &out).unwrap(), None);
+    }
+}
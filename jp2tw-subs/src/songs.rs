@@ -0,0 +1,125 @@
+//! `--songs`: sung sections (OP/ED, insert songs) styled the way subtitles
+//! conventionally mark them, in italics behind a ♪, and optionally given a
+//! looser, lyrical translation. Whisper wraps what it hears sung in note
+//! marks; cues between two marked ones close by count as part of the song,
+//! since the marks are usually only on some of its lines.
+
+use clap::ValueEnum;
+
+use crate::{skip, WhisperSegment};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Songs {
+    /// Sung lines are subtitled like dialog
+    #[default]
+    Off,
+    /// Sung lines in italics with a ♪ prefix
+    Style,
+    /// As style, and sung lines are retranslated as lyrics (natural and poetic over literal)
+    Loose,
+}
+
+/// Largest silence (seconds) between a cue and the marked sung cues on both
+/// sides of it for the cue to count as sung too.
+const MAX_GAP: f64 = 5.0;
+
+const NOTES: [char; 3] = ['♪', '♫', '♬'];
+
+/// Whisper marked the segment as sung: note marks around actual words, not a
+/// music-only cue.
+fn marked(seg: &WhisperSegment) -> bool {
+    let text = seg.text.trim();
+    text.contains(NOTES) && !strip_notes(text).is_empty()
+}
+
+impl Songs {
+    /// Which of `segments` are sung: from the note marks unless off, and
+    /// any centered in one of `ranges` (`--song-range`).
+    pub fn detect(self, segments: &[WhisperSegment], ranges: &[skip::SkipRange]) -> Vec<bool> {
+        let marks: Vec<bool> = segments
+            .iter()
+            .map(|s| {
+                let mid = (s.start + s.end) / 2.0;
+                self != Songs::Off && marked(s)
+                    || ranges.iter().any(|r| r.start <= mid && mid < r.end)
+            })
+            .collect();
+        (0..segments.len())
+            .map(|i| {
+                if marks[i] {
+                    return true;
+                }
+                let seg = &segments[i];
+                let before = (0..i)
+                    .rev()
+                    .find(|&j| marks[j])
+                    .is_some_and(|j| seg.start - segments[j].end <= MAX_GAP);
+                let after = (i + 1..segments.len())
+                    .find(|&k| marks[k])
+                    .is_some_and(|k| segments[k].start - seg.end <= MAX_GAP);
+                before && after
+            })
+            .collect()
+    }
+}
+
+/// `--song-range START-END`: a skip range without a caption.
+pub fn parse_range(s: &str) -> Result<skip::SkipRange, String> {
+    let range = skip::parse(s)?;
+    if range.label.is_some() {
+        return Err(format!("expected START-END, got {s:?}"));
+    }
+    Ok(range)
+}
+
+/// `line` without the note marks Whisper or the translator put around it.
+pub fn strip_notes(line: &str) -> String {
+    line.lines()
+        .map(|l| l.trim_matches(|c: char| NOTES.contains(&c) || c.is_whitespace()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An SRT cue's text styled as sung.
+pub fn srt(text: &str) -> String {
+    format!("<i>♪ {text}</i>")
+}
+
+/// An ASS event's text styled as sung.
+pub fn ass(text: &str) -> String {
+    format!("{{\\i1}}♪ {text}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_detect() {
+        let segments = [
+            seg(0.0, 2.0, "始まるよ"),
+            seg(3.0, 6.0, "♪ 君の名を呼ぶ ♪"),
+            seg(6.5, 9.0, "遠い空へ"),
+            seg(9.5, 12.0, "♪ 届けたい"),
+            seg(30.0, 32.0, "♪"),
+            seg(33.0, 35.0, "じゃあね"),
+        ];
+        assert_eq!(
+            Songs::Style.detect(&segments, &[]),
+            [false, true, true, true, false, false]
+        );
+        assert!(Songs::Off.detect(&segments, &[]).iter().all(|&s| !s));
+        let ranges = [parse_range("0:29-0:36").unwrap()];
+        assert_eq!(
+            Songs::Off.detect(&segments, &ranges),
+            [false, false, false, false, true, true]
+        );
+        assert!(parse_range("0:00-1:30=（片頭曲）").is_err());
+        assert_eq!(
+            strip_notes("♪ 君の名を呼ぶ ♪\n呼喚你的名字♪"),
+            "君の名を呼ぶ\n呼喚你的名字"
+        );
+        assert_eq!(srt("呼喚你的名字"), "<i>♪ 呼喚你的名字</i>");
+    }
+}
@@ -0,0 +1,264 @@
+//! `stats` subcommand: a summary of a subtitle file's timing and text, for
+//! QC and for tuning the segmentation options (`--tighten-cues`,
+//! `--no-sentence-split`, line lengths): cue count, speech time, reading speed,
+//! characters per line, the longest and shortest cues, and the gaps between
+//! cues.
+
+use anyhow::Result;
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+use crate::subparse::{self, Cue, SubtitleKind};
+use crate::{format_srt_time, formats};
+
+/// Characters per second bucket edges
+const CPS_BUCKETS: [f64; 6] = [4.0, 6.0, 8.0, 10.0, 12.0, 15.0];
+/// Characters per line bucket edges (upper bounds, inclusive)
+const LINE_BUCKETS: [usize; 6] = [4, 8, 12, 16, 20, 24];
+/// Gaps shorter than this make cues flicker
+const SHORT_GAP: f64 = 0.1;
+const BAR_WIDTH: usize = 30;
+/// Longest and shortest cues listed
+const EXTREMES: usize = 3;
+
+pub struct Stats {
+    cues: Vec<Cue>,
+}
+
+/// Non-whitespace characters in a cue.
+fn chars(cue: &Cue) -> usize {
+    cue.lines
+        .iter()
+        .map(|l| l.chars().filter(|c| !c.is_whitespace()).count())
+        .sum()
+}
+
+fn duration(cue: &Cue) -> f64 {
+    (cue.end - cue.start).max(0.0)
+}
+
+/// The value at `p` (0–1) of sorted `values`.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values[((values.len() - 1) as f64 * p).round() as usize]
+}
+
+fn histogram(out: &mut String, rows: &[(String, usize)]) {
+    let max = rows.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    for (label, n) in rows {
+        let bar = "█".repeat((n * BAR_WIDTH).div_ceil(max));
+        let _ = writeln!(out, "{}", format!("  {label:>9} {n:>5} {bar}").trim_end());
+    }
+}
+
+impl Stats {
+    pub fn new(mut cues: Vec<Cue>) -> Self {
+        cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Self { cues }
+    }
+
+    /// Seconds covered by at least one cue.
+    fn speech_time(&self) -> f64 {
+        let mut total = 0.0;
+        let mut covered_to = f64::MIN;
+        for cue in &self.cues {
+            let start = cue.start.max(covered_to);
+            if cue.end > start {
+                total += cue.end - start;
+            }
+            covered_to = covered_to.max(cue.end);
+        }
+        total
+    }
+
+    fn cps(&self) -> Vec<f64> {
+        let mut cps: Vec<f64> = self
+            .cues
+            .iter()
+            .filter(|c| duration(c) > 0.0)
+            .map(|c| chars(c) as f64 / duration(c))
+            .collect();
+        cps.sort_by(f64::total_cmp);
+        cps
+    }
+
+    /// Gaps between consecutive cues; negative for overlaps.
+    fn gaps(&self) -> Vec<f64> {
+        let mut gaps: Vec<f64> = self
+            .cues
+            .windows(2)
+            .map(|w| w[1].start - w[0].end)
+            .collect();
+        gaps.sort_by(f64::total_cmp);
+        gaps
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        let Some(last) = self.cues.iter().map(|c| c.end).reduce(f64::max) else {
+            return writeln!(f, "0 cues");
+        };
+        let first = self.cues[0].start;
+        let span = (last - first).max(0.0);
+        let speech = self.speech_time();
+        let _ = writeln!(
+            out,
+            "{} cues, {} to {}",
+            self.cues.len(),
+            format_srt_time(first),
+            format_srt_time(last)
+        );
+        let _ = writeln!(
+            out,
+            "Speech time: {} ({:.0}% of the span)",
+            format_srt_time(speech),
+            if span > 0.0 {
+                speech / span * 100.0
+            } else {
+                0.0
+            }
+        );
+        let total_chars: usize = self.cues.iter().map(chars).sum();
+        let _ = writeln!(
+            out,
+            "Characters: {total_chars} ({:.1} per cue)",
+            total_chars as f64 / self.cues.len() as f64
+        );
+
+        let cps = self.cps();
+        let _ = writeln!(
+            out,
+            "\nCharacters per second: median {:.1}, 90th percentile {:.1}, max {:.1}",
+            percentile(&cps, 0.5),
+            percentile(&cps, 0.9),
+            cps.last().copied().unwrap_or(0.0)
+        );
+        let mut rows = Vec::new();
+        let mut low = 0.0;
+        for &high in &CPS_BUCKETS {
+            let n = cps.iter().filter(|&&c| c >= low && c < high).count();
+            rows.push((format!("{low}–{high}"), n));
+            low = high;
+        }
+        rows.push((format!("{low}+"), cps.iter().filter(|&&c| c >= low).count()));
+        histogram(&mut out, &rows);
+
+        let lengths: Vec<usize> = self
+            .cues
+            .iter()
+            .flat_map(|c| c.lines.iter().map(|l| l.trim().chars().count()))
+            .filter(|&n| n > 0)
+            .collect();
+        let _ = writeln!(
+            out,
+            "\nCharacters per line: {} lines, longest {}",
+            lengths.len(),
+            lengths.iter().max().unwrap_or(&0)
+        );
+        let mut rows = Vec::new();
+        let mut low = 1;
+        for &high in &LINE_BUCKETS {
+            let n = lengths.iter().filter(|&&n| n >= low && n <= high).count();
+            rows.push((format!("{low}–{high}"), n));
+            low = high + 1;
+        }
+        rows.push((
+            format!("{low}+"),
+            lengths.iter().filter(|&&n| n >= low).count(),
+        ));
+        histogram(&mut out, &rows);
+
+        let mut by_duration: Vec<(usize, &Cue)> = self.cues.iter().enumerate().collect();
+        by_duration.sort_by(|a, b| duration(b.1).total_cmp(&duration(a.1)));
+        // Shortest first, and no cue in both lists
+        let longest = EXTREMES.min(by_duration.len());
+        let shortest: Vec<(usize, &Cue)> = by_duration[longest..]
+            .iter()
+            .rev()
+            .take(EXTREMES)
+            .copied()
+            .collect();
+        for (title, picked) in [
+            ("Longest cues", &by_duration[..longest]),
+            ("Shortest cues", &shortest[..]),
+        ] {
+            if picked.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n{title}:");
+            for &(i, cue) in picked {
+                let _ = writeln!(
+                    out,
+                    "  cue {} [{}] {:.2}s: {}",
+                    i + 1,
+                    format_srt_time(cue.start),
+                    duration(cue),
+                    cue.lines.join(" / ")
+                );
+            }
+        }
+
+        let gaps = self.gaps();
+        let (overlaps, gaps): (Vec<f64>, Vec<f64>) = gaps.iter().partition(|&&g| g < 0.0);
+        let _ = writeln!(
+            out,
+            "\nGaps: {} (median {:.2}s, max {:.2}s), {} under {SHORT_GAP}s, {} overlap(s)",
+            gaps.len(),
+            percentile(&gaps, 0.5),
+            gaps.last().copied().unwrap_or(0.0),
+            gaps.iter().filter(|&&g| g < SHORT_GAP).count(),
+            overlaps.len()
+        );
+        f.write_str(&out)
+    }
+}
+
+/// Print the stats of each file.
+pub fn run(files: &[impl AsRef<Path>]) -> Result<()> {
+    for (i, path) in files.iter().enumerate() {
+        let path = path.as_ref();
+        let text = formats::read_text(path)?;
+        let cues = subparse::parse(&text, SubtitleKind::detect(path, &text))?;
+        if i > 0 {
+            println!();
+        }
+        println!("{}", path.display());
+        print!("{}", Stats::new(cues));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let cue = |start: f64, end: f64, text: &str| Cue {
+            start,
+            end,
+            lines: text.lines().map(str::to_string).collect(),
+            style: None,
+        };
+        let stats = Stats::new(vec![
+            cue(4.0, 5.0, "好"),
+            cue(0.0, 2.0, "你好嗎\n我很好"),
+            cue(1.5, 3.0, "真的嗎"),
+            cue(3.05, 4.0, "對"),
+        ]);
+        assert!((stats.speech_time() - 4.95).abs() < 1e-9);
+        assert_eq!(stats.cps().last(), Some(&3.0));
+        let text = stats.to_string();
+        assert!(text.starts_with("4 cues, 00:00:00,000 to 00:00:05,000\nSpeech time: 00:00:04,950 (99% of the span)\nCharacters: 11"), "{text}");
+        assert!(text.contains("  cue 1 [00:00:00,000] 2.00s: 你好嗎 / 我很好"));
+        assert!(
+            text.contains("Gaps: 2 (median 0.05s, max 0.05s), 2 under 0.1s, 1 overlap(s)"),
+            "{text}"
+        );
+        assert_eq!(Stats::new(Vec::new()).to_string(), "0 cues\n");
+    }
+}
@@ -0,0 +1,151 @@
+//! `--style-guide`: the constraints professional deliveries are checked
+//! against. Timing is fixed up front (minimum and maximum duration, a
+//! minimum gap, short gaps closed), Chinese lines are wrapped to the line
+//! length, and whatever still breaks the rules after translation (reading
+//! speed, lines that cannot be wrapped into two) is reported per cue.
+
+use clap::ValueEnum;
+
+use crate::lint::{self, Issue, Severity};
+use crate::subparse::{Cue, SubtitleKind};
+use crate::{wrap, WhisperSegment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StyleGuide {
+    /// Netflix Traditional Chinese (Taiwan): 16 characters per line, 2 lines, 9 characters/s, 5/6 s to 7 s on screen, 2-frame gaps
+    NetflixTc,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    pub max_line_chars: usize,
+    pub max_lines: usize,
+    /// Characters per second, whitespace excluded
+    pub max_cps: f64,
+    pub min_duration: f64,
+    pub max_duration: f64,
+    /// Blank time between consecutive cues
+    pub min_gap: f64,
+    /// Gaps shorter than this are closed down to `min_gap`
+    pub close_gap: f64,
+}
+
+impl StyleGuide {
+    pub fn name(self) -> &'static str {
+        match self {
+            StyleGuide::NetflixTc => "netflix-tc",
+        }
+    }
+
+    pub fn rules(self) -> Rules {
+        match self {
+            // Frame counts at 24 fps: 20 frames minimum, 2-frame gaps,
+            // gaps under 12 frames chained
+            StyleGuide::NetflixTc => Rules {
+                max_line_chars: 16,
+                max_lines: 2,
+                max_cps: 9.0,
+                min_duration: 20.0 / 24.0,
+                max_duration: 7.0,
+                min_gap: 2.0 / 24.0,
+                close_gap: 12.0 / 24.0,
+            },
+        }
+    }
+}
+
+/// Bring cue timing within the rules where the neighbouring cues allow.
+pub fn retime(rules: &Rules, segments: &mut [WhisperSegment]) {
+    for i in 0..segments.len() {
+        let next_start = segments.get(i + 1).map(|n| n.start);
+        let seg = &mut segments[i];
+        let limit = next_start.map_or(f64::INFINITY, |n| n - rules.min_gap);
+        if next_start.is_some_and(|n| n - seg.end < rules.close_gap) {
+            seg.end = limit;
+        }
+        if seg.end - seg.start < rules.min_duration {
+            seg.end = (seg.start + rules.min_duration).min(limit).max(seg.end);
+        }
+        seg.end = seg
+            .end
+            .min(seg.start + rules.max_duration)
+            .max(seg.start + 0.001);
+    }
+}
+
+/// What still breaks the rules in the Chinese lines, wrapped as they will be
+/// written.
+pub fn report(rules: &Rules, segments: &[WhisperSegment], zh_lines: &[String]) -> Vec<Issue> {
+    let cues: Vec<Cue> = segments
+        .iter()
+        .zip(zh_lines)
+        .map(|(seg, zh)| Cue {
+            start: seg.start,
+            end: seg.end,
+            lines: wrap::wrap(zh, rules.max_line_chars)
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            style: None,
+        })
+        .collect();
+    let limits = lint::Limits {
+        max_cps: rules.max_cps,
+        max_line_chars: rules.max_line_chars,
+        max_lines: rules.max_lines,
+    };
+    let mut issues = lint::check(&cues, SubtitleKind::Srt, &limits);
+    for (i, cue) in cues.iter().enumerate() {
+        let duration = cue.end - cue.start;
+        if duration + 1e-6 < rules.min_duration {
+            issues.push(Issue {
+                cue: i + 1,
+                start: cue.start,
+                severity: Severity::Warning,
+                message: format!(
+                    "on screen {duration:.2}s (min {:.2}s; the next cue leaves no room)",
+                    rules.min_duration
+                ),
+            });
+        }
+    }
+    issues.sort_by_key(|issue| issue.cue);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_retime() {
+        let rules = StyleGuide::NetflixTc.rules();
+        let mut segs = vec![
+            seg(0.0, 0.3, ""),
+            seg(1.0, 2.0, ""),
+            seg(2.2, 12.0, ""),
+            seg(20.0, 21.0, ""),
+        ];
+        retime(&rules, &mut segs);
+        let ends: Vec<f64> = segs.iter().map(|s| s.end).collect();
+        // Stretched to the minimum; the short gap to cue 3 is closed;
+        // held at most 7 s
+        assert_eq!(ends, [20.0 / 24.0, 2.2 - 2.0 / 24.0, 9.2, 21.0]);
+    }
+
+    #[test]
+    fn test_report() {
+        let rules = StyleGuide::NetflixTc.rules();
+        let segs = vec![seg(0.0, 1.0, ""), seg(5.0, 9.0, "")];
+        let zh = vec![
+            "我們明天早上一起去車站接他然後去吃早餐".to_string(),
+            "好".to_string(),
+        ];
+        let issues = report(&rules, &segs, &zh);
+        // Wraps into two lines, but reads too fast
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].cue, 1);
+        assert!(issues[0].message.contains("characters/s"));
+    }
+}
@@ -0,0 +1,266 @@
+//! Subtitle readers for SRT, WebVTT, and ASS/SSA, so existing files can be
+//! brought back in for translation, syncing, linting, and re-styling. Cues
+//! keep their line breaks; markup and override tags are stripped.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+
+use crate::{formats, WhisperSegment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleKind {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleKind {
+    /// From the file extension, else from the content.
+    pub fn detect(path: &Path, text: &str) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("vtt") => Self::Vtt,
+            Some("ass" | "ssa") => Self::Ass,
+            Some("srt") => Self::Srt,
+            _ => {
+                let head = text.trim_start_matches('\u{feff}').trim_start();
+                if head.starts_with("WEBVTT") {
+                    Self::Vtt
+                } else if head.starts_with("[Script Info]") {
+                    Self::Ass
+                } else {
+                    Self::Srt
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    /// Display lines, markup removed
+    pub lines: Vec<String>,
+    /// ASS style name
+    pub style: Option<String>,
+}
+
+impl Cue {
+    /// The cue as a pipeline segment; lines are joined without a separator
+    /// (Japanese needs none).
+    pub fn to_segment(&self, id: usize) -> WhisperSegment {
+        WhisperSegment {
+            id: Some(id as u32),
+            start: self.start,
+            end: self.end,
+            text: self.lines.concat().trim().to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn read(path: &Path) -> Result<Vec<Cue>> {
+    let text = formats::read_text(path)?;
+    parse(&text, SubtitleKind::detect(path, &text))
+        .with_context(|| format!("Parse {}", path.display()))
+}
+
+/// Cues of `path` as segments, skipping empty ones.
+pub fn read_segments(path: &Path) -> Result<Vec<WhisperSegment>> {
+    Ok(to_segments(&read(path)?))
+}
+
+pub fn to_segments(cues: &[Cue]) -> Vec<WhisperSegment> {
+    cues.iter()
+        .filter(|c| c.lines.iter().any(|l| !l.trim().is_empty()))
+        .enumerate()
+        .map(|(i, c)| c.to_segment(i))
+        .collect()
+}
+
+pub fn parse(text: &str, kind: SubtitleKind) -> Result<Vec<Cue>> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    match kind {
+        SubtitleKind::Srt | SubtitleKind::Vtt => Ok(parse_blocks(&text, kind)),
+        SubtitleKind::Ass => parse_ass(&text),
+    }
+}
+
+/// SRT and WebVTT share the block layout: optional identifier, timing line,
+/// text lines, blank line.
+fn parse_blocks(text: &str, kind: SubtitleKind) -> Vec<Cue> {
+    let mut out = Vec::new();
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|l| l.trim().is_empty());
+        let Some(mut timing) = lines.next() else {
+            continue;
+        };
+        if !timing.contains("-->") {
+            // Cue identifier; WebVTT header, NOTE, STYLE, and REGION
+            // blocks have no timing line and are skipped here
+            match lines.next() {
+                Some(l) if l.contains("-->") => timing = l,
+                _ => continue,
+            }
+        }
+        let Some((a, b)) = timing.split_once("-->") else {
+            continue;
+        };
+        // WebVTT cue settings follow the end time
+        let b = b.split_whitespace().next().unwrap_or("");
+        let (Some(start), Some(end)) = (parse_timestamp(a.trim()), parse_timestamp(b)) else {
+            continue;
+        };
+        let lines: Vec<String> = lines
+            .map(|l| match kind {
+                SubtitleKind::Vtt => unescape_vtt(&strip_markup(l)),
+                _ => strip_markup(l),
+            })
+            .collect();
+        out.push(Cue {
+            start,
+            end,
+            lines,
+            style: None,
+        });
+    }
+    out
+}
+
+fn parse_ass(text: &str) -> Result<Vec<Cue>> {
+    let mut in_events = false;
+    let mut format: Option<Vec<String>> = None;
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+        if let Some(f) = line.strip_prefix("Format:") {
+            format = Some(
+                f.split(',')
+                    .map(|s| s.trim().to_ascii_lowercase())
+                    .collect(),
+            );
+            continue;
+        }
+        let Some(fields) = line.strip_prefix("Dialogue:") else {
+            continue;
+        };
+        let format = format
+            .as_ref()
+            .ok_or_else(|| anyhow!("Dialogue line before the [Events] Format line"))?;
+        // Text is last and may itself contain commas
+        let values: Vec<&str> = fields.splitn(format.len(), ',').map(str::trim).collect();
+        let field = |name: &str| {
+            format
+                .iter()
+                .position(|f| f == name)
+                .and_then(|i| values.get(i).copied())
+        };
+        let (Some(start), Some(end)) = (
+            field("start").and_then(parse_timestamp),
+            field("end").and_then(parse_timestamp),
+        ) else {
+            continue;
+        };
+        let body = field("text").unwrap_or("");
+        out.push(Cue {
+            start,
+            end,
+            lines: strip_markup(&body.replace("\\h", "\u{a0}"))
+                .split("\\N")
+                .flat_map(|l| l.split("\\n"))
+                .map(str::to_string)
+                .collect(),
+            style: field("style").map(str::to_string),
+        });
+    }
+    Ok(out)
+}
+
+/// `HH:MM:SS,mmm` (SRT), `[HH:]MM:SS.mmm` (WebVTT), or `H:MM:SS.cc` (ASS).
+pub fn parse_timestamp(s: &str) -> Option<f64> {
+    let (hms, frac) = s.trim().split_once([',', '.']).unwrap_or((s.trim(), ""));
+    let parts: Vec<u64> = hms
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let secs = match parts[..] {
+        [h, m, s] => h * 3600 + m * 60 + s,
+        [m, s] => m * 60 + s,
+        _ => return None,
+    };
+    let frac = if frac.is_empty() {
+        0.0
+    } else {
+        frac.parse::<u64>().ok()? as f64 / 10f64.powi(frac.len() as i32)
+    };
+    Some(secs as f64 + frac)
+}
+
+/// Drop `<i>`-style tags and `{\an8}`-style override blocks.
+fn strip_markup(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '<' | '{' => depth += 1,
+            '>' | '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn unescape_vtt(line: &str) -> String {
+    line.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt_and_vtt() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>こんにちは</i>\r\n{\\an8}世界\r\n\r\n2\r\n00:01:00,000 --> 00:01:01,000\r\nはい\r\n";
+        let cues = parse(srt, SubtitleKind::Srt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].lines, ["こんにちは", "世界"]);
+        assert_eq!((cues[0].start, cues[0].end), (1.0, 2.5));
+        assert_eq!(to_segments(&cues)[0].text, "こんにちは世界");
+
+        let vtt = "WEBVTT\n\nNOTE made by hand\n\nintro\n00:01.500 --> 00:03.000 line:90%\n<v 太郎>A &amp; B\n\n01:00:00.000 --> 01:00:01.000\nend\n";
+        let cues = parse(vtt, SubtitleKind::Vtt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!((cues[0].start, cues[0].end), (1.5, 3.0));
+        assert_eq!(cues[0].lines, ["A & B"]);
+        assert_eq!(cues[1].start, 3600.0);
+    }
+
+    #[test]
+    fn test_parse_ass() {
+        let ass = "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Noto\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nComment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,skip\nDialogue: 0,0:00:01.50,0:00:03.25,Top,,0,0,0,,{\\pos(10,10)}はい、そう\\Nです\n";
+        let cues = parse(ass, SubtitleKind::Ass).unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!((cues[0].start, cues[0].end), (1.5, 3.25));
+        assert_eq!(cues[0].lines, ["はい、そう", "です"]);
+        assert_eq!(cues[0].style.as_deref(), Some("Top"));
+        assert_eq!(
+            SubtitleKind::detect(Path::new("x.txt"), "[Script Info]\n"),
+            SubtitleKind::Ass
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Pure-Rust audio extraction (symphonia), used when ffmpeg is not installed.
+//! Decodes the first audio track, downmixes to mono, and resamples to 16kHz.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::wav::{WavWriter, SAMPLE_RATE};
+
+pub fn extract_audio(input: &Path, wav_out: &Path) -> Result<()> {
+    let file = File::open(input).with_context(|| format!("Open {}", input.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = input.extension().and_then(|s| s.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_context(|| format!("Unsupported container: {}", input.display()))?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && t.codec_params.sample_rate.is_some())
+        .ok_or_else(|| anyhow!("No decodable audio track in {}", input.display()))?;
+    let track_id = track.id;
+    let src_rate = track.codec_params.sample_rate.unwrap_or(SAMPLE_RATE);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported audio codec (symphonia)")?;
+
+    let mut resampler = Resampler::new(src_rate, SAMPLE_RATE);
+    let mut out = WavWriter::create(wav_out)?;
+    let mut mono: Vec<f32> = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(anyhow!("Audio demux failed: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            // Corrupt packets are skipped, as ffmpeg does
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(anyhow!("Audio decode failed: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buf.copy_interleaved_ref(decoded);
+
+        mono.clear();
+        mono.extend(
+            buf.samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+        out.write_samples(&resampler.process(&mono))?;
+    }
+    out.write_samples(&resampler.finish())?;
+    out.finish()
+}
+
+/// Streaming mono resampler: box-filter averaging when downsampling
+/// (cheap anti-aliasing, plenty for speech), linear interpolation otherwise.
+struct Resampler {
+    step: f64,
+    pos: f64,
+    buf: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(src_rate: u32, dst_rate: u32) -> Self {
+        Self {
+            step: f64::from(src_rate) / f64::from(dst_rate),
+            pos: 0.0,
+            buf: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, input: &[f32]) -> Vec<i16> {
+        self.buf.extend_from_slice(input);
+        let mut out = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        loop {
+            let i = self.pos.floor() as usize;
+            let v = if self.step >= 1.0 {
+                let end = (self.pos + self.step).floor() as usize;
+                if end >= self.buf.len() {
+                    break;
+                }
+                let window = &self.buf[i..end.max(i + 1)];
+                window.iter().sum::<f32>() / window.len() as f32
+            } else {
+                if i + 1 >= self.buf.len() {
+                    break;
+                }
+                let frac = (self.pos - i as f64) as f32;
+                self.buf[i] * (1.0 - frac) + self.buf[i + 1] * frac
+            };
+            out.push((v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+            self.pos += self.step;
+        }
+        // Drop consumed input, keeping the fractional read position
+        let consumed = (self.pos.floor() as usize).min(self.buf.len());
+        self.buf.drain(..consumed);
+        self.pos -= consumed as f64;
+        out
+    }
+
+    fn finish(&mut self) -> Vec<i16> {
+        // Pad so the tail of the input still produces output samples
+        let pad = vec![0.0; self.step.ceil() as usize + 1];
+        let tail = self.buf.len();
+        let mut out = self.process(&pad);
+        let expected = (tail as f64 / self.step).round() as usize;
+        out.truncate(expected);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resampler_downsamples_in_chunks() {
+        let mut r = Resampler::new(48_000, 16_000);
+        let mut out = Vec::new();
+        // One second of a constant signal fed in uneven pieces
+        for piece in [1000usize, 23_000, 24_000] {
+            out.extend(r.process(&vec![0.5; piece]));
+        }
+        out.extend(r.finish());
+        assert_eq!(out.len(), 16_000);
+        assert!(out.iter().all(|&s| (s - i16::MAX / 2).abs() <= 1));
+    }
+}
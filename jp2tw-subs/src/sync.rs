@@ -0,0 +1,132 @@
+//! Reference sync (`--sync-reference`): adopt the timing of an existing,
+//! correctly timed Japanese subtitle file. Transcribed cues are matched to
+//! reference cues in order by text similarity and time proximity; matched
+//! cues take the reference timing, and several cues matching one reference
+//! cue share its span in proportion to their length.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use crate::{retime, subparse, text_similarity, WhisperSegment};
+
+/// How far (seconds) a transcribed cue may sit from its reference cue.
+const MAX_DRIFT: f64 = 10.0;
+/// Combined score below which a cue keeps its own timing.
+const MIN_SCORE: f64 = 0.5;
+
+/// Reference cues from an SRT, WebVTT, or ASS file, moved onto the output timeline with the
+/// same speed factor and offset as the transcribed cues.
+pub fn load_reference(path: &Path, speed: f64, offset: f64) -> Result<Vec<WhisperSegment>> {
+    let mut cues = subparse::read_segments(path)?;
+    if cues.is_empty() {
+        return Err(anyhow!("No cues found in {}", path.display()));
+    }
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for c in &mut cues {
+        retime(c, speed, offset);
+    }
+    Ok(cues)
+}
+
+/// Retime `segments` to their matching `reference` cues; returns how many
+/// were matched.
+pub fn align(segments: &mut [WhisperSegment], reference: &[WhisperSegment]) -> usize {
+    let mut matches: Vec<Option<usize>> = vec![None; segments.len()];
+    // Matches never go backwards, so a repeated line cannot jump ahead
+    let mut next = 0;
+    for (i, seg) in segments.iter().enumerate() {
+        let best = reference
+            .iter()
+            .enumerate()
+            .skip(next)
+            .take_while(|(_, r)| r.start <= seg.end + MAX_DRIFT)
+            .filter(|(_, r)| r.end >= seg.start - MAX_DRIFT)
+            .map(|(j, r)| (j, score(seg, r)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((j, _)) = best.filter(|(_, s)| *s >= MIN_SCORE) {
+            matches[i] = Some(j);
+            next = j;
+        }
+    }
+
+    let mut i = 0;
+    while i < segments.len() {
+        let Some(j) = matches[i] else {
+            i += 1;
+            continue;
+        };
+        let run = matches[i..].iter().take_while(|m| **m == Some(j)).count();
+        let weights: Vec<f64> = segments[i..i + run]
+            .iter()
+            .map(|s| s.text.chars().count().max(1) as f64)
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let (start, end) = (reference[j].start, reference[j].end);
+        let mut t = start;
+        for (seg, w) in segments[i..i + run].iter_mut().zip(&weights) {
+            seg.start = t;
+            t += (end - start) * w / total;
+            seg.end = t;
+        }
+        i += run;
+    }
+    matches.iter().filter(|m| m.is_some()).count()
+}
+
+/// Text similarity weighted over time proximity, 0..1.
+fn score(seg: &WhisperSegment, reference: &WhisperSegment) -> f64 {
+    let mid = |s: &WhisperSegment| (s.start + s.end) / 2.0;
+    let drift = (mid(seg) - mid(reference)).abs().min(MAX_DRIFT);
+    0.7 * text_score(&seg.text, &reference.text) + 0.3 * (1.0 - drift / MAX_DRIFT)
+}
+
+/// Similarity of the bare text; a cue contained in the other (one side
+/// split differently) still counts as a strong match.
+fn text_score(a: &str, b: &str) -> f64 {
+    let bare = |s: &str| -> String { s.chars().filter(|c| c.is_alphanumeric()).collect() };
+    let (a, b) = (bare(a), bare(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let sim = text_similarity(&a, &b);
+    if a.contains(&b) || b.contains(&a) {
+        sim.max(0.8)
+    } else {
+        sim
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_align_adopts_reference_timing() {
+        let reference = vec![
+            seg(10.0, 12.0, "おはようございます。"),
+            seg(13.0, 17.0, "今日はいい天気ですね、散歩しましょう"),
+            seg(20.0, 22.0, "はい"),
+        ];
+        let mut segments = vec![
+            seg(10.6, 12.4, "おはようございます"),
+            // Whisper split the second cue in two
+            seg(13.4, 15.0, "今日はいい天気ですね"),
+            seg(15.0, 17.5, "散歩しましょう"),
+            seg(40.0, 41.0, "全然違う話"),
+        ];
+        assert_eq!(align(&mut segments, &reference), 3);
+        assert_eq!((segments[0].start, segments[0].end), (10.0, 12.0));
+        assert_eq!(segments[1].start, 13.0);
+        assert_eq!(segments[1].end, segments[2].start);
+        assert!(segments[1].end > 15.0 && segments[2].end == 17.0);
+        // Unmatched cues keep their own timing
+        assert_eq!(segments[3].start, 40.0);
+    }
+
+    #[test]
+    fn test_text_score_ignores_punctuation() {
+        assert_eq!(text_score("はい、そうです。", "はいそうです"), 1.0);
+        assert!(text_score("はい", "いいえ") < MIN_SCORE);
+    }
+}
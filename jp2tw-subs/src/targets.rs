@@ -0,0 +1,191 @@
+//! Extra target languages (`--target-lang zh-TW,en,...`). zh-TW always runs
+//! through the full pipeline (name table, translation memory, filters);
+//! every other language is translated from the same Japanese lines after
+//! it, so one transcription serves all of them. Each gets its own subtitle
+//! file and HLS track.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+
+use crate::openai::{ChatRequest, OpenAiClient, Sampling};
+use crate::{extract_first_json_object, try_parse_translations_json, Mode};
+
+/// The language every run produces.
+pub const PRIMARY: &str = "zh-TW";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// BCP 47 tag as written in file names and playlists
+    pub code: String,
+}
+
+impl Target {
+    /// English name used in prompts.
+    pub fn english_name(&self) -> &str {
+        match self.code.to_ascii_lowercase().as_str() {
+            "zh-tw" | "zh-hant" => "Traditional Chinese (Taiwan)",
+            "zh-hk" => "Traditional Chinese (Hong Kong)",
+            "zh-cn" | "zh-hans" | "zh" => "Simplified Chinese",
+            "en" | "en-us" | "en-gb" => "English",
+            "ko" => "Korean",
+            "es" => "Spanish",
+            "fr" => "French",
+            "de" => "German",
+            "pt" | "pt-br" => "Portuguese",
+            "it" => "Italian",
+            "th" => "Thai",
+            "vi" => "Vietnamese",
+            "id" => "Indonesian",
+            _ => &self.code,
+        }
+    }
+
+    /// Name shown to viewers in an HLS track menu.
+    pub fn native_name(&self) -> &str {
+        match self.code.to_ascii_lowercase().as_str() {
+            "zh-tw" | "zh-hant" | "zh-hk" => "繁體中文",
+            "zh-cn" | "zh-hans" | "zh" => "简体中文",
+            "en" | "en-us" | "en-gb" => "English",
+            "ko" => "한국어",
+            "es" => "Español",
+            "fr" => "Français",
+            "de" => "Deutsch",
+            "pt" | "pt-br" => "Português",
+            "it" => "Italiano",
+            "th" => "ไทย",
+            "vi" => "Tiếng Việt",
+            "id" => "Bahasa Indonesia",
+            _ => &self.code,
+        }
+    }
+}
+
+/// The languages of `--target-lang` other than zh-TW, in the order given.
+pub fn extras(codes: &[String]) -> Result<Vec<Target>> {
+    if !codes.iter().any(|c| c.eq_ignore_ascii_case(PRIMARY)) {
+        return Err(anyhow!(
+            "--target-lang must include {PRIMARY} (e.g. {PRIMARY},en); other languages are translated alongside it"
+        ));
+    }
+    let mut out: Vec<Target> = Vec::new();
+    for code in codes {
+        let code = code.trim();
+        let valid = !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(anyhow!("Invalid language tag {code:?} in --target-lang"));
+        }
+        if code.eq_ignore_ascii_case(PRIMARY)
+            || out.iter().any(|t| t.code.eq_ignore_ascii_case(code))
+        {
+            continue;
+        }
+        out.push(Target { code: code.into() });
+    }
+    Ok(out)
+}
+
+/// Translate `lines` (Japanese, as written to the zh-TW file) into `target`.
+pub async fn translate(
+    lines: &[String],
+    target: &Target,
+    client: &OpenAiClient,
+    model: &str,
+    batch_size: usize,
+    mode: Mode,
+    sampling: Sampling,
+) -> Result<Vec<String>> {
+    let mut out = Vec::with_capacity(lines.len());
+    for batch in lines.chunks(batch_size.max(1)) {
+        match translate_batch(batch, target, client, model, mode, sampling).await {
+            Ok(v) if v.len() == batch.len() => out.extend(v),
+            // A short or malformed reply: fall back to one line per request
+            _ => {
+                for line in batch {
+                    out.push(translate_line(line, target, client, model, mode, sampling).await?);
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn system_prompt(target: &Target, mode: Mode) -> String {
+    let name = target.english_name();
+    match mode {
+        Mode::Dialog => format!("You are a professional subtitle translator. Translate Japanese to {name}. Keep meaning, tone, and register; keep lines short enough to read as subtitles. Do not add explanations."),
+        Mode::Lyrics => format!("You are a lyricist translating Japanese song lyrics into {name}. Favor natural, poetic phrasing over literal rendering, but keep the meaning of each line. Translate every line on its own: never merge, split, or reorder lines. Do not add explanations."),
+    }
+}
+
+async fn translate_batch(
+    lines: &[String],
+    target: &Target,
+    client: &OpenAiClient,
+    model: &str,
+    mode: Mode,
+    sampling: Sampling,
+) -> Result<Vec<String>> {
+    let user = json!({
+        "instruction": format!("Translate each item to {}. Return strict JSON with {{\"translations\": string[]}} matching the input length.", target.english_name()),
+        "source_language": "ja",
+        "target_language": target.code,
+        "items": lines,
+    });
+    let req = ChatRequest::new(model)
+        .system(system_prompt(target, mode))
+        .user(user.to_string())
+        .json_object()
+        .sampling(sampling);
+    let content = client
+        .chat(&req)
+        .await
+        .with_context(|| format!("OpenAI {} translation request failed", target.code))?;
+    try_parse_translations_json(&content)
+        .or_else(|| {
+            extract_first_json_object(&content).and_then(|s| try_parse_translations_json(&s))
+        })
+        .ok_or_else(|| anyhow!("Translation JSON missing 'translations' array"))
+}
+
+async fn translate_line(
+    line: &str,
+    target: &Target,
+    client: &OpenAiClient,
+    model: &str,
+    mode: Mode,
+    sampling: Sampling,
+) -> Result<String> {
+    if line.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let req = ChatRequest::new(model)
+        .system(format!(
+            "{} Output only the translated text without quotes.",
+            system_prompt(target, mode)
+        ))
+        .user(line)
+        .sampling(sampling);
+    let content = client
+        .chat(&req)
+        .await
+        .with_context(|| format!("OpenAI {} translation request failed", target.code))?;
+    Ok(content.trim().trim_matches('"').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extras() {
+        let codes = |s: &str| s.split(',').map(String::from).collect::<Vec<_>>();
+        let found = extras(&codes("zh-TW,en,ko,EN,zh-tw")).unwrap();
+        let found: Vec<&str> = found.iter().map(|t| t.code.as_str()).collect();
+        assert_eq!(found, ["en", "ko"]);
+        assert!(extras(&codes("zh-TW")).unwrap().is_empty());
+        assert!(extras(&codes("en")).is_err());
+        assert!(extras(&codes("zh-TW,e n")).is_err());
+        assert_eq!(Target { code: "en".into() }.english_name(), "English");
+        assert_eq!(Target { code: "xx".into() }.native_name(), "xx");
+    }
+}
@@ -0,0 +1,344 @@
+//! OpenTelemetry tracing: pipeline stages and OpenAI calls are recorded as
+//! spans under one root span per run and exported once at the end over
+//! OTLP/HTTP (JSON encoding), so runs show up in Jaeger, Tempo, Honeycomb, or
+//! any OTLP collector. Disabled unless an endpoint is configured.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Internal,
+    Client,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    Str(String),
+    Int(i64),
+    F64(f64),
+    Bool(bool),
+}
+
+impl From<&str> for AttrValue {
+    fn from(v: &str) -> Self {
+        AttrValue::Str(v.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(v: String) -> Self {
+        AttrValue::Str(v)
+    }
+}
+
+impl From<i64> for AttrValue {
+    fn from(v: i64) -> Self {
+        AttrValue::Int(v)
+    }
+}
+
+impl From<usize> for AttrValue {
+    fn from(v: usize) -> Self {
+        AttrValue::Int(v as i64)
+    }
+}
+
+impl From<f64> for AttrValue {
+    fn from(v: f64) -> Self {
+        AttrValue::F64(v)
+    }
+}
+
+impl From<bool> for AttrValue {
+    fn from(v: bool) -> Self {
+        AttrValue::Bool(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SpanData {
+    name: String,
+    kind: Kind,
+    span_id: String,
+    start_ns: u128,
+    end_ns: u128,
+    attrs: Vec<(String, AttrValue)>,
+    error: Option<String>,
+}
+
+struct Collector {
+    url: String,
+    headers: Vec<(String, String)>,
+    service: String,
+    trace_id: String,
+    root_id: String,
+    root_start_ns: u128,
+    spans: Vec<SpanData>,
+}
+
+tokio::task_local! {
+    /// The current run's collector; task-local so concurrent batch jobs each
+    /// get their own trace.
+    static COLLECTOR: RefCell<Option<Collector>>;
+}
+
+/// Run `f` with a collector slot of its own; outside one, tracing is off.
+pub async fn scope<F: Future>(f: F) -> F::Output {
+    COLLECTOR.scope(RefCell::new(None), f).await
+}
+
+fn with_collector<R>(f: impl FnOnce(&mut Option<Collector>) -> R) -> Option<R> {
+    COLLECTOR.try_with(|c| f(&mut c.borrow_mut())).ok()
+}
+
+/// OTLP traces URL from `--otlp-endpoint` or the standard `OTEL_EXPORTER_OTLP_*`
+/// variables; None leaves tracing off.
+pub fn endpoint(flag: Option<&str>) -> Option<String> {
+    let base = |b: &str| format!("{}/v1/traces", b.trim_end_matches('/'));
+    flag.map(base)
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").ok())
+        .or_else(|| {
+            std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .map(|b| base(&b))
+        })
+        .filter(|u| !u.trim().is_empty())
+}
+
+/// Start collecting spans for this run (inside [`scope`]).
+pub fn init(url: String) {
+    let headers = std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let collector = Collector {
+        url,
+        headers,
+        service: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "jp2tw-subs".into()),
+        trace_id: random_hex(2),
+        root_id: random_hex(1),
+        root_start_ns: now_ns(),
+        spans: Vec::new(),
+    };
+    with_collector(|c| *c = Some(collector));
+}
+
+/// A span that ends (and is recorded) when dropped. Free when tracing is off.
+pub struct Span(Option<SpanData>);
+
+impl Span {
+    /// Set an attribute, replacing an earlier value for the same key.
+    pub fn attr(&mut self, key: &str, value: impl Into<AttrValue>) {
+        if let Some(s) = &mut self.0 {
+            let value = value.into();
+            match s.attrs.iter_mut().find(|(k, _)| k == key) {
+                Some(slot) => slot.1 = value,
+                None => s.attrs.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    pub fn fail(&mut self, message: impl std::fmt::Display) {
+        if let Some(s) = &mut self.0 {
+            s.error = Some(message.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(mut s) = self.0.take() {
+            s.end_ns = now_ns();
+            with_collector(|c| {
+                if let Some(c) = c {
+                    c.spans.push(s);
+                }
+            });
+        }
+    }
+}
+
+pub fn span(name: &str) -> Span {
+    span_of_kind(name, Kind::Internal)
+}
+
+pub fn span_of_kind(name: &str, kind: Kind) -> Span {
+    if !with_collector(|c| c.is_some()).unwrap_or(false) {
+        return Span(None);
+    }
+    Span(Some(SpanData {
+        name: name.to_string(),
+        kind,
+        span_id: random_hex(1),
+        start_ns: now_ns(),
+        end_ns: 0,
+        attrs: Vec::new(),
+        error: None,
+    }))
+}
+
+/// End the root span and send every span of the run to the collector.
+pub async fn export(root_attrs: Vec<(String, AttrValue)>, error: Option<String>) -> Result<()> {
+    let Some(c) = with_collector(Option::take).flatten() else {
+        return Ok(());
+    };
+    let root = SpanData {
+        name: "jp2tw-subs.run".into(),
+        kind: Kind::Internal,
+        span_id: c.root_id.clone(),
+        start_ns: c.root_start_ns,
+        end_ns: now_ns(),
+        attrs: root_attrs,
+        error,
+    };
+    let body = otlp_json(&c, &root);
+    let mut req = crate::http::client().post(&c.url).json(&body);
+    for (k, v) in &c.headers {
+        req = req.header(k, v);
+    }
+    let resp = req
+        .send()
+        .await
+        .with_context(|| format!("Send traces to {}", c.url))?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("OTLP collector returned {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// OTLP/JSON `ExportTraceServiceRequest` for the root span and its children.
+fn otlp_json(c: &Collector, root: &SpanData) -> Value {
+    let span = |s: &SpanData, parent: Option<&str>| {
+        let mut v = json!({
+            "traceId": c.trace_id,
+            "spanId": s.span_id,
+            "name": s.name,
+            "kind": match s.kind { Kind::Internal => 1, Kind::Client => 3 },
+            // 64-bit integers are strings in OTLP/JSON
+            "startTimeUnixNano": s.start_ns.to_string(),
+            "endTimeUnixNano": s.end_ns.to_string(),
+            "attributes": attributes(&s.attrs),
+            "status": match &s.error {
+                Some(m) => json!({"code": 2, "message": m}),
+                None => json!({"code": 1}),
+            },
+        });
+        if let Some(p) = parent {
+            v["parentSpanId"] = json!(p);
+        }
+        v
+    };
+    let mut spans = vec![span(root, None)];
+    spans.extend(c.spans.iter().map(|s| span(s, Some(&c.root_id))));
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": attributes(&[
+                    ("service.name".into(), AttrValue::Str(c.service.clone())),
+                    ("service.version".into(), env!("CARGO_PKG_VERSION").into()),
+                ]),
+            },
+            "scopeSpans": [{
+                "scope": {"name": "jp2tw-subs", "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn attributes(attrs: &[(String, AttrValue)]) -> Value {
+    attrs
+        .iter()
+        .map(|(k, v)| {
+            let value = match v {
+                AttrValue::Str(s) => json!({"stringValue": s}),
+                AttrValue::Int(i) => json!({"intValue": i.to_string()}),
+                AttrValue::F64(f) => json!({"doubleValue": f}),
+                AttrValue::Bool(b) => json!({"boolValue": b}),
+            };
+            json!({"key": k, "value": value})
+        })
+        .collect()
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+/// `words` random 64-bit words as lowercase hex (trace IDs are 2, span IDs 1).
+fn random_hex(words: usize) -> String {
+    (0..words)
+        .map(|i| {
+            let mut h = RandomState::new().build_hasher();
+            h.write_u128(now_ns());
+            h.write_usize(i);
+            format!("{:016x}", h.finish())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otlp_json_shape() {
+        let c = Collector {
+            url: String::new(),
+            headers: Vec::new(),
+            service: "svc".into(),
+            trace_id: random_hex(2),
+            root_id: random_hex(1),
+            root_start_ns: 1,
+            spans: vec![SpanData {
+                name: "openai.chat".into(),
+                kind: Kind::Client,
+                span_id: random_hex(1),
+                start_ns: 2,
+                end_ns: 3,
+                attrs: vec![("attempts".into(), 2usize.into())],
+                error: Some("rate limited".into()),
+            }],
+        };
+        let root = SpanData {
+            name: "run".into(),
+            kind: Kind::Internal,
+            span_id: c.root_id.clone(),
+            start_ns: 1,
+            end_ns: 4,
+            attrs: Vec::new(),
+            error: None,
+        };
+        let v = otlp_json(&c, &root);
+        let spans = &v["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[0]["traceId"].as_str().unwrap().len(), 32);
+        assert!(spans[0].get("parentSpanId").is_none());
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert_eq!(spans[1]["kind"], 3);
+        assert_eq!(spans[1]["attributes"][0]["value"]["intValue"], "2");
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(
+            v["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "svc"
+        );
+    }
+
+    #[test]
+    fn test_span_is_noop_when_disabled() {
+        // No collector was initialized in this test binary
+        let mut s = span("noop");
+        s.attr("k", "v");
+        assert!(s.0.is_none());
+    }
+}
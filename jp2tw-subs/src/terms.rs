@@ -0,0 +1,225 @@
+//! `--terms terms.toml`: compliance terms for client work. Banned terms are
+//! swapped for their required replacement, and brand names are forced to
+//! their one approved spelling, in every translated line. What cannot be
+//! fixed that way (a banned term with no replacement, or a brand the
+//! Japanese mentions but the translation dropped) is reported after the
+//! run for a human to resolve.
+//!
+//! ```toml
+//! [[banned]]
+//! term = "神奇寶貝"
+//! replace = "寶可夢"
+//!
+//! [[banned]]
+//! term = "最便宜"        # no replacement: reported only
+//!
+//! [[required]]
+//! term = "iPhone"
+//! variants = ["愛瘋", "蘋果手機"]
+//! source = "iPhone|アイフォン"   # the translation must say "iPhone" when this matches
+//! ```
+//!
+//! Matching ignores ASCII case, so `IPHONE` is rewritten to `iPhone` too.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::lint::{Issue, Severity};
+use crate::WhisperSegment;
+
+#[derive(Deserialize)]
+struct TermsFile {
+    #[serde(default)]
+    banned: Vec<RawBanned>,
+    #[serde(default)]
+    required: Vec<RawRequired>,
+}
+
+#[derive(Deserialize)]
+struct RawBanned {
+    term: String,
+    replace: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawRequired {
+    term: String,
+    #[serde(default)]
+    variants: Vec<String>,
+    source: Option<String>,
+}
+
+struct Banned {
+    term: String,
+    re: Regex,
+    replace: Option<String>,
+}
+
+struct Required {
+    term: String,
+    /// The term and its variants, any case
+    re: Regex,
+    source: Option<Regex>,
+}
+
+#[derive(Default)]
+pub struct Terms {
+    banned: Vec<Banned>,
+    required: Vec<Required>,
+}
+
+/// `words` as one case-insensitive literal alternation, longest first.
+fn literal(words: &[&str]) -> Result<Regex> {
+    let mut words: Vec<&str> = words.iter().copied().filter(|w| !w.is_empty()).collect();
+    if words.is_empty() {
+        return Err(anyhow::anyhow!("empty term"));
+    }
+    words.sort_by_key(|w| std::cmp::Reverse(w.chars().count()));
+    let alternation: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+    Ok(RegexBuilder::new(&alternation.join("|"))
+        .case_insensitive(true)
+        .build()?)
+}
+
+impl Terms {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Read terms {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Parse terms {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let file: TermsFile = toml::from_str(text)?;
+        let banned = file
+            .banned
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Ok(Banned {
+                    re: literal(&[&b.term]).with_context(|| format!("Banned term #{}", i + 1))?,
+                    term: b.term,
+                    replace: b.replace,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let required = file
+            .required
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let mut words = vec![r.term.as_str()];
+                words.extend(r.variants.iter().map(String::as_str));
+                Ok(Required {
+                    re: literal(&words).with_context(|| format!("Required term #{}", i + 1))?,
+                    source: r
+                        .source
+                        .as_deref()
+                        .map(Regex::new)
+                        .transpose()
+                        .with_context(|| format!("Required term #{}: invalid source", i + 1))?,
+                    term: r.term,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { banned, required })
+    }
+
+    pub fn len(&self) -> usize {
+        self.banned.len() + self.required.len()
+    }
+
+    /// `zh` with banned terms replaced and brand spellings corrected.
+    pub fn fix(&self, zh: &str) -> String {
+        let mut out = zh.to_string();
+        for b in &self.banned {
+            if let Some(replace) = &b.replace {
+                out =
+                    b.re.replace_all(&out, regex::NoExpand(replace))
+                        .into_owned();
+            }
+        }
+        for r in &self.required {
+            out =
+                r.re.replace_all(&out, regex::NoExpand(&r.term))
+                    .into_owned();
+        }
+        out
+    }
+
+    /// Violations left in the written lines, numbered by cue.
+    pub fn report(&self, segments: &[WhisperSegment], zh_lines: &[String]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for (i, (seg, zh)) in segments.iter().zip(zh_lines).enumerate() {
+            let mut report = |message: String| {
+                issues.push(Issue {
+                    cue: i + 1,
+                    start: seg.start,
+                    severity: Severity::Error,
+                    message,
+                })
+            };
+            for b in self.banned.iter().filter(|b| b.re.is_match(zh)) {
+                report(format!("banned term \"{}\"", b.term));
+            }
+            for r in &self.required {
+                let mentioned = r.source.as_ref().is_some_and(|s| s.is_match(&seg.text));
+                if mentioned && !zh.contains(&r.term) {
+                    report(format!("required term \"{}\" is missing", r.term));
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fix_and_report() {
+        let terms = Terms::parse(
+            r#"
+[[banned]]
+term = "神奇寶貝"
+replace = "寶可夢"
+
+[[banned]]
+term = "最便宜"
+
+[[required]]
+term = "iPhone"
+variants = ["愛瘋"]
+source = "iPhone|アイフォン"
+"#,
+        )
+        .unwrap();
+        assert_eq!(terms.len(), 3);
+        assert_eq!(
+            terms.fix("用IPHONE和愛瘋抓神奇寶貝"),
+            "用iPhone和iPhone抓寶可夢"
+        );
+        let segs = [
+            WhisperSegment {
+                start: 1.0,
+                text: "アイフォンを買った".into(),
+                ..Default::default()
+            },
+            WhisperSegment {
+                start: 3.0,
+                text: "行こう".into(),
+                ..Default::default()
+            },
+        ];
+        let zh = ["買了手機".to_string(), terms.fix("最便宜的")];
+        let issues = terms.report(&segs, &zh);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].message, "required term \"iPhone\" is missing");
+        assert_eq!(
+            (issues[1].cue, issues[1].message.as_str()),
+            (2, "banned term \"最便宜\"")
+        );
+    }
+}
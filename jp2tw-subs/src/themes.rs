@@ -0,0 +1,350 @@
+//! `--detect-op-ed`: in a `--jobs-file` batch of episodes, find the opening
+//! and ending themes by the audio they share. Each episode is fingerprinted
+//! (32-bit band-energy hashes every 64 ms, as in Haitsma & Kalker's audio
+//! fingerprinting) and compared with its neighbours in the manifest; a
+//! stretch of 30 s to 3 min heard in at least two of them is a theme. The
+//! ranges become `--skip-range` or `--song-range` windows of that episode's
+//! job, so a season needs no timestamps per episode.
+//!
+//! A recap repeats only the previous episode, so it is not taken for a theme
+//! once three or more episodes are compared.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use crate::{cancel, skip::SkipRange};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DetectOpEd {
+    /// Caption the themes like any other audio
+    #[default]
+    Off,
+    /// Leave them out, captioned （片頭曲） / （片尾曲）
+    Skip,
+    /// Subtitle them as songs (italics behind a ♪)
+    Songs,
+}
+
+const RATE: u32 = 8_000;
+const WINDOW: usize = 2048;
+const HOP: usize = 512;
+/// Seconds per fingerprint frame
+const FRAME: f64 = HOP as f64 / RATE as f64;
+/// Band edges span 300-2000 Hz, where music carries most of its energy
+const LOW_HZ: f64 = 300.0;
+const HIGH_HZ: f64 = 2000.0;
+const BANDS: usize = 33;
+/// Frames below this RMS (of 32768) hash nothing: silence matches silence
+const QUIET_RMS: f64 = 100.0;
+
+/// Differing bits for two frames to still count as the same audio
+const MAX_BIT_ERRORS: u32 = 10;
+/// Hashes repeated more often than this (sustained notes) cast no votes
+const MAX_HITS: usize = 50;
+/// Exact hash matches an offset needs to be checked frame by frame
+const MIN_VOTES: usize = 4;
+/// Offsets checked per episode pair, most votes first
+const CANDIDATES: usize = 8;
+/// Unmatched frames (~1 s) bridged inside a repeated stretch
+const MAX_GAP: usize = 16;
+/// Share of a stretch's frames that have to match
+const MIN_DENSITY: f64 = 0.3;
+const MIN_THEME: f64 = 30.0;
+const MAX_THEME: f64 = 180.0;
+/// Episodes each one is compared with
+const PARTNERS: usize = 3;
+
+/// The themes of each of `inputs` (episodes, in order), labelled
+/// （片頭曲） in the first half of the episode and （片尾曲） after.
+pub fn detect(inputs: &[&Path]) -> Result<Vec<Vec<SkipRange>>> {
+    let work = tempfile::tempdir()?;
+    let prints = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            cancel::check()?;
+            eprintln!(
+                "Fingerprinting episode {}/{} for OP/ED detection: {}",
+                i + 1,
+                inputs.len(),
+                input.display()
+            );
+            Ok(fingerprint(&decode(input, &work.path().join("audio.raw"))?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((0..prints.len()).map(|i| themes(&prints, i)).collect())
+}
+
+/// ffmpeg arguments writing `input`'s audio as 8 kHz mono s16le to `out`.
+fn decode_args(input: &Path, out: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-nostdin", "-y", "-loglevel", "error", "-i"]
+        .map(String::from)
+        .into();
+    args.push(input.to_string_lossy().into_owned());
+    args.extend(["-vn", "-ac", "1", "-ar", &RATE.to_string(), "-f", "s16le"].map(String::from));
+    args.push(out.to_string_lossy().into_owned());
+    args
+}
+
+fn decode(input: &Path, out: &Path) -> Result<Vec<i16>> {
+    let status = cancel::status(Command::new("ffmpeg").args(decode_args(input, out)))
+        .context("Failed to run ffmpeg to decode audio for OP/ED detection")?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg failed to decode {}", input.display()));
+    }
+    let bytes = std::fs::read(out)?;
+    let _ = std::fs::remove_file(out);
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+/// One hash per frame of 8 kHz `samples`: bit m is set when the energy
+/// difference between bands m and m+1 grew since the previous frame. Quiet
+/// frames (and the first) have none.
+fn fingerprint(samples: &[i16]) -> Vec<Option<u32>> {
+    let hann: Vec<f64> = (0..WINDOW)
+        .map(|i| 0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / WINDOW as f64).cos())
+        .collect();
+    let edges: Vec<usize> = (0..=BANDS)
+        .map(|b| {
+            let hz = LOW_HZ * (HIGH_HZ / LOW_HZ).powf(b as f64 / BANDS as f64);
+            (hz * WINDOW as f64 / f64::from(RATE)).round() as usize
+        })
+        .collect();
+    let twiddles: Vec<(f64, f64)> = (0..WINDOW / 2)
+        .map(|k| (-std::f64::consts::TAU * k as f64 / WINDOW as f64).sin_cos())
+        .collect();
+
+    let mut out = Vec::new();
+    let mut prev: Option<Vec<f64>> = None;
+    let mut at = 0;
+    while at + WINDOW <= samples.len() {
+        let frame = &samples[at..at + WINDOW];
+        let rms = (frame.iter().map(|&s| f64::from(s).powi(2)).sum::<f64>() / WINDOW as f64).sqrt();
+        let mut re: Vec<f64> = frame
+            .iter()
+            .zip(&hann)
+            .map(|(&s, w)| f64::from(s) * w)
+            .collect();
+        let mut im = vec![0.0; WINDOW];
+        fft(&mut re, &mut im, &twiddles);
+        let energy: Vec<f64> = edges
+            .windows(2)
+            .map(|e| {
+                (e[0]..e[1].max(e[0] + 1))
+                    .map(|k| re[k] * re[k] + im[k] * im[k])
+                    .sum()
+            })
+            .collect();
+        let hash = prev.as_ref().map(|p| {
+            (0..BANDS - 1).fold(0u32, |bits, m| {
+                let grew = energy[m] - energy[m + 1] - (p[m] - p[m + 1]) > 0.0;
+                bits << 1 | u32::from(grew)
+            })
+        });
+        out.push(hash.filter(|_| rms >= QUIET_RMS));
+        prev = Some(energy);
+        at += HOP;
+    }
+    out
+}
+
+/// In-place radix-2 FFT; `twiddles[k]` is (sin, cos) of -2πk/n.
+fn fft(re: &mut [f64], im: &mut [f64], twiddles: &[(f64, f64)]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (s, c) = twiddles[k * step];
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * c - im[b] * s;
+                let ti = re[b] * s + im[b] * c;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Frame ranges (inclusive, merged) of `a` whose audio is also in `b`.
+fn repeats(a: &[Option<u32>], b: &[Option<u32>]) -> Vec<(usize, usize)> {
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (j, hash) in b.iter().enumerate() {
+        if let Some(hash) = hash {
+            index.entry(*hash).or_default().push(j);
+        }
+    }
+    // Audio shared by both sits at one offset between them
+    let mut votes: HashMap<isize, usize> = HashMap::new();
+    for (i, hash) in a.iter().enumerate() {
+        let Some(hits) = hash.and_then(|h| index.get(&h)) else {
+            continue;
+        };
+        if hits.len() <= MAX_HITS {
+            for &j in hits {
+                *votes.entry(i as isize - j as isize).or_default() += 1;
+            }
+        }
+    }
+    let mut offsets: Vec<(isize, usize)> =
+        votes.into_iter().filter(|&(_, n)| n >= MIN_VOTES).collect();
+    offsets.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+
+    let min_frames = (MIN_THEME / FRAME) as usize;
+    let mut out = Vec::new();
+    for &(d, _) in offsets.iter().take(CANDIDATES) {
+        let same = |i: usize| {
+            let j = i as isize - d;
+            match (
+                a[i],
+                usize::try_from(j)
+                    .ok()
+                    .and_then(|j| b.get(j))
+                    .copied()
+                    .flatten(),
+            ) {
+                (Some(x), Some(y)) => (x ^ y).count_ones() <= MAX_BIT_ERRORS,
+                _ => false,
+            }
+        };
+        // (first, last, matched frames) of the current stretch
+        let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+        for i in (0..a.len()).filter(|&i| same(i)) {
+            match runs.last_mut() {
+                Some((_, last, n)) if i - *last <= MAX_GAP => {
+                    *last = i;
+                    *n += 1;
+                }
+                _ => runs.push((i, i, 1)),
+            }
+        }
+        out.extend(
+            runs.into_iter()
+                .filter(|&(first, last, n)| {
+                    let len = last - first + 1;
+                    len >= min_frames && n as f64 >= MIN_DENSITY * len as f64
+                })
+                .map(|(first, last, _)| (first, last)),
+        );
+    }
+    out.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (first, last) in out {
+        match merged.last_mut() {
+            Some((_, end)) if first <= *end + 1 => *end = (*end).max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    merged
+}
+
+/// The themes of episode `i`: stretches repeated in enough of its nearest
+/// episodes.
+fn themes(prints: &[Vec<Option<u32>>], i: usize) -> Vec<SkipRange> {
+    let mut partners: Vec<usize> = (0..prints.len()).filter(|&j| j != i).collect();
+    partners.sort_by_key(|&j| (j.abs_diff(i), j));
+    partners.truncate(PARTNERS);
+    let need = partners.len().min(2);
+    let frames = prints[i].len();
+    if need == 0 || frames == 0 {
+        return Vec::new();
+    }
+    let mut heard = vec![0; frames];
+    for j in partners {
+        for (first, last) in repeats(&prints[i], &prints[j]) {
+            heard[first..=last].iter_mut().for_each(|n| *n += 1);
+        }
+    }
+    let mut out = Vec::new();
+    let mut at = 0;
+    while at < frames {
+        if heard[at] < need {
+            at += 1;
+            continue;
+        }
+        let first = at;
+        while at < frames && heard[at] >= need {
+            at += 1;
+        }
+        let (start, end) = (first as f64 * FRAME, at as f64 * FRAME);
+        if (MIN_THEME..=MAX_THEME).contains(&(end - start)) {
+            let label = if start < frames as f64 * FRAME / 2.0 {
+                "（片頭曲）"
+            } else {
+                "（片尾曲）"
+            };
+            out.push(SkipRange {
+                start,
+                end,
+                label: Some(label.to_string()),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic stand-in for the hashes of unrelated audio.
+    fn noise(seed: u32, n: usize) -> Vec<Option<u32>> {
+        let mut x = seed.max(1);
+        (0..n)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                Some(x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_themes() {
+        // A 60 s "OP" at different places in three episodes
+        let op = noise(7, 940);
+        let episode = |seed: u32, at: usize| {
+            let mut e = noise(seed, 3000);
+            e.splice(at..at + op.len(), op.iter().copied());
+            e
+        };
+        let prints = [episode(1, 100), episode(2, 500), episode(3, 0)];
+        let found = themes(&prints, 1);
+        assert_eq!(found.len(), 1);
+        assert!((found[0].start - 500.0 * FRAME).abs() < 1.0);
+        assert!((found[0].end - 1440.0 * FRAME).abs() < 1.0);
+        assert_eq!(found[0].label.as_deref(), Some("（片頭曲）"));
+        assert!(repeats(&prints[0], &noise(4, 3000)).is_empty());
+
+        // Audio shifted by one hop hashes the same from there on
+        let tone: Vec<i16> = (0..RATE as usize * 2)
+            .map(|i| ((i as f64 * 0.37).sin() * 8000.0 + (i as f64 * 0.05).sin() * 4000.0) as i16)
+            .collect();
+        let (a, b) = (fingerprint(&tone), fingerprint(&tone[HOP..]));
+        assert_eq!(a[2..], b[1..b.len()]);
+    }
+}
@@ -0,0 +1,228 @@
+//! Translation memory (`--tm`): Japanese -> zh-TW pairs from earlier runs,
+//! reused before calling the API. The store is a standard TMX 1.4 file, so it
+//! can be exchanged with CAT tools as-is; `--tm-import` merges other TMX files.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::formats::xml_escape;
+use crate::text_similarity;
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+pub struct TranslationMemory {
+    path: PathBuf,
+    entries: Vec<(String, String)>,
+    index: HashMap<String, usize>,
+    min_similarity: f64,
+    dirty: bool,
+}
+
+impl TranslationMemory {
+    /// Open the memory at `path`, starting empty if the file does not exist.
+    pub fn open(path: &Path, min_similarity: f64) -> Result<Self> {
+        let mut tm = Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+            index: HashMap::new(),
+            min_similarity: min_similarity.clamp(0.0, 1.0),
+            dirty: false,
+        };
+        if path.exists() {
+            for (ja, zh) in read_tmx(path)? {
+                tm.insert(&ja, &zh);
+            }
+            tm.dirty = false;
+        }
+        Ok(tm)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Merge a TMX file; returns the number of ja/zh-TW units read.
+    pub fn import(&mut self, tmx: &Path) -> Result<usize> {
+        let pairs = read_tmx(tmx)?;
+        let n = pairs.len();
+        for (ja, zh) in pairs {
+            self.insert(&ja, &zh);
+        }
+        Ok(n)
+    }
+
+    /// Stored translation for an exact match, else for the most similar
+    /// source at or above the similarity threshold.
+    pub fn lookup(&self, ja: &str) -> Option<&str> {
+        let key = ja.trim();
+        if let Some(&i) = self.index.get(key) {
+            return Some(&self.entries[i].1);
+        }
+        if self.min_similarity >= 1.0 || key.is_empty() {
+            return None;
+        }
+        let len = key.chars().count() as f64;
+        let mut best: Option<(f64, usize)> = None;
+        for (i, (src, _)) in self.entries.iter().enumerate() {
+            // Cheap length bound before the edit-distance check
+            let other = src.chars().count() as f64;
+            if (len - other).abs() > (1.0 - self.min_similarity) * len.max(other) {
+                continue;
+            }
+            let sim = text_similarity(key, src);
+            if sim >= self.min_similarity && best.is_none_or(|(b, _)| sim > b) {
+                best = Some((sim, i));
+            }
+        }
+        best.map(|(_, i)| self.entries[i].1.as_str())
+    }
+
+    /// Add or update a pair; the newest translation of a source wins.
+    pub fn insert(&mut self, ja: &str, zh: &str) {
+        let (ja, zh) = (ja.trim(), zh.trim());
+        if ja.is_empty() || zh.is_empty() {
+            return;
+        }
+        match self.index.get(ja) {
+            Some(&i) if self.entries[i].1 == zh => return,
+            Some(&i) => self.entries[i].1 = zh.to_string(),
+            None => {
+                self.index.insert(ja.to_string(), self.entries.len());
+                self.entries.push((ja.to_string(), zh.to_string()));
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Write the memory back as TMX if anything changed.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".part");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, to_tmx(&self.entries))
+            .with_context(|| format!("Write translation memory {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Move {} into place", tmp.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn read_tmx(path: &Path) -> Result<Vec<(String, String)>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Read TMX {}", path.display()))?;
+    parse_tmx(&text).with_context(|| format!("Parse TMX {}", path.display()))
+}
+
+/// (ja, zh-TW) pairs from every `<tu>` with both variants. Plain `zh` and
+/// `zh-Hant*` count as Taiwan Chinese; simplified variants are skipped.
+fn parse_tmx(text: &str) -> Result<Vec<(String, String)>> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| anyhow!("{}", e))?;
+    let mut out = Vec::new();
+    for tu in doc.descendants().filter(|n| n.has_tag_name("tu")) {
+        let mut ja = None;
+        let mut zh = None;
+        for tuv in tu.children().filter(|n| n.has_tag_name("tuv")) {
+            let lang = tuv
+                .attribute((XML_NS, "lang"))
+                .or_else(|| tuv.attribute("lang"))
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            let Some(seg) = tuv.children().find(|n| n.has_tag_name("seg")) else {
+                continue;
+            };
+            let text = seg_text(seg);
+            if lang == "ja" || lang.starts_with("ja-") {
+                ja = Some(text);
+            } else if is_traditional_chinese(&lang) {
+                zh = Some(text);
+            }
+        }
+        if let (Some(ja), Some(zh)) = (ja, zh) {
+            out.push((ja, zh));
+        }
+    }
+    Ok(out)
+}
+
+fn is_traditional_chinese(lang: &str) -> bool {
+    matches!(lang, "zh" | "zh-tw" | "zh-hk" | "zh-mo") || lang.starts_with("zh-hant")
+}
+
+/// Segment text without inline native codes (`<ph>`, `<bpt>`, `<ept>`, `<it>`).
+fn seg_text(seg: roxmltree::Node) -> String {
+    let mut s = String::new();
+    for n in seg.descendants().filter(|n| n.is_text()) {
+        let in_code = n.ancestors().take_while(|a| *a != seg).any(|a| {
+            a.has_tag_name("ph")
+                || a.has_tag_name("bpt")
+                || a.has_tag_name("ept")
+                || a.has_tag_name("it")
+        });
+        if !in_code {
+            s.push_str(n.text().unwrap_or(""));
+        }
+    }
+    s.trim().to_string()
+}
+
+fn to_tmx(entries: &[(String, String)]) -> String {
+    let mut s = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<tmx version=\"1.4\">\n",
+        "  <header creationtool=\"jp2tw-subs\" creationtoolversion=\"",
+        env!("CARGO_PKG_VERSION"),
+        "\" segtype=\"sentence\" o-tmf=\"jp2tw-subs\" adminlang=\"en\" srclang=\"ja\" datatype=\"plaintext\"/>\n",
+        "  <body>\n"
+    ));
+    for (ja, zh) in entries {
+        s.push_str(&format!(
+            "    <tu>\n      <tuv xml:lang=\"ja\"><seg>{}</seg></tuv>\n      <tuv xml:lang=\"zh-TW\"><seg>{}</seg></tuv>\n    </tu>\n",
+            xml_escape(ja),
+            xml_escape(zh)
+        ));
+    }
+    s.push_str("  </body>\n</tmx>\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tmx_round_trip_and_import_langs() {
+        let entries = vec![("「A&B」です".to_string(), "是「A&B」".to_string())];
+        assert_eq!(parse_tmx(&to_tmx(&entries)).unwrap(), entries);
+
+        let external = r#"<?xml version="1.0"?>
+<tmx version="1.4"><header srclang="ja-JP"/><body>
+  <tu><tuv xml:lang="ja-JP"><seg>こんにちは<ph>{1}</ph></seg></tuv>
+      <tuv xml:lang="zh-Hant-TW"><seg>你好</seg></tuv></tu>
+  <tu><tuv lang="JA"><seg>猫</seg></tuv><tuv lang="zh-CN"><seg>猫</seg></tuv></tu>
+</body></tmx>"#;
+        assert_eq!(
+            parse_tmx(external).unwrap(),
+            vec![("こんにちは".to_string(), "你好".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_lookup_exact_and_fuzzy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.tmx");
+        let mut tm = TranslationMemory::open(&path, 0.8).unwrap();
+        tm.insert("今日はいい天気ですね", "今天天氣真好呢");
+        tm.save().unwrap();
+
+        let tm = TranslationMemory::open(&path, 0.8).unwrap();
+        assert_eq!(tm.len(), 1);
+        assert_eq!(tm.lookup("今日はいい天気ですね"), Some("今天天氣真好呢"));
+        assert_eq!(tm.lookup("今日はいい天気ですよ"), Some("今天天氣真好呢"));
+        assert_eq!(tm.lookup("明日は雨"), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Token estimates for sizing translation batches: a tiktoken-style count
+//! without shipping a vocabulary (about one token per kana, kanji, or
+//! punctuation mark, and one per four characters of ASCII words), and the
+//! greedy split of lines into batches that fit a token budget.
+
+use std::ops::Range;
+
+/// Approximate tokens in `text`, close to OpenAI's tokenizers for Japanese
+/// and Chinese and slightly over for English.
+pub fn count(text: &str) -> u64 {
+    let mut n = 0;
+    // Length of the current ASCII word or number
+    let mut run: u64 = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            run += 1;
+            continue;
+        }
+        n += run.div_ceil(4);
+        run = 0;
+        if !c.is_whitespace() {
+            n += 1;
+        }
+    }
+    n + run.div_ceil(4)
+}
+
+/// Estimated tokens a line adds to a translation request: the line, a reply
+/// about as long, and the JSON quoting around both.
+pub fn line_cost(line: &str) -> u64 {
+    count(line) * 2 + 4
+}
+
+/// Consecutive batches of `costs` of at most `max_lines` lines and `budget`
+/// tokens each. A line over budget on its own still gets a batch.
+pub fn batches(costs: &[u64], max_lines: usize, budget: u64) -> Vec<Range<usize>> {
+    let max_lines = max_lines.max(1);
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (i, &cost) in costs.iter().enumerate() {
+        if i > start && (i - start == max_lines || used + cost > budget) {
+            out.push(start..i);
+            start = i;
+            used = 0;
+        }
+        used += cost;
+    }
+    if start < costs.len() {
+        out.push(start..costs.len());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_and_batches() {
+        assert_eq!(count("ありがとう。"), 6);
+        assert_eq!(count("Hello, world"), 5);
+        assert_eq!(count(""), 0);
+        assert_eq!(line_cost("はい"), 8);
+
+        assert_eq!(batches(&[10, 10, 10, 10, 10], 60, 25), [0..2, 2..4, 4..5]);
+        assert_eq!(batches(&[10, 10, 10], 2, 1000), [0..2, 2..3]);
+        // Dense lines get batches of their own
+        assert_eq!(batches(&[5, 100, 5], 60, 50), [0..1, 1..2, 2..3]);
+        assert!(batches(&[], 60, 50).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! `--tighten-cues`: Whisper's segment times often include half a second of
+//! the silence around a line. An energy-based voice activity pass over the
+//! chunk's audio finds where speech starts and stops inside each segment,
+//! and the cue is shrunk to that, with a little lead-in and hang time. Cues
+//! only ever get shorter; one with no clear speech (music, noise) is left
+//! as it is.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::{wav, WhisperSegment};
+
+/// Seconds per energy frame
+const FRAME: f64 = 0.02;
+/// Speech is this many dB above the chunk's noise floor
+const SPEECH_DB: f64 = 12.0;
+/// ...and never quieter than this (dBFS), so near-silent chunks stay put
+const MIN_SPEECH_DBFS: f64 = -50.0;
+/// Frames in a row that count as speech rather than a click
+const MIN_RUN: usize = 3;
+/// Kept before the first and after the last speech frame
+const LEAD_IN: f64 = 0.1;
+const HANG: f64 = 0.25;
+/// Cues are not shrunk below this many seconds
+const MIN_CUE: f64 = 0.5;
+
+/// Shrink `segments` (absolute times) to the speech in `chunk`, a 16 kHz
+/// mono WAV starting `start` seconds into the input; returns how many
+/// changed.
+pub fn tighten(segments: &mut [WhisperSegment], chunk: &Path, start: f64) -> Result<usize> {
+    let mut r = BufReader::new(File::open(chunk).context("Open chunk for --tighten-cues")?);
+    let len = wav::seek_to_data(&mut r)?;
+    let mut pcm = Vec::with_capacity(len as usize);
+    r.take(len).read_to_end(&mut pcm)?;
+    let samples: Vec<i16> = pcm
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    let speech = speech_frames(&samples);
+    let mut changed = 0;
+    for seg in segments.iter_mut() {
+        if let Some((s, e)) = speech_span(&speech, seg.start - start, seg.end - start) {
+            let (s, e) = (s + start, e + start);
+            if (s, e) != (seg.start, seg.end) {
+                seg.start = s;
+                seg.end = e;
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Whether each 20 ms frame of 16 kHz `samples` holds speech-level energy.
+fn speech_frames(samples: &[i16]) -> Vec<bool> {
+    let per_frame = (FRAME * f64::from(wav::SAMPLE_RATE)) as usize;
+    let db: Vec<f64> = samples
+        .chunks(per_frame)
+        .map(|frame| {
+            // First difference: a cheap high-pass that ignores hum and rumble
+            let power = frame
+                .windows(2)
+                .map(|w| (f64::from(w[1]) - f64::from(w[0])).powi(2))
+                .sum::<f64>()
+                / frame.len().max(1) as f64;
+            10.0 * (power.max(1.0) / 32768f64.powi(2)).log10()
+        })
+        .collect();
+    if db.is_empty() {
+        return Vec::new();
+    }
+    let mut sorted = db.clone();
+    sorted.sort_by(f64::total_cmp);
+    let floor = sorted[sorted.len() / 10];
+    let threshold = (floor + SPEECH_DB).max(MIN_SPEECH_DBFS);
+    db.into_iter().map(|d| d > threshold).collect()
+}
+
+/// The speech within `start..end` (seconds into the chunk), padded and
+/// clamped to it; `None` when there is none or it would be too short.
+fn speech_span(speech: &[bool], start: f64, end: f64) -> Option<(f64, f64)> {
+    let first = (start / FRAME).floor().max(0.0) as usize;
+    let last = ((end / FRAME).ceil() as usize).min(speech.len());
+    let frames = speech.get(first..last)?;
+    let runs = |at: usize| frames[at..].iter().take(MIN_RUN).all(|&s| s);
+    let on = (0..frames.len()).find(|&i| runs(i))?;
+    let off = (0..frames.len())
+        .rev()
+        .find(|&i| i + 1 >= MIN_RUN && runs(i + 1 - MIN_RUN))?;
+    let s = ((first + on) as f64 * FRAME - LEAD_IN).max(start);
+    let e = ((first + off + 1) as f64 * FRAME + HANG).min(end);
+    (e - s >= MIN_CUE).then_some((s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seg;
+
+    #[test]
+    fn test_tighten() {
+        // 1 s quiet, 1 s "speech", 1 s quiet
+        let rate = wav::SAMPLE_RATE as usize;
+        let samples: Vec<i16> = (0..rate * 3)
+            .map(|i| {
+                let loud = (rate..rate * 2).contains(&i);
+                let amp = if loud { 8000.0 } else { 20.0 };
+                ((i as f64 * 0.3).sin() * amp) as i16
+            })
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let chunk = dir.path().join("chunk.wav");
+        let mut w = wav::WavWriter::create(&chunk).unwrap();
+        w.write_samples(&samples).unwrap();
+        w.finish().unwrap();
+
+        let mut segs = [
+            seg(600.2, 602.8, ""),
+            // Nothing but quiet: left alone
+            seg(602.2, 603.0, ""),
+        ];
+        assert_eq!(tighten(&mut segs, &chunk, 600.0).unwrap(), 1);
+        assert!((segs[0].start - 600.9).abs() < 0.03, "{}", segs[0].start);
+        assert!((segs[0].end - 602.25).abs() < 0.03, "{}", segs[0].end);
+        assert_eq!((segs[1].start, segs[1].end), (602.2, 603.0));
+    }
+}
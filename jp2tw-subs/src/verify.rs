@@ -0,0 +1,127 @@
+//! Post-encode checks: ffmpeg can exit 0 and still leave a truncated file
+//! (full disk, a killed encoder, a broken input packet). After burn-in and
+//! concatenation the output is probed with ffprobe and compared with what
+//! it was made from: duration within tolerance, a video stream, and audio
+//! when the source had audio. A mismatch fails the run instead of shipping
+//! the file. `--no-verify-output` skips the checks.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Allowed duration difference: encoders round the last frame and the
+/// audio and video tracks rarely end together.
+const TOLERANCE_SECONDS: f64 = 1.0;
+const TOLERANCE_SHARE: f64 = 0.005;
+
+/// What ffprobe reports about a media file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Media {
+    pub duration: Option<f64>,
+    pub video: usize,
+    pub audio: usize,
+}
+
+pub fn probe(path: &Path) -> Result<Media> {
+    let out = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration:stream=codec_type",
+            "-of",
+            "json",
+        ])
+        .arg(path)
+        .output()
+        .context("ffprobe is required to verify the output")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffprobe cannot read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    parse(&String::from_utf8_lossy(&out.stdout))
+}
+
+fn parse(json: &str) -> Result<Media> {
+    let v: serde_json::Value = serde_json::from_str(json).context("Parse ffprobe JSON")?;
+    let streams = v["streams"].as_array().cloned().unwrap_or_default();
+    let count = |kind: &str| {
+        streams
+            .iter()
+            .filter(|s| s["codec_type"].as_str() == Some(kind))
+            .count()
+    };
+    Ok(Media {
+        // ffprobe prints times as strings
+        duration: v["format"]["duration"]
+            .as_str()
+            .and_then(|d| d.parse().ok()),
+        video: count("video"),
+        audio: count("audio"),
+    })
+}
+
+/// Check `output` against its `source` (probed before encoding) when it
+/// should last `expected` seconds.
+pub fn check(output: &Path, source: &Media, expected: Option<f64>) -> Result<()> {
+    let media = probe(output)?;
+    compare(&media, source, expected)
+        .with_context(|| format!("{} failed verification", output.display()))
+}
+
+fn compare(media: &Media, source: &Media, expected: Option<f64>) -> Result<()> {
+    if media.video == 0 {
+        return Err(anyhow!("it has no video stream"));
+    }
+    if source.audio > 0 && media.audio == 0 {
+        return Err(anyhow!("it has no audio stream, but the source has"));
+    }
+    if let Some(expected) = expected {
+        let actual = media
+            .duration
+            .ok_or_else(|| anyhow!("ffprobe reports no duration; the file may be truncated"))?;
+        let tolerance = TOLERANCE_SECONDS.max(expected * TOLERANCE_SHARE);
+        if (actual - expected).abs() > tolerance {
+            return Err(anyhow!(
+                "it lasts {actual:.1} s instead of {expected:.1} s; ffmpeg may have stopped early (disk full, killed encoder). Rerun, or pass --no-verify-output to keep it anyway"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_compare() {
+        let source = parse(
+            r#"{"streams":[{"codec_type":"video"},{"codec_type":"audio"}],"format":{"duration":"1440.064000"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            source,
+            Media {
+                duration: Some(1440.064),
+                video: 1,
+                audio: 1
+            }
+        );
+        assert!(compare(&source, &source, Some(1440.0)).is_ok());
+        let truncated = Media {
+            duration: Some(900.0),
+            ..source.clone()
+        };
+        let err = compare(&truncated, &source, Some(1440.0)).unwrap_err();
+        assert!(err.to_string().contains("900.0 s instead of 1440.0 s"));
+        let silent = Media {
+            audio: 0,
+            ..source.clone()
+        };
+        assert!(compare(&silent, &source, Some(1440.0)).is_err());
+    }
+}
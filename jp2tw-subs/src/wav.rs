@@ -0,0 +1,195 @@
+//! Minimal 16kHz mono PCM WAV helpers shared by the audio backends.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Streaming writer for 16kHz mono s16le WAV; patches sizes on `finish`.
+pub struct WavWriter {
+    out: BufWriter<File>,
+    data_len: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut out = BufWriter::new(
+            File::create(path).with_context(|| format!("Create WAV at {}", path.display()))?,
+        );
+        write_header(&mut out, 0)?;
+        Ok(Self { out, data_len: 0 })
+    }
+
+    pub fn write_pcm(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes)?;
+        self.data_len = self.data_len.saturating_add(bytes.len() as u32);
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "symphonia-fallback"), allow(dead_code))]
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<()> {
+        for s in samples {
+            self.out.write_all(&s.to_le_bytes())?;
+        }
+        self.data_len = self.data_len.saturating_add((samples.len() * 2) as u32);
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let mut f = self.out.into_inner().map_err(|e| anyhow!("{}", e))?;
+        f.seek(SeekFrom::Start(0))?;
+        write_header(&mut f, self.data_len)?;
+        f.flush()?;
+        Ok(())
+    }
+}
+
+pub fn write_header<W: Write>(w: &mut W, data_len: u32) -> Result<()> {
+    write_header_at(w, SAMPLE_RATE, data_len)
+}
+
+/// A mono s16le WAV header for `sample_rate`.
+pub fn write_header_at<W: Write>(w: &mut W, sample_rate: u32, data_len: u32) -> Result<()> {
+    let byte_rate = sample_rate * 2;
+    w.write_all(b"RIFF")?;
+    w.write_all(&(36 + data_len).to_le_bytes())?;
+    w.write_all(b"WAVEfmt ")?;
+    w.write_all(&16u32.to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?; // PCM
+    w.write_all(&1u16.to_le_bytes())?; // mono
+    w.write_all(&sample_rate.to_le_bytes())?;
+    w.write_all(&byte_rate.to_le_bytes())?;
+    w.write_all(&2u16.to_le_bytes())?; // block align
+    w.write_all(&16u16.to_le_bytes())?; // bits per sample
+    w.write_all(b"data")?;
+    w.write_all(&data_len.to_le_bytes())?;
+    Ok(())
+}
+
+/// Position the reader at the start of the `data` chunk and return its length.
+pub fn seek_to_data<R: Read + Seek>(r: &mut R) -> Result<u64> {
+    let mut riff = [0u8; 12];
+    r.read_exact(&mut riff).context("Read WAV header")?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(anyhow!("Not a RIFF/WAVE file"));
+    }
+    loop {
+        let mut hdr = [0u8; 8];
+        r.read_exact(&mut hdr)
+            .map_err(|_| anyhow!("Malformed WAV: no data chunk"))?;
+        let len = u32::from_le_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]) as u64;
+        if &hdr[0..4] == b"data" {
+            return Ok(len);
+        }
+        r.seek(SeekFrom::Current((len + (len & 1)) as i64))?;
+    }
+}
+
+/// Split a 16kHz mono PCM WAV into `chunk_NNNNN.wav` files of `chunk_seconds`
+/// each (same naming as ffmpeg's segment muxer), streaming from disk.
+pub fn segment_wav(wav_path: &Path, out_dir: &Path, chunk_seconds: u32) -> Result<Vec<PathBuf>> {
+    let mut r = BufReader::new(File::open(wav_path).context("Open WAV for segmenting")?);
+    let mut remaining = seek_to_data(&mut r)?;
+    let bytes_per_chunk = (chunk_seconds.max(1) as u64) * (SAMPLE_RATE as u64) * 2;
+
+    let mut chunks = Vec::new();
+    while remaining > 0 {
+        let path = out_dir.join(format!("chunk_{:05}.wav", chunks.len()));
+        let mut w = WavWriter::create(&path)?;
+        let want = remaining.min(bytes_per_chunk);
+        let got = copy_pcm(&mut r, &mut w, want)?;
+        // Short when the header claimed more data than the file holds
+        remaining = if got < want { 0 } else { remaining - got };
+        w.finish()?;
+        chunks.push(path);
+    }
+    Ok(chunks)
+}
+
+/// Split a 16kHz mono PCM WAV into two halves next to it, `<stem>.1.wav` and
+/// `<stem>.2.wav`; returns them with the first half's length in seconds.
+pub fn split_in_half(wav_path: &Path) -> Result<(PathBuf, PathBuf, f64)> {
+    let mut r = BufReader::new(File::open(wav_path).context("Open WAV for splitting")?);
+    let len = seek_to_data(&mut r)?;
+    // Whole 16-bit samples
+    let first_len = len / 4 * 2;
+    let stem = wav_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("audio");
+    let [first, second] = [1, 2].map(|n| wav_path.with_file_name(format!("{stem}.{n}.wav")));
+    for (path, n) in [(&first, first_len), (&second, len - first_len)] {
+        let mut w = WavWriter::create(path)?;
+        copy_pcm(&mut r, &mut w, n)?;
+        w.finish()?;
+    }
+    Ok((first, second, first_len as f64 / f64::from(SAMPLE_RATE * 2)))
+}
+
+/// Write the first `seconds` of a 16kHz mono PCM WAV to `out`; shorter
+/// input is copied whole.
+pub fn write_head(wav_path: &Path, seconds: u32, out: &Path) -> Result<()> {
+    let mut r = BufReader::new(File::open(wav_path).context("Open WAV for its head")?);
+    let len = seek_to_data(&mut r)?;
+    let mut w = WavWriter::create(out)?;
+    copy_pcm(
+        &mut r,
+        &mut w,
+        len.min(u64::from(seconds * SAMPLE_RATE * 2)),
+    )?;
+    w.finish()
+}
+
+/// Copy up to `n` bytes of PCM from `r`; returns how many there were.
+fn copy_pcm<R: Read>(r: &mut R, w: &mut WavWriter, n: u64) -> Result<u64> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut left = n;
+    while left > 0 {
+        let want = left.min(buf.len() as u64) as usize;
+        let got = r.read(&mut buf[..want])?;
+        if got == 0 {
+            break;
+        }
+        w.write_pcm(&buf[..got])?;
+        left -= got as u64;
+    }
+    Ok(n - left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_wav_splits_pcm() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("audio.wav");
+        // 2.5 seconds of silence
+        let mut w = WavWriter::create(&wav).unwrap();
+        w.write_samples(&vec![0i16; (SAMPLE_RATE as usize) * 5 / 2])
+            .unwrap();
+        w.finish().unwrap();
+
+        let chunks = segment_wav(&wav, dir.path(), 1).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].ends_with("chunk_00000.wav"));
+        let mut last = File::open(&chunks[2]).unwrap();
+        assert_eq!(seek_to_data(&mut last).unwrap(), SAMPLE_RATE as u64);
+
+        let (first, second, seconds) = split_in_half(&chunks[0]).unwrap();
+        assert!(first.ends_with("chunk_00000.1.wav"));
+        assert_eq!(seconds, 0.5);
+        let mut half = File::open(&second).unwrap();
+        assert_eq!(seek_to_data(&mut half).unwrap(), SAMPLE_RATE as u64);
+
+        let head = dir.path().join("head.wav");
+        write_head(&wav, 2, &head).unwrap();
+        let mut f = File::open(&head).unwrap();
+        assert_eq!(seek_to_data(&mut f).unwrap(), SAMPLE_RATE as u64 * 4);
+        write_head(&chunks[2], 2, &head).unwrap();
+        let mut f = File::open(&head).unwrap();
+        assert_eq!(seek_to_data(&mut f).unwrap(), SAMPLE_RATE as u64);
+    }
+}
@@ -0,0 +1,286 @@
+//! `--transcription-backend whisper-cpp`: transcribe locally with the
+//! whisper.cpp CLI (`whisper-cli`) instead of the OpenAI API. Nothing is
+//! uploaded and nothing is billed.
+//!
+//! `--whisper-device` picks where it runs. `auto` (the default) uses the GPU
+//! when the machine has one whisper.cpp can drive (Metal on Apple silicon,
+//! an NVIDIA card with CUDA) and the CPU otherwise; when a GPU run fails,
+//! for example because whisper.cpp was built without GPU support or the
+//! model does not fit in video memory, the chunk is retried on the CPU and
+//! the rest of the run stays there. `cpu` and `gpu` force one or the other.
+//!
+//! The ggml model file comes from `--whisper-cpp-model`
+//! (e.g. `ggml-large-v3.bin`). Each chunk runs `whisper-cli -oj` and its JSON
+//! output is read back:
+//!
+//! ```json
+//! {"result": {"language": "ja"},
+//!  "transcription": [{"offsets": {"from": 0, "to": 2500}, "text": " こんにちは"}]}
+//! ```
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::process::Command;
+
+use crate::backend::{BoxFuture, Config, TranscriptionBackend};
+use crate::openai::{ApiError, TranscriptionRequest, TranscriptionResponse};
+use crate::WhisperSegment;
+
+/// The whisper.cpp CLI, found in PATH
+const BIN: &str = "whisper-cli";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Device {
+    /// The GPU when one is found, else the CPU; GPU failures fall back to the CPU
+    #[default]
+    Auto,
+    Cpu,
+    Gpu,
+}
+
+impl Device {
+    /// Whether to start on the GPU, given whether one was found.
+    fn use_gpu(self, gpu_found: bool) -> bool {
+        match self {
+            Device::Auto => gpu_found,
+            Device::Cpu => false,
+            Device::Gpu => true,
+        }
+    }
+}
+
+/// Whether the machine has a GPU whisper.cpp's usual builds can drive.
+fn gpu_found() -> bool {
+    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        return true;
+    }
+    std::process::Command::new("nvidia-smi")
+        .arg("-L")
+        .output()
+        .is_ok_and(|out| out.status.success() && !out.stdout.is_empty())
+}
+
+pub struct WhisperCpp {
+    model: PathBuf,
+    device: Device,
+    /// Cleared for good after a failed GPU run under `auto`
+    gpu: AtomicBool,
+}
+
+impl WhisperCpp {
+    pub fn new(config: &Config) -> Result<Self> {
+        let Some(model) = &config.whisper_cpp_model else {
+            bail!("The whisper-cpp backend needs a ggml model file: pass --whisper-cpp-model path/to/ggml-large-v3.bin");
+        };
+        if !model.is_file() {
+            bail!("whisper.cpp model {} not found", model.display());
+        }
+        std::process::Command::new(BIN)
+            .arg("--help")
+            .output()
+            .with_context(|| format!("Run {BIN}; install whisper.cpp and put {BIN} in PATH"))?;
+        let device = config.whisper_device;
+        let gpu = device.use_gpu(device == Device::Auto && gpu_found());
+        eprintln!(
+            "whisper.cpp transcribes on the {}",
+            if gpu { "GPU" } else { "CPU" }
+        );
+        Ok(Self {
+            model: model.clone(),
+            device,
+            gpu: gpu.into(),
+        })
+    }
+
+    /// `whisper-cli` arguments for one chunk.
+    fn argv(
+        &self,
+        audio: &Path,
+        language: Option<&str>,
+        prefix: &Path,
+        gpu: bool,
+    ) -> Vec<OsString> {
+        let mut argv: Vec<OsString> = vec![
+            "-m".into(),
+            self.model.clone().into(),
+            "-f".into(),
+            audio.into(),
+            "-l".into(),
+            language.unwrap_or("auto").into(),
+            "-oj".into(),
+            "-np".into(),
+            "-of".into(),
+            prefix.into(),
+        ];
+        if !gpu {
+            argv.push("-ng".into());
+        }
+        argv
+    }
+
+    async fn run(
+        &self,
+        audio: &Path,
+        language: Option<&str>,
+    ) -> Result<TranscriptionResponse, ApiError> {
+        let gpu = self.gpu.load(Ordering::Relaxed);
+        match self.run_on(audio, language, gpu).await {
+            Err(e) if gpu && self.device == Device::Auto => {
+                eprintln!("Warning: whisper.cpp failed on the GPU ({e}); retrying on the CPU for the rest of the run");
+                self.gpu.store(false, Ordering::Relaxed);
+                self.run_on(audio, language, false).await
+            }
+            result => result,
+        }
+    }
+
+    async fn run_on(
+        &self,
+        audio: &Path,
+        language: Option<&str>,
+        gpu: bool,
+    ) -> Result<TranscriptionResponse, ApiError> {
+        let prefix = audio.with_extension("whisper-cpp");
+        let output = Command::new(BIN)
+            .args(self.argv(audio, language, &prefix, gpu))
+            .output()
+            .await
+            .map_err(|e| ApiError::Backend(format!("{BIN}: {e}")))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let tail: Vec<&str> = stderr.lines().rev().take(3).collect();
+            return Err(ApiError::Backend(format!(
+                "{BIN} failed ({}): {}",
+                output.status,
+                tail.into_iter().rev().collect::<Vec<_>>().join(" / ")
+            )));
+        }
+        let json = prefix.with_extension("whisper-cpp.json");
+        let text = std::fs::read_to_string(&json);
+        let _ = std::fs::remove_file(&json);
+        let text = text.map_err(|e| ApiError::Backend(format!("{}: {e}", json.display())))?;
+        parse(&text)
+    }
+}
+
+impl TranscriptionBackend for WhisperCpp {
+    fn transcribe<'a>(
+        &'a self,
+        req: &'a TranscriptionRequest,
+    ) -> BoxFuture<'a, Result<TranscriptionResponse, ApiError>> {
+        Box::pin(self.run(&req.audio, req.language))
+    }
+}
+
+#[derive(Deserialize)]
+struct Output {
+    #[serde(default)]
+    result: Option<Detected>,
+    transcription: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Detected {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    /// Milliseconds from the start of the chunk
+    offsets: Offsets,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct Offsets {
+    from: u64,
+    to: u64,
+}
+
+/// whisper.cpp's JSON output as a Whisper API response.
+fn parse(json: &str) -> Result<TranscriptionResponse, ApiError> {
+    let output: Output =
+        serde_json::from_str(json).map_err(|e| ApiError::Backend(format!("{BIN} output: {e}")))?;
+    let segments: Vec<WhisperSegment> = output
+        .transcription
+        .into_iter()
+        .filter(|item| !item.text.trim().is_empty())
+        .enumerate()
+        .map(|(i, item)| WhisperSegment {
+            id: Some(i as u32),
+            start: item.offsets.from as f64 / 1000.0,
+            end: item.offsets.to as f64 / 1000.0,
+            text: item.text.trim().to_string(),
+            ..Default::default()
+        })
+        .collect();
+    let text = segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    Ok(TranscriptionResponse {
+        text: Some(text),
+        segments: Some(segments),
+        language: output.result.and_then(|r| r.language),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{"systeminfo": "AVX = 1", "result": {"language": "ja"}, "transcription": [
+            {"timestamps": {"from": "00:00:00,000", "to": "00:00:02,500"}, "offsets": {"from": 0, "to": 2500}, "text": " こんにちは"},
+            {"timestamps": {"from": "00:00:02,500", "to": "00:00:03,000"}, "offsets": {"from": 2500, "to": 3000}, "text": " "},
+            {"timestamps": {"from": "00:00:03,000", "to": "00:00:05,120"}, "offsets": {"from": 3000, "to": 5120}, "text": "元気？"}
+        ]}"#;
+        let response = parse(json).unwrap();
+        let segments = response.segments.unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].id, Some(1));
+        assert_eq!((segments[1].start, segments[1].end), (3.0, 5.12));
+        assert_eq!(response.text.as_deref(), Some("こんにちは元気？"));
+        assert_eq!(response.language.as_deref(), Some("ja"));
+        assert!(matches!(parse("{}"), Err(ApiError::Backend(_))));
+    }
+
+    #[test]
+    fn test_device_and_argv() {
+        assert!(Device::Auto.use_gpu(true));
+        assert!(!Device::Auto.use_gpu(false));
+        assert!(!Device::Cpu.use_gpu(true));
+        assert!(Device::Gpu.use_gpu(false));
+        let cpp = WhisperCpp {
+            model: "ggml-large-v3.bin".into(),
+            device: Device::Auto,
+            gpu: true.into(),
+        };
+        let argv = |gpu| {
+            cpp.argv(
+                Path::new("c.wav"),
+                Some("ja"),
+                Path::new("c.whisper-cpp"),
+                gpu,
+            )
+            .into_iter()
+            .map(|a| a.into_string().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ")
+        };
+        assert_eq!(
+            argv(true),
+            "-m ggml-large-v3.bin -f c.wav -l ja -oj -np -of c.whisper-cpp"
+        );
+        assert_eq!(
+            argv(false),
+            "-m ggml-large-v3.bin -f c.wav -l ja -oj -np -of c.whisper-cpp -ng"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Line wrapping for `--max-line-chars`, following the CJK line-breaking
+//! rules (kinsoku, 避頭點): closing brackets and sentence punctuation never
+//! start a line, opening brackets never end one, and Latin words and
+//! numbers are not split. libass only wraps at spaces, so unwrapped Chinese
+//! runs off the frame.
+//!
+//! Text that fits on two lines is split into two balanced ones, the lower
+//! one the longer if anything, preferring breaks after punctuation and
+//! particles, rather than a full line and a short orphan.
+
+/// Wrap each line of `text` to at most `width` characters where the rules
+/// allow; a line with no allowed break within `width` breaks at the next one.
+pub fn wrap(text: &str, width: usize) -> String {
+    text.lines()
+        .map(|line| wrap_line(line.trim(), width).join("\n"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if width == 0 {
+        return vec![line.to_string()];
+    }
+    let text = |s: &[char]| s.iter().collect::<String>().trim().to_string();
+    let mut out = Vec::new();
+    let mut rest = &chars[..];
+    while rest.len() > width {
+        if rest.len() <= 2 * width {
+            if let Some(cut) = balanced_break(rest, width) {
+                out.push(text(&rest[..cut]));
+                rest = &rest[cut..];
+                break;
+            }
+        }
+        let cut = (1..=width)
+            .rev()
+            .find(|&p| can_break(rest, p))
+            .or_else(|| (width + 1..rest.len()).find(|&p| can_break(rest, p)))
+            .unwrap_or(rest.len());
+        out.push(text(&rest[..cut]));
+        rest = &rest[cut..];
+        while rest.first() == Some(&' ') {
+            rest = &rest[1..];
+        }
+    }
+    if !rest.is_empty() {
+        out.push(text(rest));
+    }
+    out
+}
+
+/// The best allowed break of `chars` into two lines of at most `width`.
+fn balanced_break(chars: &[char], width: usize) -> Option<usize> {
+    (1..chars.len())
+        .filter(|&p| p <= width && chars.len() - p <= width && can_break(chars, p))
+        .min_by_key(|&p| break_cost(chars, p))
+}
+
+/// Cost of a two-line break before `chars[p]`: the length difference, more
+/// if the top line is the longer, less after punctuation or a particle.
+fn break_cost(chars: &[char], p: usize) -> i64 {
+    let (top, bottom) = (p as i64, (chars.len() - p) as i64);
+    let mut cost = 2 * (top - bottom).abs();
+    if top > bottom {
+        cost += 2;
+    }
+    match chars[p - 1] {
+        '，' | '、' | '。' | '！' | '？' | '：' | '；' | '…' | ' ' | ',' => cost -= 4,
+        c if is_particle(c) => cost -= 2,
+        _ => {}
+    }
+    cost
+}
+
+/// Chinese and Japanese particles that end a phrase.
+fn is_particle(c: char) -> bool {
+    matches!(
+        c,
+        '的' | '了'
+            | '嗎'
+            | '呢'
+            | '吧'
+            | '啊'
+            | '著'
+            | '過'
+            | 'は'
+            | 'が'
+            | 'を'
+            | 'に'
+            | 'で'
+            | 'と'
+            | 'も'
+            | 'へ'
+            | 'の'
+    )
+}
+
+/// Whether a line may end just before `chars[i]`.
+fn can_break(chars: &[char], i: usize) -> bool {
+    let (Some(&before), Some(&after)) = (chars.get(i.wrapping_sub(1)), chars.get(i)) else {
+        return false;
+    };
+    let inside_word = is_word(before) && is_word(after);
+    !(no_start(after) || no_end(before) || inside_word)
+}
+
+/// Characters that may not begin a line.
+fn no_start(c: char) -> bool {
+    matches!(
+        c,
+        '、' | '。'
+            | '，'
+            | '．'
+            | '！'
+            | '？'
+            | '：'
+            | '；'
+            | '…'
+            | '‥'
+            | '—'
+            | '）'
+            | '」'
+            | '』'
+            | '】'
+            | '〉'
+            | '》'
+            | '〕'
+            | '］'
+            | '｝'
+            | '”'
+            | '’'
+            | ')'
+            | ']'
+            | '}'
+            | '!'
+            | '?'
+            | ','
+            | '.'
+            | ':'
+            | ';'
+            | '%'
+            | 'ー'
+            | '〜'
+            | '～'
+            | '・'
+            | 'ぁ'
+            | 'ぃ'
+            | 'ぅ'
+            | 'ぇ'
+            | 'ぉ'
+            | 'っ'
+            | 'ゃ'
+            | 'ゅ'
+            | 'ょ'
+            | 'ゎ'
+            | 'ァ'
+            | 'ィ'
+            | 'ゥ'
+            | 'ェ'
+            | 'ォ'
+            | 'ッ'
+            | 'ャ'
+            | 'ュ'
+            | 'ョ'
+            | 'ヮ'
+            | 'ヵ'
+            | 'ヶ'
+    )
+}
+
+/// Characters that may not end a line.
+fn no_end(c: char) -> bool {
+    matches!(
+        c,
+        '（' | '「'
+            | '『'
+            | '【'
+            | '〈'
+            | '《'
+            | '〔'
+            | '［'
+            | '｛'
+            | '“'
+            | '‘'
+            | '('
+            | '['
+            | '{'
+    )
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '\'' | '-' | '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_follows_kinsoku() {
+        // The greedy break would start the second line with 」 and 。
+        assert_eq!(
+            wrap("他說「我們走吧」。然後我們去吃飯", 8),
+            "他說「我們走\n吧」。然後\n我們去吃飯"
+        );
+        // An opening bracket stays with its text
+        assert_eq!(
+            wrap("我們一起去看「鬼滅之刃」", 7),
+            "我們一起去看\n「鬼滅之刃」"
+        );
+        assert_eq!(wrap("我買了 iPhone15 很好", 8), "我買了\niPhone15\n很好");
+        assert_eq!(wrap("短句\n也好", 8), "短句\n也好");
+    }
+
+    #[test]
+    fn test_two_lines_are_balanced() {
+        // Filling the first line would leave 吧 alone on the second
+        assert_eq!(wrap("我們一起去公園散步吧", 9), "我們一起去\n公園散步吧");
+        // Punctuation beats perfect balance
+        assert_eq!(
+            wrap("今天天氣很好，我們一起去公園散步吧", 12),
+            "今天天氣很好，\n我們一起去公園散步吧"
+        );
+        // The lower line takes the extra character
+        assert_eq!(wrap("一二三四五六七", 6), "一二三\n四五六七");
+    }
+}
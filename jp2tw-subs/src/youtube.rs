@@ -0,0 +1,198 @@
+//! `upload-captions`: push a finished subtitle file to a YouTube video as a
+//! caption track through the Data API (`captions.insert`, or
+//! `captions.update` when the video already has a track with the same
+//! language and name).
+//!
+//! Authorization is an OAuth access token with the
+//! `https://www.googleapis.com/auth/youtube.force-ssl` scope, taken from
+//! `YOUTUBE_ACCESS_TOKEN`, or minted from a credentials JSON holding
+//! `client_id`, `client_secret`, and a `refresh_token` for an OAuth client
+//! of the user's own Google Cloud project.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::http;
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const API_URL: &str = "https://www.googleapis.com/youtube/v3/captions";
+const UPLOAD_URL: &str = "https://www.googleapis.com/upload/youtube/v3/captions";
+const BOUNDARY: &str = "jp2tw-caption-upload";
+
+#[derive(Deserialize)]
+struct Credentials {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+}
+
+pub struct Upload<'a> {
+    pub video_id: &'a str,
+    pub file: &'a Path,
+    /// BCP-47 language of the track
+    pub language: &'a str,
+    /// Track name shown in the player's caption menu
+    pub name: &'a str,
+    pub draft: bool,
+}
+
+pub async fn upload(upload: &Upload<'_>, credentials: Option<&Path>) -> Result<()> {
+    let body =
+        std::fs::read(upload.file).with_context(|| format!("Read {}", upload.file.display()))?;
+    let client = http::client();
+    let token = access_token(&client, credentials).await?;
+
+    // An update replaces the file of an existing track; only isDraft can change
+    let existing = find_track(&client, &token, upload).await?;
+    let (request, metadata) = match &existing {
+        Some(id) => (
+            client.put(UPLOAD_URL),
+            json!({ "id": id, "snippet": { "isDraft": upload.draft } }),
+        ),
+        None => (
+            client.post(UPLOAD_URL),
+            json!({ "snippet": {
+                "videoId": upload.video_id,
+                "language": upload.language,
+                "name": upload.name,
+                "isDraft": upload.draft,
+            } }),
+        ),
+    };
+    let resp = request
+        .query(&[("part", "snippet"), ("uploadType", "multipart")])
+        .bearer_auth(&token)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            format!("multipart/related; boundary={BOUNDARY}"),
+        )
+        .body(multipart_related(&metadata, &body))
+        .timeout(http::timeout())
+        .send()
+        .await
+        .context("YouTube caption upload failed")?;
+    let status = resp.status();
+    let reply: Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!(
+            "YouTube API returned {status}: {}",
+            api_error(&reply)
+        ));
+    }
+    let verb = if existing.is_some() {
+        "Updated"
+    } else {
+        "Added"
+    };
+    eprintln!(
+        "{verb} caption track {:?} ({}) on video {} (id {})",
+        upload.name,
+        upload.language,
+        upload.video_id,
+        reply["id"].as_str().unwrap_or("?")
+    );
+    Ok(())
+}
+
+/// Id of the video's caption track with the same language and name, if any.
+async fn find_track(
+    client: &reqwest::Client,
+    token: &str,
+    upload: &Upload<'_>,
+) -> Result<Option<String>> {
+    let resp = client
+        .get(API_URL)
+        .query(&[("part", "snippet"), ("videoId", upload.video_id)])
+        .bearer_auth(token)
+        .timeout(http::timeout())
+        .send()
+        .await
+        .context("List YouTube caption tracks")?;
+    let status = resp.status();
+    let reply: Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!(
+            "YouTube API returned {status}: {}",
+            api_error(&reply)
+        ));
+    }
+    Ok(reply["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|item| {
+            let snippet = &item["snippet"];
+            snippet["language"].as_str() == Some(upload.language)
+                && snippet["name"].as_str() == Some(upload.name)
+        })
+        .and_then(|item| item["id"].as_str().map(str::to_string)))
+}
+
+async fn access_token(client: &reqwest::Client, credentials: Option<&Path>) -> Result<String> {
+    let Some(path) = credentials else {
+        return std::env::var("YOUTUBE_ACCESS_TOKEN").map_err(|_| {
+            anyhow!("Set YOUTUBE_ACCESS_TOKEN or pass --credentials with an OAuth refresh token")
+        });
+    };
+    let text = std::fs::read_to_string(path).with_context(|| format!("Read {}", path.display()))?;
+    let creds: Credentials = serde_json::from_str(&text).with_context(|| {
+        format!(
+            "{} needs client_id, client_secret, and refresh_token",
+            path.display()
+        )
+    })?;
+    let resp = client
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", creds.client_id.as_str()),
+            ("client_secret", creds.client_secret.as_str()),
+            ("refresh_token", creds.refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ])
+        .timeout(http::timeout())
+        .send()
+        .await
+        .context("OAuth token request failed")?;
+    let status = resp.status();
+    let reply: Value = resp.json().await.unwrap_or_default();
+    reply["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("OAuth token request returned {status}: {reply}"))
+}
+
+/// The `multipart/related` body the upload endpoint takes: JSON metadata,
+/// then the caption file.
+fn multipart_related(metadata: &Value, file: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{BOUNDARY}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{metadata}\r\n--{BOUNDARY}\r\nContent-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+fn api_error(reply: &Value) -> String {
+    reply["error"]["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| reply.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_related() {
+        let body = multipart_related(&json!({"snippet": {"name": "中文"}}), b"1\n00:00:01,000");
+        let text = String::from_utf8(body).unwrap();
+        assert!(text.starts_with(&format!("--{BOUNDARY}\r\nContent-Type: application/json")));
+        assert!(text.contains("{\"snippet\":{\"name\":\"中文\"}}\r\n"));
+        assert!(text.contains("octet-stream\r\n\r\n1\n00:00:01,000\r\n"));
+        assert!(text.ends_with(&format!("--{BOUNDARY}--\r\n")));
+    }
+}
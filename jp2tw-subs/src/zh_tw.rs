@@ -0,0 +1,149 @@
+//! Traditional Chinese (Taiwan) text helpers.
+
+/// Simplified-only characters and their Taiwan forms. Characters that are
+/// also Japanese shinjitai (学, 国, 会, 点, ...) are left out, since
+/// bilingual cues carry the Japanese line too.
+const SIMPLIFIED: &str = concat!(
+    "这们说个时对为过还么样让给吗见觉谁话该请谢认识语读问间门开关东车长马鸟鱼爱头买卖",
+    "乐书实现热从众无专业丢两严乡习亲传伤备变听员围图场坏处夺妈孙导岁帮师带应边运进远",
+    "连选钱铁错闭队阳阴难飞风发经气战单归续欢虽离题颜页级纪线练组细终结绝统继红约网总",
+    "节华亿优伟义农决况刚则剧办务动劳势协卫历压县吓响园团块坚复够妇宁审宫寻层岛张录忆",
+    "怀态忧恶惊愿户执扫护报换敌显术杀杂权极枪标树桥检毕汉汤测满灭灵烦烧爷环电疗盖确种",
+    "稳穷笔简紧纸罗职联胜脑脸艺药获营补观规视计讨训议记讲许论设访证评诉词译试诗误课调",
+    "谈负财责败货质贵费资赶轮软转轻较达迟遗释钟银锁镜闪闹闻阵际陆险顶顺须顾预领饭饮馆",
+    "验骂骗鸡龙",
+);
+/// Taiwan forms, position for position.
+const TRADITIONAL: &str = concat!(
+    "這們說個時對為過還麼樣讓給嗎見覺誰話該請謝認識語讀問間門開關東車長馬鳥魚愛頭買賣",
+    "樂書實現熱從眾無專業丟兩嚴鄉習親傳傷備變聽員圍圖場壞處奪媽孫導歲幫師帶應邊運進遠",
+    "連選錢鐵錯閉隊陽陰難飛風發經氣戰單歸續歡雖離題顏頁級紀線練組細終結絕統繼紅約網總",
+    "節華億優偉義農決況剛則劇辦務動勞勢協衛歷壓縣嚇響園團塊堅復夠婦寧審宮尋層島張錄憶",
+    "懷態憂惡驚願戶執掃護報換敵顯術殺雜權極槍標樹橋檢畢漢湯測滿滅靈煩燒爺環電療蓋確種",
+    "穩窮筆簡緊紙羅職聯勝腦臉藝藥獲營補觀規視計討訓議記講許論設訪證評訴詞譯試詩誤課調",
+    "談負財責敗貨質貴費資趕輪軟轉輕較達遲遺釋鐘銀鎖鏡閃鬧聞陣際陸險頂順須顧預領飯飲館",
+    "驗罵騙雞龍",
+);
+
+/// Simplified-only characters in `text`, each with its Taiwan form.
+pub fn simplified_chars(text: &str) -> Vec<(char, char)> {
+    text.chars()
+        .filter_map(|c| {
+            let i = SIMPLIFIED.chars().position(|s| s == c)?;
+            Some((c, TRADITIONAL.chars().nth(i)?))
+        })
+        .collect()
+}
+
+/// Typeset `text` the Taiwan way: full-width punctuation next to Chinese,
+/// 「」 and 『』 for quotes, …… and —— for ellipses and dashes, single
+/// rather than doubled marks, and no spaces around full-width punctuation.
+/// Latin text between Chinese keeps its own punctuation.
+pub fn normalize_punctuation(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let cjk_line = chars.iter().any(|&c| is_cjk(c));
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut quote_open = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&d| d == c).count();
+        let ellipsis = match c {
+            '…' => true,
+            '。' => run >= 2,
+            '・' => run >= 3,
+            '.' => run >= 2 && cjk_line,
+            _ => false,
+        };
+        let dash = matches!(c, '—' | '―') || (c == '-' && run >= 2 && cjk_line);
+        if ellipsis || dash {
+            out.extend(if ellipsis { "……" } else { "——" }.chars());
+            i += run;
+            continue;
+        }
+        let prev = out.iter().rev().find(|c| **c != ' ').copied();
+        let next = chars[i + 1..].iter().find(|c| **c != ' ').copied();
+        let near_cjk = prev.is_some_and(is_cjk) || next.is_some_and(is_cjk);
+        let mapped = match c {
+            '“' if cjk_line => '「',
+            '”' if cjk_line => '」',
+            '‘' if near_cjk => '『',
+            '’' if near_cjk => '』',
+            '"' if cjk_line => {
+                quote_open = !quote_open;
+                if quote_open {
+                    '」'
+                } else {
+                    '「'
+                }
+            }
+            ',' if near_cjk => '，',
+            '!' if near_cjk => '！',
+            '?' if near_cjk => '？',
+            ':' if near_cjk => '：',
+            ';' if near_cjk => '；',
+            '(' if near_cjk => '（',
+            ')' if near_cjk => '）',
+            '.' if prev.is_some_and(is_han) && next.is_none_or(is_cjk) => '。',
+            c => c,
+        };
+        let doubled = matches!(mapped, '，' | '、' | '。' | '；' | '：' | '！' | '？')
+            && out.last() == Some(&mapped);
+        if !doubled {
+            out.push(mapped);
+        }
+        i += 1;
+    }
+    // Full-width marks carry their own spacing
+    let spaced = |c: Option<&char>| c.is_some_and(|&c| is_fullwidth_punct(c));
+    (0..out.len())
+        .filter(|&i| out[i] != ' ' || !(spaced(out.get(i + 1)) || i > 0 && spaced(out.get(i - 1))))
+        .map(|i| out[i])
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// Han, kana, and full-width punctuation.
+fn is_cjk(c: char) -> bool {
+    is_han(c) || matches!(c, '\u{3040}'..='\u{30FF}') || is_fullwidth_punct(c)
+}
+
+fn is_fullwidth_punct(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303F}' | '\u{FF01}'..='\u{FF0F}' | '\u{FF1A}'..='\u{FF20}' | '…' | '—')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_line_up() {
+        assert_eq!(SIMPLIFIED.chars().count(), TRADITIONAL.chars().count());
+    }
+
+    #[test]
+    fn test_simplified_chars_skips_japanese() {
+        assert_eq!(simplified_chars("这是什么"), [('这', '這'), ('么', '麼')]);
+        // Shared Japanese forms and Taiwan text pass
+        assert!(simplified_chars("学校に行く 這是什麼").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_punctuation() {
+        let n = normalize_punctuation;
+        assert_eq!(n("你好,我是小明!"), "你好，我是小明！");
+        assert_eq!(n("“走吧”他說..."), "「走吧」他說……");
+        assert_eq!(n("他說\"好\"。"), "他說「好」。");
+        assert_eq!(n("等等。。。真的…"), "等等……真的……");
+        assert_eq!(n("我-- 不是―"), "我——不是——");
+        assert_eq!(n("什麼？？好，，走吧 ! "), "什麼？好，走吧！");
+        assert_eq!(n("我買了 iPhone 15, 很好."), "我買了 iPhone 15，很好。");
+        // Latin-only lines are left alone
+        assert_eq!(n("Hello, world... It's 3.5!"), "Hello, world... It's 3.5!");
+    }
+}